@@ -1,4 +1,5 @@
 use std::borrow::Cow;
+use std::cell::RefCell;
 use std::collections::btree_map::Entry;
 // Copyright 2021, 2022 Zachary Stewart
 //
@@ -7,23 +8,53 @@ use std::collections::btree_map::Entry;
 //   You may obtain a copy of the License at
 //
 //       http://www.apache.org/licenses/LICENSE-2.0
-use std::collections::{BTreeMap, HashMap};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::rc::Rc;
 use std::str::FromStr;
 use std::{fmt, mem};
 
+use gloo::events::EventListener;
+use gloo::file::callbacks::{read_as_text, FileReader};
+use gloo::file::{File, FileReadError};
 use gloo::storage::errors::StorageError;
 use gloo::storage::{LocalStorage, Storage};
+use gloo::timers::callback::Timeout;
 use log::warn;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 use uuid::Uuid;
+use wasm_bindgen::{JsCast, JsValue};
+use web_sys::{HtmlInputElement, HtmlTextAreaElement};
+use yew::html::Scope;
 use yew::prelude::*;
 
-use satisfactory_accounting::accounting::{Group, Node};
-use satisfactory_accounting::database::{Database, DatabaseVersion};
-
-use crate::node_display::{BalanceSortMode, NodeDisplay, NodeMeta, NodeMetadata};
+use satisfactory_accounting::accounting::{Group, ItemReport, Node, NodeError, ProductionTrace};
+use satisfactory_accounting::bom::BillOfMaterials;
+use satisfactory_accounting::database::{Database, DatabaseVersion, ItemId};
+use satisfactory_accounting::factory::{Factory, FactoryMetadata, ItemNames};
+use satisfactory_accounting::logistics::{BeltTier, PipeTier};
+use satisfactory_accounting::migration::migrate;
+
+use crate::clickedit::ClickEdit;
+use crate::events::{get_value_from_input_event, get_value_from_textarea_change};
+use crate::export::download_factory;
+use crate::history::{edited_path, History, HistoryDepth};
+use crate::import::{database_mismatch_notice, parse_factory};
+use crate::item_label;
+use crate::node_display::{
+    clear_cut, clear_cut_path, clear_dragging, collapse_below, copy_children, delete_children,
+    focus_element, focus_is_lost, move_children, move_group_by_id, node_element_id, node_name,
+    outermost_paths, parse_clock_speed, split_deletion_blocked, trend_series, BalanceLegend,
+    BalanceSortMode, Breadcrumbs, Choice, ChooseFromList, ClockRounding, ClockStep,
+    CollapseControls, FocusAction, FocusStep, Icon, ItemFilter, ItemGroup, ItemGroupsEditor,
+    ItemReportView, ItemSetEditor, ItemTrends, MachineCalculator, NodeDisplay, NodeFocus, NodeMeta,
+    NodeMetadata, NodeTree, Overview, PinnedNodes, PowerChart, PowerPlanner, PowerSummary,
+    Problems, ProducedItems, ProductionTraceView, Sandbox, Selection, SelectionAction,
+    TargetScorecard,
+};
+use crate::notifications::{Notice, Notices, Notifications, Notifier, NOTICE_TIMEOUT_MS};
+use crate::numbers::{format_number, DecimalSeparator, Precision, RateUnit};
+use crate::share::{decode_share, encode_share, ShareError, MAX_SHARE_URL_LEN};
 
 /// Key that the app state is stored under.
 const DB_KEY: &str = "zstewart.satisfactorydb.state.database";
@@ -41,6 +72,17 @@ pub enum OverlayWindow {
     WorldChooser,
     DatabaseChooser,
     UserSettings,
+    ProductionTrace,
+    FactoryInfo,
+    Overview,
+    Inventory,
+    Targets,
+    MachineCalculator,
+    PowerPlanner,
+    Legend,
+    RebuildReport,
+    Problems,
+    Share,
 }
 
 /// App-wide settings specific to the user rather than the world.
@@ -49,6 +91,75 @@ pub struct UserSettings {
     /// Whether empty balance values should be hidden.
     pub hide_empty_balances: bool,
     pub balance_sort_mode: BalanceSortMode,
+    /// Whether building nodes should be tinted based on their building category.
+    #[serde(default)]
+    pub color_by_category: bool,
+    /// How clock speeds entered by the user are rounded.
+    #[serde(default)]
+    pub clock_rounding: ClockRounding,
+    /// How much the arrow keys change a clock speed being edited.
+    #[serde(default)]
+    pub clock_step: ClockStep,
+    /// Whether groups show the throughput of related items as simplified ratios.
+    #[serde(default)]
+    pub show_ratios: bool,
+    /// Whether groups still list items whose production and consumption cancel out to
+    /// exactly zero. Off by default, so a group only shows what it takes in or puts out.
+    #[serde(default)]
+    pub show_cancelled_balances: bool,
+    /// Whether to show how the factory's net rates of watched items changed over the
+    /// edits kept in the undo history.
+    #[serde(default)]
+    pub show_trends: bool,
+    /// Items whose trends are shown.
+    #[serde(default)]
+    pub watched_items: Vec<ItemId>,
+    /// Whether power is left out of balances, for planning materials only. Power is still
+    /// computed.
+    #[serde(default)]
+    pub hide_power: bool,
+    /// Whether groups with no unmet inputs or undeclared outputs should be collapsed
+    /// automatically.
+    #[serde(default)]
+    pub auto_collapse_balanced: bool,
+    /// Tier of belt used when estimating how many belts an output needs.
+    #[serde(default)]
+    pub belt_tier: BeltTier,
+    /// Tier of pipe used when estimating how many pipes a fluid output needs.
+    #[serde(default)]
+    pub pipe_tier: PipeTier,
+    /// Unit item rates are shown and entered in.
+    #[serde(default)]
+    pub rate_unit: RateUnit,
+    /// Named sets of items defined by the user, which balances can be filtered to.
+    #[serde(default)]
+    pub item_groups: Vec<ItemGroup>,
+    /// Filter applied to all balances, if any.
+    #[serde(default)]
+    pub item_filter: Option<ItemFilter>,
+    /// Clock speed every building is expected to run at, if any. Buildings are colored by
+    /// how far they are from it.
+    #[serde(default)]
+    pub efficiency_target: Option<f32>,
+    /// Tag to search for. Nodes which don't have a matching tag, either themselves or on
+    /// an ancestor or descendant, are dimmed. Empty for no filter.
+    #[serde(default)]
+    pub tag_filter: AttrValue,
+    /// Decimal separator accepted in numbers the user enters.
+    #[serde(default)]
+    pub decimal_separator: DecimalSeparator,
+    /// Indentation and spacing of nested nodes.
+    #[serde(default)]
+    pub tree_layout: TreeLayout,
+    /// Maximum number of steps kept in the undo history.
+    #[serde(default)]
+    pub undo_depth: HistoryDepth,
+    /// Number of decimal places shown in balances.
+    #[serde(default)]
+    pub balance_precision: Precision,
+    /// Items left out of AWESOME Sink point totals, e.g. ores which would never be sunk.
+    #[serde(default)]
+    pub sink_excluded: BTreeSet<ItemId>,
 }
 
 impl UserSettings {
@@ -59,10 +170,47 @@ impl UserSettings {
 
     /// Save the current user settings.
     fn save(&self) {
-        if let Err(e) = LocalStorage::set(USER_SETTINGS_KEY, &self) {
+        if let Err(e) = LocalStorage::set(USER_SETTINGS_KEY, self) {
             warn!("Unable to save world: {}", e);
         }
     }
+
+    /// Format a number shown in a balance according to the user's precision and decimal
+    /// separator.
+    pub fn format(&self, value: f32) -> String {
+        format_number(value, self.balance_precision, self.decimal_separator)
+    }
+}
+
+/// Indentation and spacing of the node tree, in pixels.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct TreeLayout {
+    /// Horizontal space between a group's edge and its children.
+    pub indent: u32,
+    /// Vertical space between sibling nodes.
+    pub spacing: u32,
+}
+
+impl Default for TreeLayout {
+    fn default() -> Self {
+        Self {
+            indent: 5,
+            spacing: 5,
+        }
+    }
+}
+
+impl TreeLayout {
+    /// Largest indent or spacing which can be set.
+    const MAX: u32 = 40;
+
+    /// CSS custom properties which apply this layout to the node tree.
+    fn style(self) -> String {
+        format!(
+            "--tree-indent: {}px; --tree-spacing: {}px;",
+            self.indent, self.spacing
+        )
+    }
 }
 
 /// Unique ID of a world.
@@ -134,10 +282,9 @@ impl FromStr for WorldId {
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         const PREFIX: &str = "zstewart.satisfactorydb.state.world.";
-        if s.starts_with(PREFIX) {
-            Ok(WorldId(s[PREFIX.len()..].parse()?))
-        } else {
-            Err(ParseWorldIdError::IncorrectPrefix)
+        match s.strip_prefix(PREFIX) {
+            Some(uuid) => Ok(WorldId(uuid.parse()?)),
+            None => Err(ParseWorldIdError::IncorrectPrefix),
         }
     }
 }
@@ -191,6 +338,14 @@ impl DatabaseChoice {
         }
     }
 
+    /// Get the version of the database for this choice, if it has one.
+    fn version(&self) -> Option<DatabaseVersion> {
+        match *self {
+            DatabaseChoice::Standard(version) => Some(version),
+            DatabaseChoice::Custom(ref db) => db.version,
+        }
+    }
+
     /// Return true if this is a standard database with the specified version.
     fn is_standard_version(&self, version: DatabaseVersion) -> bool {
         match *self {
@@ -217,6 +372,20 @@ struct World {
     node_metadata: NodeMetadata,
     /// Non-undo metadata about this particular world.
     global_metadata: GlobalMetadata,
+    /// Descriptive information about this world's factory, included when it is exported or
+    /// shared, and replaced by the metadata of an imported or shared factory.
+    #[serde(default)]
+    factory_metadata: FactoryMetadata,
+    /// Last known names of items used in this world, so they can still be shown if the
+    /// database no longer has them.
+    #[serde(default)]
+    item_names: Rc<ItemNames>,
+    /// Consumables the user has collected in this world.
+    #[serde(default)]
+    inventory: Inventory,
+    /// Target output rates the factory is compared against.
+    #[serde(default)]
+    bom: BillOfMaterials,
 }
 
 impl World {
@@ -316,7 +485,55 @@ impl World {
             root,
             node_metadata: metadata,
             global_metadata,
+            factory_metadata: Default::default(),
+            item_names: Default::default(),
+            inventory: Default::default(),
+            bom: Default::default(),
+        }
+    }
+
+    /// If the page was opened from a share link, build a new world holding the shared
+    /// factory and its metadata, so it doesn't replace any of the user's own. The factory
+    /// is removed from the URL so reloading the page doesn't load it again. Returns an
+    /// error if the link is corrupt.
+    fn take_shared() -> Option<Result<Self, ShareError>> {
+        let window = gloo::utils::window();
+        let location = window.location();
+        let shared = decode_share(&location.hash().ok()?)?;
+        let url = format!(
+            "{}{}",
+            location.pathname().unwrap_or_default(),
+            location.search().unwrap_or_default()
+        );
+        if let Err(e) = window
+            .history()
+            .and_then(|history| history.replace_state_with_url(&JsValue::NULL, "", Some(&url)))
+        {
+            warn!("Unable to remove shared factory from the URL: {e:?}");
         }
+        let factory = match shared {
+            Ok(factory) => factory,
+            Err(e) => return Some(Err(e)),
+        };
+        let mut world = Self::new();
+        world.factory_metadata = factory.metadata;
+        world.item_names = Rc::new(factory.item_names);
+        let root = migrate(
+            &factory.root.create_copy(),
+            factory.database_version,
+            &world.database.get(),
+        )
+        .root;
+        world.root = if root.group().is_some() {
+            root
+        } else {
+            Group {
+                children: vec![root],
+                ..Group::empty()
+            }
+            .into()
+        };
+        Some(Ok(world))
     }
 
     /// Create a new empty world with the default database version.
@@ -326,6 +543,10 @@ impl World {
             root: Group::empty_node(),
             node_metadata: Default::default(),
             global_metadata: Default::default(),
+            factory_metadata: Default::default(),
+            item_names: Default::default(),
+            inventory: Default::default(),
+            bom: Default::default(),
         }
     }
 
@@ -347,7 +568,7 @@ impl World {
 
     /// Save the state of the current world.
     fn save(&self, id: WorldId) {
-        if let Err(e) = LocalStorage::set(id.to_string(), &self) {
+        if let Err(e) = LocalStorage::set(id.to_string(), self) {
             warn!("Unable to save world: {}", e);
         }
     }
@@ -366,6 +587,18 @@ pub struct GlobalMetadata {
     hide_empty_balances: bool,
 }
 
+/// Counts of consumables the user owns in a world, compared against what the factory
+/// needs.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Inventory {
+    /// Number of power shards owned.
+    #[serde(default)]
+    power_shards: u32,
+    /// Number of Somersloops owned.
+    #[serde(default)]
+    somersloops: u32,
+}
+
 /// State tracked for undo/redo.
 struct UnReDoState {
     /// Database at this undo/redo version.
@@ -379,6 +612,12 @@ pub enum Msg {
     ReplaceRoot {
         replacement: Node,
     },
+    /// Move a group, by id, into another group, by id, at the given position.
+    MoveGroup {
+        moved: Uuid,
+        into: Uuid,
+        position: usize,
+    },
     UpdateMetadata {
         id: Uuid,
         meta: NodeMeta,
@@ -394,10 +633,98 @@ pub enum Msg {
     SetBalanceSortMode {
         sort_mode: BalanceSortMode,
     },
+    /// Set whether building nodes are colored by category.
+    ToggleCategoryColors {
+        color_by_category: bool,
+    },
+    /// Set whether groups show item ratios.
+    ToggleRatios {
+        show_ratios: bool,
+    },
+    /// Set whether groups list items which cancel out to zero.
+    ToggleCancelledBalances {
+        show_cancelled_balances: bool,
+    },
+    /// Set whether trends of watched items are shown.
+    ToggleTrends {
+        show_trends: bool,
+    },
+    /// Replace the items whose trends are shown.
+    SetWatchedItems {
+        watched_items: Vec<ItemId>,
+    },
+    /// Set whether power is left out of balances.
+    ToggleHidePower {
+        hide_power: bool,
+    },
+    /// Set whether balanced groups are automatically collapsed.
+    ToggleAutoCollapse {
+        auto_collapse_balanced: bool,
+    },
+    /// Change which decimal separator is accepted in entered numbers.
+    SetDecimalSeparator {
+        decimal_separator: DecimalSeparator,
+    },
+    /// Change how entered clock speeds are rounded.
+    SetClockRounding {
+        clock_rounding: ClockRounding,
+    },
+    /// Change how much the arrow keys change clock speeds.
+    SetClockStep {
+        clock_step: ClockStep,
+    },
+    /// Change the indentation and spacing of the node tree.
+    SetTreeLayout {
+        tree_layout: TreeLayout,
+    },
+    /// Change how many steps of undo history are kept.
+    SetUndoDepth {
+        undo_depth: HistoryDepth,
+    },
+    /// Change how many decimal places balances show.
+    SetBalancePrecision {
+        balance_precision: Precision,
+    },
+    /// Change the belt tier used to estimate logistics.
+    SetBeltTier {
+        belt_tier: BeltTier,
+    },
+    /// Change the pipe tier used to estimate logistics.
+    SetPipeTier {
+        pipe_tier: PipeTier,
+    },
+    /// Change the unit item rates are shown in.
+    SetRateUnit {
+        rate_unit: RateUnit,
+    },
+    /// Replace the user's custom item groups.
+    SetItemGroups {
+        item_groups: Vec<ItemGroup>,
+    },
+    /// Replace the items left out of sink point totals.
+    SetSinkExcluded {
+        sink_excluded: BTreeSet<ItemId>,
+    },
+    /// Change the filter applied to balances.
+    SetItemFilter {
+        item_filter: Option<ItemFilter>,
+    },
+    /// Change the tag nodes are searched for.
+    SetTagFilter {
+        tag_filter: AttrValue,
+    },
+    /// Change the clock speed buildings are compared against.
+    SetEfficiencyTarget {
+        efficiency_target: Option<f32>,
+    },
     Undo,
     Redo,
     /// Set the database to the given database choice.
     SetDb(DatabaseChoice),
+    /// Read a custom database from a file chosen by the user.
+    UploadDb(File),
+    /// Use the custom database read from a file, if it is valid.
+    DbFileLoaded(Result<String, FileReadError>),
     /// Set the status of show_deprecated_databases.
     ShowDeprecated(bool),
     /// Select a particular world.
@@ -412,6 +739,84 @@ pub enum Msg {
     DeleteForever(WorldId),
     /// Show or hide one of the overlay windows.
     SetWindow(OverlayWindow),
+    /// Expand the group at the given path and all of its ancestors, then scroll to it.
+    JumpTo {
+        path: Vec<usize>,
+    },
+    /// Replace the descriptive metadata of the current world's factory.
+    SetFactoryMetadata(FactoryMetadata),
+    /// Set the consumables owned in the current world.
+    SetInventory(Inventory),
+    /// Parse and set the target output rates of the current world.
+    SetBom(AttrValue),
+    /// Rebuild every node in the tree with the current database and show any errors.
+    RebuildAll,
+    /// Trace the production of an item by the node at the given path and show the trace.
+    TraceProduction {
+        path: Vec<usize>,
+        item: ItemId,
+    },
+    /// Show the item chooser for finding where an item is made and used.
+    StartFindItem,
+    /// Close the item chooser without choosing an item.
+    CancelFindItem,
+    /// Show every building which produces or consumes the given item.
+    FindItem {
+        item: ItemId,
+    },
+    /// Build a link which encodes the current factory and show it.
+    ShareFactory,
+    /// Download the whole current factory as a file.
+    ExportFactory,
+    /// Start reading a factory file to replace the whole tree.
+    ImportFactory(File),
+    /// A factory file to replace the tree finished loading.
+    FactoryFileLoaded(Result<String, FileReadError>),
+    /// Fork a copy of the given group into the sandbox, replacing any sandbox already
+    /// open.
+    OpenSandbox {
+        node: Node,
+    },
+    /// Replace the working copy of the group in the sandbox.
+    UpdateSandbox {
+        replacement: Node,
+    },
+    /// Replace the group the sandbox was forked from with its working copy, and close the
+    /// sandbox.
+    MergeSandbox,
+    /// Close the sandbox without changing the factory.
+    DiscardSandbox,
+    /// Toggle the node at the given path in the selection, or select the range of
+    /// siblings up to it.
+    SelectNode {
+        path: Vec<usize>,
+        range: bool,
+    },
+    /// Move the selected nodes to the given position, or insert copies of them there.
+    MoveSelection {
+        dest_path: Vec<usize>,
+        copy: bool,
+    },
+    /// Insert a copy of each selected node right after it.
+    DuplicateSelection,
+    /// Delete every selected node which isn't locked against deletion.
+    DeleteSelection,
+    /// Deselect all nodes.
+    ClearSelection,
+    /// Move the keyboard focus, or track which node has it.
+    Focus(FocusAction),
+    /// Add a node from the machine calculator or power planner to the focused group, or to
+    /// the root if no group is focused.
+    AddCalculatedNode(Node),
+    /// Collapse every group deeper than the given depth below the root and expand the
+    /// rest, or expand every group if no depth is given.
+    CollapseBelow {
+        depth: Option<usize>,
+    },
+    /// Show a notice to the user.
+    Notify(Notice),
+    /// Stop showing the notice with the given ID.
+    DismissNotice(u64),
 }
 
 /// Current state of the app.
@@ -420,36 +825,65 @@ pub struct App {
     user_settings: Rc<UserSettings>,
     /// Overlay window to show.
     overlay_window: OverlayWindow,
+    /// Most recently requested production trace.
+    production_trace: Option<ProductionTrace>,
+    /// Factory-wide report on the item in the most recent production trace or item search.
+    item_report: Option<ItemReport>,
+    /// Whether the chooser for an item to search for is open.
+    finding_item: bool,
+    /// Id of the HTML element to scroll to after the next render.
+    scroll_to: Option<String>,
+    /// Copy of a group the user is experimenting on, if any. Not saved, so it is lost
+    /// when the page is reloaded.
+    sandbox: Option<Sandbox>,
+    /// Nodes selected to be moved, copied, or deleted together.
+    selection: Selection,
+    /// Node with keyboard focus.
+    focus: NodeFocus,
+    /// Whether to give the focused node browser focus after the next render, because it
+    /// was moved to with the keyboard.
+    focus_pending: bool,
+    /// Notices telling the user why something didn't work.
+    notices: Notices,
+    /// Handle given to the rest of the app for showing notices.
+    notifier: Notifier,
+    /// Most recently built link to share the factory, if building it succeeded.
+    share_link: Option<AttrValue>,
     /// World with a "confirm delete" window currently present.
     pending_delete: Option<WorldId>,
+    /// Error from parsing the most recently entered target output rates, if any.
+    bom_error: Option<String>,
+    /// Buildings which failed to build the last time all nodes were rebuilt.
+    rebuild_errors: Vec<NodeError>,
     /// Whether to show deprecated database versions in the list.
     show_deprecated_databases: bool,
+    /// Reader for a custom database file being loaded, kept so the read isn't cancelled.
+    custom_db_reader: Option<FileReader>,
+    /// Error from the most recently uploaded custom database, if it couldn't be used.
+    custom_db_error: Option<String>,
+    /// Reader for a factory file being imported, kept so the read isn't cancelled.
+    factory_reader: Option<FileReader>,
     /// Listing of available worlds.
     worlds: Worlds,
     /// State of the currently selected world.
     world: World,
     /// Selected database.
     database: Rc<Database>,
-    /// Stack of previous states for undo.
-    undo_stack: Vec<UnReDoState>,
-    /// Stack of future states for redo.
-    redo_stack: Vec<UnReDoState>,
+    /// Previous and future states for undo and redo.
+    history: History<UnReDoState>,
+    /// Listener for the undo and redo keyboard shortcuts, kept so it stays registered.
+    _undo_shortcuts: EventListener,
 }
 
 impl App {
-    /// Add a state to the Undo stack, clearing the redo stack and any history beyond 100
-    /// items.
-    fn add_undo_state(&mut self, previous_state: UnReDoState) {
-        self.undo_stack.push(previous_state);
-        if self.undo_stack.len() > 100 {
-            let num_to_remove = self.undo_stack.len() - 100;
-            self.undo_stack.drain(..num_to_remove);
-        }
-        self.redo_stack.clear();
-    }
-
     /// Saves the world into the
-    fn save_world(&self) {
+    fn save_world(&mut self) {
+        let item_names =
+            ItemNames::collect(&self.world.root, &self.database, &self.world.item_names);
+        if *self.world.item_names != item_names {
+            self.world.item_names = Rc::new(item_names);
+        }
+        self.focus = self.focus.resolve(&self.world.root);
         self.world.save(self.worlds.selected);
     }
 }
@@ -458,7 +892,7 @@ impl Component for App {
     type Message = Msg;
     type Properties = ();
 
-    fn create(_ctx: &Context<Self>) -> Self {
+    fn create(ctx: &Context<Self>) -> Self {
         let (worlds, world) = match Worlds::load() {
             Ok(mut worlds) => {
                 let world = match World::load(worlds.selected) {
@@ -500,6 +934,26 @@ impl Component for App {
                 (worlds, world)
             }
         };
+        let (mut worlds, mut world) = (worlds, world);
+        // A shared factory's info is shown when it is opened, if it has any.
+        let mut overlay_window = OverlayWindow::None;
+        match World::take_shared() {
+            Some(Ok(shared)) => {
+                let id = WorldId::new();
+                shared.save(id);
+                worlds.worlds.insert(id, shared.storage_metadata());
+                worlds.selected = id;
+                worlds.save();
+                if !shared.factory_metadata.is_empty() {
+                    overlay_window = OverlayWindow::FactoryInfo;
+                }
+                world = shared;
+            }
+            Some(Err(e)) => ctx.link().send_message(Msg::Notify(Notice::new(format!(
+                "Unable to load shared factory: {e}"
+            )))),
+            None => {}
+        }
         let database = world.database.get();
 
         let user_settings = Rc::new(match UserSettings::load() {
@@ -520,24 +974,89 @@ impl Component for App {
             }
         });
 
+        let undo_shortcuts = {
+            let link = ctx.link().clone();
+            EventListener::new(&gloo::utils::document(), "keydown", move |e| {
+                let Some(e) = e.dyn_ref::<KeyboardEvent>() else {
+                    return;
+                };
+                if !(e.ctrl_key() || e.meta_key()) {
+                    return;
+                }
+                // Leave undo of text being edited to the browser.
+                if e.target().is_some_and(|target| {
+                    target.has_type::<HtmlInputElement>()
+                        || target.has_type::<HtmlTextAreaElement>()
+                }) {
+                    return;
+                }
+                let msg = match &*e.key() {
+                    "z" | "Z" if e.shift_key() => Msg::Redo,
+                    "z" | "Z" => Msg::Undo,
+                    "y" | "Y" => Msg::Redo,
+                    _ => return,
+                };
+                e.prevent_default();
+                link.send_message(msg);
+            })
+        };
+
         Self {
+            history: History::new(user_settings.undo_depth),
             user_settings,
-            overlay_window: OverlayWindow::None,
+            overlay_window,
+            production_trace: None,
+            item_report: None,
+            finding_item: false,
+            scroll_to: None,
+            sandbox: None,
+            selection: Default::default(),
+            focus: Default::default(),
+            focus_pending: false,
+            notices: Default::default(),
+            notifier: Notifier::new(ctx.link().callback(Msg::Notify)),
+            share_link: None,
             pending_delete: None,
+            bom_error: None,
+            rebuild_errors: Vec::new(),
             show_deprecated_databases: false,
+            custom_db_reader: None,
+            custom_db_error: None,
+            factory_reader: None,
             worlds,
             world,
             database,
-            undo_stack: Vec::new(),
-            redo_stack: Vec::new(),
+            _undo_shortcuts: undo_shortcuts,
         }
     }
 
-    fn update(&mut self, _ctx: &Context<Self>, msg: Self::Message) -> bool {
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            Msg::ReplaceRoot { .. } | Msg::Undo | Msg::Redo => {
+                // Paths into the tree may have changed, so a cut building can't be pasted
+                // and the selected paths may point at different nodes.
+                clear_cut_path();
+                self.selection = Default::default();
+            }
+            Msg::OpenSandbox { .. } | Msg::MergeSandbox | Msg::DiscardSandbox => {
+                // Cut and dragged nodes may belong to the sandbox being replaced.
+                clear_cut();
+                clear_dragging();
+            }
+            Msg::SetWorld(_) | Msg::CreateWorld | Msg::DeleteForever(_) => {
+                clear_cut();
+                self.selection = Default::default();
+                self.focus = Default::default();
+                self.sandbox = None;
+            }
+            _ => {}
+        }
         match msg {
             Msg::ReplaceRoot { replacement } => {
+                let edited = edited_path(&self.world.root, &replacement);
                 let (previous, new_name) = self.world.update_root(replacement);
-                self.add_undo_state(previous);
+                self.history
+                    .record(previous, Some(edited), js_sys::Date::now());
                 if let Some(new_name) = new_name {
                     match self.worlds.worlds.entry(self.worlds.selected) {
                         Entry::Occupied(mut entry) => entry.get_mut().name = new_name,
@@ -551,6 +1070,22 @@ impl Component for App {
                 self.save_world();
                 true
             }
+            Msg::MoveGroup {
+                moved,
+                into,
+                position,
+            } => {
+                let Some(root) = self.world.root.group() else {
+                    warn!("Root is not a group");
+                    return false;
+                };
+                if let Some(new_root) = move_group_by_id(root, moved, into, position) {
+                    ctx.link().send_message(Msg::ReplaceRoot {
+                        replacement: new_root.into(),
+                    });
+                }
+                false
+            }
             Msg::UpdateMetadata { id, meta } => {
                 self.world.node_metadata.set_meta(id, meta);
                 self.save_world();
@@ -560,7 +1095,7 @@ impl Component for App {
                 if updates.is_empty() {
                     false
                 } else {
-                    self.world.node_metadata.batch_update(updates.into_iter());
+                    self.world.node_metadata.batch_update(updates);
                     self.save_world();
                     true
                 }
@@ -580,43 +1115,214 @@ impl Component for App {
                 true
             }
             Msg::SetBalanceSortMode { sort_mode: _ } => false,
-            Msg::Undo => match self.undo_stack.pop() {
-                Some(previous) => {
-                    let next = self.world.apply_undo_state(previous);
-                    self.redo_stack.push(next);
+            Msg::ToggleCategoryColors { color_by_category } => {
+                Rc::make_mut(&mut self.user_settings).color_by_category = color_by_category;
+                self.user_settings.save();
+                true
+            }
+            Msg::ToggleRatios { show_ratios } => {
+                Rc::make_mut(&mut self.user_settings).show_ratios = show_ratios;
+                self.user_settings.save();
+                true
+            }
+            Msg::ToggleCancelledBalances {
+                show_cancelled_balances,
+            } => {
+                Rc::make_mut(&mut self.user_settings).show_cancelled_balances =
+                    show_cancelled_balances;
+                self.user_settings.save();
+                true
+            }
+            Msg::ToggleTrends { show_trends } => {
+                Rc::make_mut(&mut self.user_settings).show_trends = show_trends;
+                self.user_settings.save();
+                true
+            }
+            Msg::SetWatchedItems { watched_items } => {
+                Rc::make_mut(&mut self.user_settings).watched_items = watched_items;
+                self.user_settings.save();
+                true
+            }
+            Msg::ToggleHidePower { hide_power } => {
+                Rc::make_mut(&mut self.user_settings).hide_power = hide_power;
+                self.user_settings.save();
+                true
+            }
+            Msg::ToggleAutoCollapse {
+                auto_collapse_balanced,
+            } => {
+                Rc::make_mut(&mut self.user_settings).auto_collapse_balanced =
+                    auto_collapse_balanced;
+                self.user_settings.save();
+                true
+            }
+            Msg::SetDecimalSeparator { decimal_separator }
+                if self.user_settings.decimal_separator != decimal_separator =>
+            {
+                Rc::make_mut(&mut self.user_settings).decimal_separator = decimal_separator;
+                self.user_settings.save();
+                true
+            }
+            Msg::SetDecimalSeparator {
+                decimal_separator: _,
+            } => false,
+            Msg::SetClockRounding { clock_rounding }
+                if self.user_settings.clock_rounding != clock_rounding =>
+            {
+                Rc::make_mut(&mut self.user_settings).clock_rounding = clock_rounding;
+                self.user_settings.save();
+                true
+            }
+            Msg::SetClockRounding { clock_rounding: _ } => false,
+            Msg::SetClockStep { clock_step } if self.user_settings.clock_step != clock_step => {
+                Rc::make_mut(&mut self.user_settings).clock_step = clock_step;
+                self.user_settings.save();
+                true
+            }
+            Msg::SetClockStep { clock_step: _ } => false,
+            Msg::SetTreeLayout { tree_layout } if self.user_settings.tree_layout != tree_layout => {
+                Rc::make_mut(&mut self.user_settings).tree_layout = tree_layout;
+                self.user_settings.save();
+                true
+            }
+            Msg::SetTreeLayout { tree_layout: _ } => false,
+            Msg::SetUndoDepth { undo_depth } if self.user_settings.undo_depth != undo_depth => {
+                Rc::make_mut(&mut self.user_settings).undo_depth = undo_depth;
+                self.user_settings.save();
+                self.history.set_depth(undo_depth);
+                true
+            }
+            Msg::SetUndoDepth { undo_depth: _ } => false,
+            Msg::SetBalancePrecision { balance_precision }
+                if self.user_settings.balance_precision != balance_precision =>
+            {
+                Rc::make_mut(&mut self.user_settings).balance_precision = balance_precision;
+                self.user_settings.save();
+                true
+            }
+            Msg::SetBalancePrecision {
+                balance_precision: _,
+            } => false,
+            Msg::SetBeltTier { belt_tier } if self.user_settings.belt_tier != belt_tier => {
+                Rc::make_mut(&mut self.user_settings).belt_tier = belt_tier;
+                self.user_settings.save();
+                true
+            }
+            Msg::SetBeltTier { belt_tier: _ } => false,
+            Msg::SetPipeTier { pipe_tier } if self.user_settings.pipe_tier != pipe_tier => {
+                Rc::make_mut(&mut self.user_settings).pipe_tier = pipe_tier;
+                self.user_settings.save();
+                true
+            }
+            Msg::SetPipeTier { pipe_tier: _ } => false,
+            Msg::SetRateUnit { rate_unit } if self.user_settings.rate_unit != rate_unit => {
+                Rc::make_mut(&mut self.user_settings).rate_unit = rate_unit;
+                self.user_settings.save();
+                true
+            }
+            Msg::SetRateUnit { rate_unit: _ } => false,
+            Msg::SetItemGroups { item_groups } => {
+                let settings = Rc::make_mut(&mut self.user_settings);
+                // Drop the filter if its group was deleted.
+                if let Some(ItemFilter::Custom(id)) = settings.item_filter {
+                    if !item_groups.iter().any(|group| group.id == id) {
+                        settings.item_filter = None;
+                    }
+                }
+                settings.item_groups = item_groups;
+                self.user_settings.save();
+                true
+            }
+            Msg::SetSinkExcluded { sink_excluded } => {
+                Rc::make_mut(&mut self.user_settings).sink_excluded = sink_excluded;
+                self.user_settings.save();
+                true
+            }
+            Msg::SetItemFilter { item_filter } if self.user_settings.item_filter != item_filter => {
+                Rc::make_mut(&mut self.user_settings).item_filter = item_filter;
+                self.user_settings.save();
+                true
+            }
+            Msg::SetItemFilter { item_filter: _ } => false,
+            Msg::SetTagFilter { tag_filter } if self.user_settings.tag_filter != tag_filter => {
+                Rc::make_mut(&mut self.user_settings).tag_filter = tag_filter;
+                self.user_settings.save();
+                true
+            }
+            Msg::SetTagFilter { tag_filter: _ } => false,
+            Msg::SetEfficiencyTarget { efficiency_target }
+                if self.user_settings.efficiency_target != efficiency_target =>
+            {
+                Rc::make_mut(&mut self.user_settings).efficiency_target = efficiency_target;
+                self.user_settings.save();
+                true
+            }
+            Msg::SetEfficiencyTarget {
+                efficiency_target: _,
+            } => false,
+            Msg::Undo => {
+                if self
+                    .history
+                    .undo(|previous| self.world.apply_undo_state(previous))
+                {
                     self.save_world();
                     true
-                }
-                None => {
+                } else {
                     warn!("Nothing to undo");
                     false
                 }
-            },
-            Msg::Redo => match self.redo_stack.pop() {
-                Some(next) => {
-                    let previous = self.world.apply_undo_state(next);
-                    self.undo_stack.push(previous);
+            }
+            Msg::Redo => {
+                if self.history.redo(|next| self.world.apply_undo_state(next)) {
                     self.save_world();
                     true
-                }
-                None => {
+                } else {
                     warn!("Nothing to redo");
                     false
                 }
-            },
+            }
             Msg::SetDb(database) => {
+                self.custom_db_error = None;
                 self.database = database.get();
+                let from = self.world.database.version();
                 let previous = UnReDoState {
                     database: mem::replace(&mut self.world.database, database),
                     root: {
-                        let new_root = self.world.root.rebuild(&*self.database);
+                        let new_root = migrate(&self.world.root, from, &self.database).root;
                         mem::replace(&mut self.world.root, new_root)
                     },
                 };
-                self.add_undo_state(previous);
+                if let Some(sandbox) = &mut self.sandbox {
+                    sandbox.set_root(migrate(sandbox.root(), from, &self.database).root);
+                }
+                self.history.record(previous, None, js_sys::Date::now());
                 self.save_world();
                 true
             }
+            Msg::UploadDb(file) => {
+                let link = ctx.link().clone();
+                self.custom_db_reader = Some(read_as_text(&file, move |result| {
+                    link.send_message(Msg::DbFileLoaded(result))
+                }));
+                false
+            }
+            Msg::DbFileLoaded(result) => {
+                self.custom_db_reader = None;
+                match result
+                    .map_err(|e| e.to_string())
+                    .and_then(|json| Database::from_json(&json).map_err(|e| e.to_string()))
+                {
+                    Ok(database) => {
+                        ctx.link()
+                            .send_message(Msg::SetDb(DatabaseChoice::Custom(Rc::new(database))));
+                    }
+                    Err(e) => {
+                        warn!("Unable to load custom database: {e}");
+                        self.custom_db_error = Some(e);
+                    }
+                }
+                true
+            }
             Msg::ShowDeprecated(show_deprecated) => {
                 if self.show_deprecated_databases != show_deprecated {
                     self.show_deprecated_databases = show_deprecated;
@@ -637,8 +1343,7 @@ impl Component for App {
                             self.worlds.selected = world_id;
                             self.world = world;
                             self.database = self.world.database.get();
-                            self.undo_stack.clear();
-                            self.redo_stack.clear();
+                            self.history.clear();
                             self.worlds.save();
                             true
                         }
@@ -657,8 +1362,7 @@ impl Component for App {
                 self.worlds.selected = new_id;
                 self.world = world;
                 self.database = self.world.database.get();
-                self.undo_stack.clear();
-                self.redo_stack.clear();
+                self.history.clear();
                 self.worlds.save();
                 true
             }
@@ -694,8 +1398,7 @@ impl Component for App {
                                 self.worlds.selected = id;
                                 self.world = world;
                                 self.database = self.world.database.get();
-                                self.undo_stack.clear();
-                                self.redo_stack.clear();
+                                self.history.clear();
                                 self.worlds.save();
                                 return true;
                             }
@@ -712,12 +1415,68 @@ impl Component for App {
                     self.worlds.selected = new_id;
                     self.world = world;
                     self.database = self.world.database.get();
-                    self.undo_stack.clear();
-                    self.redo_stack.clear();
+                    self.history.clear();
                     self.worlds.save();
                 }
                 true
             }
+            Msg::JumpTo { path } => {
+                let mut node = &self.world.root;
+                let mut updates = HashMap::new();
+                for &idx in &path {
+                    match node.group().and_then(|group| group.get_child(idx)) {
+                        Some(child) => node = child,
+                        None => {
+                            warn!("Cannot jump to {path:?}, path does not exist");
+                            return false;
+                        }
+                    }
+                    if let Some(group) = node.group() {
+                        updates.insert(group.id, self.world.node_metadata.meta(group.id).expand());
+                    }
+                }
+                let Some(group) = node.group() else {
+                    warn!("Cannot jump to {path:?}, not a group");
+                    return false;
+                };
+                self.scroll_to = Some(node_element_id(group));
+                self.overlay_window = OverlayWindow::None;
+                self.world.node_metadata.batch_update(updates);
+                self.save_world();
+                true
+            }
+            Msg::SetInventory(inventory) => {
+                if self.world.inventory == inventory {
+                    false
+                } else {
+                    self.world.inventory = inventory;
+                    self.save_world();
+                    true
+                }
+            }
+            Msg::SetBom(text) => match BillOfMaterials::parse(&text, &self.database) {
+                Ok(bom) => {
+                    self.bom_error = None;
+                    if self.world.bom != bom {
+                        self.world.bom = bom;
+                        self.save_world();
+                    }
+                    true
+                }
+                Err(e) => {
+                    self.bom_error = Some(e.to_string());
+                    true
+                }
+            },
+            Msg::SetFactoryMetadata(metadata) => {
+                if self.world.factory_metadata == metadata {
+                    false
+                } else {
+                    self.world.factory_metadata = metadata;
+                    self.save_world();
+                    true
+                }
+            }
             Msg::SetWindow(overlay) => {
                 if self.pending_delete.is_some() {
                     self.pending_delete = None;
@@ -731,65 +1490,548 @@ impl Component for App {
                     false
                 }
             }
-        }
-    }
-
-    fn view(&self, ctx: &Context<Self>) -> Html {
-        let link = ctx.link();
-        let replace = link.callback(|(idx, replacement)| {
-            assert!(idx == 0, "Attempting to replace index {} at the root", idx);
-            Msg::ReplaceRoot { replacement }
-        });
-        let set_metadata = link.callback(|(id, meta)| Msg::UpdateMetadata { id, meta });
-        let batch_set_metadata = link.callback(|updates| Msg::BatchUpdateMetadata { updates });
-        let chooseworld = if self.overlay_window == OverlayWindow::WorldChooser {
-            link.callback(|_| Msg::SetWindow(OverlayWindow::None))
-        } else {
-            link.callback(|_| Msg::SetWindow(OverlayWindow::WorldChooser))
-        };
-        let undo = link.callback(|_| Msg::Undo);
-        let redo = link.callback(|_| Msg::Redo);
-        let choosedb = if self.overlay_window == OverlayWindow::DatabaseChooser {
-            link.callback(|_| Msg::SetWindow(OverlayWindow::None))
-        } else {
-            link.callback(|_| Msg::SetWindow(OverlayWindow::DatabaseChooser))
-        };
-        let move_node =
-            Callback::from(|_| warn!("Root node tried to ask parent to move one of its children"));
-
-        let settings = if self.overlay_window == OverlayWindow::UserSettings {
-            link.callback(|_| Msg::SetWindow(OverlayWindow::None))
-        } else {
-            link.callback(|_| Msg::SetWindow(OverlayWindow::UserSettings))
-        };
-
-        let hide_empty_balances = self.user_settings.hide_empty_balances;
-        let toggle_empty_balances = link.callback(move |_| Msg::ToggleEmptyBalances {
-            hide_empty_balances: !hide_empty_balances,
-        });
-        let hidden_balances = hide_empty_balances.then(|| "hide-empty-balances");
-        html! {
-            <ContextProvider<Rc<Database>> context={Rc::clone(&self.database)}>
-            <ContextProvider<Rc<UserSettings>> context={Rc::clone(&self.user_settings)}>
-            <ContextProvider<NodeMetadata> context={self.world.node_metadata.clone()}>
-            <div class="App">
-                <div class="navbar">
-                    <div class="appheader">{"SATISFACTORY ACCOUNTING"}</div>
-                </div>
-                <div class="menubar">
-                    <span class="section">
-                        <button class="open-world" title="Choose World" onclick={chooseworld}>
-                            <span class="material-icons">{"folder_open"}</span>
-                        </button>
-                        <button class="unredo" title="Undo"
-                            onclick={undo}
-                            disabled={self.undo_stack.is_empty()}>
-                            <span class="material-icons">{"undo"}</span>
-                        </button>
-                        <button class="unredo" title="Redo"
-                            onclick={redo}
-                            disabled={self.redo_stack.is_empty()}>
-                            <span class="material-icons">{"redo"}</span>
+            Msg::RebuildAll => {
+                let report = self.world.root.rebuild_with_report(&self.database);
+                self.rebuild_errors = report.errors;
+                self.pending_delete = None;
+                self.overlay_window = OverlayWindow::RebuildReport;
+                ctx.link().send_message(Msg::ReplaceRoot {
+                    replacement: report.root,
+                });
+                true
+            }
+            Msg::TraceProduction { path, item } => {
+                match ProductionTrace::new(&self.world.root, &path, item) {
+                    Some(trace) => {
+                        self.production_trace = Some(trace);
+                        self.item_report = Some(ItemReport::new(&self.world.root, item));
+                        self.finding_item = false;
+                        self.pending_delete = None;
+                        self.overlay_window = OverlayWindow::ProductionTrace;
+                    }
+                    None => warn!("Cannot trace production from missing node {path:?}"),
+                }
+                true
+            }
+            Msg::StartFindItem => {
+                self.finding_item = true;
+                self.pending_delete = None;
+                self.overlay_window = OverlayWindow::ProductionTrace;
+                true
+            }
+            Msg::CancelFindItem => {
+                self.finding_item = false;
+                true
+            }
+            Msg::FindItem { item } => {
+                self.finding_item = false;
+                self.production_trace = None;
+                self.item_report = Some(ItemReport::new(&self.world.root, item));
+                self.overlay_window = OverlayWindow::ProductionTrace;
+                true
+            }
+            Msg::ShareFactory => {
+                let Some(root) = self.world.root.group() else {
+                    warn!("Root is not a group");
+                    return false;
+                };
+                let factory = Factory::export(
+                    root,
+                    self.world.factory_metadata.clone(),
+                    &self.database,
+                    &self.world.item_names,
+                );
+                self.share_link = match encode_share(&factory) {
+                    Ok(fragment) => {
+                        let href = gloo::utils::window().location().href().unwrap_or_default();
+                        let base = href.split('#').next().unwrap_or_default();
+                        Some(format!("{base}#{fragment}").into())
+                    }
+                    Err(e) => {
+                        warn!("Unable to encode factory to share: {e}");
+                        None
+                    }
+                };
+                self.overlay_window = OverlayWindow::Share;
+                true
+            }
+            Msg::ExportFactory => {
+                if let Some(root) = self.world.root.group() {
+                    download_factory(&Factory::export(
+                        root,
+                        self.world.factory_metadata.clone(),
+                        &self.database,
+                        &self.world.item_names,
+                    ));
+                }
+                false
+            }
+            Msg::ImportFactory(file) => {
+                let link = ctx.link().clone();
+                self.factory_reader = Some(read_as_text(&file, move |result| {
+                    link.send_message(Msg::FactoryFileLoaded(result))
+                }));
+                false
+            }
+            Msg::FactoryFileLoaded(result) => {
+                self.factory_reader = None;
+                match result.map_err(|e| e.to_string()).and_then(|json| {
+                    parse_factory(&json, &self.database).map_err(|e| e.to_string())
+                }) {
+                    Ok((factory, errors)) => {
+                        // Buildings with IDs the database doesn't have are kept as
+                        // warnings and listed, rather than failing the import. Otherwise
+                        // the factory's info is shown, if it has any.
+                        if !errors.is_empty() {
+                            self.pending_delete = None;
+                            self.overlay_window = OverlayWindow::RebuildReport;
+                        } else if !factory.metadata.is_empty() {
+                            self.pending_delete = None;
+                            self.overlay_window = OverlayWindow::FactoryInfo;
+                        }
+                        self.rebuild_errors = errors;
+                        if let Some(notice) =
+                            database_mismatch_notice(&factory.root, &self.database)
+                        {
+                            self.notifier.notify(notice);
+                        }
+                        // Names are set before the root is replaced, so labels of items
+                        // this database lacks are kept when the names are next collected.
+                        self.world.factory_metadata = factory.metadata;
+                        self.world.item_names = Rc::new(factory.item_names);
+                        ctx.link().send_message(Msg::ReplaceRoot {
+                            replacement: factory.root,
+                        });
+                        true
+                    }
+                    Err(e) => {
+                        ctx.link().send_message(Msg::Notify(Notice::new(format!(
+                            "Unable to import factory: {e}"
+                        ))));
+                        false
+                    }
+                }
+            }
+            Msg::OpenSandbox { node } => match Sandbox::fork(&node) {
+                Some(sandbox) => {
+                    self.sandbox = Some(sandbox);
+                    true
+                }
+                None => {
+                    warn!("Only groups can be forked into the sandbox");
+                    false
+                }
+            },
+            Msg::UpdateSandbox { replacement } => match &mut self.sandbox {
+                Some(sandbox) => {
+                    // A building cut from the sandbox is found by path, which may have
+                    // changed.
+                    clear_cut_path();
+                    sandbox.set_root(replacement);
+                    true
+                }
+                None => {
+                    warn!("Cannot update the sandbox when none is open");
+                    false
+                }
+            },
+            Msg::MergeSandbox => {
+                let Some(sandbox) = &self.sandbox else {
+                    warn!("Cannot merge the sandbox when none is open");
+                    return false;
+                };
+                match sandbox.merge_into(&self.world.root) {
+                    Some(replacement) => {
+                        self.sandbox = None;
+                        ctx.link().send_message(Msg::ReplaceRoot { replacement });
+                        true
+                    }
+                    None => {
+                        self.notifier.notify(Notice::new(
+                            "The group the sandbox was forked from is no longer in the factory.",
+                        ));
+                        false
+                    }
+                }
+            }
+            Msg::DiscardSandbox => self.sandbox.take().is_some(),
+            Msg::SelectNode { path, range } => {
+                if range {
+                    self.selection.select_range(path);
+                } else {
+                    self.selection.toggle(path);
+                }
+                true
+            }
+            Msg::Focus(action) => {
+                let focus = match action {
+                    FocusAction::Focused(path) => NodeFocus::at(&self.world.root, path),
+                    FocusAction::Step { from, step } => {
+                        let Some(focus) = NodeFocus::step(&self.world.root, &from, step) else {
+                            return false;
+                        };
+                        // Moving into a collapsed group expands it to show its children.
+                        if let Some(group) = self.world.root.get_at(&from).and_then(Node::group) {
+                            let meta = self.world.node_metadata.meta(group.id);
+                            if step == FocusStep::FirstChild && meta.clone().expand() != meta {
+                                self.world.node_metadata.set_meta(group.id, meta.expand());
+                                self.save_world();
+                            }
+                        }
+                        self.focus_pending = true;
+                        focus
+                    }
+                    FocusAction::Set(path) => {
+                        self.focus_pending = true;
+                        NodeFocus::at(&self.world.root, path)
+                    }
+                    FocusAction::Clear => NodeFocus::default(),
+                };
+                if focus == self.focus && !self.focus_pending {
+                    return false;
+                }
+                self.focus = focus;
+                true
+            }
+            Msg::AddCalculatedNode(node) => {
+                let root = &self.world.root;
+                let mut dest = self
+                    .focus
+                    .path()
+                    .filter(|path| root.get_at(path).is_some_and(|node| node.group().is_some()))
+                    .unwrap_or_default()
+                    .to_vec();
+                dest.push(
+                    root.get_at(&dest)
+                        .and_then(Node::group)
+                        .map_or(0, |group| group.children.len()),
+                );
+                match root.insert_at(&dest, node) {
+                    Ok(replacement) => {
+                        self.overlay_window = OverlayWindow::None;
+                        ctx.link().send_message(Msg::ReplaceRoot { replacement });
+                        ctx.link().send_message(Msg::Focus(FocusAction::Set(dest)));
+                        true
+                    }
+                    Err(e) => {
+                        warn!("Unable to add calculated buildings: {e}");
+                        self.notifier
+                            .notify(Notice::new(format!("Unable to add the buildings: {e}")));
+                        false
+                    }
+                }
+            }
+            Msg::CollapseBelow { depth } => {
+                let updates = collapse_below(&self.world.node_metadata, &self.world.root, depth);
+                if updates.is_empty() {
+                    false
+                } else {
+                    self.world.node_metadata.batch_update(updates);
+                    self.save_world();
+                    true
+                }
+            }
+            Msg::Notify(notice) => {
+                warn!("{}", notice.message);
+                let id = self.notices.push(&self.world.root, notice);
+                let link = ctx.link().clone();
+                Timeout::new(NOTICE_TIMEOUT_MS, move || {
+                    link.send_message(Msg::DismissNotice(id))
+                })
+                .forget();
+                true
+            }
+            Msg::DismissNotice(id) => self.notices.dismiss(id),
+            Msg::MoveSelection { dest_path, copy } => {
+                let Some(root) = self.world.root.group() else {
+                    warn!("Root is not a group");
+                    return false;
+                };
+                let paths = self.selection.paths();
+                let new_root = if copy {
+                    let new_meta = RefCell::new(HashMap::new());
+                    let new_root =
+                        copy_children(root, &paths, &dest_path, &|old: &Group, new: &mut Group| {
+                            let meta = self.world.node_metadata.meta(old.id);
+                            new_meta.borrow_mut().insert(new.id, meta);
+                        });
+                    self.world.node_metadata.batch_update(new_meta.into_inner());
+                    new_root
+                } else {
+                    move_children(root, &paths, &dest_path)
+                };
+                if let Some(new_root) = new_root {
+                    ctx.link().send_message(Msg::ReplaceRoot {
+                        replacement: new_root.into(),
+                    });
+                }
+                false
+            }
+            Msg::DuplicateSelection => {
+                let Some(mut new_root) = self.world.root.group().cloned() else {
+                    warn!("Root is not a group");
+                    return false;
+                };
+                let new_meta = RefCell::new(HashMap::new());
+                let copy_meta = |old: &Group, new: &mut Group| {
+                    let meta = self.world.node_metadata.meta(old.id);
+                    new_meta.borrow_mut().insert(new.id, meta);
+                };
+                // Copy from the end so the paths of earlier nodes aren't shifted.
+                for path in outermost_paths(&self.selection.paths()).into_iter().rev() {
+                    let mut dest = path.clone();
+                    *dest.last_mut().expect("root cannot be selected") += 1;
+                    match copy_children(&new_root, &[path], &dest, &copy_meta) {
+                        Some(copied) => new_root = copied,
+                        None => return false,
+                    }
+                }
+                self.world.node_metadata.batch_update(new_meta.into_inner());
+                ctx.link().send_message(Msg::ReplaceRoot {
+                    replacement: new_root.into(),
+                });
+                false
+            }
+            Msg::DeleteSelection => {
+                let Some(root) = self.world.root.group() else {
+                    warn!("Root is not a group");
+                    return false;
+                };
+                let (paths, blocked) = split_deletion_blocked(root, self.selection.paths());
+                if !blocked.is_empty() {
+                    let names: Vec<_> = blocked
+                        .iter()
+                        .filter_map(|path| self.world.root.get_at(path))
+                        .map(|node| node_name(node, &self.database))
+                        .collect();
+                    self.notifier.notify(Notice::new(format!(
+                        "Skipped deleting {}; locked against deletion or containing \
+                        locked nodes",
+                        names.join(", ")
+                    )));
+                }
+                if let Some(new_root) = delete_children(root, &paths) {
+                    ctx.link().send_message(Msg::ReplaceRoot {
+                        replacement: new_root.into(),
+                    });
+                }
+                false
+            }
+            Msg::ClearSelection => {
+                if self.selection.is_empty() {
+                    false
+                } else {
+                    self.selection = Default::default();
+                    true
+                }
+            }
+        }
+    }
+
+    fn rendered(&mut self, _ctx: &Context<Self>, _first_render: bool) {
+        if let Some(path) = self.focus.path() {
+            // Re-rendering may have removed the focused element when its node was deleted
+            // or moved, so give focus to whichever node replaced it.
+            if std::mem::take(&mut self.focus_pending) || focus_is_lost() {
+                focus_element(path);
+            }
+        }
+        if let Some(id) = self.scroll_to.take() {
+            match gloo::utils::document().get_element_by_id(&id) {
+                Some(element) => element.scroll_into_view(),
+                None => warn!("Cannot scroll to missing element {id}"),
+            }
+        }
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let link = ctx.link();
+        let replace = link.callback(|(idx, replacement)| {
+            assert!(idx == 0, "Attempting to replace index {} at the root", idx);
+            Msg::ReplaceRoot { replacement }
+        });
+        let set_metadata = link.callback(|(id, meta)| Msg::UpdateMetadata { id, meta });
+        let batch_set_metadata = link.callback(|updates| Msg::BatchUpdateMetadata { updates });
+        let trace_item = link.callback(|(path, item)| Msg::TraceProduction { path, item });
+        let jump_to = link.callback(|path| Msg::JumpTo { path });
+        let jump_to_ancestor = link.batch_callback(|path: Vec<usize>| {
+            vec![
+                Msg::JumpTo { path: path.clone() },
+                Msg::Focus(FocusAction::Set(path)),
+            ]
+        });
+        let jump_to_node = jump_to_node(link);
+        let produced_items = ProducedItems(Rc::new(self.world.root.produced_items()));
+        let chooseworld = if self.overlay_window == OverlayWindow::WorldChooser {
+            link.callback(|_| Msg::SetWindow(OverlayWindow::None))
+        } else {
+            link.callback(|_| Msg::SetWindow(OverlayWindow::WorldChooser))
+        };
+        let undo = link.callback(|_| Msg::Undo);
+        let redo = link.callback(|_| Msg::Redo);
+        let choosedb = if self.overlay_window == OverlayWindow::DatabaseChooser {
+            link.callback(|_| Msg::SetWindow(OverlayWindow::None))
+        } else {
+            link.callback(|_| Msg::SetWindow(OverlayWindow::DatabaseChooser))
+        };
+        let move_node =
+            Callback::from(|_| warn!("Root node tried to ask parent to move one of its children"));
+        let move_group = link.callback(|(moved, into, position)| Msg::MoveGroup {
+            moved,
+            into,
+            position,
+        });
+        let focus_action = link.callback(Msg::Focus);
+        let selection_action = link.callback(|action| match action {
+            SelectionAction::Select { path, range } => Msg::SelectNode { path, range },
+            SelectionAction::MoveTo { dest_path, copy } => Msg::MoveSelection { dest_path, copy },
+        });
+
+        let overview = if self.overlay_window == OverlayWindow::Overview {
+            link.callback(|_| Msg::SetWindow(OverlayWindow::None))
+        } else {
+            link.callback(|_| Msg::SetWindow(OverlayWindow::Overview))
+        };
+
+        let factory_info = if self.overlay_window == OverlayWindow::FactoryInfo {
+            link.callback(|_| Msg::SetWindow(OverlayWindow::None))
+        } else {
+            link.callback(|_| Msg::SetWindow(OverlayWindow::FactoryInfo))
+        };
+
+        let inventory = if self.overlay_window == OverlayWindow::Inventory {
+            link.callback(|_| Msg::SetWindow(OverlayWindow::None))
+        } else {
+            link.callback(|_| Msg::SetWindow(OverlayWindow::Inventory))
+        };
+
+        let targets = if self.overlay_window == OverlayWindow::Targets {
+            link.callback(|_| Msg::SetWindow(OverlayWindow::None))
+        } else {
+            link.callback(|_| Msg::SetWindow(OverlayWindow::Targets))
+        };
+
+        let machine_calculator = if self.overlay_window == OverlayWindow::MachineCalculator {
+            link.callback(|_| Msg::SetWindow(OverlayWindow::None))
+        } else {
+            link.callback(|_| Msg::SetWindow(OverlayWindow::MachineCalculator))
+        };
+
+        let power_planner = if self.overlay_window == OverlayWindow::PowerPlanner {
+            link.callback(|_| Msg::SetWindow(OverlayWindow::None))
+        } else {
+            link.callback(|_| Msg::SetWindow(OverlayWindow::PowerPlanner))
+        };
+
+        let legend = if self.overlay_window == OverlayWindow::Legend {
+            link.callback(|_| Msg::SetWindow(OverlayWindow::None))
+        } else {
+            link.callback(|_| Msg::SetWindow(OverlayWindow::Legend))
+        };
+
+        let share = if self.overlay_window == OverlayWindow::Share {
+            link.callback(|_| Msg::SetWindow(OverlayWindow::None))
+        } else {
+            link.callback(|_| Msg::ShareFactory)
+        };
+
+        let export_factory = link.callback(|_| Msg::ExportFactory);
+        let import_factory = link.batch_callback(|e: Event| {
+            let input: HtmlInputElement = e.target_dyn_into()?;
+            let file = input.files().and_then(|files| files.get(0));
+            // Clear the input so choosing the same file again is still a change.
+            input.set_value("");
+            file.map(|file| Msg::ImportFactory(file.into()))
+        });
+
+        let problems = if self.overlay_window == OverlayWindow::Problems {
+            link.callback(|_| Msg::SetWindow(OverlayWindow::None))
+        } else {
+            link.callback(|_| Msg::SetWindow(OverlayWindow::Problems))
+        };
+
+        let find_item = link.callback(|_| Msg::StartFindItem);
+
+        let settings = if self.overlay_window == OverlayWindow::UserSettings {
+            link.callback(|_| Msg::SetWindow(OverlayWindow::None))
+        } else {
+            link.callback(|_| Msg::SetWindow(OverlayWindow::UserSettings))
+        };
+
+        let hide_empty_balances = self.user_settings.hide_empty_balances;
+        let toggle_empty_balances = link.callback(move |_| Msg::ToggleEmptyBalances {
+            hide_empty_balances: !hide_empty_balances,
+        });
+        let hidden_balances = hide_empty_balances.then_some("hide-empty-balances");
+        html! {
+            <ContextProvider<Rc<Database>> context={Rc::clone(&self.database)}>
+            <ContextProvider<Rc<UserSettings>> context={Rc::clone(&self.user_settings)}>
+            <ContextProvider<NodeMetadata> context={self.world.node_metadata.clone()}>
+            <ContextProvider<Rc<ItemNames>> context={Rc::clone(&self.world.item_names)}>
+            <ContextProvider<FactoryMetadata> context={self.world.factory_metadata.clone()}>
+            <ContextProvider<ProducedItems> context={produced_items}>
+            <ContextProvider<Selection> context={self.selection.clone()}>
+            <ContextProvider<NodeFocus> context={self.focus.clone()}>
+            <ContextProvider<Notifier> context={self.notifier.clone()}>
+            <div class="App">
+                <div class="navbar">
+                    <div class="appheader">{"SATISFACTORY ACCOUNTING"}</div>
+                </div>
+                <div class="menubar">
+                    <span class="section">
+                        <button class="open-world" title="Choose World" onclick={chooseworld}>
+                            <span class="material-icons">{"folder_open"}</span>
+                        </button>
+                        <button class="unredo" title="Undo"
+                            onclick={undo}
+                            disabled={!self.history.can_undo()}>
+                            <span class="material-icons">{"undo"}</span>
+                        </button>
+                        <button class="unredo" title="Redo"
+                            onclick={redo}
+                            disabled={!self.history.can_redo()}>
+                            <span class="material-icons">{"redo"}</span>
+                        </button>
+                        <button class="overview" title="Overview" onclick={overview}>
+                            <span class="material-icons">{"account_tree"}</span>
+                        </button>
+                        <button class="factory-info" title="Factory Info" onclick={factory_info}>
+                            <span class="material-icons">{"info"}</span>
+                        </button>
+                        <button class="share" title="Share Link" onclick={share}>
+                            <span class="material-icons">{"share"}</span>
+                        </button>
+                        <button class="export-factory" title="Export Factory"
+                            onclick={export_factory}>
+                            <span class="material-icons">{"download"}</span>
+                        </button>
+                        <label class="import-factory" title="Import Factory, Replacing the Tree">
+                            <span class="material-icons">{"upload"}</span>
+                            <input type="file" accept=".json,application/json"
+                                onchange={import_factory} />
+                        </label>
+                        <button class="inventory" title="Inventory Check" onclick={inventory}>
+                            <span class="material-icons">{"inventory_2"}</span>
+                        </button>
+                        <button class="targets" title="Target Outputs" onclick={targets}>
+                            <span class="material-icons">{"flag"}</span>
+                        </button>
+                        <button class="machine-calculator" title="Machine Calculator"
+                            onclick={machine_calculator}>
+                            <span class="material-icons">{"calculate"}</span>
+                        </button>
+                        <button class="power-planner" title="Power Planner"
+                            onclick={power_planner}>
+                            <span class="material-icons">{"bolt"}</span>
+                        </button>
+                        <button class="legend" title="Balance Legend" onclick={legend}>
+                            <span class="material-icons">{"help_outline"}</span>
+                        </button>
+                        <button class="problems" title="Problems" onclick={problems}>
+                            <span class="material-icons">{"report_problem"}</span>
+                        </button>
+                        <button class="find-item" title="Find Item" onclick={find_item}>
+                            <span class="material-icons">{"search"}</span>
                         </button>
                         <button class="choose-database" title="Choose Database" onclick={choosedb}>
                             <span class="material-icons">{"factory"}</span>
@@ -806,6 +2048,10 @@ impl Component for App {
                             }
                         </label>
                     </span>
+                    <CollapseControls root={self.world.root.clone()}
+                        collapse_below={link.callback(|depth| Msg::CollapseBelow { depth })} />
+                    { self.item_filter_chips(ctx) }
+                    { self.tag_filter(ctx) }
                     <span class="section">
                         <button class="settings" title="Settings" onclick={settings}>
                             <span class="material-icons">{"settings"}</span>
@@ -818,19 +2064,50 @@ impl Component for App {
                         </a>
                     </span>
                 </div>
-                <div class={classes!("appbody", hidden_balances)}>
+                <div class={classes!("appbody", hidden_balances)}
+                    style={self.user_settings.tree_layout.style()}>
+                    <PowerSummary root={self.world.root.clone()} />
+                    { self.item_trends(ctx) }
+                    <PinnedNodes root={self.world.root.clone()} {jump_to}
+                        set_metadata={set_metadata.clone()} />
+                    { self.selection_bar(ctx) }
                     <NodeDisplay node={self.world.root.clone()}
                         path={Vec::new()}
                         {replace} {set_metadata} {batch_set_metadata}
-                        {move_node} />
+                        {move_node} {move_group} {trace_item} {selection_action} {focus_action}
+                        open_sandbox={link.callback(|node| Msg::OpenSandbox { node })} />
+                    if let Some(path) = self.focus.path() {
+                        <Breadcrumbs root={self.world.root.clone()} path={path.to_vec()}
+                            jump_to={jump_to_ancestor} />
+                    }
                 </div>
+                { self.sandbox_panel(ctx) }
                 { self.world_chooser(ctx) }
                 { self.database_chooser(ctx) }
                 { self.user_settings_window(ctx) }
+                { self.production_trace_window(ctx) }
+                { self.factory_info_window(ctx) }
+                { self.overview_window(ctx) }
+                { self.inventory_window(ctx) }
+                { self.targets_window(ctx) }
+                { self.machine_calculator_window(ctx) }
+                { self.power_planner_window(ctx) }
+                { self.legend_window(ctx) }
+                { self.rebuild_report_window(ctx) }
+                { self.problems_window(ctx) }
+                { self.share_window(ctx) }
                 if let Some(pending) = self.pending_delete {
                     { self.confirm_delete(ctx, pending) }
                 }
+                <Notifications notices={self.notices.shown().to_vec()}
+                    dismiss={link.callback(Msg::DismissNotice)} jump_to={jump_to_node} />
             </div>
+            </ContextProvider<Notifier>>
+            </ContextProvider<NodeFocus>>
+            </ContextProvider<Selection>>
+            </ContextProvider<ProducedItems>>
+            </ContextProvider<FactoryMetadata>>
+            </ContextProvider<Rc<ItemNames>>>
             </ContextProvider<NodeMetadata>>
             </ContextProvider<Rc<UserSettings>>>
             </ContextProvider<Rc<Database>>>
@@ -904,7 +2181,32 @@ impl App {
         let close = link.callback(|_| Msg::SetWindow(OverlayWindow::None));
         let show_deprecated = self.show_deprecated_databases;
         let toggle_deprecated = link.callback(move |_| Msg::ShowDeprecated(!show_deprecated));
+        let rebuild_all = link.callback(|_| Msg::RebuildAll);
+        let upload_db = link.batch_callback(|e: Event| {
+            let input: HtmlInputElement = e.target_dyn_into()?;
+            let file = input.files().and_then(|files| files.get(0));
+            // Clear the input so choosing the same file again is still a change.
+            input.set_value("");
+            file.map(|file| Msg::UploadDb(file.into()))
+        });
 
+        let custom = match &self.world.database {
+            DatabaseChoice::Custom(db) => Some(html! {
+                <div class="database-list-row">
+                    <div class="version-namedesc">
+                        <span class="version-name">{"Custom"}</span>
+                        <span class="version-description">{format!(
+                            "Uploaded database with {} buildings, {} recipes, and {} items.",
+                            db.buildings.len(),
+                            db.recipes.len(),
+                            db.items.len(),
+                        )}</span>
+                    </div>
+                    <span class="material-icons">{"radio_button_checked"}</span>
+                </div>
+            }),
+            DatabaseChoice::Standard(_) => None,
+        };
         let databases = DatabaseVersion::ALL
             .iter()
             .filter(|version| show_deprecated || !version.is_deprecated())
@@ -942,6 +2244,15 @@ impl App {
                 <div class="close-bar">
                     <h3>{"Choose Database"}</h3>
                     <span class="right-buttons">
+                        <label class="upload-db" title="Upload a Custom Database">
+                            <span>{"Upload"}</span>
+                            <span class="material-icons">{"upload_file"}</span>
+                            <input type="file" accept=".json,application/json" onchange={upload_db} />
+                        </label>
+                        <button class="rebuild-all" title="Rebuild All Nodes" onclick={rebuild_all}>
+                            <span>{"Rebuild All"}</span>
+                            <span class="material-icons">{"autorenew"}</span>
+                        </button>
                         <button class="show-deprecated" title="Show Deprecated Versions" onclick={toggle_deprecated}>
                             <span>{"Deprecated Versions"}</span>
                             <span class="material-icons">{
@@ -957,52 +2268,690 @@ impl App {
                         </button>
                     </span>
                 </div>
+                if let Some(error) = &self.custom_db_error {
+                    <div class="custom-db-error">{error}</div>
+                }
                 <div class="database-list">
+                    { for custom }
                     { for databases }
                 </div>
             </div>
         }
     }
 
-    fn confirm_delete(&self, ctx: &Context<Self>, id: WorldId) -> Html {
+    /// Display the most recent production trace.
+    fn production_trace_window(&self, ctx: &Context<Self>) -> Html {
         let link = ctx.link();
-        let cancel = link.callback(|_| Msg::CancelDelete);
-        let delete = link.callback(move |_| Msg::DeleteForever(id));
-
-        let name = match self.worlds.worlds.get(&id) {
-            Some(meta) if !meta.name.is_empty() => &meta.name,
-            Some(_) => "<Empty Name>",
-            None => "<Not Found>",
+        let close = link.callback(|_| Msg::SetWindow(OverlayWindow::None));
+        let hidden = match self.overlay_window {
+            OverlayWindow::ProductionTrace => None,
+            _ => Some("hide"),
         };
+        let jump_to = link.callback(|path| Msg::JumpTo { path });
+        let chooser = self.finding_item.then(|| {
+            // Any item used anywhere in the factory has an entry in the root's balance.
+            let choices: Vec<_> = self
+                .world
+                .root
+                .balance()
+                .balances
+                .keys()
+                .map(|&item| {
+                    let (name, image) =
+                        item_label(self.database.get(item), item, &self.world.item_names);
+                    Choice {
+                        id: item,
+                        name,
+                        image: html! { <Icon icon={image} /> },
+                        featured: None,
+                        group: None,
+                    }
+                })
+                .collect();
+            let selected = link.callback(|item| Msg::FindItem { item });
+            let cancelled = link.callback(|()| Msg::CancelFindItem);
+            html! {
+                <div class="find-item">
+                    <ChooseFromList<ItemId> {choices} {selected} {cancelled} />
+                </div>
+            }
+        });
         html! {
-            <div class="overlay-delete-window">
-                <h2>{"Are you sure you want to delete World "}{name}</h2>
-                <h3>{"This CANNOT be undone!"}</h3>
-                <div class="button-row">
-                    <button class="cancel" title="Cancel" onclick={cancel}>
-                        <span>{"Cancel"}</span>
-                        <span class="material-icons">{"arrow_back"}</span>
-                    </button>
-                    <button class="delete-forever" title="Delete Forever" onclick={delete}>
-                        <span>{"Delete"}</span>
-                        <span class="material-icons">{"delete_forever"}</span>
+            <div class={classes!("overlay-window", hidden)}>
+                <div class="close-bar">
+                    <h3>{"Production Trace"}</h3>
+                    <button class="close" title="Close" onclick={close}>
+                        <span class="material-icons">{"close"}</span>
                     </button>
                 </div>
+                <div class="trace-list">
+                    { chooser }
+                    if let Some(trace) = &self.production_trace {
+                        <ProductionTraceView trace={trace.clone()} />
+                    }
+                    if let Some(report) = &self.item_report {
+                        <ItemReportView report={report.clone()} {jump_to} />
+                    }
+                </div>
             </div>
         }
     }
 
-    /// Display the user settings window. This is always displayed and is hidden in CSS
-    /// when not needed.
-    fn user_settings_window(&self, ctx: &Context<Self>) -> Html {
+    /// Chips for choosing which items are shown in balances.
+    fn item_filter_chips(&self, ctx: &Context<Self>) -> Html {
         let link = ctx.link();
-        let close = link.callback(|_| Msg::SetWindow(OverlayWindow::None));
-
+        let settings = &self.user_settings;
+        let chip = |filter: Option<ItemFilter>| {
+            let selected = (settings.item_filter == filter).then_some("selected");
+            let name = match filter {
+                Some(filter) => filter.name(&settings.item_groups),
+                None => "All Items".into(),
+            };
+            let onclick = link.callback(move |_| Msg::SetItemFilter {
+                item_filter: filter,
+            });
+            html! {
+                <button class={classes!("filter-chip", selected)} {onclick}>{name}</button>
+            }
+        };
+        let custom = settings
+            .item_groups
+            .iter()
+            .map(|group| chip(Some(ItemFilter::Custom(group.id))));
+        html! {
+            <span class="section item-filters" title="Filter Balances">
+                { chip(None) }
+                { for ItemFilter::BUILTIN.iter().map(|&filter| chip(Some(filter))) }
+                { for custom }
+            </span>
+        }
+    }
+
+    /// Search box for highlighting the nodes with a particular tag.
+    fn tag_filter(&self, ctx: &Context<Self>) -> Html {
+        let oninput = ctx.link().callback(|e: InputEvent| Msg::SetTagFilter {
+            tag_filter: get_value_from_input_event(e),
+        });
+        html! {
+            <label class="section tag-filter" title="Search by Tag">
+                <span class="material-icons-outlined">{"sell"}</span>
+                <input type="search" placeholder="Search tags"
+                    value={self.user_settings.tag_filter.clone()} {oninput} />
+            </label>
+        }
+    }
+
+    fn overview_window(&self, ctx: &Context<Self>) -> Html {
+        let link = ctx.link();
+        let close = link.callback(|_| Msg::SetWindow(OverlayWindow::None));
+        let jump_to = link.callback(|path| Msg::JumpTo { path });
+        let hidden = match self.overlay_window {
+            OverlayWindow::Overview => None,
+            _ => Some("hide"),
+        };
+        html! {
+            <div class={classes!("overlay-window", hidden)}>
+                <div class="close-bar">
+                    <h3>{"Overview"}</h3>
+                    <button class="close" title="Close" onclick={close}>
+                        <span class="material-icons">{"close"}</span>
+                    </button>
+                </div>
+                <div class="overview-list">
+                    if self.overlay_window == OverlayWindow::Overview {
+                        <PowerChart root={self.world.root.clone()} />
+                        <Overview root={self.world.root.clone()} {jump_to} />
+                    }
+                </div>
+            </div>
+        }
+    }
+
+    fn factory_info_window(&self, ctx: &Context<Self>) -> Html {
+        let link = ctx.link();
+        let close = link.callback(|_| Msg::SetWindow(OverlayWindow::None));
+        let metadata = &self.world.factory_metadata;
+        let field =
+            |label: &'static str, value: &AttrValue, set: fn(&mut FactoryMetadata, AttrValue)| {
+                let metadata = metadata.clone();
+                let on_commit = link.callback(move |value| {
+                    let mut metadata = metadata.clone();
+                    set(&mut metadata, value);
+                    Msg::SetFactoryMetadata(metadata)
+                });
+                html! {
+                    <div class="info-row">
+                        <span class="info-label">{label}</span>
+                        <ClickEdit value={value.clone()} title={label} {on_commit} />
+                    </div>
+                }
+            };
+        let hidden = match self.overlay_window {
+            OverlayWindow::FactoryInfo => None,
+            _ => Some("hide"),
+        };
+        html! {
+            <div class={classes!("overlay-window", "factory-info", hidden)}>
+                <div class="close-bar">
+                    <h3>{"Factory Info"}</h3>
+                    <button class="close" title="Close" onclick={close}>
+                        <span class="material-icons">{"close"}</span>
+                    </button>
+                </div>
+                <div class="info-list">
+                    { field("Title", &metadata.title, |m, v| m.title = v) }
+                    { field("Author", &metadata.author, |m, v| m.author = v) }
+                    { field("Description", &metadata.description, |m, v| m.description = v) }
+                    { field("Game Version", &metadata.game_version, |m, v| m.game_version = v) }
+                </div>
+            </div>
+        }
+    }
+
+    /// Display the inventory check window, comparing the consumables the factory needs with
+    /// those the user owns. This is always displayed and is hidden in CSS when not needed.
+    fn inventory_window(&self, ctx: &Context<Self>) -> Html {
+        let link = ctx.link();
+        let close = link.callback(|_| Msg::SetWindow(OverlayWindow::None));
+        let inventory = self.world.inventory;
+        let consumable =
+            |name: &'static str, owned: u32, required: u32, set: fn(&mut Inventory, u32)| {
+                let set_owned = link.batch_callback(move |text: AttrValue| {
+                    let mut inventory = inventory;
+                    set(&mut inventory, text.trim().parse().ok()?);
+                    Some(Msg::SetInventory(inventory))
+                });
+                let (class, status) = if required > owned {
+                    ("shortfall", format!("Short by {}", required - owned))
+                } else {
+                    ("surplus", format!("{} spare", owned - required))
+                };
+                html! {
+                    <>
+                        <h4>{name}</h4>
+                        <div class="info-row">
+                            <span class="info-label">{"Owned"}</span>
+                            <ClickEdit value={AttrValue::from(owned.to_string())}
+                                title={format!("{name} Owned")} on_commit={set_owned} />
+                        </div>
+                        <div class="info-row">
+                            <span class="info-label">{"Required"}</span>
+                            <span>{required}</span>
+                        </div>
+                        <div class={classes!("info-row", "inventory-status", class)}>{status}</div>
+                    </>
+                }
+            };
+        let hidden = match self.overlay_window {
+            OverlayWindow::Inventory => None,
+            _ => Some("hide"),
+        };
+        html! {
+            <div class={classes!("overlay-window", "inventory-check", hidden)}>
+                <div class="close-bar">
+                    <h3>{"Inventory Check"}</h3>
+                    <button class="close" title="Close" onclick={close}>
+                        <span class="material-icons">{"close"}</span>
+                    </button>
+                </div>
+                <div class="info-list">
+                    { consumable("Power Shards", inventory.power_shards,
+                        self.world.root.power_shards(), |i, n| i.power_shards = n) }
+                    { consumable("Somersloops", inventory.somersloops,
+                        self.world.root.somersloops(), |i, n| i.somersloops = n) }
+                </div>
+            </div>
+        }
+    }
+
+    /// Display the target outputs window, where the user enters a bill of materials and
+    /// sees how the factory's net outputs compare to it.
+    fn targets_window(&self, ctx: &Context<Self>) -> Html {
+        let link = ctx.link();
+        let close = link.callback(|_| Msg::SetWindow(OverlayWindow::None));
+        let onchange = link.callback(|e: Event| Msg::SetBom(get_value_from_textarea_change(e)));
+        let hidden = match self.overlay_window {
+            OverlayWindow::Targets => None,
+            _ => Some("hide"),
+        };
+        html! {
+            <div class={classes!("overlay-window", "targets", hidden)}>
+                <div class="close-bar">
+                    <h3>{"Target Outputs"}</h3>
+                    <button class="close" title="Close" onclick={close}>
+                        <span class="material-icons">{"close"}</span>
+                    </button>
+                </div>
+                <div class="info-list">
+                    <h4>{"Bill of Materials"}</h4>
+                    <p>{"One \"Item Name: rate per minute\" entry per line."}</p>
+                    <textarea class="bom-input" rows="6" {onchange}
+                        value={self.world.bom.format(&self.database)} />
+                    if let Some(error) = &self.bom_error {
+                        <div class="bom-error">{error}</div>
+                    }
+                    if !self.world.bom.is_empty() {
+                        <TargetScorecard bom={self.world.bom.clone()}
+                            root={self.world.root.clone()} />
+                    }
+                </div>
+            </div>
+        }
+    }
+
+    /// Display the calculator for how many buildings are needed to make a target rate of an
+    /// item.
+    fn machine_calculator_window(&self, ctx: &Context<Self>) -> Html {
+        let link = ctx.link();
+        let close = link.callback(|_| Msg::SetWindow(OverlayWindow::None));
+        let add = link.callback(Msg::AddCalculatedNode);
+        let hidden = match self.overlay_window {
+            OverlayWindow::MachineCalculator => None,
+            _ => Some("hide"),
+        };
+        html! {
+            <div class={classes!("overlay-window", "machine-calculator", hidden)}>
+                <div class="close-bar">
+                    <h3>{"Machine Calculator"}</h3>
+                    <button class="close" title="Close" onclick={close}>
+                        <span class="material-icons">{"close"}</span>
+                    </button>
+                </div>
+                <div class="info-list">
+                    if self.overlay_window == OverlayWindow::MachineCalculator {
+                        <MachineCalculator {add} />
+                    }
+                </div>
+            </div>
+        }
+    }
+
+    /// Display the planner for generators to meet a power target.
+    fn power_planner_window(&self, ctx: &Context<Self>) -> Html {
+        let link = ctx.link();
+        let close = link.callback(|_| Msg::SetWindow(OverlayWindow::None));
+        let add = link.callback(Msg::AddCalculatedNode);
+        let hidden = match self.overlay_window {
+            OverlayWindow::PowerPlanner => None,
+            _ => Some("hide"),
+        };
+        html! {
+            <div class={classes!("overlay-window", "power-planner", hidden)}>
+                <div class="close-bar">
+                    <h3>{"Power Planner"}</h3>
+                    <button class="close" title="Close" onclick={close}>
+                        <span class="material-icons">{"close"}</span>
+                    </button>
+                </div>
+                <div class="info-list">
+                    if self.overlay_window == OverlayWindow::PowerPlanner {
+                        <PowerPlanner {add} />
+                    }
+                </div>
+            </div>
+        }
+    }
+
+    /// Display the key to the colors and icons used in balances.
+    fn legend_window(&self, ctx: &Context<Self>) -> Html {
+        let link = ctx.link();
+        let close = link.callback(|_| Msg::SetWindow(OverlayWindow::None));
+        let hidden = match self.overlay_window {
+            OverlayWindow::Legend => None,
+            _ => Some("hide"),
+        };
+        html! {
+            <div class={classes!("overlay-window", "legend", hidden)}>
+                <div class="close-bar">
+                    <h3>{"Balance Legend"}</h3>
+                    <button class="close" title="Close" onclick={close}>
+                        <span class="material-icons">{"close"}</span>
+                    </button>
+                </div>
+                <div class="info-list">
+                    if self.overlay_window == OverlayWindow::Legend {
+                        <BalanceLegend />
+                    }
+                </div>
+            </div>
+        }
+    }
+
+    /// Display trends of the watched items over the undo history, if enabled.
+    fn item_trends(&self, ctx: &Context<Self>) -> Html {
+        if !self.user_settings.show_trends {
+            return html! {};
+        }
+        let roots: Vec<&Node> = self
+            .history
+            .past()
+            .map(|state| &state.root)
+            .chain([&self.world.root])
+            .collect();
+        let series: Vec<_> = self
+            .user_settings
+            .watched_items
+            .iter()
+            .map(|&item| (item, trend_series(&roots, item)))
+            .collect();
+        let set_watched = ctx
+            .link()
+            .callback(|watched_items| Msg::SetWatchedItems { watched_items });
+        html! {
+            <ItemTrends root={self.world.root.clone()} {series} {set_watched} />
+        }
+    }
+
+    /// Display the items the whole factory doesn't balance.
+    fn problems_window(&self, ctx: &Context<Self>) -> Html {
+        let link = ctx.link();
+        let close = link.callback(|_| Msg::SetWindow(OverlayWindow::None));
+        let find_item = link.callback(|item| Msg::FindItem { item });
+        let jump_to = jump_to_node(link);
+        let hidden = match self.overlay_window {
+            OverlayWindow::Problems => None,
+            _ => Some("hide"),
+        };
+        html! {
+            <div class={classes!("overlay-window", hidden)}>
+                <div class="close-bar">
+                    <h3>{"Problems"}</h3>
+                    <button class="close" title="Close" onclick={close}>
+                        <span class="material-icons">{"close"}</span>
+                    </button>
+                </div>
+                <div class="info-list">
+                    if self.overlay_window == OverlayWindow::Problems {
+                        <Problems root={self.world.root.clone()} {find_item} {jump_to} />
+                    }
+                </div>
+            </div>
+        }
+    }
+
+    /// Display a link which encodes the whole factory. This is always displayed and is
+    /// hidden in CSS when not needed.
+    fn share_window(&self, ctx: &Context<Self>) -> Html {
+        let link = ctx.link();
+        let close = link.callback(|_| Msg::SetWindow(OverlayWindow::None));
+        let export = link.callback(|_| Msg::ExportFactory);
+        let select_all = Callback::from(|e: FocusEvent| {
+            if let Some(input) = e.target_dyn_into::<HtmlInputElement>() {
+                input.select();
+            }
+        });
+        let hidden = match self.overlay_window {
+            OverlayWindow::Share => None,
+            _ => Some("hide"),
+        };
+        html! {
+            <div class={classes!("overlay-window", "share-window", hidden)}>
+                <div class="close-bar">
+                    <h3>{"Share Link"}</h3>
+                    <button class="close" title="Close" onclick={close}>
+                        <span class="material-icons">{"close"}</span>
+                    </button>
+                </div>
+                <div class="info-list">
+                    if let Some(share_link) = &self.share_link {
+                        <p>{"Anyone who opens this link gets a copy of the factory as a new world."}</p>
+                        <input class="share-link" type="text" readonly=true
+                            value={share_link.clone()} onfocus={select_all} />
+                        if share_link.len() > MAX_SHARE_URL_LEN {
+                            <div class="share-warning">
+                                <span class="material-icons">{"warning"}</span>
+                                <span>
+                                    {format!(
+                                        "This link is {} characters long, which some \
+                                        browsers and chat apps cut short. Consider \
+                                        exporting the factory to a file instead.",
+                                        share_link.len(),
+                                    )}
+                                </span>
+                                <button class="export" title="Export as Factory"
+                                    onclick={export}>
+                                    <span class="material-icons">{"download"}</span>
+                                </button>
+                            </div>
+                        }
+                    } else {
+                        <p>{"Unable to build a link for this factory."}</p>
+                    }
+                </div>
+            </div>
+        }
+    }
+
+    /// Display the number of selected nodes and the actions which apply to all of them, if
+    /// any are selected.
+    fn selection_bar(&self, ctx: &Context<Self>) -> Html {
+        if self.selection.is_empty() {
+            return html! {};
+        }
+        let link = ctx.link();
+        let duplicate = link.callback(|_| Msg::DuplicateSelection);
+        let delete = link.callback(|_| Msg::DeleteSelection);
+        let clear = link.callback(|_| Msg::ClearSelection);
+        let count = self.selection.len();
+        html! {
+            <div class="selection-bar">
+                <span class="selection-count">
+                    {count}{if count == 1 { " node selected" } else { " nodes selected" }}
+                </span>
+                <button class="copy" title="Duplicate Selected" onclick={duplicate}>
+                    <span class="material-icons">{"content_copy"}</span>
+                </button>
+                <button class="delete" title="Delete Selected" onclick={delete}>
+                    <span class="material-icons">{"delete"}</span>
+                </button>
+                <button class="clear" title="Clear Selection" onclick={clear}>
+                    <span class="material-icons">{"close"}</span>
+                </button>
+            </div>
+        }
+    }
+
+    /// Display the sandbox holding a copy of a group being experimented on, if one is
+    /// open.
+    fn sandbox_panel(&self, ctx: &Context<Self>) -> Html {
+        let Some(sandbox) = &self.sandbox else {
+            return html! {};
+        };
+        let link = ctx.link();
+        let replace = link.callback(|(_, replacement)| Msg::UpdateSandbox { replacement });
+        let move_node = Callback::from(|_| {
+            warn!("Sandbox root tried to ask parent to move one of its children")
+        });
+        let move_group = {
+            let root = sandbox.root().clone();
+            link.batch_callback(move |(moved, into, position)| {
+                let new_root = move_group_by_id(root.group()?, moved, into, position)?;
+                Some(Msg::UpdateSandbox {
+                    replacement: new_root.into(),
+                })
+            })
+        };
+        let set_metadata = link.callback(|(id, meta)| Msg::UpdateMetadata { id, meta });
+        let batch_set_metadata = link.callback(|updates| Msg::BatchUpdateMetadata { updates });
+        // Traces, the selection, and keyboard focus are tracked by path in the factory, so
+        // they can't reach into the sandbox.
+        let trace_item = {
+            let notifier = self.notifier.clone();
+            Callback::from(move |_| {
+                notifier.notify(Notice::new(
+                    "Production can't be traced in the sandbox until it is merged.",
+                ))
+            })
+        };
+        let selection_action = Callback::noop();
+        let focus_action = Callback::noop();
+        // Notices from the sandbox can't be jumped to for the same reason.
+        let notifier = Notifier::new(link.callback(|notice: Notice| {
+            Msg::Notify(Notice {
+                path: None,
+                ..notice
+            })
+        }));
+        let merge = link.callback(|_| Msg::MergeSandbox);
+        let discard = link.callback(|_| Msg::DiscardSandbox);
+        let hidden_balances = self
+            .user_settings
+            .hide_empty_balances
+            .then_some("hide-empty-balances");
+        html! {
+            <div class={classes!("SandboxPanel", hidden_balances)}
+                style={self.user_settings.tree_layout.style()}>
+                <div class="sandbox-header">
+                    <h3>{"Sandbox"}</h3>
+                    <button class="merge" title="Replace the Original Group with the Sandbox"
+                        onclick={merge}>
+                        <span class="material-icons">{"call_merge"}</span>
+                    </button>
+                    <button class="discard" title="Discard Sandbox" onclick={discard}>
+                        <span class="material-icons">{"delete"}</span>
+                    </button>
+                </div>
+                <p class="sandbox-hint">
+                    {"Changes here don't affect the factory until the sandbox is merged."}
+                </p>
+                <ContextProvider<Selection> context={Selection::default()}>
+                <ContextProvider<NodeFocus> context={NodeFocus::default()}>
+                <ContextProvider<Notifier> context={notifier}>
+                <ContextProvider<NodeTree> context={NodeTree::Sandbox}>
+                    <NodeDisplay node={sandbox.root().clone()} path={Vec::new()}
+                        {replace} {set_metadata} {batch_set_metadata}
+                        {move_node} {move_group} {trace_item} {selection_action} {focus_action} />
+                </ContextProvider<NodeTree>>
+                </ContextProvider<Notifier>>
+                </ContextProvider<NodeFocus>>
+                </ContextProvider<Selection>>
+            </div>
+        }
+    }
+
+    /// Display the buildings which failed to build when all nodes were last rebuilt.
+    fn rebuild_report_window(&self, ctx: &Context<Self>) -> Html {
+        let link = ctx.link();
+        let close = link.callback(|_| Msg::SetWindow(OverlayWindow::None));
+        let hidden = match self.overlay_window {
+            OverlayWindow::RebuildReport => None,
+            _ => Some("hide"),
+        };
+        let errors = self.rebuild_errors.iter().map(|error| {
+            // Buildings aren't shown separately, so jump to the group containing it.
+            let parent = error.path[..error.path.len().saturating_sub(1)].to_vec();
+            let group_name = self
+                .world
+                .root
+                .get_at(&parent)
+                .and_then(|node| node.group())
+                .map(|group| group.name.clone())
+                .unwrap_or_default();
+            let jump = link.callback(move |_| Msg::JumpTo {
+                path: parent.clone(),
+            });
+            html! {
+                <div class="rebuild-error">
+                    <span class="rebuild-group">{group_name}</span>
+                    <span class="rebuild-message">{error.error.to_string()}</span>
+                    <button class="jump" title="Jump to Group" onclick={jump}>
+                        <span class="material-icons">{"arrow_forward"}</span>
+                    </button>
+                </div>
+            }
+        });
+        html! {
+            <div class={classes!("overlay-window", hidden)}>
+                <div class="close-bar">
+                    <h3>{"Rebuild Report"}</h3>
+                    <button class="close" title="Close" onclick={close}>
+                        <span class="material-icons">{"close"}</span>
+                    </button>
+                </div>
+                <div class="info-list">
+                    if self.rebuild_errors.is_empty() {
+                        <p>{"Every building was rebuilt without errors."}</p>
+                    } else {
+                        <p>{match self.rebuild_errors.len() {
+                            1 => "1 building failed to build.".to_string(),
+                            n => format!("{n} buildings failed to build."),
+                        }}</p>
+                        { for errors }
+                    }
+                </div>
+            </div>
+        }
+    }
+
+    fn confirm_delete(&self, ctx: &Context<Self>, id: WorldId) -> Html {
+        let link = ctx.link();
+        let cancel = link.callback(|_| Msg::CancelDelete);
+        let delete = link.callback(move |_| Msg::DeleteForever(id));
+
+        let name = match self.worlds.worlds.get(&id) {
+            Some(meta) if !meta.name.is_empty() => &meta.name,
+            Some(_) => "<Empty Name>",
+            None => "<Not Found>",
+        };
+        html! {
+            <div class="overlay-delete-window">
+                <h2>{"Are you sure you want to delete World "}{name}</h2>
+                <h3>{"This CANNOT be undone!"}</h3>
+                <div class="button-row">
+                    <button class="cancel" title="Cancel" onclick={cancel}>
+                        <span>{"Cancel"}</span>
+                        <span class="material-icons">{"arrow_back"}</span>
+                    </button>
+                    <button class="delete-forever" title="Delete Forever" onclick={delete}>
+                        <span>{"Delete"}</span>
+                        <span class="material-icons">{"delete_forever"}</span>
+                    </button>
+                </div>
+            </div>
+        }
+    }
+
+    /// Display the user settings window. This is always displayed and is hidden in CSS
+    /// when not needed.
+    fn user_settings_window(&self, ctx: &Context<Self>) -> Html {
+        let link = ctx.link();
+        let close = link.callback(|_| Msg::SetWindow(OverlayWindow::None));
+
         let hide_empty_balances = self.user_settings.hide_empty_balances;
         let toggle_empty_balances = link.callback(move |_| Msg::ToggleEmptyBalances {
             hide_empty_balances: !hide_empty_balances,
         });
 
+        let color_by_category = self.user_settings.color_by_category;
+        let toggle_category_colors = link.callback(move |_| Msg::ToggleCategoryColors {
+            color_by_category: !color_by_category,
+        });
+
+        let show_ratios = self.user_settings.show_ratios;
+        let toggle_ratios = link.callback(move |_| Msg::ToggleRatios {
+            show_ratios: !show_ratios,
+        });
+
+        let show_cancelled_balances = self.user_settings.show_cancelled_balances;
+        let toggle_cancelled_balances = link.callback(move |_| Msg::ToggleCancelledBalances {
+            show_cancelled_balances: !show_cancelled_balances,
+        });
+
+        let show_trends = self.user_settings.show_trends;
+        let toggle_trends = link.callback(move |_| Msg::ToggleTrends {
+            show_trends: !show_trends,
+        });
+
+        let hide_power = self.user_settings.hide_power;
+        let toggle_hide_power = link.callback(move |_| Msg::ToggleHidePower {
+            hide_power: !hide_power,
+        });
+
+        let auto_collapse_balanced = self.user_settings.auto_collapse_balanced;
+        let toggle_auto_collapse = link.callback(move |_| Msg::ToggleAutoCollapse {
+            auto_collapse_balanced: !auto_collapse_balanced,
+        });
+
         let sort_by_item = link.callback(move |_| Msg::SetBalanceSortMode {
             sort_mode: BalanceSortMode::Item,
         });
@@ -1011,6 +2960,171 @@ impl App {
             sort_mode: BalanceSortMode::IOItem,
         });
 
+        let clock_rounding = self.user_settings.clock_rounding;
+        let rounding_option = |rounding: ClockRounding, label: &'static str| {
+            let onclick = link.callback(move |_| Msg::SetClockRounding {
+                clock_rounding: rounding,
+            });
+            html! {
+                <span class="setting-row toggle" {onclick}>
+                    <span>{label}</span>
+                    <span class="material-icons">{
+                        if clock_rounding == rounding {
+                            "radio_button_checked"
+                        } else {
+                            "radio_button_unchecked"
+                        }
+                    }</span>
+                </span>
+            }
+        };
+
+        let decimal_separator = self.user_settings.decimal_separator;
+        let separator_option = |separator: DecimalSeparator, label: &'static str| {
+            let onclick = link.callback(move |_| Msg::SetDecimalSeparator {
+                decimal_separator: separator,
+            });
+            html! {
+                <span class="setting-row toggle" {onclick}>
+                    <span>{label}</span>
+                    <span class="material-icons">{
+                        if decimal_separator == separator {
+                            "radio_button_checked"
+                        } else {
+                            "radio_button_unchecked"
+                        }
+                    }</span>
+                </span>
+            }
+        };
+
+        let clock_step = self.user_settings.clock_step;
+        let step_option = |label: &'static str, value: f32, set: fn(&mut ClockStep, f32)| {
+            let on_commit = link.batch_callback(move |text: AttrValue| {
+                let value =
+                    parse_clock_speed(&text, decimal_separator).filter(|&value| value > 0.0)?;
+                let mut clock_step = clock_step;
+                set(&mut clock_step, value);
+                Some(Msg::SetClockStep { clock_step })
+            });
+            html! {
+                <div class="setting-row">
+                    <span>{label}</span>
+                    <ClickEdit value={AttrValue::from(value.to_string())} title={label}
+                        {on_commit} />
+                </div>
+            }
+        };
+
+        let tree_layout = self.user_settings.tree_layout;
+        let layout_option = |label: &'static str, value: u32, set: fn(&mut TreeLayout, u32)| {
+            let on_commit = link.batch_callback(move |text: AttrValue| {
+                let value = text.trim().parse::<u32>().ok()?.min(TreeLayout::MAX);
+                let mut tree_layout = tree_layout;
+                set(&mut tree_layout, value);
+                Some(Msg::SetTreeLayout { tree_layout })
+            });
+            html! {
+                <div class="setting-row">
+                    <span>{label}</span>
+                    <ClickEdit value={AttrValue::from(value.to_string())} title={label}
+                        {on_commit} />
+                </div>
+            }
+        };
+
+        let undo_depth = self.user_settings.undo_depth;
+        let on_undo_depth = link.batch_callback(|text: AttrValue| {
+            let undo_depth = HistoryDepth(text.trim().parse::<usize>().ok()?);
+            Some(Msg::SetUndoDepth { undo_depth })
+        });
+
+        let rate_unit = self.user_settings.rate_unit;
+        let rate_unit_option = |unit: RateUnit, label: &'static str| {
+            let onclick = link.callback(move |_| Msg::SetRateUnit { rate_unit: unit });
+            html! {
+                <span class="setting-row toggle" {onclick}>
+                    <span>{label}</span>
+                    <span class="material-icons">{
+                        if rate_unit == unit {
+                            "radio_button_checked"
+                        } else {
+                            "radio_button_unchecked"
+                        }
+                    }</span>
+                </span>
+            }
+        };
+
+        let balance_precision = self.user_settings.balance_precision;
+        let on_balance_precision = link.batch_callback(|text: AttrValue| {
+            let places = text.trim().parse::<u8>().ok()?;
+            let balance_precision = Precision(places.min(Precision::MAX));
+            Some(Msg::SetBalancePrecision { balance_precision })
+        });
+
+        let belt_tier = self.user_settings.belt_tier;
+        let belt_options = BeltTier::ALL.iter().map(|&tier| {
+            let onclick = link.callback(move |_| Msg::SetBeltTier { belt_tier: tier });
+            html! {
+                <span class="setting-row toggle" {onclick}>
+                    <span>{format!(
+                        "{} ({}{})",
+                        tier.name(),
+                        rate_unit.convert(tier.throughput()),
+                        rate_unit.suffix()
+                    )}</span>
+                    <span class="material-icons">{
+                        if belt_tier == tier {
+                            "radio_button_checked"
+                        } else {
+                            "radio_button_unchecked"
+                        }
+                    }</span>
+                </span>
+            }
+        });
+
+        let pipe_tier = self.user_settings.pipe_tier;
+        let pipe_options = PipeTier::ALL.iter().map(|&tier| {
+            let onclick = link.callback(move |_| Msg::SetPipeTier { pipe_tier: tier });
+            html! {
+                <span class="setting-row toggle" {onclick}>
+                    <span>{format!(
+                        "{} ({}{})",
+                        tier.name(),
+                        rate_unit.convert(tier.throughput()),
+                        rate_unit.suffix()
+                    )}</span>
+                    <span class="material-icons">{
+                        if pipe_tier == tier {
+                            "radio_button_checked"
+                        } else {
+                            "radio_button_unchecked"
+                        }
+                    }</span>
+                </span>
+            }
+        });
+
+        let efficiency_target: AttrValue = match self.user_settings.efficiency_target {
+            Some(target) => target.to_string().into(),
+            None => "".into(),
+        };
+        let set_efficiency_target = link.batch_callback(move |text: AttrValue| {
+            if text.trim().is_empty() {
+                Some(Msg::SetEfficiencyTarget {
+                    efficiency_target: None,
+                })
+            } else {
+                parse_clock_speed(&text, decimal_separator)
+                    .filter(|&target| target > 0.0)
+                    .map(|target| Msg::SetEfficiencyTarget {
+                        efficiency_target: Some(target),
+                    })
+            }
+        });
+
         let hidden = match self.overlay_window {
             OverlayWindow::UserSettings => None,
             _ => Some("hide"),
@@ -1034,6 +3148,66 @@ impl App {
                             }
                         }</span>
                     </span>
+                    <span class="setting-row toggle" onclick={toggle_category_colors}>
+                        <span>{"Color Buildings by Category"}</span>
+                        <span class="material-icons">{
+                            if color_by_category {
+                                "check_box"
+                            } else {
+                                "check_box_outline_blank"
+                            }
+                        }</span>
+                    </span>
+                    <span class="setting-row toggle" onclick={toggle_ratios}>
+                        <span>{"Show Item Ratios in Groups"}</span>
+                        <span class="material-icons">{
+                            if show_ratios {
+                                "check_box"
+                            } else {
+                                "check_box_outline_blank"
+                            }
+                        }</span>
+                    </span>
+                    <span class="setting-row toggle" onclick={toggle_cancelled_balances}>
+                        <span>{"Show Items Which Cancel Out in Groups"}</span>
+                        <span class="material-icons">{
+                            if show_cancelled_balances {
+                                "check_box"
+                            } else {
+                                "check_box_outline_blank"
+                            }
+                        }</span>
+                    </span>
+                    <span class="setting-row toggle" onclick={toggle_trends}>
+                        <span>{"Show Trends of Watched Items"}</span>
+                        <span class="material-icons">{
+                            if show_trends {
+                                "check_box"
+                            } else {
+                                "check_box_outline_blank"
+                            }
+                        }</span>
+                    </span>
+                    <span class="setting-row toggle" onclick={toggle_hide_power}>
+                        <span>{"Hide Power in Balances"}</span>
+                        <span class="material-icons">{
+                            if hide_power {
+                                "check_box"
+                            } else {
+                                "check_box_outline_blank"
+                            }
+                        }</span>
+                    </span>
+                    <span class="setting-row toggle" onclick={toggle_auto_collapse}>
+                        <span>{"Auto-Collapse Balanced Groups"}</span>
+                        <span class="material-icons">{
+                            if auto_collapse_balanced {
+                                "check_box"
+                            } else {
+                                "check_box_outline_blank"
+                            }
+                        }</span>
+                    </span>
                     <div class="setting-group">
                         <h4>{"Balance Sort Mode"}</h4>
                         <span class="setting-row toggle" onclick={sort_by_item}>
@@ -1057,8 +3231,88 @@ impl App {
                             }</span>
                         </span>
                     </div>
+                    <div class="setting-group">
+                        <h4>{"Rate Unit"}</h4>
+                        { rate_unit_option(RateUnit::PerMinute, "Items per minute") }
+                        { rate_unit_option(RateUnit::PerSecond, "Items per second") }
+                    </div>
+                    <div class="setting-group">
+                        <h4>{"Balance Precision"}</h4>
+                        <div class="setting-row">
+                            <span>{"Decimal places shown"}</span>
+                            <ClickEdit value={AttrValue::from(balance_precision.0.to_string())}
+                                title="Decimal places shown" on_commit={on_balance_precision} />
+                        </div>
+                    </div>
+                    <div class="setting-group">
+                        <h4>{"Decimal Separator"}</h4>
+                        { separator_option(DecimalSeparator::Period, "Period (66.67)") }
+                        { separator_option(DecimalSeparator::Comma, "Comma (66,67)") }
+                    </div>
+                    <div class="setting-group">
+                        <h4>{"Clock Speed Rounding"}</h4>
+                        { rounding_option(ClockRounding::Exact, "Use clock speeds exactly as entered") }
+                        { rounding_option(ClockRounding::Game, "Round to the game's precision (0.0001%)") }
+                        { rounding_option(ClockRounding::WholePercent, "Round to whole percentages") }
+                    </div>
+                    <div class="setting-group">
+                        <h4>{"Clock Speed Arrow Key Steps"}</h4>
+                        { step_option("Up/Down", clock_step.small, |s, v| s.small = v) }
+                        { step_option("Shift+Up/Down", clock_step.large, |s, v| s.large = v) }
+                    </div>
+                    <div class="setting-group">
+                        <h4>{"Tree Layout (pixels)"}</h4>
+                        { layout_option("Indentation", tree_layout.indent, |l, v| l.indent = v) }
+                        { layout_option("Spacing", tree_layout.spacing, |l, v| l.spacing = v) }
+                    </div>
+                    <div class="setting-group">
+                        <h4>{"Undo History"}</h4>
+                        <div class="setting-row">
+                            <span>{"Steps to keep"}</span>
+                            <ClickEdit value={AttrValue::from(undo_depth.0.to_string())}
+                                title="Steps to keep" on_commit={on_undo_depth} />
+                        </div>
+                    </div>
+                    <div class="setting-group">
+                        <h4>{"Efficiency Target"}</h4>
+                        <div class="setting-row">
+                            <span>{"Target clock speed (blank for none)"}</span>
+                            <ClickEdit value={efficiency_target} title="Target Clock Speed"
+                                on_commit={set_efficiency_target} />
+                        </div>
+                    </div>
+                    <div class="setting-group">
+                        <h4>{"Belt Tier for Logistics Estimates"}</h4>
+                        { for belt_options }
+                    </div>
+                    <div class="setting-group">
+                        <h4>{"Pipe Tier for Logistics Estimates"}</h4>
+                        { for pipe_options }
+                    </div>
+                    <div class="setting-group">
+                        <h4>{"Item Groups"}</h4>
+                        <ItemGroupsEditor groups={self.user_settings.item_groups.clone()}
+                            update={link.callback(|item_groups| Msg::SetItemGroups { item_groups })} />
+                    </div>
+                    <div class="setting-group">
+                        <h4>{"Excluded from Sink Points"}</h4>
+                        <ItemSetEditor items={self.user_settings.sink_excluded.clone()}
+                            update={link.callback(|sink_excluded| Msg::SetSinkExcluded { sink_excluded })} />
+                    </div>
                 </div>
             </div>
         }
     }
 }
+
+/// Callback to jump to the node at a path and focus it. Buildings aren't shown
+/// separately, so this expands the group containing the node.
+fn jump_to_node(link: &Scope<App>) -> Callback<Vec<usize>> {
+    link.batch_callback(|path: Vec<usize>| {
+        let parent = path[..path.len().saturating_sub(1)].to_vec();
+        vec![
+            Msg::JumpTo { path: parent },
+            Msg::Focus(FocusAction::Set(path)),
+        ]
+    })
+}
@@ -21,6 +21,20 @@ pub struct Props {
     pub suffix: Html,
     /// Callback to invoke when the edit is committed.
     pub on_commit: Callback<AttrValue>,
+    /// Callback to compute the text after pressing Up or Down while editing. If it returns
+    /// a value, that replaces the edit text and is committed immediately, without ending
+    /// the edit.
+    #[prop_or_default]
+    pub step: Option<Callback<(AttrValue, Step), Option<AttrValue>>>,
+}
+
+/// Step requested with the arrow keys.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Step {
+    /// True for Up, false for Down.
+    pub up: bool,
+    /// Whether Shift was held, asking for a larger step.
+    pub large: bool,
 }
 
 pub enum Msg {
@@ -32,6 +46,8 @@ pub enum Msg {
     FinishEdit,
     /// Cancel editing without changing the value.
     Cancel,
+    /// Step the value with the arrow keys.
+    Step(Step),
 }
 
 /// Helper to display some text with click-to-edit.
@@ -46,6 +62,7 @@ pub struct ClickEdit {
     // Memoized callbacks:
     oninput: Callback<InputEvent>,
     onkeyup: Callback<KeyboardEvent>,
+    onkeydown: Callback<KeyboardEvent>,
     onblur: Callback<FocusEvent>,
     onsubmit: Callback<SubmitEvent>,
     onclick: Callback<MouseEvent>,
@@ -72,6 +89,18 @@ impl Component for ClickEdit {
                 "Esc" | "Escape" => Some(Msg::Cancel),
                 _ => None,
             }),
+            onkeydown: link.batch_callback(|e: KeyboardEvent| {
+                let up = match &*e.key() {
+                    "ArrowUp" | "Up" => true,
+                    "ArrowDown" | "Down" => false,
+                    _ => return None,
+                };
+                e.prevent_default();
+                Some(Msg::Step(Step {
+                    up,
+                    large: e.shift_key(),
+                }))
+            }),
             onblur: link.callback(|_| Msg::FinishEdit),
             onsubmit: link.callback(|e: SubmitEvent| {
                 e.prevent_default();
@@ -127,6 +156,19 @@ impl Component for ClickEdit {
                 self.edit_text = None;
                 true
             }
+            Msg::Step(step) => {
+                let (Some(edit_text), Some(step_fn)) = (&self.edit_text, &ctx.props().step) else {
+                    return false;
+                };
+                match step_fn.emit((edit_text.clone(), step)) {
+                    Some(stepped) => {
+                        ctx.props().on_commit.emit(stepped.clone());
+                        self.edit_text = Some(stepped);
+                        true
+                    }
+                    None => false,
+                }
+            }
         }
     }
 
@@ -142,13 +184,14 @@ impl Component for ClickEdit {
         if let Some(value) = self.edit_text.clone() {
             let oninput = self.oninput.clone();
             let onkeyup = self.onkeyup.clone();
+            let onkeydown = self.onkeydown.clone();
             let onblur = self.onblur.clone();
             let onsubmit = self.onsubmit.clone();
             html! {
                 <form {class} {title} {onsubmit}>
                     { prefix.clone() }
                     <input class="value" type="text" {value}
-                        {oninput} {onblur} {onkeyup} ref={self.input.clone()} />
+                        {oninput} {onblur} {onkeyup} {onkeydown} ref={self.input.clone()} />
                     { suffix.clone() }
                 </form>
             }
@@ -1,5 +1,5 @@
 use wasm_bindgen::JsCast as _;
-use web_sys::{Event, HtmlInputElement, InputEvent};
+use web_sys::{Event, HtmlInputElement, HtmlTextAreaElement, InputEvent};
 use yew::AttrValue;
 
 /// Extract the text value from the target of an InputEvent.
@@ -9,3 +9,17 @@ pub fn get_value_from_input_event(e: InputEvent) -> AttrValue {
     let target: HtmlInputElement = event_target.dyn_into().unwrap();
     target.value().into()
 }
+
+/// Extract the value from the target of a change event on an input.
+pub fn get_value_from_input_change(e: Event) -> AttrValue {
+    let event_target = e.target().unwrap();
+    let target: HtmlInputElement = event_target.dyn_into().unwrap();
+    target.value().into()
+}
+
+/// Extract the text value from the target of a change event on a textarea.
+pub fn get_value_from_textarea_change(e: Event) -> AttrValue {
+    let event_target = e.target().unwrap();
+    let target: HtmlTextAreaElement = event_target.dyn_into().unwrap();
+    target.value().into()
+}
@@ -0,0 +1,115 @@
+// Copyright 2024 Zachary Stewart
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//! Exporting factories and reports out of the app.
+
+use gloo::file::{Blob, ObjectUrl};
+use gloo::timers::callback::Timeout;
+use log::warn;
+use satisfactory_accounting::accounting::ItemReport;
+use satisfactory_accounting::database::Database;
+use satisfactory_accounting::factory::Factory;
+use wasm_bindgen::JsCast;
+use web_sys::HtmlAnchorElement;
+
+/// Download the factory as a JSON file named after its title.
+pub fn download_factory(factory: &Factory) {
+    let json = match serde_json::to_string_pretty(factory) {
+        Ok(json) => json,
+        Err(e) => {
+            warn!("Unable to serialize factory: {}", e);
+            return;
+        }
+    };
+    download_file(
+        &json,
+        "application/json",
+        &file_name(&factory.metadata.title, "factory", "json"),
+    );
+}
+
+/// Format of an exported [`ItemReport`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum ReportFormat {
+    /// Comma separated values, for spreadsheets.
+    Csv,
+    /// Plain text, one line per building.
+    Text,
+}
+
+/// Download an item report as a file named after the item.
+pub fn download_item_report(report: &ItemReport, database: &Database, format: ReportFormat) {
+    let title = database
+        .get(report.item)
+        .map_or_else(|| report.item.to_string(), |item| item.name.to_string());
+    let (contents, mime, extension) = match format {
+        ReportFormat::Csv => (report.to_csv(database), "text/csv", "csv"),
+        ReportFormat::Text => (report.to_text(database), "text/plain", "txt"),
+    };
+    download_file(&contents, mime, &file_name(&title, "report", extension));
+}
+
+/// Download `contents` as a file with the given MIME type and name.
+fn download_file(contents: &str, mime: &str, file_name: &str) {
+    let url = ObjectUrl::from(Blob::new_with_options(contents, Some(mime)));
+    let anchor = match gloo::utils::document()
+        .create_element("a")
+        .map(|element| element.dyn_into::<HtmlAnchorElement>())
+    {
+        Ok(Ok(anchor)) => anchor,
+        _ => {
+            warn!("Unable to create download link");
+            return;
+        }
+    };
+    anchor.set_href(&url);
+    anchor.set_download(file_name);
+    anchor.click();
+    // The download may not have started reading the URL yet, so keep it alive a little
+    // longer before it is revoked.
+    Timeout::new(10_000, move || drop(url)).forget();
+}
+
+/// Build a file name from a title, replacing characters which aren't safe in file names.
+/// `fallback` is used as the name if the title is empty.
+fn file_name(title: &str, fallback: &str, extension: &str) -> String {
+    let name: String = title
+        .trim()
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || matches!(c, ' ' | '-' | '_') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if name.is_empty() {
+        format!("{fallback}.{extension}")
+    } else {
+        format!("{name}.{extension}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn file_names() {
+        assert_eq!(
+            file_name("Steel Mill", "factory", "json"),
+            "Steel Mill.json"
+        );
+        assert_eq!(
+            file_name("  Oil/Plastic: v2 ", "factory", "json"),
+            "Oil_Plastic_ v2.json"
+        );
+        assert_eq!(file_name("", "factory", "json"), "factory.json");
+        assert_eq!(file_name("Iron Ingot", "report", "csv"), "Iron Ingot.csv");
+    }
+}
@@ -0,0 +1,270 @@
+// Copyright 2024 Zachary Stewart
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//! Undo and redo history.
+
+use satisfactory_accounting::accounting::Node;
+use serde::{Deserialize, Serialize};
+
+/// Edits to the same node closer together than this many milliseconds are undone as a
+/// single step, so e.g. stepping a clock speed doesn't take many undos to reverse.
+const COALESCE_WINDOW_MS: f64 = 1000.0;
+
+/// Maximum number of steps kept in the undo history.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct HistoryDepth(pub usize);
+
+impl Default for HistoryDepth {
+    fn default() -> Self {
+        Self(100)
+    }
+}
+
+/// Stacks of previous and future states for undo and redo.
+#[derive(Debug)]
+pub struct History<T> {
+    /// Previous states, most recent last.
+    undo_stack: Vec<T>,
+    /// States which were undone, most recently undone last.
+    redo_stack: Vec<T>,
+    /// Path of the node changed by the most recent edit and when it happened, if it can
+    /// be coalesced with the next edit.
+    last_edit: Option<(Vec<usize>, f64)>,
+    /// Maximum number of undo states kept.
+    depth: HistoryDepth,
+}
+
+impl<T> History<T> {
+    /// Create an empty history which keeps up to `depth` undo steps.
+    pub fn new(depth: HistoryDepth) -> Self {
+        Self {
+            undo_stack: Vec::new(),
+            redo_stack: Vec::new(),
+            last_edit: None,
+            depth,
+        }
+    }
+
+    /// Record `previous` as the state from before an edit, clearing the redo stack.
+    /// `edited` is the path of the node which was changed, if known, and `now` is the
+    /// time of the edit in milliseconds. If the same node was also changed by the
+    /// previous edit within a short time, the edits are merged into one undo step.
+    pub fn record(&mut self, previous: T, edited: Option<Vec<usize>>, now: f64) {
+        self.redo_stack.clear();
+        let coalesce = match (&self.last_edit, &edited) {
+            (Some((last, at)), Some(path)) => {
+                last == path && now - at < COALESCE_WINDOW_MS && !self.undo_stack.is_empty()
+            }
+            _ => false,
+        };
+        if !coalesce {
+            self.undo_stack.push(previous);
+            self.truncate();
+        }
+        self.last_edit = edited.map(|path| (path, now));
+    }
+
+    /// Undo the most recent edit. `apply` is given the state to restore and must return
+    /// the state it replaced, for redo. Returns false if there is nothing to undo.
+    pub fn undo(&mut self, apply: impl FnOnce(T) -> T) -> bool {
+        self.last_edit = None;
+        match self.undo_stack.pop() {
+            Some(previous) => {
+                self.redo_stack.push(apply(previous));
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Redo the most recently undone edit. `apply` is given the state to restore and
+    /// must return the state it replaced, for undo. Returns false if there is nothing to
+    /// redo.
+    pub fn redo(&mut self, apply: impl FnOnce(T) -> T) -> bool {
+        self.last_edit = None;
+        match self.redo_stack.pop() {
+            Some(next) => {
+                self.undo_stack.push(apply(next));
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Whether there is an edit to undo.
+    pub fn can_undo(&self) -> bool {
+        !self.undo_stack.is_empty()
+    }
+
+    /// Whether there is an undone edit to redo.
+    pub fn can_redo(&self) -> bool {
+        !self.redo_stack.is_empty()
+    }
+
+    /// Get the states from before each edit which can still be undone, oldest first.
+    pub fn past(&self) -> impl Iterator<Item = &T> {
+        self.undo_stack.iter()
+    }
+
+    /// Forget all history, e.g. when switching worlds.
+    pub fn clear(&mut self) {
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+        self.last_edit = None;
+    }
+
+    /// Change the maximum number of undo steps, dropping the oldest if there are now
+    /// too many.
+    pub fn set_depth(&mut self, depth: HistoryDepth) {
+        self.depth = depth;
+        self.truncate();
+    }
+
+    /// Drop the oldest undo states beyond the depth limit.
+    fn truncate(&mut self) {
+        if self.undo_stack.len() > self.depth.0 {
+            let num_to_remove = self.undo_stack.len() - self.depth.0;
+            self.undo_stack.drain(..num_to_remove);
+        }
+    }
+}
+
+/// Find the path of the node which differs between `old` and `new`, descending as long
+/// as only a single child of a group changed. If several children changed or the
+/// structure of a group changed, this is the path of that group.
+///
+/// Unchanged children are usually shared with the old tree, so they are recognized by
+/// pointer without comparing their contents.
+pub fn edited_path(old: &Node, new: &Node) -> Vec<usize> {
+    let mut path = Vec::new();
+    let (mut old, mut new) = (old, new);
+    while let (Some(old_group), Some(new_group)) = (old.group(), new.group()) {
+        if old_group.id != new_group.id || old_group.children.len() != new_group.children.len() {
+            break;
+        }
+        let mut changed = old_group
+            .children
+            .iter()
+            .zip(&new_group.children)
+            .enumerate()
+            .filter(|(_, (old, new))| !old.same_as(new) && old != new);
+        match (changed.next(), changed.next()) {
+            (Some((idx, (old_child, new_child))), None) => {
+                path.push(idx);
+                old = old_child;
+                new = new_child;
+            }
+            _ => break,
+        }
+    }
+    path
+}
+
+#[cfg(test)]
+mod tests {
+    use satisfactory_accounting::accounting::Group;
+
+    use super::*;
+
+    #[test]
+    fn rapid_edits_to_one_node_coalesce() {
+        let mut history = History::new(HistoryDepth::default());
+        history.record(0, Some(vec![1]), 0.0);
+        history.record(1, Some(vec![1]), 500.0);
+        history.record(2, Some(vec![1]), 900.0);
+        // A different node starts a new step, as does a pause.
+        history.record(3, Some(vec![2]), 1000.0);
+        history.record(4, Some(vec![2]), 5000.0);
+        // Edits without a known node never coalesce.
+        history.record(5, None, 5100.0);
+        history.record(6, None, 5200.0);
+
+        let mut restored = Vec::new();
+        while history.undo(|state| {
+            restored.push(state);
+            state
+        }) {}
+        assert_eq!(restored, vec![6, 5, 4, 3, 0]);
+    }
+
+    #[test]
+    fn undo_breaks_coalescing() {
+        let mut history = History::new(HistoryDepth::default());
+        history.record(0, Some(vec![0]), 0.0);
+        assert!(history.undo(|_| 1));
+        assert!(history.can_redo());
+        history.record(0, Some(vec![0]), 100.0);
+        assert!(!history.can_redo());
+        history.record(1, Some(vec![0]), 200.0);
+        assert!(history.undo(|state| state));
+        assert!(!history.can_undo());
+    }
+
+    #[test]
+    fn depth_caps_history() {
+        let mut history = History::new(HistoryDepth(3));
+        for state in 0..5 {
+            history.record(state, None, 0.0);
+        }
+        let mut restored = Vec::new();
+        while history.undo(|state| {
+            restored.push(state);
+            state
+        }) {}
+        assert_eq!(restored, vec![4, 3, 2]);
+
+        for state in 0..5 {
+            history.record(state, None, 0.0);
+        }
+        assert!(history.past().copied().eq([2, 3, 4]));
+
+        for state in 0..5 {
+            history.record(state, None, 0.0);
+        }
+        history.set_depth(HistoryDepth(1));
+        assert!(history.undo(|state| {
+            assert_eq!(state, 4);
+            state
+        }));
+        assert!(!history.can_undo());
+    }
+
+    #[test]
+    fn edited_path_finds_changed_node() {
+        let leaf = Group::empty();
+        let inner = Group {
+            children: vec![leaf.clone().into(), leaf.clone().into()],
+            ..Group::empty()
+        };
+        let root = Group {
+            children: vec![leaf.clone().into(), inner.clone().into()],
+            ..Group::empty()
+        };
+
+        let mut changed_inner = inner.clone();
+        changed_inner.children[1] = Group {
+            name: "Renamed".into(),
+            ..leaf.clone()
+        }
+        .into();
+        let mut changed = root.clone();
+        changed.children[1] = changed_inner.into();
+        assert_eq!(
+            edited_path(&root.clone().into(), &changed.into()),
+            vec![1, 1]
+        );
+
+        let mut renamed = root.clone();
+        renamed.name = "Renamed".into();
+        assert!(edited_path(&root.clone().into(), &renamed.into()).is_empty());
+
+        let mut added = root.clone();
+        added.children.push(leaf.into());
+        assert!(edited_path(&root.into(), &added.into()).is_empty());
+    }
+}
@@ -0,0 +1,243 @@
+// Copyright 2024 Zachary Stewart
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//! Importing factories into the app.
+
+use satisfactory_accounting::accounting::{Group, Node, NodeError, RebuildReport};
+use satisfactory_accounting::database::Database;
+use satisfactory_accounting::factory::Factory;
+use satisfactory_accounting::migration::migrate;
+
+use crate::notifications::Notice;
+
+/// Parse a factory exported by the app, or a single bare node, so it can be added to
+/// the tree. Groups get new ids, so the import can sit alongside the tree it came from.
+/// The nodes are migrated to `database`, remapping IDs renamed since the database version
+/// the factory was exported with, and the report lists any buildings with IDs the
+/// database still doesn't have.
+pub fn parse_import(json: &str, database: &Database) -> Result<RebuildReport, serde_json::Error> {
+    let factory = parse(json)?;
+    Ok(migrate(
+        &factory.root.create_copy(),
+        factory.database_version,
+        database,
+    ))
+}
+
+/// Parse a factory exported by the app, or a single bare node, to replace the whole tree,
+/// keeping the factory's metadata and item names. Unlike [`parse_import`], groups keep
+/// their ids, so exporting and importing a tree gives back the same tree. A bare node
+/// which isn't a group is put in one, since the root must be a group. IDs are migrated
+/// and checked against `database` the same way, and the returned factory's root is the
+/// migrated tree. Its database version is still the one it was exported with.
+pub fn parse_factory(
+    json: &str,
+    database: &Database,
+) -> Result<(Factory, Vec<NodeError>), serde_json::Error> {
+    let mut factory = parse(json)?;
+    if factory.root.group().is_none() {
+        factory.root = Group {
+            children: vec![factory.root],
+            ..Group::empty()
+        }
+        .into();
+    }
+    let report = migrate(&factory.root, factory.database_version, database);
+    factory.root = report.root;
+    Ok((factory, report.errors))
+}
+
+/// Notice to show if buildings in an imported tree were last built against a database
+/// other than `database`, which happens when they couldn't be rebuilt against it.
+pub fn database_mismatch_notice(root: &Node, database: &Database) -> Option<Notice> {
+    let mut versions = root.database_versions();
+    if let Some(current) = database.version {
+        versions.remove(&current);
+    }
+    if versions.is_empty() {
+        return None;
+    }
+    let names = versions
+        .iter()
+        .map(|version| version.name())
+        .collect::<Vec<_>>()
+        .join(", ");
+    Some(Notice::new(format!(
+        "Some imported buildings were last built against a different database ({names})"
+    )))
+}
+
+/// Parse either an exported factory or a bare node. A bare node is given empty metadata
+/// and no database version.
+fn parse(json: &str) -> Result<Factory, serde_json::Error> {
+    match serde_json::from_str::<Factory>(json) {
+        Ok(factory) => Ok(factory),
+        Err(_) => Ok(Factory {
+            metadata: Default::default(),
+            item_names: Default::default(),
+            database_version: None,
+            root: serde_json::from_str(json)?,
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use satisfactory_accounting::accounting::{
+        BuildError, BuildNode, Building, GeneratorSettings, Group,
+    };
+    use satisfactory_accounting::database::{BuildingId, DatabaseVersion, U7Subversion};
+    use satisfactory_accounting::factory::FactoryMetadata;
+    use satisfactory_accounting::sample::manufacturer;
+
+    use super::*;
+
+    /// Build a constructor switched to `recipe`, which may not be in the database.
+    fn constructor(db: &Database, recipe: &str) -> Node {
+        let building = manufacturer("Desc_ConstructorMk1_C", recipe, 1.0, 1);
+        match building.clone().build_node(db) {
+            Ok(node) => node,
+            Err(e) => e.into_warning_node(building),
+        }
+    }
+
+    #[test]
+    fn import_exported_factory() {
+        let db = DatabaseVersion::LATEST.load_database();
+        let group = Group {
+            name: "Plates".into(),
+            children: vec![constructor(&db, "Recipe_IronPlate_C")],
+            ..Group::empty()
+        };
+        let factory = Factory::export(&group, Default::default(), &db, &Default::default());
+        let json = serde_json::to_string(&factory).unwrap();
+
+        let report = parse_import(&json, &db).unwrap();
+        assert!(report.errors.is_empty());
+        let imported = report.root.group().unwrap();
+        assert_eq!(imported.name, group.name);
+        assert_ne!(imported.id, factory.root.group().unwrap().id);
+        assert_eq!(report.root.balance(), factory.root.balance());
+    }
+
+    #[test]
+    fn import_bare_node_with_unknown_ids() {
+        let db = DatabaseVersion::LATEST.load_database();
+        let node = constructor(&db, "Recipe_Missing_C");
+        let json = serde_json::to_string(&node).unwrap();
+
+        let report = parse_import(&json, &db).unwrap();
+        assert_eq!(report.errors.len(), 1);
+        assert_eq!(
+            report.errors[0].error,
+            BuildError::UnknownRecipe("Recipe_Missing_C".into())
+        );
+        assert!(parse_import("not json", &db).is_err());
+    }
+
+    #[test]
+    fn import_migrates_renamed_ids() {
+        let u7 = DatabaseVersion::U7(U7Subversion::Initial).load_database();
+        let burner = Building {
+            building: Some("Desc_GeneratorBiomass_C".into()),
+            settings: GeneratorSettings {
+                fuel: Some("Desc_Leaves_C".into()),
+                clock_speed: 1.0,
+            }
+            .into(),
+            ..Default::default()
+        };
+        let group = Group {
+            children: vec![burner.build_node(&u7).unwrap()],
+            ..Group::empty()
+        };
+        let factory = Factory::export(&group, Default::default(), &u7, &Default::default());
+        let json = serde_json::to_string(&factory).unwrap();
+
+        let db = DatabaseVersion::LATEST.load_database();
+        let report = parse_import(&json, &db).unwrap();
+        assert!(report.errors.is_empty());
+        let imported = report.root.group().unwrap();
+        assert_eq!(
+            imported.children[0].building().unwrap().building,
+            Some("Desc_GeneratorBiomass_Automated_C".into())
+        );
+        assert!(database_mismatch_notice(&report.root, &db).is_none());
+    }
+
+    #[test]
+    fn import_warns_of_buildings_left_on_another_database() {
+        let u7 = DatabaseVersion::U7(U7Subversion::Initial).load_database();
+        let id = BuildingId::from("Desc_GeneratorGeoThermal_C");
+        let geothermal = Building {
+            building: Some(id),
+            settings: u7[id].get_default_settings(),
+            ..Default::default()
+        };
+        let group = Group {
+            children: vec![geothermal.build_node(&u7).unwrap()],
+            ..Group::empty()
+        };
+        let factory = Factory::export(&group, Default::default(), &u7, &Default::default());
+        let json = serde_json::to_string(&factory).unwrap();
+
+        // The building no longer exists, so it can't be rebuilt and keeps its old stamp.
+        let db = DatabaseVersion::LATEST.load_database();
+        let report = parse_import(&json, &db).unwrap();
+        assert_eq!(report.errors.len(), 1);
+        let notice = database_mismatch_notice(&report.root, &db).unwrap();
+        assert!(notice.message.contains(u7.version.unwrap().name()));
+    }
+
+    #[test]
+    fn factory_round_trips() {
+        let db = DatabaseVersion::LATEST.load_database();
+        let plates = constructor(&db, "Recipe_IronPlate_C");
+        let mut capped = plates.building().unwrap().clone();
+        capped.copies = 3;
+        capped.tags = vec!["plates".into()];
+        capped.note = "Feeds the rotor line".into();
+        capped.output_caps.insert("Desc_IronPlate_C".into(), 30.0);
+        let group = Group {
+            name: "Main".into(),
+            children: vec![
+                plates,
+                capped.build_node(&db).unwrap(),
+                Group {
+                    name: "Nested".into(),
+                    children: vec![constructor(&db, "Recipe_IronRod_C"), Group::empty_node()],
+                    byproducts: ["Desc_IronRod_C".into()].into(),
+                    ..Group::empty()
+                }
+                .into(),
+                constructor(&db, "Recipe_Missing_C"),
+            ],
+            ..Group::empty()
+        };
+        let metadata = FactoryMetadata {
+            title: "Main Base".into(),
+            description: "Plates and rods.".into(),
+            ..Default::default()
+        };
+        let factory = Factory::export(&group, metadata, &db, &Default::default());
+        let json = serde_json::to_string_pretty(&factory).unwrap();
+
+        let (imported, errors) = parse_factory(&json, &db).unwrap();
+        assert_eq!(imported, factory);
+        // The unknown recipe is reported rather than failing the import.
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].path, vec![3]);
+
+        // A bare building is put in a group so it can be the root.
+        let json = serde_json::to_string(&constructor(&db, "Recipe_IronPlate_C")).unwrap();
+        let (imported, _) = parse_factory(&json, &db).unwrap();
+        assert!(imported.metadata.is_empty());
+        let root = imported.root.group().unwrap();
+        assert!(root.children[0].building().is_some());
+        assert!(parse_factory("not json", &db).is_err());
+    }
+}
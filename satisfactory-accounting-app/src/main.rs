@@ -10,17 +10,25 @@ use std::rc::Rc;
 use uuid::Uuid;
 use yew::prelude::*;
 
-use satisfactory_accounting::database::Database;
+use satisfactory_accounting::database::{Database, Item, ItemId};
+use satisfactory_accounting::factory::{FactoryMetadata, ItemNames};
 
 use crate::app::App;
 
 use self::app::UserSettings;
-use self::node_display::{NodeMeta, NodeMetadata};
+use self::node_display::{NodeMeta, NodeMetadata, ProducedItems};
+use self::notifications::{Notice, Notifier};
 
 mod app;
 mod clickedit;
 mod events;
+mod export;
+mod history;
+mod import;
 mod node_display;
+mod notifications;
+mod numbers;
+mod share;
 
 fn main() {
     console_log::init_with_level(log::Level::Debug).expect("Unable to init logger");
@@ -39,6 +47,23 @@ trait CtxHelper {
     /// Get the user settings from context, throw if context is missing (gets default if
     /// not set).
     fn settings(&self) -> Rc<UserSettings>;
+
+    /// Get the last known names of items from context, throw if context is missing.
+    fn item_names(&self) -> Rc<ItemNames>;
+
+    /// Get the metadata of the current factory from context, throw if context is missing.
+    fn factory_metadata(&self) -> FactoryMetadata;
+
+    /// Get the items produced anywhere in the factory from context, throw if context is
+    /// missing.
+    fn produced_items(&self) -> ProducedItems;
+
+    /// Get the notifier from context, for showing notices from callbacks. Throw if
+    /// context is missing.
+    fn notifier(&self) -> Notifier;
+
+    /// Show a notice to the user, throw if context is missing.
+    fn notify(&self, notice: Notice);
 }
 
 impl<T: Component> CtxHelper for Context<T> {
@@ -65,6 +90,42 @@ impl<T: Component> CtxHelper for Context<T> {
             .expect("user settings context to be set");
         settings
     }
+
+    fn item_names(&self) -> Rc<ItemNames> {
+        let (names, _) = self
+            .link()
+            .context::<Rc<ItemNames>>(Callback::noop())
+            .expect("item names context to be set");
+        names
+    }
+
+    fn factory_metadata(&self) -> FactoryMetadata {
+        let (metadata, _) = self
+            .link()
+            .context::<FactoryMetadata>(Callback::noop())
+            .expect("factory metadata context to be set");
+        metadata
+    }
+
+    fn produced_items(&self) -> ProducedItems {
+        let (produced, _) = self
+            .link()
+            .context::<ProducedItems>(Callback::noop())
+            .expect("produced items context to be set");
+        produced
+    }
+
+    fn notifier(&self) -> Notifier {
+        let (notifier, _) = self
+            .link()
+            .context::<Notifier>(Callback::noop())
+            .expect("notifier context to be set");
+        notifier
+    }
+
+    fn notify(&self, notice: Notice) {
+        self.notifier().notify(notice);
+    }
 }
 
 /// Get the database from context.
@@ -72,3 +133,37 @@ impl<T: Component> CtxHelper for Context<T> {
 fn use_db() -> Rc<Database> {
     use_context::<Rc<Database>>().expect("database context to be set")
 }
+
+/// Get the handle for showing notices from context.
+#[hook]
+fn use_notifier() -> Notifier {
+    use_context::<Notifier>().expect("notifier context to be set")
+}
+
+/// Get the last known names of items from context.
+#[hook]
+fn use_item_names() -> Rc<ItemNames> {
+    use_context::<Rc<ItemNames>>().expect("item names context to be set")
+}
+
+/// Get the name and icon to show for an item, falling back to its last known name if it
+/// isn't in the database.
+fn item_label(
+    item: Option<&Item>,
+    id: ItemId,
+    names: &ItemNames,
+) -> (AttrValue, Option<AttrValue>) {
+    match item {
+        Some(item) => (item.name.clone().into(), Some(item.image.clone().into())),
+        None => match names.get(id) {
+            Some(label) => (label.name.clone(), Some(label.image.clone())),
+            None => ("Unknown Item".into(), None),
+        },
+    }
+}
+
+/// Get the user settings from context.
+#[hook]
+fn use_settings() -> Rc<UserSettings> {
+    use_context::<Rc<UserSettings>>().expect("user settings context to be set")
+}
@@ -6,34 +6,98 @@
 //
 //       http://www.apache.org/licenses/LICENSE-2.0
 use std::cell::RefCell;
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::rc::Rc;
 
+use gloo::file::callbacks::{read_as_text, FileReader};
+use gloo::file::{File, FileReadError};
+use gloo::timers::callback::Timeout;
 use log::warn;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 use yew::prelude::*;
 
 use satisfactory_accounting::accounting::{
-    BuildNode, Building, BuildingSettings, GeneratorSettings, GeothermalSettings, Group,
-    ManufacturerSettings, MinerSettings, Node, NodeKind, PumpSettings, ResourcePurity,
-    StationSettings,
+    BuildError, BuildNode, Building, BuildingSettings, ChildSortKey, GeneratorSettings,
+    GeothermalSettings, Group, GroupColor, ManufacturerSettings, MinerSettings, Node, NodeKind,
+    Port, PumpSettings, ResourcePurity, StationSettings,
 };
 use satisfactory_accounting::database::{
-    BuildingId, BuildingKind, BuildingKindId, BuildingType, ItemId, RecipeId,
+    BuildingId, BuildingKind, BuildingKindId, BuildingType, Database, ItemId, RecipeId,
 };
+use satisfactory_accounting::factory::Factory;
 
-use crate::CtxHelper;
+use crate::export::download_factory;
+use crate::import::{database_mismatch_notice, parse_import};
+use crate::notifications::Notice;
+use crate::{item_label, CtxHelper};
+use building::BuildingSection;
 
+use self::balance::rounded;
 pub use self::balance::BalanceSortMode;
+pub use self::breadcrumbs::{node_name, Breadcrumbs};
+pub use self::building::choose_from_list::{Choice, ChooseFromList};
+pub use self::building::{parse_clock_speed, ClockRounding, ClockStep};
+pub use self::collapse::{collapse_below, CollapseControls};
+pub use self::cut::{clear_cut, clear_cut_path};
+pub use self::drag::clear_dragging;
+pub use self::focus::{focus_element, focus_is_lost, FocusAction, FocusStep, NodeFocus};
+pub use self::graph_manipulation::{
+    copy_children, delete_children, move_children, move_group_by_id, outermost_paths,
+    split_deletion_blocked,
+};
+pub use self::group::node_element_id;
+pub use self::icon::Icon;
+pub use self::item_groups::{ItemFilter, ItemGroup, ItemGroupsEditor, ItemSetEditor};
+pub use self::legend::BalanceLegend;
+pub use self::machine_calculator::MachineCalculator;
+use self::notes::Note;
+pub use self::overview::Overview;
+pub use self::pinned::PinnedNodes;
+pub use self::power_chart::PowerChart;
+pub use self::power_planner::PowerPlanner;
+pub use self::power_summary::PowerSummary;
+pub use self::problems::Problems;
+pub use self::sandbox::{NodeTree, Sandbox};
+pub use self::selection::{Selection, SelectionAction};
+use self::tags::Tags;
+pub use self::targets::TargetScorecard;
+pub use self::trace::{ItemReportView, ProductionTraceView};
+pub use self::trends::{trend_series, ItemTrends};
 
 mod balance;
+mod breadcrumbs;
 mod building;
+mod clipboard;
+mod collapse;
 mod copies;
+mod cut;
 mod drag;
+mod focus;
 mod graph_manipulation;
 mod group;
 mod icon;
+mod item_groups;
+mod legend;
+mod machine_calculator;
+mod notes;
+mod overview;
+mod pinned;
+mod power_chart;
+mod power_planner;
+mod power_summary;
+mod problems;
+mod sandbox;
+mod selection;
+mod tags;
+mod targets;
+mod trace;
+mod trends;
+
+/// Items produced anywhere in the current factory, provided as context so buildings can
+/// warn about inputs with no source.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ProducedItems(pub Rc<BTreeSet<ItemId>>);
 
 /// Mapping of node medatata by node id.
 #[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
@@ -75,6 +139,34 @@ impl NodeMetadata {
 pub struct NodeMeta {
     /// Whether the node should be shown collapsed or expanded.
     collapsed: bool,
+    /// Whether the user explicitly expanded the node, which keeps it from being
+    /// auto-collapsed.
+    #[serde(default)]
+    expanded: bool,
+    /// Whether the node is pinned to the top of the page so it stays visible while
+    /// scrolling.
+    #[serde(default)]
+    pinned: bool,
+}
+
+impl NodeMeta {
+    /// This metadata, changed to show the node explicitly expanded.
+    pub fn expand(self) -> Self {
+        NodeMeta {
+            collapsed: false,
+            expanded: true,
+            ..self
+        }
+    }
+
+    /// This metadata, changed to show the node collapsed.
+    pub fn collapse(self) -> Self {
+        NodeMeta {
+            collapsed: true,
+            expanded: false,
+            ..self
+        }
+    }
 }
 
 #[derive(Debug, PartialEq, Properties)]
@@ -89,14 +181,35 @@ pub struct Props {
     /// Callback to tell the parent to copy this node.
     #[prop_or_default]
     pub copy: Option<Callback<usize>>,
+    /// Callback to fork a group into the sandbox. Not set inside the sandbox itself.
+    #[prop_or_default]
+    pub open_sandbox: Option<Callback<Node>>,
     /// Callback to tell the parent to replace this node.
     pub replace: Callback<(usize, Node)>,
-    /// Callback to tell the parent to move a node.
-    pub move_node: Callback<(Vec<usize>, Vec<usize>)>,
+    /// Callback to tell the parent to move a node, or copy it if the flag is set.
+    pub move_node: Callback<(Vec<usize>, Vec<usize>, bool)>,
+    /// Callback to move a group, by id, into another group, by id, at a position.
+    pub move_group: Callback<(Uuid, Uuid, usize)>,
     /// Callback to set the metadata of a node.
     pub set_metadata: Callback<(Uuid, NodeMeta)>,
     /// Callback to set the metadata of many nodes at once.
     pub batch_set_metadata: Callback<HashMap<Uuid, NodeMeta>>,
+    /// Callback to request a trace of where an item produced by a node comes from.
+    pub trace_item: Callback<(Vec<usize>, ItemId)>,
+    /// Callback to change the selection or act on the selected nodes.
+    pub selection_action: Callback<SelectionAction>,
+    /// Callback to move the keyboard focus or tell the app which node has it.
+    pub focus_action: Callback<FocusAction>,
+    /// Whether another group with the same parent has the same name as this one.
+    #[prop_or_default]
+    pub duplicate_name: bool,
+    /// Whether an ancestor of this node matches the tag filter, so this node shouldn't
+    /// be dimmed even if it doesn't match itself.
+    #[prop_or_default]
+    pub tag_matched: bool,
+    /// Items produced by the parent group, used to recommend recipes which consume them.
+    #[prop_or_default]
+    pub available_items: Rc<BTreeSet<ItemId>>,
 }
 
 /// Messages which can be sent to a Node.
@@ -104,6 +217,18 @@ pub enum Msg {
     // Shared messages:
     /// Set the number of virtual copies of this building or group.
     SetCopyCount { copies: u32 },
+    /// Toggle whether this building or group is locked against deletion.
+    ToggleDeleteLock,
+    /// Replace the tags of this building or group.
+    SetTags { tags: Vec<AttrValue> },
+    /// Replace the note of this building or group.
+    SetNote { note: AttrValue },
+
+    // Messages for buildings:
+    /// Toggle whether one section of this building is hidden.
+    ToggleSection { section: BuildingSection },
+    /// Hide the hint about recipe inputs which aren't produced in the factory.
+    DismissInputHint,
 
     // Messages for groups:
     /// Replace the child at the given index with the specified node.
@@ -116,17 +241,67 @@ pub enum Msg {
     AddChild { child: Node },
     /// Rename this node.
     Rename { name: AttrValue },
+    /// Reorder the children of this group by the given key.
+    SortChildren { key: ChildSortKey, descending: bool },
+    /// Set the accent color of this group, or remove it.
+    SetColor { color: Option<GroupColor> },
+    /// Merge child buildings with identical settings into single buildings.
+    MergeDuplicates,
+    /// Rebuild this node and its children against the current database.
+    Revalidate,
+    /// Toggle whether an output of this group is a byproduct to sink.
+    ToggleByproduct { item: ItemId },
+    /// Replace the interface declared for this group.
+    SetInterface { interface: BTreeMap<ItemId, Port> },
+    /// Spread a target rate of an item across the child buildings at the given indexes in
+    /// proportion to their weights.
+    DistributeOutput {
+        item: ItemId,
+        target: f32,
+        weights: Vec<(usize, f32)>,
+    },
+    /// Download this group as a standalone factory.
+    Export,
     /// When another node starts being dragged over this one.
     DragEnter { insert_pos: usize },
     /// When another node is dragged over this one.
     DragOver { insert_pos: usize },
     /// When another dragging node leaves this one.
     DragLeave,
-    /// Move a node between positions.
+    /// Something being dragged entered this collapsed group.
+    HoverCollapsed,
+    /// Something being dragged left this collapsed group.
+    LeaveCollapsed,
+    /// Something was dragged over this collapsed group long enough to expand it.
+    ExpandForDrop,
+    /// A file was dropped on this group, to be imported at the given child position.
+    DropFile { insert_pos: usize, file: File },
+    /// A dropped file finished loading.
+    FileLoaded {
+        insert_pos: usize,
+        result: Result<String, FileReadError>,
+    },
+    /// Mark this node to be moved by the next paste.
+    Cut,
+    /// Move the node which was cut into this node, or paste a node from the system
+    /// clipboard if nothing was cut.
+    Paste,
+    /// Copy this node to the system clipboard.
+    CopyToClipboard,
+    /// Text was read from the system clipboard to paste into this group.
+    ClipboardRead { text: String },
+    /// Copying or pasting with the system clipboard failed.
+    ClipboardFailed { message: AttrValue },
+    /// Hide the most recent clipboard failure.
+    DismissClipboardError,
+    /// Move a node between positions, or insert a copy of it if `copy` is set.
     MoveNode {
         src_path: Vec<usize>,
         dest_path: Vec<usize>,
+        copy: bool,
     },
+    /// Move all selected nodes to a position, or insert copies of them if `copy` is set.
+    MoveSelection { dest_path: Vec<usize>, copy: bool },
 
     // Messages for buildings:
     /// Change the building type of this node.
@@ -137,6 +312,11 @@ pub enum Msg {
     ChangeItem { id: ItemId },
     /// Change the clock speed for the building.
     ChangeClockSpeed { clock_speed: f32 },
+    /// Change the clock speed so all copies of the building together output `rate` of
+    /// `item`.
+    SetOutputRate { item: ItemId, rate: f32 },
+    /// Limit the total output of `item` from the building, or remove the limit if None.
+    SetOutputCap { item: ItemId, cap: Option<f32> },
     /// Change the resource purity for the node the building is on.
     ChangePurity { purity: ResourcePurity },
     /// Change the number of nodes of a particular purity for a pump.
@@ -148,6 +328,8 @@ pub enum Msg {
     },
     /// Change the consumption of a Station.
     ChangeConsumption { consumption: f32 },
+    /// Change the number of Somersloops amplifying a manufacturer.
+    ChangeSomersloops { somersloops: u32 },
 }
 
 /// Display for a single AccountingGraph node.
@@ -161,6 +343,19 @@ pub struct NodeDisplay {
     /// Number of virtual insert markers requested. Used to prevent flicker, since
     /// dragenter happens for a new element before dragleave for the prior element.
     insert_count: usize,
+    /// Whether the user dismissed the hint about recipe inputs with no source. Reset
+    /// when the recipe changes.
+    input_hint_dismissed: bool,
+    /// Reader for a file dropped on this group, which must be kept alive until it
+    /// finishes loading.
+    file_reader: Option<FileReader>,
+    /// Number of drag enters over this collapsed group not yet matched by a leave.
+    hover_count: usize,
+    /// Timer to expand this collapsed group while something is dragged over it.
+    expand_timer: Option<Timeout>,
+    /// Why the most recent copy to or paste from the system clipboard failed, until
+    /// dismissed.
+    clipboard_error: Option<AttrValue>,
 }
 
 impl Component for NodeDisplay {
@@ -185,14 +380,78 @@ impl Component for NodeDisplay {
                     NodeKind::Building(building) => {
                         let mut new_bldg = building.clone();
                         new_bldg.copies = copies;
-                        match new_bldg.build_node(&db) {
-                            Ok(new_node) => ctx.props().replace.emit((our_idx, new_node)),
-                            Err(e) => warn!("Unable to build node: {}", e),
+                        self.replace_building(ctx, &db, new_bldg);
+                    }
+                }
+                false
+            }
+            Msg::ToggleDeleteLock => {
+                match ctx.props().node.kind() {
+                    NodeKind::Group(group) => {
+                        let mut new_group = group.clone();
+                        new_group.delete_locked = !new_group.delete_locked;
+                        ctx.props().replace.emit((our_idx, new_group.into()));
+                    }
+                    NodeKind::Building(building) => {
+                        let mut new_bldg = building.clone();
+                        new_bldg.delete_locked = !new_bldg.delete_locked;
+                        self.replace_building(ctx, &db, new_bldg);
+                    }
+                }
+                false
+            }
+            Msg::SetTags { tags } => {
+                match ctx.props().node.kind() {
+                    NodeKind::Group(group) => {
+                        let mut new_group = group.clone();
+                        new_group.tags = tags;
+                        ctx.props().replace.emit((our_idx, new_group.into()));
+                    }
+                    NodeKind::Building(building) => {
+                        let mut new_bldg = building.clone();
+                        new_bldg.tags = tags;
+                        self.replace_building(ctx, &db, new_bldg);
+                    }
+                }
+                false
+            }
+            Msg::SetNote { note } => {
+                let note: AttrValue = note.trim().to_owned().into();
+                if note != *ctx.props().node.note() {
+                    match ctx.props().node.kind() {
+                        NodeKind::Group(group) => {
+                            let mut new_group = group.clone();
+                            new_group.note = note;
+                            ctx.props().replace.emit((our_idx, new_group.into()));
+                        }
+                        NodeKind::Building(building) => {
+                            let mut new_bldg = building.clone();
+                            new_bldg.note = note;
+                            self.replace_building(ctx, &db, new_bldg);
+                        }
+                    }
+                }
+                false
+            }
+            Msg::ToggleSection { section } => {
+                if let NodeKind::Building(building) = ctx.props().node.kind() {
+                    let mut new_bldg = building.clone();
+                    match section {
+                        BuildingSection::Settings => {
+                            new_bldg.hide_settings = !new_bldg.hide_settings
                         }
+                        BuildingSection::Balance => new_bldg.hide_balance = !new_bldg.hide_balance,
                     }
+                    self.replace_building(ctx, &db, new_bldg);
+                } else {
+                    warn!("Cannot toggle sections of a non-building");
                 }
                 false
             }
+            Msg::DismissInputHint => {
+                self.input_hint_dismissed = true;
+                true
+            }
             Msg::ReplaceChild { idx, replacement } => {
                 if let NodeKind::Group(group) = ctx.props().node.kind() {
                     if idx < group.children.len() {
@@ -212,15 +471,26 @@ impl Component for NodeDisplay {
             }
             Msg::DeleteChild { idx } => {
                 if let NodeKind::Group(group) = ctx.props().node.kind() {
-                    if idx < group.children.len() {
-                        let mut new_group = group.clone();
-                        new_group.children.remove(idx);
-                        ctx.props().replace.emit((our_idx, new_group.into()));
-                    } else {
+                    if idx >= group.children.len() {
                         warn!(
                             "Cannot delete child index {}; out of range for this group",
                             idx
                         );
+                    } else if group.children[idx].deletion_blocked() {
+                        let name = node_name(&group.children[idx], &ctx.db());
+                        let mut path = ctx.props().path.clone();
+                        path.push(idx);
+                        ctx.notify(
+                            Notice::new(format!(
+                                "Can't delete {name}; it or one of its children is locked \
+                                against deletion. Unlock it first."
+                            ))
+                            .at(path),
+                        );
+                    } else {
+                        let mut new_group = group.clone();
+                        new_group.children.remove(idx);
+                        ctx.props().replace.emit((our_idx, new_group.into()));
                     }
                 } else {
                     warn!("Cannot delete child of a non-group");
@@ -252,6 +522,33 @@ impl Component for NodeDisplay {
                 }
                 false
             }
+            Msg::SortChildren { key, descending } => {
+                if let NodeKind::Group(group) = ctx.props().node.kind() {
+                    let new_group = group.sorted_by(key, descending, &db);
+                    if new_group != *group {
+                        ctx.props().replace.emit((our_idx, new_group.into()));
+                    }
+                } else {
+                    warn!("Cannot sort children of a non-group");
+                }
+                false
+            }
+            Msg::MergeDuplicates => {
+                if let NodeKind::Group(group) = ctx.props().node.kind() {
+                    match group.merge_duplicate_buildings(&db) {
+                        Some(new_group) => ctx.props().replace.emit((our_idx, new_group.into())),
+                        None => warn!("No duplicate buildings to merge"),
+                    }
+                } else {
+                    warn!("Cannot merge children of a non-group");
+                }
+                false
+            }
+            Msg::Revalidate => {
+                let rebuilt = ctx.props().node.rebuild(&db);
+                ctx.props().replace.emit((our_idx, rebuilt));
+                false
+            }
             Msg::AddChild { child } => {
                 if let NodeKind::Group(group) = ctx.props().node.kind() {
                     let mut new_group = group.clone();
@@ -262,6 +559,49 @@ impl Component for NodeDisplay {
                 }
                 false
             }
+            Msg::DropFile { insert_pos, file } => {
+                self.insert_pos = None;
+                self.insert_count = 0;
+                let link = ctx.link().clone();
+                self.file_reader = Some(read_as_text(&file, move |result| {
+                    link.send_message(Msg::FileLoaded { insert_pos, result })
+                }));
+                true
+            }
+            Msg::FileLoaded { insert_pos, result } => {
+                self.file_reader = None;
+                let report = match result
+                    .map_err(|e| e.to_string())
+                    .and_then(|json| parse_import(&json, &db).map_err(|e| e.to_string()))
+                {
+                    Ok(report) => report,
+                    Err(e) => {
+                        ctx.notify(Notice::new(format!("Unable to import dropped file: {e}")));
+                        return false;
+                    }
+                };
+                if !report.errors.is_empty() {
+                    ctx.notify(
+                        Notice::new(format!(
+                            "{} imported buildings don't match the database",
+                            report.errors.len()
+                        ))
+                        .at(ctx.props().path.clone()),
+                    );
+                }
+                if let Some(notice) = database_mismatch_notice(&report.root, &db) {
+                    ctx.notify(notice.at(ctx.props().path.clone()));
+                }
+                if let NodeKind::Group(group) = ctx.props().node.kind() {
+                    let mut new_group = group.clone();
+                    let insert_pos = insert_pos.min(new_group.children.len());
+                    new_group.children.insert(insert_pos, report.root);
+                    ctx.props().replace.emit((our_idx, new_group.into()));
+                } else {
+                    warn!("Cannot import into a non-group");
+                }
+                false
+            }
             Msg::Rename { name } => {
                 if let NodeKind::Group(group) = ctx.props().node.kind() {
                     let name = name.trim().to_owned().into();
@@ -275,6 +615,94 @@ impl Component for NodeDisplay {
                 }
                 false
             }
+            Msg::SetColor { color } => {
+                if let NodeKind::Group(group) = ctx.props().node.kind() {
+                    if group.color != color {
+                        let mut new_group = group.clone();
+                        new_group.color = color;
+                        ctx.props().replace.emit((our_idx, new_group.into()));
+                    }
+                } else {
+                    warn!("Cannot set the color of a non-group");
+                }
+                false
+            }
+            Msg::ToggleByproduct { item } => {
+                if let NodeKind::Group(group) = ctx.props().node.kind() {
+                    let mut new_group = group.clone();
+                    if !new_group.byproducts.remove(&item) {
+                        new_group.byproducts.insert(item);
+                    }
+                    ctx.props().replace.emit((our_idx, new_group.into()));
+                } else {
+                    warn!("Cannot mark byproducts of a non-group");
+                }
+                false
+            }
+            Msg::SetInterface { interface } => {
+                if let NodeKind::Group(group) = ctx.props().node.kind() {
+                    let new_group = Group {
+                        interface,
+                        ..group.clone()
+                    };
+                    ctx.props().replace.emit((our_idx, new_group.into()));
+                } else {
+                    warn!("Cannot declare the interface of a non-group");
+                }
+                false
+            }
+            Msg::DistributeOutput {
+                item,
+                target,
+                weights,
+            } => {
+                let NodeKind::Group(group) = ctx.props().node.kind() else {
+                    warn!("Cannot share the output of a non-group");
+                    return false;
+                };
+                let rounding = ctx.settings().clock_rounding;
+                let Some(weighted) =
+                    group.distribute_output(&db, item, target, &weights, |clock| {
+                        rounding.apply(clock)
+                    })
+                else {
+                    warn!("Cannot share the output of {item} between these buildings");
+                    return false;
+                };
+                if !weighted.exact() {
+                    let (name, _) = item_label(db.get(item), item, &ctx.item_names());
+                    let unit = ctx.settings().rate_unit;
+                    ctx.notify(
+                        Notice::new(format!(
+                            "Buildings make {}{} {name} instead of the {}{} target",
+                            rounded(unit.convert(weighted.achieved())),
+                            unit.suffix(),
+                            rounded(unit.convert(target)),
+                            unit.suffix(),
+                        ))
+                        .at(ctx.props().path.clone()),
+                    );
+                }
+                ctx.props().replace.emit((our_idx, weighted.group.into()));
+                false
+            }
+            Msg::Export => {
+                if let NodeKind::Group(group) = ctx.props().node.kind() {
+                    let mut metadata = ctx.factory_metadata();
+                    // A subgroup is its own factory, so only keep the metadata which
+                    // still applies to it.
+                    if !ctx.props().path.is_empty() {
+                        if !group.name.is_empty() {
+                            metadata.title = group.name.clone();
+                        }
+                        metadata.description = Default::default();
+                    }
+                    download_factory(&Factory::export(group, metadata, &db, &ctx.item_names()));
+                } else {
+                    warn!("Cannot export a building");
+                }
+                false
+            }
             Msg::DragEnter { insert_pos } => {
                 self.insert_count = self
                     .insert_count
@@ -295,6 +723,27 @@ impl Component for NodeDisplay {
                     false
                 }
             }
+            Msg::Cut => self.cut(ctx),
+            Msg::Paste => {
+                self.paste(ctx);
+                false
+            }
+            Msg::CopyToClipboard => {
+                self.copy_to_clipboard(ctx);
+                self.clipboard_error.take().is_some()
+            }
+            Msg::ClipboardRead { text } => match self.paste_clipboard_text(ctx, &text) {
+                Ok(()) => self.clipboard_error.take().is_some(),
+                Err(message) => {
+                    self.clipboard_error = Some(message);
+                    true
+                }
+            },
+            Msg::ClipboardFailed { message } => {
+                self.clipboard_error = Some(message);
+                true
+            }
+            Msg::DismissClipboardError => self.clipboard_error.take().is_some(),
             Msg::DragLeave => {
                 self.insert_count = self.insert_count.saturating_sub(1);
                 if self.insert_count == 0 {
@@ -304,9 +753,27 @@ impl Component for NodeDisplay {
                     false
                 }
             }
+            Msg::HoverCollapsed => {
+                self.hover_collapsed(ctx);
+                false
+            }
+            Msg::LeaveCollapsed => {
+                self.leave_collapsed();
+                false
+            }
+            Msg::ExpandForDrop => {
+                self.hover_count = 0;
+                self.expand_timer = None;
+                if let NodeKind::Group(group) = ctx.props().node.kind() {
+                    let meta = ctx.meta(group.id).expand();
+                    ctx.props().set_metadata.emit((group.id, meta));
+                }
+                false
+            }
             Msg::MoveNode {
                 src_path,
                 dest_path,
+                copy,
             } => {
                 let path = &ctx.props().path[..];
                 let prefix_len = path.len();
@@ -321,11 +788,24 @@ impl Component for NodeDisplay {
                     // This node is the common ancestor of the source and destination
                     // paths.
                     if let NodeKind::Group(group) = ctx.props().node.kind() {
-                        if let Some(new_group) = graph_manipulation::move_child(
-                            group,
-                            &src_path[prefix_len..],
-                            &dest_path[prefix_len..],
-                        ) {
+                        let src = &src_path[prefix_len..];
+                        let dest = &dest_path[prefix_len..];
+                        let new_group = if copy {
+                            let new_meta = RefCell::new(HashMap::new());
+                            let new_group = graph_manipulation::copy_child(
+                                group,
+                                src,
+                                dest,
+                                &|old: &Group, new: &mut Group| {
+                                    new_meta.borrow_mut().insert(new.id, ctx.meta(old.id));
+                                },
+                            );
+                            ctx.props().batch_set_metadata.emit(new_meta.into_inner());
+                            new_group
+                        } else {
+                            graph_manipulation::move_child(group, src, dest)
+                        };
+                        if let Some(new_group) = new_group {
                             ctx.props().replace.emit((our_idx, new_group.into()));
                         }
                     } else {
@@ -333,8 +813,19 @@ impl Component for NodeDisplay {
                     }
                 } else {
                     // No common ancestor yet, ask parent to do the move.
-                    ctx.props().move_node.emit((src_path, dest_path));
+                    ctx.props().move_node.emit((src_path, dest_path, copy));
+                }
+                if self.insert_pos.is_some() {
+                    self.insert_pos = None;
+                    true
+                } else {
+                    false
                 }
+            }
+            Msg::MoveSelection { dest_path, copy } => {
+                ctx.props()
+                    .selection_action
+                    .emit(SelectionAction::MoveTo { dest_path, copy });
                 if self.insert_pos.is_some() {
                     self.insert_pos = None;
                     true
@@ -354,10 +845,7 @@ impl Component for NodeDisplay {
                             }
                             None => warn!("New building ID is unknown."),
                         }
-                        match new_bldg.build_node(&db) {
-                            Ok(new_node) => ctx.props().replace.emit((our_idx, new_node)),
-                            Err(e) => warn!("Unable to build node: {}", e),
-                        }
+                        self.replace_building(ctx, &db, new_bldg);
                     }
                 } else {
                     warn!("Cannot change building type id of a non-building");
@@ -379,9 +867,13 @@ impl Component for NodeDisplay {
                             ..
                         }) => {
                             if !m.available_recipes.contains(&id) {
-                                warn!(
-                                    "Recipe {} is not available for building {}",
-                                    id, building_id
+                                self.report_build_error(
+                                    ctx,
+                                    &db,
+                                    BuildError::IncompatibleRecipe {
+                                        recipe: id,
+                                        building: building_id,
+                                    },
                                 );
                                 return false;
                             }
@@ -416,10 +908,8 @@ impl Component for NodeDisplay {
                     settings,
                     ..building.clone()
                 };
-                match new_bldg.build_node(&db) {
-                    Ok(new_node) => ctx.props().replace.emit((our_idx, new_node)),
-                    Err(e) => warn!("Unable to build node: {}", e),
-                }
+                self.input_hint_dismissed = false;
+                self.replace_building(ctx, &db, new_bldg);
                 false
             }
             Msg::ChangeItem { id } => {
@@ -431,16 +921,17 @@ impl Component for NodeDisplay {
                     }
                 };
                 let kind_id = if let Some(building_id) = building.building {
+                    let incompatible = BuildError::IncompatibleItem {
+                        item: id,
+                        building: building_id,
+                    };
                     match db.get(building_id) {
                         Some(BuildingType {
                             kind: BuildingKind::Miner(m),
                             ..
                         }) => {
                             if !m.allowed_resources.contains(&id) {
-                                warn!(
-                                    "Resource {} is not available for building {}",
-                                    id, building_id
-                                );
+                                self.report_build_error(ctx, &db, incompatible);
                                 return false;
                             }
                             BuildingKindId::Miner
@@ -450,7 +941,7 @@ impl Component for NodeDisplay {
                             ..
                         }) => {
                             if !g.allowed_fuel.contains(&id) {
-                                warn!("Fuel {} is not available for building {}", id, building_id);
+                                self.report_build_error(ctx, &db, incompatible);
                                 return false;
                             }
                             BuildingKindId::Generator
@@ -460,10 +951,7 @@ impl Component for NodeDisplay {
                             ..
                         }) => {
                             if !p.allowed_resources.contains(&id) {
-                                warn!(
-                                    "Resource {} is not available for building {}",
-                                    id, building_id
-                                );
+                                self.report_build_error(ctx, &db, incompatible);
                                 return false;
                             }
                             BuildingKindId::Pump
@@ -473,7 +961,7 @@ impl Component for NodeDisplay {
                             ..
                         }) => {
                             if !s.allowed_fuel.contains(&id) {
-                                warn!("Fuel {} is not available for building {}", id, building_id);
+                                self.report_build_error(ctx, &db, incompatible);
                                 return false;
                             }
                             BuildingKindId::Station
@@ -518,7 +1006,6 @@ impl Component for NodeDisplay {
                         GeneratorSettings {
                             fuel: Some(id),
                             clock_speed: settings.clock_speed(),
-                            ..Default::default()
                         }
                         .into()
                     }
@@ -557,10 +1044,7 @@ impl Component for NodeDisplay {
                     settings,
                     ..building.clone()
                 };
-                match new_bldg.build_node(&db) {
-                    Ok(new_node) => ctx.props().replace.emit((our_idx, new_node)),
-                    Err(e) => warn!("Unable to build node: {}", e),
-                }
+                self.replace_building(ctx, &db, new_bldg);
 
                 false
             }
@@ -569,16 +1053,58 @@ impl Component for NodeDisplay {
                     if building.settings.clock_speed() != clock_speed {
                         let mut new_bldg = building.clone();
                         new_bldg.settings.set_clock_speed(clock_speed);
-                        match new_bldg.build_node(&db) {
-                            Ok(new_node) => ctx.props().replace.emit((our_idx, new_node)),
-                            Err(e) => warn!("Unable to build node: {}", e),
-                        }
+                        self.replace_building(ctx, &db, new_bldg);
                     }
                 } else {
                     warn!("Cannot change clock speed of a non-building");
                 }
                 false
             }
+            Msg::SetOutputCap { item, cap } => {
+                let NodeKind::Building(building) = ctx.props().node.kind() else {
+                    warn!("Cannot cap the output of a non-building");
+                    return false;
+                };
+                if building.output_caps.get(&item).copied() != cap {
+                    let mut new_bldg = building.clone();
+                    match cap {
+                        Some(cap) => new_bldg.output_caps.insert(item, cap),
+                        None => new_bldg.output_caps.remove(&item),
+                    };
+                    self.replace_building(ctx, &db, new_bldg);
+                }
+                false
+            }
+            Msg::SetOutputRate { item, rate } => {
+                let building = match ctx.props().node.kind() {
+                    NodeKind::Building(building) => building,
+                    _ => {
+                        warn!("Cannot set output rate of a non-building");
+                        return false;
+                    }
+                };
+                let rounding = ctx.settings().clock_rounding;
+                match building
+                    .clock_for_output(&db, item, rate)
+                    .map(|clock| clock.rounded(rate, |clock| rounding.apply(clock)))
+                {
+                    Some(clock) => {
+                        if !clock.exact {
+                            warn!(
+                                "Output of {rate} {item} is not achievable, snapped to {}",
+                                clock.achieved
+                            );
+                        }
+                        if building.settings.clock_speed() != clock.clock_speed {
+                            let mut new_bldg = building.clone();
+                            new_bldg.settings.set_clock_speed(clock.clock_speed);
+                            self.replace_building(ctx, &db, new_bldg);
+                        }
+                    }
+                    None => warn!("Cannot set output rate of {item} for this building"),
+                }
+                false
+            }
             Msg::ChangePurity { purity } => {
                 let building = match ctx.props().node.kind() {
                     NodeKind::Building(building) => building,
@@ -597,11 +1123,7 @@ impl Component for NodeDisplay {
                         ..ms.clone()
                     }
                     .into(),
-                    BuildingSettings::Geothermal(gs) => GeothermalSettings {
-                        purity,
-                        ..gs.clone()
-                    }
-                    .into(),
+                    BuildingSettings::Geothermal(_) => GeothermalSettings { purity }.into(),
                     _ => {
                         warn!(
                             "Building kind {:?} does not support purity",
@@ -614,10 +1136,7 @@ impl Component for NodeDisplay {
                     settings,
                     ..building.clone()
                 };
-                match new_bldg.build_node(&db) {
-                    Ok(new_node) => ctx.props().replace.emit((our_idx, new_node)),
-                    Err(e) => warn!("Unable to build node: {}", e),
-                }
+                self.replace_building(ctx, &db, new_bldg);
 
                 false
             }
@@ -655,10 +1174,7 @@ impl Component for NodeDisplay {
                     settings,
                     ..building.clone()
                 };
-                match new_bldg.build_node(&db) {
-                    Ok(new_node) => ctx.props().replace.emit((our_idx, new_node)),
-                    Err(e) => warn!("Unable to build node: {}", e),
-                }
+                self.replace_building(ctx, &db, new_bldg);
 
                 false
             }
@@ -692,10 +1208,37 @@ impl Component for NodeDisplay {
                     settings,
                     ..building.clone()
                 };
-                match new_bldg.build_node(&db) {
-                    Ok(new_node) => ctx.props().replace.emit((our_idx, new_node)),
-                    Err(e) => warn!("Unable to build node: {}", e),
-                }
+                self.replace_building(ctx, &db, new_bldg);
+
+                false
+            }
+            Msg::ChangeSomersloops { somersloops } => {
+                let building = match ctx.props().node.kind() {
+                    NodeKind::Building(building) => building,
+                    _ => {
+                        warn!("Cannot change Somersloops of a non-building");
+                        return false;
+                    }
+                };
+                let settings = match &building.settings {
+                    BuildingSettings::Manufacturer(ms) => ManufacturerSettings {
+                        somersloops,
+                        ..ms.clone()
+                    }
+                    .into(),
+                    _ => {
+                        warn!(
+                            "Building kind {:?} does not support Somersloops",
+                            building.settings.kind_id()
+                        );
+                        return false;
+                    }
+                };
+                let new_bldg = Building {
+                    settings,
+                    ..building.clone()
+                };
+                self.replace_building(ctx, &db, new_bldg);
 
                 false
             }
@@ -717,7 +1260,27 @@ impl Component for NodeDisplay {
 const DRAG_INSERT_POINT: &str = "drag-insert-point";
 
 impl NodeDisplay {
-    /// Creates the delete button, if the parent allows this node to be deleted.
+    /// Build the given building and ask the parent to replace this node with it. If the
+    /// building has an error, it is kept as a warning node so the error is visible.
+    fn replace_building(&self, ctx: &Context<Self>, db: &Database, building: Building) {
+        let our_idx = ctx.props().path.last().copied().unwrap_or_default();
+        let node = match building.clone().build_node(db) {
+            Ok(node) => node,
+            Err(e) => {
+                self.report_build_error(ctx, db, e);
+                e.into_warning_node(building)
+            }
+        };
+        ctx.props().replace.emit((our_idx, node));
+    }
+
+    /// Tell the user why an edit to this node couldn't be built or was refused.
+    fn report_build_error(&self, ctx: &Context<Self>, db: &Database, error: BuildError) {
+        ctx.notify(Notice::build_error(error, db).at(ctx.props().path.clone()));
+    }
+
+    /// Creates the delete button and the lock which guards it, if the parent allows this
+    /// node to be deleted.
     fn delete_button(&self, ctx: &Context<Self>) -> Html {
         match ctx.props().delete.clone() {
             Some(delete_from_parent) => {
@@ -727,11 +1290,33 @@ impl NodeDisplay {
                     .last()
                     .copied()
                     .expect("Parent provided a delete callback, but this is the root node.");
+                let node = &ctx.props().node;
+                let locked = node.delete_locked();
+                let toggle_lock = ctx.link().callback(|_| Msg::ToggleDeleteLock);
+                let (lock_icon, lock_title) = if locked {
+                    ("lock", "Unlock to allow deletion")
+                } else {
+                    ("lock_open", "Lock against deletion")
+                };
+                let blocked = node.deletion_blocked();
+                let delete_title = if locked {
+                    "Locked against deletion"
+                } else if blocked {
+                    "Contains nodes which are locked against deletion"
+                } else {
+                    "Delete"
+                };
                 let onclick = Callback::from(move |_| delete_from_parent.emit(idx));
                 html! {
-                    <button {onclick} class="delete" title="Delete">
-                        <span class="material-icons">{"delete"}</span>
-                    </button>
+                    <>
+                        <button onclick={toggle_lock} title={lock_title}
+                            class={classes!("delete-lock", locked.then_some("locked"))}>
+                            <span class="material-icons">{lock_icon}</span>
+                        </button>
+                        <button {onclick} class="delete" title={delete_title} disabled={blocked}>
+                            <span class="material-icons">{"delete"}</span>
+                        </button>
+                    </>
                 }
             }
             None => html! {},
@@ -758,4 +1343,35 @@ impl NodeDisplay {
             None => html! {},
         }
     }
+
+    /// Creates the editor for the tags of this node.
+    fn tag_editor(&self, ctx: &Context<Self>) -> Html {
+        let update = ctx.link().callback(|tags| Msg::SetTags { tags });
+        html! {
+            <Tags tags={ctx.props().node.tags().to_vec()} {update} />
+        }
+    }
+
+    /// Creates the editor for the note of this node.
+    fn note_editor(&self, ctx: &Context<Self>) -> Html {
+        let update = ctx.link().callback(|note| Msg::SetNote { note });
+        html! {
+            <Note note={ctx.props().node.note().clone()} {update} />
+        }
+    }
+
+    /// Whether this node or one of its ancestors has a tag matching the user's tag
+    /// filter. Always true if there is no filter.
+    fn tag_matched(&self, ctx: &Context<Self>) -> bool {
+        let filter = &ctx.settings().tag_filter;
+        ctx.props().tag_matched || filter.trim().is_empty() || ctx.props().node.has_tag(filter)
+    }
+
+    /// Class to dim this node if neither it, its ancestors, nor its descendants match the
+    /// user's tag filter.
+    fn tag_dimmed(&self, ctx: &Context<Self>) -> Option<&'static str> {
+        let filter = &ctx.settings().tag_filter;
+        let matched = self.tag_matched(ctx) || ctx.props().node.contains_tag(filter);
+        (!matched).then_some("tag-dimmed")
+    }
 }
@@ -5,13 +5,17 @@
 //   You may obtain a copy of the License at
 //
 //       http://www.apache.org/licenses/LICENSE-2.0
-use satisfactory_accounting::database::Item;
+use satisfactory_accounting::database::{Item, ItemId};
+use satisfactory_accounting::logistics::Lines;
 use serde::{Deserialize, Serialize};
 use yew::prelude::*;
 
-use super::NodeDisplay;
+use super::building::has_clock_speed;
+use super::{Msg, NodeDisplay};
+use crate::clickedit::ClickEdit;
 use crate::node_display::icon::Icon;
-use crate::CtxHelper;
+use crate::numbers::RateUnit;
+use crate::{item_label, CtxHelper};
 
 /// How entries in the balance should be sorted.
 #[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
@@ -24,16 +28,79 @@ pub enum BalanceSortMode {
     IOItem,
 }
 
+/// Icon flagging a building input or output which needs more than one belt.
+pub(super) const BELT_WARNING_ICON: &str = "warning";
+
+/// Icon flagging a building input or output which needs more than one pipe.
+pub(super) const PIPE_WARNING_ICON: &str = "plumbing";
+
+/// Icon hinting that a building's input rates scale with its clock speed.
+pub(super) const CLOCK_HINT_ICON: &str = "timer";
+
+/// Special state of an entry in the balance, marked with a class on the entry row.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub(super) enum EntryMarker {
+    /// Surplus of a group marked as a byproduct to sink.
+    Byproduct,
+    /// Expected rate of an output which varies between runs.
+    Averaged,
+    /// Output of a building limited by a cap.
+    Capped,
+    /// Capped output where the building makes more than the cap, so it backs up.
+    BackedUp,
+}
+
+impl EntryMarker {
+    /// All markers, in the order they're explained in the legend.
+    pub(super) const ALL: [Self; 4] = [
+        Self::Byproduct,
+        Self::Averaged,
+        Self::Capped,
+        Self::BackedUp,
+    ];
+
+    /// Class added to the entry row.
+    pub(super) fn class(self) -> &'static str {
+        match self {
+            Self::Byproduct => "byproduct",
+            Self::Averaged => "averaged",
+            Self::Capped => "capped",
+            Self::BackedUp => "backed-up",
+        }
+    }
+
+    /// Explanation of the marker for the legend.
+    pub(super) fn description(self) -> &'static str {
+        match self {
+            Self::Byproduct => "Surplus marked as a byproduct to sink",
+            Self::Averaged => "Expected rate of an output which varies between runs",
+            Self::Capped => "Output capped to what one belt or merger carries",
+            Self::BackedUp => "Capped output which backs up because more is made",
+        }
+    }
+}
+
 impl NodeDisplay {
     /// Build the display for a node's balance.
+    ///
+    /// Groups leave out items which they make exactly as much of as they use, unless the
+    /// user asked to see them.
     pub(super) fn view_balance(&self, ctx: &Context<Self>, vertical: bool) -> Html {
+        if ctx.props().node.building().is_some() {
+            return self.view_building_balance(ctx, vertical);
+        }
         let balance = ctx.props().node.balance();
         let db = ctx.db();
+        let show_cancelled = ctx.settings().show_cancelled_balances;
+        let shown = |itemid: ItemId, rate: f32| {
+            (show_cancelled || rate != 0.0) && self.item_shown(ctx, itemid)
+        };
         let item_balances: Html = match ctx.settings().balance_sort_mode {
             BalanceSortMode::Item => balance
                 .balances
                 .iter()
-                .map(|(&itemid, &rate)| display_item(db.get(itemid), rate))
+                .filter(|(&itemid, &rate)| shown(itemid, rate))
+                .map(|(&itemid, &rate)| self.display_item(ctx, db.get(itemid), itemid, rate))
                 .collect(),
             BalanceSortMode::IOItem => balance
                 .balances
@@ -41,50 +108,388 @@ impl NodeDisplay {
                 .filter(|(_, &rate)| rate > 0.0)
                 .chain(balance.balances.iter().filter(|(_, &rate)| rate == 0.0))
                 .chain(balance.balances.iter().filter(|(_, &rate)| rate < 0.0))
-                // Weird NaN handling? I guess I could probably just use is_nan here?
-                .chain(
-                    balance
-                        .balances
-                        .iter()
-                        .filter(|(_, &rate)| !(rate < 0.0) && !(rate == 0.0) && !(rate > 0.0)),
-                )
-                .map(|(&itemid, &rate)| display_item(db.get(itemid), rate))
+                .chain(balance.balances.iter().filter(|(_, &rate)| rate.is_nan()))
+                .filter(|(&itemid, &rate)| shown(itemid, rate))
+                .map(|(&itemid, &rate)| self.display_item(ctx, db.get(itemid), itemid, rate))
                 .collect(),
         };
         html! {
             <div class={classes!("balance", balance_block_style(vertical))} title="Power">
-                <div class={classes!("entry-row", "power-entry", balance_style(balance.power))}>
-                    <Icon icon="power-line" />
-                    <div class="balance-value">{rounded(balance.power)}</div>
-                </div>
+                if !ctx.settings().hide_power {
+                    <div class={classes!("entry-row", "power-entry", balance_style(balance.power))}>
+                        <Icon icon="power-line" />
+                        <div class="balance-value">{ctx.settings().format(balance.power)}</div>
+                    </div>
+                }
+                { self.sink_entry(ctx) }
+                { rate_unit_label(ctx) }
                 <div class="item-entries">
                 { item_balances }
                 </div>
             </div>
         }
     }
-}
 
-fn display_item(item: Option<&Item>, rate: f32) -> Html {
-    match item {
-        Some(item) => html! {
-            <div class={classes!("entry-row", balance_style(rate))}
-                title={Some(item.name.clone())}>
-                <Icon icon={item.image.clone()}/>
-                <div class="balance-value">{rounded(rate)}</div>
+    /// AWESOME Sink points per unit time the surplus items of the node are worth. Hidden if
+    /// no surplus can be sunk, which includes databases from before sink points were
+    /// recorded.
+    fn sink_entry(&self, ctx: &Context<Self>) -> Html {
+        let db = ctx.db();
+        let settings = ctx.settings();
+        let unit = settings.rate_unit;
+        let value = ctx
+            .props()
+            .node
+            .balance()
+            .sink_value(&db, &settings.sink_excluded);
+        let points = unit.convert(value.points);
+        if rounded(points) <= 0.0 {
+            return html! {};
+        }
+        let mut title = format!("AWESOME Sink points{} from surplus", unit.suffix());
+        let partial = (!value.unsinkable.is_empty()).then(|| {
+            let names = ctx.item_names();
+            let unsinkable: Vec<_> = value
+                .unsinkable
+                .iter()
+                .map(|&item| item_label(db.get(item), item, &names).0)
+                .collect();
+            title.push_str(&format!("; can't sink {}", unsinkable.join(", ")));
+            "partial"
+        });
+        html! {
+            <div class={classes!("entry-row", "sink-entry", partial)} {title}>
+                <Icon icon="awesome-sink" />
+                <div class="balance-value">{settings.format(points)}</div>
             </div>
-        },
-        None => html! {
-            <div class={classes!("entry-row", balance_style(rate))}
-                title="Unknown Item">
-                <Icon />
-                <div class="balance-value">{rounded(rate)}</div>
+        }
+    }
+
+    /// Compact net power of the node, for its header.
+    pub(super) fn power_readout(&self, ctx: &Context<Self>) -> Html {
+        let power = ctx.props().node.balance().power;
+        let text = ctx.settings().format(power);
+        if text == "0" {
+            return html! {};
+        }
+        html! {
+            <span class={classes!("PowerReadout", balance_style(power))} title="Net Power">
+                {text}{" MW"}
+            </span>
+        }
+    }
+
+    /// Build the display for a building's balance, with inputs and outputs shown as
+    /// separate, labeled groups.
+    fn view_building_balance(&self, ctx: &Context<Self>, vertical: bool) -> Html {
+        let balance = ctx.props().node.balance();
+        let db = ctx.db();
+        let clock_hint = self.input_clock_hint(ctx);
+        let section = |label: &'static str, class: &'static str, consumed: bool| {
+            let entries: Vec<Html> = balance
+                .balances
+                .iter()
+                .filter(|(&itemid, &rate)| (rate < 0.0) == consumed && self.item_shown(ctx, itemid))
+                .map(|(&itemid, &rate)| self.display_item(ctx, db.get(itemid), itemid, rate))
+                .collect();
+            if entries.is_empty() {
+                html! {}
+            } else {
+                html! {
+                    <div class={classes!("item-section", class)}>
+                        <span class="section-label">{label}</span>
+                        if consumed {
+                            { clock_hint.clone() }
+                        }
+                        { for entries }
+                    </div>
+                }
+            }
+        };
+        html! {
+            <div class={classes!("balance", balance_block_style(vertical))} title="Power">
+                if !ctx.settings().hide_power {
+                    <div class={classes!("entry-row", "power-entry", balance_style(balance.power))}>
+                        <Icon icon="power-line" />
+                        <div class="balance-value">{ctx.settings().format(balance.power)}</div>
+                    </div>
+                }
+                { self.sink_entry(ctx) }
+                { rate_unit_label(ctx) }
+                <div class="item-entries split">
+                    { section("Consumes", "consumes", true) }
+                    { section("Produces", "produces", false) }
+                </div>
             </div>
-        },
+        }
+    }
+
+    /// Hint that a building's input rates scale with its clock speed, so whatever feeds
+    /// it has to scale too. Only shown for buildings with an adjustable clock.
+    fn input_clock_hint(&self, ctx: &Context<Self>) -> Html {
+        let Some(building) = ctx.props().node.building() else {
+            return html! {};
+        };
+        if !has_clock_speed(&building.settings) {
+            return html! {};
+        }
+        let clock_speed = building.settings.clock_speed();
+        let title = format!(
+            "Input rates scale with the clock speed ({}%), so feeders must scale too",
+            rounded(clock_speed * 100.0)
+        );
+        let scaled = (clock_speed != 1.0).then_some("scaled");
+        html! {
+            <span class={classes!("clock-hint", "material-icons-outlined", scaled)} {title}>
+                {CLOCK_HINT_ICON}
+            </span>
+        }
+    }
+
+    /// Display a single item in the balance. Items which are produced can be clicked to
+    /// trace where they come from, outputs of groups can be right-clicked to mark them as
+    /// byproducts to sink, and outputs of buildings can be right-clicked to cap them at
+    /// the capacity of a single belt or pipe.
+    fn display_item(
+        &self,
+        ctx: &Context<Self>,
+        item: Option<&Item>,
+        itemid: ItemId,
+        rate: f32,
+    ) -> Html {
+        let (onclick, traceable) = if rate > 0.0 {
+            let path = ctx.props().path.clone();
+            let trace_item = ctx.props().trace_item.clone();
+            let onclick = Callback::from(move |_| trace_item.emit((path.clone(), itemid)));
+            (Some(onclick), Some("traceable"))
+        } else {
+            (None, None)
+        };
+        let (oncontextmenu, byproduct) = match ctx.props().node.group() {
+            Some(group) if rate > 0.0 => {
+                let oncontextmenu = ctx.link().callback(move |e: MouseEvent| {
+                    e.prevent_default();
+                    Msg::ToggleByproduct { item: itemid }
+                });
+                let byproduct = group
+                    .byproducts
+                    .contains(&itemid)
+                    .then_some(EntryMarker::Byproduct.class());
+                (Some(oncontextmenu), byproduct)
+            }
+            None if rate > 0.0 => match (ctx.props().node.building(), item) {
+                (Some(building), Some(item)) => {
+                    let settings = ctx.settings();
+                    let cap = (!building.output_caps.contains_key(&itemid))
+                        .then(|| Lines::capacity(item, settings.belt_tier, settings.pipe_tier));
+                    let oncontextmenu = ctx.link().callback(move |e: MouseEvent| {
+                        e.prevent_default();
+                        Msg::SetOutputCap { item: itemid, cap }
+                    });
+                    (Some(oncontextmenu), None)
+                }
+                _ => (None, None),
+            },
+            _ => (None, None),
+        };
+        let cap = ctx
+            .props()
+            .node
+            .building()
+            .and_then(|building| building.output_caps.get(&itemid).copied())
+            .filter(|_| rate > 0.0);
+        let backed_up = ctx.props().node.backed_up().get(&itemid).copied();
+        let unit = ctx.settings().rate_unit;
+        let (name, image) = item_label(item, itemid, &ctx.item_names());
+        let mut title = match byproduct {
+            Some(_) => format!("{name} (byproduct to sink)"),
+            None => name.to_string(),
+        };
+        let averaged = rate > 0.0
+            && ctx
+                .props()
+                .node
+                .building()
+                .is_some_and(|building| building.output_is_averaged(&ctx.db(), itemid));
+        if averaged {
+            title.push_str("\nExpected value: the actual output varies between runs");
+        }
+        if let Some(cap) = cap {
+            let suffix = unit.suffix();
+            title.push_str(&format!(
+                "\nCapped at {}{suffix}",
+                rounded(unit.convert(cap))
+            ));
+            if let Some(backed_up) = backed_up {
+                title.push_str(&format!(
+                    ", {}{suffix} backed up",
+                    rounded(unit.convert(backed_up))
+                ));
+            }
+        }
+        if let Some(buildings) = self.fractional_buildings(ctx, itemid, rate) {
+            title.push_str(&format!(
+                "\nNeeds {buildings:.2} buildings at 100% clock speed, so some must be clocked"
+            ));
+        }
+        let capped = cap.map(|_| EntryMarker::Capped.class());
+        let backed_up = backed_up.map(|_| EntryMarker::BackedUp.class());
+        let averaged = averaged.then_some(EntryMarker::Averaged.class());
+        // A single building's inputs have to arrive on its own belts too, so they're
+        // checked as well as outputs, and flagged since the machine can't be fed as is.
+        let building = ctx.props().node.building().is_some();
+        let lines = item
+            .filter(|_| rate > 0.0 || (building && rate < 0.0))
+            .map(|item| {
+                let settings = ctx.settings();
+                Lines::for_rate(item, rate.abs(), settings.belt_tier, settings.pipe_tier)
+            })
+            .filter(|lines| lines.count > 1);
+        if let Some(lines) = lines {
+            let (line, splitter) = match (lines.pipes, rate > 0.0) {
+                (true, _) => ("pipes", "junctions"),
+                (false, true) => ("belts", "splitters"),
+                (false, false) => ("belts", "mergers"),
+            };
+            let verb = if rate > 0.0 { "split" } else { "merged" };
+            title.push_str(&format!(
+                "\nNeeds {} {line}, {verb} with {} {splitter}",
+                lines.count, lines.splitters
+            ));
+        }
+        let belt_warning = building && lines.is_some();
+        html! {
+            <div class={classes!(
+                "entry-row",
+                balance_style(rate),
+                traceable,
+                byproduct,
+                averaged,
+                capped,
+                backed_up
+            )}
+                {title} {onclick} {oncontextmenu}>
+                <Icon icon={image}/>
+                if self.output_editable(ctx, itemid, rate) {
+                    { self.output_rate_edit(ctx, itemid, rate) }
+                } else {
+                    <div class="balance-value">{ctx.settings().format(unit.convert(rate))}</div>
+                }
+                if let Some(lines) = lines {
+                    if belt_warning {
+                        <span class="belt-warning material-icons warning">{
+                            if lines.pipes { PIPE_WARNING_ICON } else { BELT_WARNING_ICON }
+                        }</span>
+                    }
+                    <span class="line-count">{format!("\u{d7}{}", lines.count)}</span>
+                }
+                if let Some(cap) = cap {
+                    { self.output_cap_edit(ctx, itemid, cap) }
+                }
+            </div>
+        }
+    }
+
+    /// Whether the item passes the filter selected by the user, if any.
+    fn item_shown(&self, ctx: &Context<Self>, itemid: ItemId) -> bool {
+        let settings = ctx.settings();
+        settings
+            .item_filter
+            .is_none_or(|filter| filter.matches(&ctx.db(), &settings.item_groups, itemid))
+    }
+
+    /// Whether the output rate of this item can be edited directly, which is true for
+    /// items produced by overclockable buildings.
+    fn output_editable(&self, ctx: &Context<Self>, itemid: ItemId, rate: f32) -> bool {
+        rate > 0.0
+            && ctx.props().node.building().is_some_and(|building| {
+                building.clock_for_output(&ctx.db(), itemid, rate).is_some()
+            })
+    }
+
+    /// Number of buildings needed to make `rate` of `item` at 100% clock speed, if that
+    /// isn't a whole number.
+    fn fractional_buildings(&self, ctx: &Context<Self>, itemid: ItemId, rate: f32) -> Option<f32> {
+        // Counts within this much of a whole number may as well be whole.
+        const TOLERANCE: f32 = 1e-3;
+        if rate <= 0.0 {
+            return None;
+        }
+        let building = ctx.props().node.building()?;
+        let buildings = building
+            .clock_for_output(&ctx.db(), itemid, rate)?
+            .buildings_at_full_clock(rate);
+        ((buildings - buildings.round()).abs() > TOLERANCE).then_some(buildings)
+    }
+
+    /// Editor for the cap on the output of a building. Clearing it removes the cap.
+    fn output_cap_edit(&self, ctx: &Context<Self>, item: ItemId, cap: f32) -> Html {
+        let separator = ctx.settings().decimal_separator;
+        let unit = ctx.settings().rate_unit;
+        let on_commit = ctx.link().batch_callback(move |text: AttrValue| {
+            let text = text.trim();
+            if text.is_empty() {
+                Some(Msg::SetOutputCap { item, cap: None })
+            } else {
+                separator
+                    .parse(text)
+                    .filter(|cap: &f32| cap.is_finite() && *cap >= 0.0)
+                    .map(|cap| Msg::SetOutputCap {
+                        item,
+                        cap: Some(unit.to_per_minute(cap)),
+                    })
+            }
+        });
+        // Keep clicks in the editor from also tracing the item.
+        let onclick = Callback::from(|e: MouseEvent| e.stop_propagation());
+        let prefix = html! { <span>{"\u{2264}"}</span> };
+        html! {
+            <span class="output-cap" {onclick}>
+                <ClickEdit value={rounded(unit.convert(cap)).to_string()}
+                    title="Output Cap (clear to remove)"
+                    {on_commit} {prefix} />
+            </span>
+        }
+    }
+
+    /// Editor for the total output rate of a building, which sets the clock speed.
+    fn output_rate_edit(&self, ctx: &Context<Self>, item: ItemId, rate: f32) -> Html {
+        let separator = ctx.settings().decimal_separator;
+        let unit = ctx.settings().rate_unit;
+        let on_commit = ctx.link().batch_callback(move |text: AttrValue| {
+            separator
+                .parse(&text)
+                .filter(|rate: &f32| rate.is_finite() && *rate > 0.0)
+                .map(|rate| Msg::SetOutputRate {
+                    item,
+                    rate: unit.to_per_minute(rate),
+                })
+        });
+        // Keep clicks in the editor from also tracing the item.
+        let onclick = Callback::from(|e: MouseEvent| e.stop_propagation());
+        html! {
+            <span class="balance-value" {onclick}>
+                <ClickEdit value={rounded(unit.convert(rate)).to_string()}
+                    title="Total Output"
+                    {on_commit} />
+            </span>
+        }
+    }
+}
+
+/// Label for the unit of item rates, shown only when it differs from the game's usual
+/// per-minute rates.
+fn rate_unit_label(ctx: &Context<NodeDisplay>) -> Html {
+    let unit = ctx.settings().rate_unit;
+    if unit == RateUnit::PerMinute {
+        return html! {};
+    }
+    html! {
+        <span class="rate-unit" title="Item rates are per second">{unit.suffix()}</span>
     }
 }
 
-fn rounded(val: f32) -> f32 {
+pub(super) fn rounded(val: f32) -> f32 {
     (val * 100.0).round() / 100.0
 }
 
@@ -96,7 +501,7 @@ fn balance_block_style(vertical: bool) -> &'static str {
     }
 }
 
-fn balance_style(balance: f32) -> &'static str {
+pub(super) fn balance_style(balance: f32) -> &'static str {
     if balance < 0.0 {
         "negative"
     } else if balance > 0.0 {
@@ -0,0 +1,141 @@
+// Copyright 2024 Zachary Stewart
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+use std::ops::Range;
+
+use satisfactory_accounting::accounting::{Node, NodeKind};
+use satisfactory_accounting::database::Database;
+use yew::prelude::*;
+
+use crate::use_db;
+
+/// Most segments to show before the middle of the trail is elided.
+const MAX_SEGMENTS: usize = 6;
+/// Segments always shown at the start of an elided trail.
+const LEADING_SEGMENTS: usize = 2;
+
+#[derive(Debug, PartialEq, Properties)]
+pub struct Props {
+    /// Root of the factory.
+    pub root: Node,
+    /// Path to the node to show the trail to.
+    pub path: Vec<usize>,
+    /// Callback to jump to the group at the given path.
+    pub jump_to: Callback<Vec<usize>>,
+}
+
+/// Trail of group names from the root to a node, so it's clear where a deeply nested node
+/// is. Each group in the trail can be clicked to jump to it.
+#[function_component]
+pub fn Breadcrumbs(props: &Props) -> Html {
+    let db = use_db();
+    let segments: Vec<_> = (0..=props.path.len())
+        .map_while(|depth| {
+            let path = &props.path[..depth];
+            props.root.get_at(path).map(|node| (path.to_vec(), node))
+        })
+        .collect();
+    let hidden = elided(segments.len());
+
+    let segment = |(path, node): &(Vec<usize>, &Node)| {
+        let (name, unnamed) = segment_name(node, &db);
+        let unnamed = unnamed.then_some("unnamed");
+        match node.kind() {
+            NodeKind::Group(_) => {
+                let jump_to = {
+                    let jump_to = props.jump_to.clone();
+                    let path = path.clone();
+                    Callback::from(move |_| jump_to.emit(path.clone()))
+                };
+                // Keep focus on the current node, so the trail isn't hidden before the
+                // click lands.
+                let onmousedown = Callback::from(|e: MouseEvent| e.prevent_default());
+                html! {
+                    <span class={classes!("segment", "group", unnamed)} title="Jump to Group"
+                        onclick={jump_to} {onmousedown}>
+                        {name}
+                    </span>
+                }
+            }
+            NodeKind::Building(_) => html! {
+                <span class={classes!("segment", "building", unnamed)}>{name}</span>
+            },
+        }
+    };
+    let separator = || html! { <span class="material-icons separator">{"chevron_right"}</span> };
+
+    let mut trail = Vec::new();
+    for (idx, entry) in segments.iter().enumerate() {
+        if hidden.contains(&idx) {
+            if idx == hidden.start {
+                let title = segments[hidden.clone()]
+                    .iter()
+                    .map(|(_, node)| segment_name(node, &db).0)
+                    .collect::<Vec<_>>()
+                    .join(" \u{203a} ");
+                trail.push(separator());
+                trail.push(html! { <span class="segment elided" {title}>{"\u{2026}"}</span> });
+            }
+            continue;
+        }
+        if idx > 0 {
+            trail.push(separator());
+        }
+        trail.push(segment(entry));
+    }
+    html! {
+        <nav class="Breadcrumbs" aria-label="Path to the focused node">
+            { for trail }
+        </nav>
+    }
+}
+
+/// Name to show for a node, e.g. in messages about it.
+pub fn node_name(node: &Node, db: &Database) -> AttrValue {
+    segment_name(node, db).0
+}
+
+/// Name to show for a node in the trail, and whether it is a placeholder for a node with
+/// no name.
+fn segment_name(node: &Node, db: &Database) -> (AttrValue, bool) {
+    match node.kind() {
+        NodeKind::Group(group) if group.name.is_empty() => ("unnamed".into(), true),
+        NodeKind::Group(group) => (group.name.clone(), false),
+        NodeKind::Building(building) => match building.building.and_then(|id| db.get(id)) {
+            Some(building) => (building.name.clone().into(), false),
+            None => ("building".into(), true),
+        },
+    }
+}
+
+/// Range of segments to hide in the middle of a trail with `len` segments, which is empty
+/// if the whole trail fits. The first segments and the segments nearest the node are kept.
+fn elided(len: usize) -> Range<usize> {
+    if len <= MAX_SEGMENTS {
+        0..0
+    } else {
+        // One of the shown slots goes to the ellipsis.
+        LEADING_SEGMENTS..len - (MAX_SEGMENTS - LEADING_SEGMENTS - 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn elide_long_trails() {
+        assert_eq!(elided(1), 0..0);
+        assert_eq!(elided(MAX_SEGMENTS), 0..0);
+        assert_eq!(elided(7), 2..4);
+        assert_eq!(elided(20), 2..17);
+        // The trail always fits with the ellipsis.
+        for len in MAX_SEGMENTS + 1..30 {
+            assert_eq!(len - elided(len).len() + 1, MAX_SEGMENTS);
+        }
+    }
+}
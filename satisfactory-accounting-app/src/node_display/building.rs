@@ -7,31 +7,49 @@ use log::warn;
 //
 //       http://www.apache.org/licenses/LICENSE-2.0
 use satisfactory_accounting::accounting::{
-    BuildError, Building, BuildingSettings, GeneratorSettings, GeothermalSettings,
-    ManufacturerSettings, MinerSettings, PumpSettings, ResourcePurity, StationSettings,
+    power_shards_for_clock, BuildError, Building, BuildingSettings, GeneratorSettings,
+    GeothermalSettings, ManufacturerSettings, MinerSettings, PumpSettings, ResourcePurity,
+    StationSettings,
 };
-use satisfactory_accounting::database::BuildingId;
+use satisfactory_accounting::database::{BuildingId, BuildingKind, ItemAmount, ItemId};
 use yew::prelude::*;
 
+use crate::node_display::balance::rounded;
 use crate::node_display::copies::VirtualCopies;
+use crate::node_display::focus::focus_attr;
+use crate::node_display::icon::Icon;
 use crate::node_display::{Msg, NodeDisplay};
-use crate::CtxHelper;
+use crate::{item_label, CtxHelper};
 
 use building_type::BuildingTypeDisplay;
 use clock::ClockSpeed;
+pub use clock::{parse_clock_speed, ClockRounding, ClockStep, TargetDeviation};
+use exact_rate::ExactRate;
 use item::ItemDisplay;
 use multi_purity::MultiPurity;
 use purity::Purity;
 use recipe::RecipeDisplay;
+use somersloops::Somersloops;
 use station_consumption::StationConsumption;
 
+/// Section of a building node which can be hidden independently.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum BuildingSection {
+    /// The building's recipe, clock speed, and other settings.
+    Settings,
+    /// The building's balance.
+    Balance,
+}
+
 mod building_type;
-mod choose_from_list;
+pub(super) mod choose_from_list;
 mod clock;
+mod exact_rate;
 mod item;
 mod multi_purity;
 mod purity;
 mod recipe;
+mod somersloops;
 mod station_consumption;
 
 impl NodeDisplay {
@@ -39,21 +57,52 @@ impl NodeDisplay {
     pub(super) fn view_building(&self, ctx: &Context<Self>, building: &Building) -> Html {
         let update_copies = ctx.link().callback(|copies| Msg::SetCopyCount { copies });
         let change_type = ctx.link().callback(|id| Msg::ChangeType { id });
+        let category = if ctx.settings().color_by_category {
+            building
+                .building
+                .and_then(|id| ctx.db().get(id).map(|b| b.category()))
+                .map(|category| format!("category-{}", category.ident()))
+        } else {
+            None
+        };
+        let target = ctx.settings().efficiency_target.and_then(|target| {
+            has_clock_speed(&building.settings)
+                .then(|| TargetDeviation::of(building.settings.clock_speed(), target).class())
+        });
+        let cut = self.is_cut(ctx).then_some("cut");
+        let selected = self.is_selected(ctx).then_some("selected");
+        let dimmed = self.tag_dimmed(ctx);
+        let focused = self.is_focused(ctx).then_some("focused");
+        let onkeydown = self.keydown_handler(ctx);
+        let onfocus = self.focus_handler(ctx);
+        let focus_path = focus_attr(&ctx.props().path);
         html! {
-            <div class="NodeDisplay building">
+            <div class={classes!("NodeDisplay", "building", category, target, cut, selected, focused, dimmed)}
+                data-focus-path={focus_path} tabindex="-1" {onkeydown} {onfocus}>
                 <div class="section">
                     {self.drag_handle(ctx)}
                     <div class="section spaced">
                         <BuildingTypeDisplay id={building.building} {change_type} />
-                        {self.view_building_settings(ctx, building)}
+                        if !building.hide_settings {
+                            {self.view_building_settings(ctx, building)}
+                        }
                     </div>
                 </div>
                 <div class="section">
                     if let Some(warning) = ctx.props().node.warning() {
                         {self.view_warning(warning)}
-                    } else {
+                    } else if !building.hide_balance {
                         {self.view_balance(ctx, false)}
+                    } else {
+                        {self.power_readout(ctx)}
                     }
+                    {self.missing_choice_warning(building)}
+                    {self.missing_input_hint(ctx, building)}
+                    {self.clipboard_warning(ctx)}
+                    {self.tag_editor(ctx)}
+                    {self.note_editor(ctx)}
+                    {self.section_toggle(ctx, BuildingSection::Settings, building.hide_settings)}
+                    {self.section_toggle(ctx, BuildingSection::Balance, building.hide_balance)}
                     <VirtualCopies copies={building.copies} {update_copies} />
                     {self.copy_button(ctx)}
                     {self.delete_button(ctx)}
@@ -62,6 +111,83 @@ impl NodeDisplay {
         }
     }
 
+    /// Button to hide or show one section of the building.
+    fn section_toggle(&self, ctx: &Context<Self>, section: BuildingSection, hidden: bool) -> Html {
+        let onclick = ctx.link().callback(move |_| Msg::ToggleSection { section });
+        let (icon, name) = match section {
+            BuildingSection::Settings => ("tune", "Settings"),
+            BuildingSection::Balance => ("balance", "Balance"),
+        };
+        let title = if hidden {
+            format!("Show {name}")
+        } else {
+            format!("Hide {name}")
+        };
+        html! {
+            <button {onclick} {title}
+                class={classes!("section-toggle", hidden.then_some("section-hidden"))}>
+                <span class="material-icons">{icon}</span>
+            </button>
+        }
+    }
+
+    /// Warning that the building is missing a recipe, resource, or fuel, so it doesn't
+    /// make anything.
+    fn missing_choice_warning(&self, building: &Building) -> Html {
+        match building.missing_choice() {
+            Some(missing) => {
+                let title =
+                    format!("{missing}; this building won't produce anything until one is chosen");
+                html! {
+                    <span class="MissingChoice material-icons warning" {title}>
+                        {"warning"}
+                    </span>
+                }
+            }
+            None => html! {},
+        }
+    }
+
+    /// Hint listing the inputs of the building's recipe which nothing in the factory
+    /// produces, unless the user dismissed it.
+    fn missing_input_hint(&self, ctx: &Context<Self>, building: &Building) -> Html {
+        if self.input_hint_dismissed {
+            return html! {};
+        }
+        let BuildingSettings::Manufacturer(settings) = &building.settings else {
+            return html! {};
+        };
+        let db = ctx.db();
+        let Some(recipe) = settings.recipe.and_then(|id| db.get(id)) else {
+            return html! {};
+        };
+        let produced = ctx.produced_items();
+        let names = ctx.item_names();
+        let missing: Vec<_> = recipe
+            .ingredients
+            .iter()
+            .filter(|ingredient| !produced.0.contains(&ingredient.item))
+            .map(|ingredient| item_label(db.get(ingredient.item), ingredient.item, &names).0)
+            .collect();
+        if missing.is_empty() {
+            return html! {};
+        }
+        let dismiss = ctx.link().callback(|_| Msg::DismissInputHint);
+        let title = format!(
+            "Nothing in this factory produces {}. Add buildings to supply them, or import them.",
+            missing.join(", ")
+        );
+        html! {
+            <span class="input-hint" {title}>
+                <span class="material-icons-outlined">{"info"}</span>
+                <span>{"Needs a source"}</span>
+                <button class="dismiss" title="Dismiss" onclick={dismiss}>
+                    <span class="material-icons">{"close"}</span>
+                </button>
+            </span>
+        }
+    }
+
     fn view_warning(&self, err: BuildError) -> Html {
         // TODO: give better error messages.
         html! {
@@ -109,12 +235,123 @@ impl NodeDisplay {
         html! {
             <>
                 <RecipeDisplay building_id={building} recipe_id={settings.recipe}
-                    {change_recipe} />
+                    {change_recipe} available_items={ctx.props().available_items.clone()} />
                 { self.view_clock_controls_if_overclockable(ctx, building, settings.clock_speed) }
+                { self.view_amplification(ctx, building, settings) }
+                { self.view_recipe_io(ctx, building, settings) }
+            </>
+        }
+    }
+
+    /// Somersloop controls for a manufacturer which can be amplified, along with how the
+    /// power cost of the extra output compares to building another copy.
+    fn view_amplification(
+        &self,
+        ctx: &Context<Self>,
+        building: BuildingId,
+        settings: &ManufacturerSettings,
+    ) -> Html {
+        let db = ctx.db();
+        let slots = match db.get(building).map(|b| &b.kind) {
+            Some(BuildingKind::Manufacturer(m)) if m.somersloop_slots > 0 => m.somersloop_slots,
+            _ => return html! {},
+        };
+        let update_somersloops = ctx
+            .link()
+            .callback(|somersloops| Msg::ChangeSomersloops { somersloops });
+        let comparison = ctx
+            .props()
+            .node
+            .building()
+            .and_then(|building| building.amplification_comparison(&db));
+        let comparison = match comparison {
+            Some(comparison) => {
+                let user_settings = ctx.settings();
+                let unit = user_settings.rate_unit;
+                let format = |value| user_settings.format(value);
+                let per_output = |power, output| format(power / unit.convert(output));
+                let amplified = per_output(comparison.extra_power, comparison.extra_output);
+                let copy = per_output(comparison.copy_power, comparison.copy_output);
+                let (item, _) =
+                    item_label(db.get(comparison.item), comparison.item, &ctx.item_names());
+                let suffix = unit.suffix();
+                let title = format!(
+                    "Amplifying with {} Somersloops adds {}{suffix} {item} for {} MW, \
+                    {amplified} MW per {item}{suffix}. Building another copy instead adds \
+                    {}{suffix} for {} MW, {copy} MW per {item}{suffix}, but also needs more \
+                    inputs.",
+                    comparison.somersloops,
+                    format(unit.convert(comparison.extra_output)),
+                    format(comparison.extra_power),
+                    format(unit.convert(comparison.copy_output)),
+                    format(comparison.copy_power),
+                );
+                html! {
+                    <span class="amplification" {title}>
+                        <span class="material-icons-outlined">{"bolt"}</span>
+                        <span>{format!("{amplified} vs {copy} MW per item{suffix}")}</span>
+                    </span>
+                }
+            }
+            None => html! {},
+        };
+        html! {
+            <>
+                <Somersloops somersloops={settings.somersloops} {slots} {update_somersloops} />
+                {comparison}
             </>
         }
     }
 
+    /// Inline summary of the rates the manufacturer's recipe consumes and produces at, for
+    /// a single copy at its current clock speed.
+    fn view_recipe_io(
+        &self,
+        ctx: &Context<Self>,
+        building: BuildingId,
+        settings: &ManufacturerSettings,
+    ) -> Html {
+        let db = ctx.db();
+        let Some(recipe) = settings.recipe.and_then(|id| db.get(id)) else {
+            return html! {};
+        };
+        let Some(BuildingKind::Manufacturer(m)) = db.get(building).map(|b| &b.kind) else {
+            return html! {};
+        };
+        let runs = recipe.runs_per_minute(m.manufacturing_speed * settings.clock_speed);
+        let user_settings = ctx.settings();
+        let unit = user_settings.rate_unit;
+        let format = |value| user_settings.format(value);
+        let names = ctx.item_names();
+        let view_amounts = |amounts: &[ItemAmount]| {
+            amounts
+                .iter()
+                .map(|amount| {
+                    let (name, image) = item_label(db.get(amount.item), amount.item, &names);
+                    html! {
+                        <span class="recipe-item" title={name}>
+                            <Icon icon={image} />
+                            <span>{format(unit.convert(amount.amount * runs))}</span>
+                        </span>
+                    }
+                })
+                .collect::<Html>()
+        };
+        let cycle = recipe.time / (m.manufacturing_speed * settings.clock_speed);
+        let title = format!(
+            "{} s per cycle at this clock speed ({} s at 100%)",
+            rounded(cycle),
+            rounded(recipe.time)
+        );
+        html! {
+            <span class="RecipeIo" {title}>
+                { view_amounts(&recipe.ingredients) }
+                <span class="material-icons">{"arrow_right_alt"}</span>
+                { view_amounts(&recipe.products) }
+            </span>
+        }
+    }
+
     /// Display the settings for a miner.
     fn view_miner_settings(
         &self,
@@ -207,7 +444,39 @@ impl NodeDisplay {
         }
     }
 
-    /// If the building can be overclocked, returns the clock controls, otherwise returns None.
+    /// Products of this building whose rate can be set through its clock speed, with their
+    /// current total rates. The products of a recipe are listed in recipe order, so the
+    /// primary product comes first.
+    fn rate_targets(&self, ctx: &Context<Self>) -> Vec<(ItemId, f32)> {
+        let Some(building) = ctx.props().node.building() else {
+            return Vec::new();
+        };
+        let db = ctx.db();
+        let balances = &ctx.props().node.balance().balances;
+        let mut outputs: Vec<_> = balances
+            .iter()
+            .filter(|&(&item, &rate)| {
+                rate > 0.0 && building.clock_for_output(&db, item, rate).is_some()
+            })
+            .map(|(&item, &rate)| (item, rate))
+            .collect();
+        if let BuildingSettings::Manufacturer(settings) = &building.settings {
+            if let Some(recipe) = settings.recipe.and_then(|id| db.get(id)) {
+                let position = |item| {
+                    recipe
+                        .products
+                        .iter()
+                        .position(|product| product.item == item)
+                        .unwrap_or(usize::MAX)
+                };
+                outputs.sort_by_key(|&(item, _)| position(item));
+            }
+        }
+        outputs
+    }
+
+    /// If the building can be overclocked, returns the clock controls and the number of
+    /// power shards the clock speed needs, otherwise returns None.
     fn view_clock_controls_if_overclockable(
         &self,
         ctx: &Context<Self>,
@@ -226,8 +495,40 @@ impl NodeDisplay {
                 let update_speed = ctx
                     .link()
                     .callback(|clock_speed| Msg::ChangeClockSpeed { clock_speed });
-                Some(html! { <ClockSpeed clock_speed={current_clock_speed} {update_speed} /> })
+                let shards = power_shards_for_clock(current_clock_speed);
+                let outputs = self.rate_targets(ctx);
+                let set_rate = ctx
+                    .link()
+                    .callback(|(item, rate)| Msg::SetOutputRate { item, rate });
+                Some(html! {
+                    <>
+                        <ClockSpeed clock_speed={current_clock_speed} {update_speed} />
+                        if !outputs.is_empty() {
+                            <ExactRate {outputs} {set_rate} />
+                        }
+                        if shards > 0 {
+                            <span class="shard-count" title="Power Shards Needed per Copy">
+                                <span class="material-icons-outlined">{"offline_bolt"}</span>
+                                <span>{shards}</span>
+                            </span>
+                        }
+                    </>
+                })
             }
         }
     }
 }
+
+/// Whether the building has an adjustable clock speed which can be compared to the
+/// efficiency target.
+pub(super) fn has_clock_speed(settings: &BuildingSettings) -> bool {
+    match settings {
+        BuildingSettings::Manufacturer(_)
+        | BuildingSettings::Miner(_)
+        | BuildingSettings::Generator(_)
+        | BuildingSettings::Pump(_) => true,
+        BuildingSettings::Geothermal(_)
+        | BuildingSettings::PowerConsumer
+        | BuildingSettings::Station(_) => false,
+    }
+}
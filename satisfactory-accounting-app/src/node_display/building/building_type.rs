@@ -67,15 +67,18 @@ impl Component for BuildingTypeDisplay {
         let db = ctx.db();
         let link = ctx.link();
         if self.editing {
-            let choices: Vec<_> = db
-                .buildings
-                .values()
+            let mut buildings: Vec<_> = db.buildings.values().collect();
+            buildings.sort_by_key(|building| building.kind.kind_id());
+            let choices: Vec<_> = buildings
+                .into_iter()
                 .map(|building| Choice {
                     id: building.id,
                     name: building.name.clone().into(),
                     image: html! {
                         <Icon icon={building.image.clone()}/>
                     },
+                    featured: None,
+                    group: Some(building.kind.kind_id().name().into()),
                 })
                 .collect();
 
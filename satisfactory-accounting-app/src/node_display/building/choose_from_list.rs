@@ -5,6 +5,7 @@
 //   You may obtain a copy of the License at
 //
 //       http://www.apache.org/licenses/LICENSE-2.0
+use std::cmp::Ordering;
 use std::marker::PhantomData;
 
 use fuzzy_matcher::skim::SkimMatcherV2;
@@ -25,6 +26,12 @@ pub struct Choice<Id> {
     pub name: AttrValue,
     /// Name of the image to show. This should be the the slug for the icon.
     pub image: Html,
+    /// If set, this choice is listed ahead of the others and marked, with this text
+    /// explaining the marker.
+    pub featured: Option<AttrValue>,
+    /// Heading to list this choice under while nothing has been entered. Groups are
+    /// shown in the order they first appear in the choices.
+    pub group: Option<AttrValue>,
 }
 
 #[derive(PartialEq, Properties)]
@@ -72,19 +79,28 @@ impl<I: PartialEq + Copy + Clone + 'static> Component for ChooseFromList<I> {
     type Properties = Props<I>;
 
     fn create(ctx: &Context<Self>) -> Self {
-        let mut filtered: Vec<_> = ctx
-            .props()
-            .choices
-            .iter()
-            .cloned()
-            .map(|choice| (0, choice))
-            .collect();
-        filtered.sort_by(|(_, c1), (_, c2)| c1.name.cmp(&c2.name));
+        let choices = &ctx.props().choices;
+        let group_rank = |choice: &Choice<I>| {
+            choices
+                .iter()
+                .position(|other| other.group == choice.group)
+                .unwrap_or(0)
+        };
+        let mut filtered: Vec<_> = choices.iter().cloned().map(|choice| (0, choice)).collect();
+        // Featured choices keep the order they were given in, so callers can rank them.
+        filtered.sort_by(|(_, c1), (_, c2)| match (&c1.featured, &c2.featured) {
+            (Some(_), Some(_)) => Ordering::Equal,
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => group_rank(c1)
+                .cmp(&group_rank(c2))
+                .then_with(|| c1.name.cmp(&c2.name)),
+        });
         Self {
             input: "".into(),
             highlighted: 0,
             filtered,
-            matcher: Default::default(),
+            matcher: SkimMatcherV2::default().ignore_case(),
             input_ref: Default::default(),
             _phantom: PhantomData,
         }
@@ -124,18 +140,21 @@ impl<I: PartialEq + Copy + Clone + 'static> Component for ChooseFromList<I> {
             Msg::UpdateInput { input } => {
                 if input != self.input {
                     self.input = input;
+                    // Spaces are ignored so multi-word names match however they're typed.
+                    let pattern: String =
+                        self.input.chars().filter(|c| !c.is_whitespace()).collect();
                     self.filtered = ctx
                         .props()
                         .choices
                         .iter()
                         .filter_map(|choice| {
                             self.matcher
-                                .fuzzy_match(&choice.name, &self.input)
+                                .fuzzy_match(&choice.name, &pattern)
                                 .map(|score| (score, choice.clone()))
                         })
                         .collect();
                     self.filtered.sort_by(|(s1, c1), (s2, c2)| {
-                        s1.cmp(s2).then_with(|| c1.name.cmp(&c2.name))
+                        s2.cmp(s1).then_with(|| c1.name.cmp(&c2.name))
                     });
                     self.highlighted = 0;
                     true
@@ -191,29 +210,42 @@ impl<I: PartialEq + Copy + Clone + 'static> Component for ChooseFromList<I> {
                 filtered_idx: highlighted,
             }
         });
+        // Group headings only make sense in the unfiltered list, which is sorted by group.
+        let show_groups = self.input.trim().is_empty();
+        let items = self.filtered.iter().enumerate().map(|(i, (_, item))| {
+            let selected = (i == self.highlighted).then_some("selected");
+            let onclick = link.callback(move |_| Msg::Select { filtered_idx: i });
+            let onmouseenter = link.callback(move |_| Msg::Hover { filtered_idx: i });
+            let heading = item.group.as_ref().filter(|&group| {
+                show_groups
+                    && item.featured.is_none()
+                    && (i == 0 || self.filtered[i - 1].1.group.as_ref() != Some(group))
+            });
+            html! {
+                <>
+                    if let Some(group) = heading {
+                        <div class="available-group">{group}</div>
+                    }
+                    <div tabindex="-1" class={classes!("available-item", selected)}
+                        {onclick} {onmouseenter}>
+                        {item.image.clone()}
+                        <span>{&item.name}</span>
+                        if let Some(title) = item.featured.clone() {
+                            <span class="featured material-icons" {title}>
+                                {"star"}
+                            </span>
+                        }
+                    </div>
+                </>
+            }
+        });
         html! {
             <form class="ChooseFromList" {onsubmit} {onfocusout}>
                 <input type="text" value={self.input.clone()}
                     {onkeydown} {onkeyup} {oninput}
                     ref={self.input_ref.clone()} />
                 <div class="available">
-                    { for self.filtered.iter().enumerate().map(|(i, (_, item))| {
-                        let selected = (i == self.highlighted).then(|| "selected");
-                        let onclick = link.callback(move |_|
-                            Msg::Select {
-                            filtered_idx: i,
-                        });
-                        let onmouseenter = link.callback(move |_| Msg::Hover {
-                            filtered_idx: i,
-                        });
-                        html! {
-                            <div tabindex="-1" class={classes!("available-item", selected)}
-                                {onclick} {onmouseenter}>
-                                {item.image.clone()}
-                                <span>{&item.name}</span>
-                            </div>
-                        }
-                    }) }
+                    { for items }
                 </div>
             </form>
         }
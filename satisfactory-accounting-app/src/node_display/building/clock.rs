@@ -5,9 +5,132 @@
 //   You may obtain a copy of the License at
 //
 //       http://www.apache.org/licenses/LICENSE-2.0
+use satisfactory_accounting::accounting::{MAX_CLOCK_SPEED, MIN_CLOCK_SPEED};
+use serde::{Deserialize, Serialize};
 use yew::prelude::*;
 
-use crate::clickedit::ClickEdit;
+use crate::clickedit::{ClickEdit, Step};
+use crate::events::get_value_from_input_change;
+use crate::numbers::DecimalSeparator;
+use crate::use_settings;
+
+/// How clock speeds entered by the user should be rounded.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub enum ClockRounding {
+    /// Use the clock speed exactly as entered.
+    #[default]
+    Exact,
+    /// Round to the precision the game allows, which is 4 decimal places of percent.
+    Game,
+    /// Round to whole percentages.
+    WholePercent,
+}
+
+impl ClockRounding {
+    /// Round the given clock speed multiplier according to this rounding mode.
+    pub fn apply(self, clock_speed: f32) -> f32 {
+        let steps = match self {
+            Self::Exact => return clock_speed,
+            Self::Game => 1_000_000.0,
+            Self::WholePercent => 100.0,
+        };
+        (clock_speed * steps).round() / steps
+    }
+}
+
+/// How much the arrow keys change a clock speed while it is being edited.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ClockStep {
+    /// Change for a plain arrow key press.
+    pub small: f32,
+    /// Change when Shift is held.
+    pub large: f32,
+}
+
+impl Default for ClockStep {
+    fn default() -> Self {
+        Self {
+            small: 0.01,
+            large: 0.25,
+        }
+    }
+}
+
+impl ClockStep {
+    /// Apply a step to the clock speed in `text`, rounding and clamping the result.
+    /// Returns None if the text isn't a valid clock speed.
+    fn apply(
+        self,
+        text: &str,
+        step: Step,
+        rounding: ClockRounding,
+        separator: DecimalSeparator,
+    ) -> Option<f32> {
+        let size = if step.large { self.large } else { self.small };
+        let delta = if step.up { size } else { -size };
+        let value = parse_clock_speed(text, separator)? + delta;
+        // Round off float error from the addition before applying the user's rounding.
+        let value = rounding.apply(ClockRounding::Game.apply(value));
+        Some(value.clamp(MIN_CLOCK_SPEED, MAX_CLOCK_SPEED))
+    }
+}
+
+/// How far a building's clock speed is from the user's efficiency target.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum TargetDeviation {
+    /// Clock speed matches the target.
+    OnTarget,
+    /// Clock speed is within 10 percentage points of the target.
+    Near,
+    /// Clock speed is far from the target.
+    Off,
+}
+
+impl TargetDeviation {
+    /// Classify how far `clock_speed` is from `target`.
+    pub fn of(clock_speed: f32, target: f32) -> Self {
+        // Allow for float error and the rounding of entered clock speeds.
+        const TOLERANCE: f32 = 0.0005;
+        let deviation = (clock_speed - target).abs();
+        if deviation < TOLERANCE {
+            Self::OnTarget
+        } else if deviation <= 0.1 + TOLERANCE {
+            Self::Near
+        } else {
+            Self::Off
+        }
+    }
+
+    /// CSS class used to color nodes with this deviation.
+    pub fn class(self) -> &'static str {
+        match self {
+            Self::OnTarget => "target-met",
+            Self::Near => "target-near",
+            Self::Off => "target-off",
+        }
+    }
+}
+
+/// Parse a clock speed entered by the user. In addition to plain decimals, accepts
+/// fractions like `2/3`, since many ideal clock speeds are repeating decimals. A fraction
+/// too large to be a clock speed, like `200/3`, is taken as a percentage. Returns None if
+/// the text isn't a valid, finite clock speed.
+pub fn parse_clock_speed(text: &str, separator: DecimalSeparator) -> Option<f32> {
+    let value = match text.split_once('/') {
+        Some((numerator, denominator)) => {
+            let numerator: f64 = separator.parse(numerator)?;
+            let denominator: f64 = separator.parse(denominator)?;
+            let value = numerator / denominator;
+            if value > MAX_CLOCK_SPEED as f64 {
+                (value / 100.0) as f32
+            } else {
+                value as f32
+            }
+        }
+        None => separator.parse(text)?,
+    };
+    value.is_finite().then_some(value)
+}
 
 #[derive(Debug, PartialEq, Properties)]
 pub struct Props {
@@ -20,11 +143,41 @@ pub struct Props {
 /// Display and editing for clock speed.
 #[function_component]
 pub fn ClockSpeed(props: &Props) -> Html {
+    let settings = use_settings();
+    let rounding = settings.clock_rounding;
+    let separator = settings.decimal_separator;
+    let step = use_callback(
+        (settings.clock_step, rounding, separator),
+        |(text, step): (AttrValue, Step), &(clock_step, rounding, separator)| {
+            clock_step
+                .apply(&text, step, rounding, separator)
+                .map(|value| AttrValue::from(value.to_string()))
+        },
+    );
     let on_commit = use_callback(
-        props.update_speed.clone(),
-        |edit_text: AttrValue, update_speed| {
-            if let Ok(value) = edit_text.parse::<f32>() {
-                update_speed.emit(value.clamp(0.01, 2.5));
+        (props.update_speed.clone(), rounding, separator),
+        |edit_text: AttrValue, (update_speed, rounding, separator)| {
+            if let Some(value) = parse_clock_speed(&edit_text, *separator) {
+                update_speed.emit(
+                    rounding
+                        .apply(value)
+                        .clamp(MIN_CLOCK_SPEED, MAX_CLOCK_SPEED),
+                );
+            }
+        },
+    );
+
+    // The slider only reports its value once released, so dragging it doesn't create an
+    // undo state for every step.
+    let onchange = use_callback(
+        (props.update_speed.clone(), rounding),
+        |e: Event, (update_speed, rounding)| {
+            if let Ok(value) = get_value_from_input_change(e).parse::<f32>() {
+                update_speed.emit(
+                    rounding
+                        .apply(value)
+                        .clamp(MIN_CLOCK_SPEED, MAX_CLOCK_SPEED),
+                );
             }
         },
     );
@@ -34,6 +187,144 @@ pub fn ClockSpeed(props: &Props) -> Html {
         <span class="material-icons-outlined">{"timer"}</span>
     };
     html! {
-        <ClickEdit {value} class="ClockSpeed" title="Clock Speed" {on_commit} {prefix} />
+        <>
+            <ClickEdit value={value.clone()} class="ClockSpeed" title="Clock Speed" {on_commit}
+                {step} {prefix} />
+            <input type="range" class="clock-slider" title="Clock Speed"
+                min={MIN_CLOCK_SPEED.to_string()} max={MAX_CLOCK_SPEED.to_string()} step="0.01"
+                {value} {onchange} />
+        </>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rounding_two_thirds() {
+        let clock = 0.666667;
+        assert_eq!(ClockRounding::Exact.apply(clock), clock);
+        assert!((ClockRounding::Game.apply(clock) - clock).abs() < 1e-7);
+        assert!((ClockRounding::Game.apply(0.666_666_3) - 0.666_666).abs() < 1e-7);
+        assert_eq!(ClockRounding::WholePercent.apply(clock), 0.67);
+    }
+
+    #[test]
+    fn parse_fractions() {
+        assert_eq!(
+            parse_clock_speed("1/3", DecimalSeparator::Period),
+            Some(1.0 / 3.0)
+        );
+        assert_eq!(
+            parse_clock_speed("5/6", DecimalSeparator::Period),
+            Some(5.0 / 6.0)
+        );
+        assert_eq!(
+            parse_clock_speed(" 2 / 3 ", DecimalSeparator::Period),
+            Some(2.0 / 3.0)
+        );
+        assert_eq!(
+            parse_clock_speed("200/3", DecimalSeparator::Period),
+            Some(2.0 / 3.0)
+        );
+        assert_eq!(
+            parse_clock_speed("1.5", DecimalSeparator::Period),
+            Some(1.5)
+        );
+    }
+
+    #[test]
+    fn parse_comma_decimals() {
+        assert_eq!(parse_clock_speed("0,5", DecimalSeparator::Comma), Some(0.5));
+        assert_eq!(parse_clock_speed("0.5", DecimalSeparator::Comma), Some(0.5));
+        assert_eq!(
+            parse_clock_speed("1,5/3", DecimalSeparator::Comma),
+            Some(0.5)
+        );
+        assert_eq!(parse_clock_speed("0,5", DecimalSeparator::Period), None);
+    }
+
+    #[test]
+    fn parse_invalid_fractions() {
+        assert_eq!(parse_clock_speed("1/0", DecimalSeparator::Period), None);
+        assert_eq!(parse_clock_speed("0/0", DecimalSeparator::Period), None);
+        assert_eq!(parse_clock_speed("1/", DecimalSeparator::Period), None);
+        assert_eq!(parse_clock_speed("/3", DecimalSeparator::Period), None);
+        assert_eq!(parse_clock_speed("1/2/3", DecimalSeparator::Period), None);
+        assert_eq!(parse_clock_speed("a/b", DecimalSeparator::Period), None);
+    }
+
+    #[test]
+    fn step_clock_speed() {
+        let steps = ClockStep::default();
+        let up = Step {
+            up: true,
+            large: false,
+        };
+        let large_down = Step {
+            up: false,
+            large: true,
+        };
+        assert_eq!(
+            steps.apply("0.5", up, ClockRounding::Exact, DecimalSeparator::Period),
+            Some(0.51)
+        );
+        assert_eq!(
+            steps.apply("0.66", up, ClockRounding::Exact, DecimalSeparator::Period),
+            Some(0.67)
+        );
+        assert_eq!(
+            steps.apply(
+                "1",
+                large_down,
+                ClockRounding::Exact,
+                DecimalSeparator::Period
+            ),
+            Some(0.75)
+        );
+        assert_eq!(
+            steps.apply("2.49", up, ClockRounding::Exact, DecimalSeparator::Period),
+            Some(2.5)
+        );
+        assert_eq!(
+            steps.apply("2.5", up, ClockRounding::Exact, DecimalSeparator::Period),
+            Some(2.5)
+        );
+        assert_eq!(
+            steps.apply(
+                "0.1",
+                large_down,
+                ClockRounding::Exact,
+                DecimalSeparator::Period
+            ),
+            Some(0.01)
+        );
+        assert_eq!(
+            steps.apply(
+                "2/3",
+                up,
+                ClockRounding::WholePercent,
+                DecimalSeparator::Period
+            ),
+            Some(0.68)
+        );
+        assert_eq!(
+            steps.apply("fast", up, ClockRounding::Exact, DecimalSeparator::Period),
+            None
+        );
+    }
+
+    #[test]
+    fn target_deviation() {
+        assert_eq!(TargetDeviation::of(1.0, 1.0), TargetDeviation::OnTarget);
+        assert_eq!(
+            TargetDeviation::of(2.0 / 3.0, 0.666667),
+            TargetDeviation::OnTarget
+        );
+        assert_eq!(TargetDeviation::of(0.95, 1.0), TargetDeviation::Near);
+        assert_eq!(TargetDeviation::of(1.1, 1.0), TargetDeviation::Near);
+        assert_eq!(TargetDeviation::of(0.5, 1.0), TargetDeviation::Off);
+        assert_eq!(TargetDeviation::of(2.5, 1.0), TargetDeviation::Off);
     }
 }
@@ -0,0 +1,97 @@
+// Copyright 2024 Zachary Stewart
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+use satisfactory_accounting::database::ItemId;
+use yew::prelude::*;
+
+use crate::clickedit::ClickEdit;
+use crate::node_display::icon::Icon;
+use crate::{item_label, use_db, use_item_names, use_settings};
+
+#[derive(Debug, PartialEq, Properties)]
+pub struct Props {
+    /// Products whose rate can be set through the clock speed, primary product first,
+    /// with their current total rates per minute.
+    pub outputs: Vec<(ItemId, f32)>,
+    /// Callback to set the clock speed so the building makes a total rate per minute of
+    /// an item.
+    pub set_rate: Callback<(ItemId, f32)>,
+}
+
+/// Button and menu to set the clock speed of a building from the rate it should make one
+/// of its products at.
+#[function_component]
+pub fn ExactRate(props: &Props) -> Html {
+    let db = use_db();
+    let names = use_item_names();
+    let settings = use_settings();
+    let open = use_state(|| false);
+    let chosen = use_state(|| None::<ItemId>);
+
+    let toggle_open = {
+        let open = open.clone();
+        Callback::from(move |_| open.set(!*open))
+    };
+    let Some(&(item, rate)) = props
+        .outputs
+        .iter()
+        .find(|&&(item, _)| Some(item) == *chosen)
+        .or_else(|| props.outputs.first())
+    else {
+        return html! {};
+    };
+
+    let products = (props.outputs.len() > 1).then(|| {
+        let products = props.outputs.iter().map(|&(product, _)| {
+            let (name, image) = item_label(db.get(product), product, &names);
+            let class = classes!("product", (product == item).then_some("chosen"));
+            let onclick = {
+                let chosen = chosen.clone();
+                Callback::from(move |_| chosen.set(Some(product)))
+            };
+            html! {
+                <button {class} title={format!("Target {name}")} {onclick}>
+                    <Icon icon={image} />
+                </button>
+            }
+        });
+        html! { <span class="products">{ for products }</span> }
+    });
+    let on_commit = {
+        let set_rate = props.set_rate.clone();
+        let open = open.clone();
+        let separator = settings.decimal_separator;
+        let unit = settings.rate_unit;
+        Callback::from(move |text: AttrValue| {
+            if let Some(rate) = separator
+                .parse::<f32>(&text)
+                .filter(|rate| rate.is_finite() && *rate > 0.0)
+            {
+                open.set(false);
+                set_rate.emit((item, unit.to_per_minute(rate)));
+            }
+        })
+    };
+    let (name, _) = item_label(db.get(item), item, &names);
+    let value = AttrValue::from(settings.format(settings.rate_unit.convert(rate)));
+    let suffix = html! { <span class="unit">{settings.rate_unit.suffix()}</span> };
+    html! {
+        <span class="ExactRate">
+            <button class="exact-rate-toggle" title="Set Clock Speed from an Output Rate"
+                onclick={toggle_open}>
+                <span class="material-icons">{"speed"}</span>
+            </button>
+            if *open {
+                <div class="exact-rate-menu">
+                    { for products }
+                    <ClickEdit {value} title={format!("Total {name} Output")} {on_commit}
+                        {suffix} />
+                </div>
+            }
+        </span>
+    }
+}
@@ -107,11 +107,15 @@ impl Component for ItemDisplay {
                         image: html! {
                             <Icon icon={item.image.clone()}/>
                         },
+                        featured: None,
+                        group: None,
                     },
                     None => Choice {
                         id: item_id,
                         name: format!("Unknown Item {}", item_id).into(),
                         image: html! { <Icon /> },
+                        featured: None,
+                        group: None,
                     },
                 })
                 .collect();
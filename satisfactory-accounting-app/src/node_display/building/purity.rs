@@ -61,6 +61,8 @@ impl Component for Purity {
                     id: purity,
                     name: purity.name().into(),
                     image: purity_icon(purity),
+                    featured: None,
+                    group: None,
                 })
                 .collect();
             let selected = link.callback(|purity| Msg::Select { purity });
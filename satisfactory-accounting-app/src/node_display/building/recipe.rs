@@ -5,8 +5,12 @@
 //   You may obtain a copy of the License at
 //
 //       http://www.apache.org/licenses/LICENSE-2.0
+use std::cmp::Reverse;
+use std::collections::BTreeSet;
+use std::rc::Rc;
+
 use log::warn;
-use satisfactory_accounting::database::{BuildingId, BuildingKind, RecipeId};
+use satisfactory_accounting::database::{BuildingId, BuildingKind, ItemId, RecipeId};
 use yew::prelude::*;
 
 use crate::node_display::building::choose_from_list::{Choice, ChooseFromList};
@@ -21,6 +25,10 @@ pub struct Props {
     pub recipe_id: Option<RecipeId>,
     /// Callback to change the type of this building.
     pub change_recipe: Callback<RecipeId>,
+    /// Items already produced in the building's group. Recipes which consume them are
+    /// recommended first.
+    #[prop_or_default]
+    pub available_items: Rc<BTreeSet<ItemId>>,
 }
 
 /// Messages for [`BuildingTypeDisplay`]
@@ -94,23 +102,46 @@ impl Component for RecipeDisplay {
         };
         let link = ctx.link();
         if self.editing {
-            let choices: Vec<_> = recipes
+            let available = &ctx.props().available_items;
+            let mut choices: Vec<_> = recipes
                 .iter()
                 .map(|&recipe_id| match db.get(recipe_id) {
-                    Some(recipe) => Choice {
-                        id: recipe.id,
-                        name: recipe.name.clone().into(),
-                        image: html! {
-                            <Icon icon={recipe.image.clone()} />
-                        },
-                    },
-                    None => Choice {
-                        id: recipe_id,
-                        name: format!("Unknown Recipe {}", recipe_id).into(),
-                        image: html! { <Icon /> },
-                    },
+                    Some(recipe) => {
+                        let overlap = recipe.input_overlap(available);
+                        let featured = match overlap {
+                            0 => recipe
+                                .is_standard_for(building_id)
+                                .then(|| "Standard recipe for this building".into()),
+                            1 => Some("Uses an item made in this group".into()),
+                            n => Some(format!("Uses {n} items made in this group").into()),
+                        };
+                        let choice = Choice {
+                            id: recipe.id,
+                            name: recipe.name.clone().into(),
+                            image: html! {
+                                <Icon icon={recipe.image.clone()} />
+                            },
+                            featured,
+                            group: None,
+                        };
+                        (overlap, choice)
+                    }
+                    None => {
+                        let choice = Choice {
+                            id: recipe_id,
+                            name: format!("Unknown Recipe {}", recipe_id).into(),
+                            image: html! { <Icon /> },
+                            featured: None,
+                            group: None,
+                        };
+                        (0, choice)
+                    }
                 })
                 .collect();
+            // Recommend recipes using the most available items first, ahead of the
+            // standard recipe.
+            choices.sort_by_key(|(overlap, choice)| (Reverse(*overlap), choice.featured.is_none()));
+            let choices: Vec<_> = choices.into_iter().map(|(_, choice)| choice).collect();
 
             let selected = link.callback(|id| Msg::Select { id });
             let cancelled = link.callback(|()| Msg::ToggleEdit { editing: false });
@@ -0,0 +1,42 @@
+// Copyright 2024 Zachary Stewart
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+use yew::prelude::*;
+
+use crate::clickedit::ClickEdit;
+
+#[derive(Debug, PartialEq, Properties)]
+pub struct Props {
+    /// Last set number of Somersloops.
+    pub somersloops: u32,
+    /// Number of Somersloop slots in the building.
+    pub slots: u32,
+    /// Callback to change the actual value.
+    pub update_somersloops: Callback<u32>,
+}
+
+/// Display and editing for the number of Somersloops amplifying a building.
+#[function_component]
+pub fn Somersloops(props: &Props) -> Html {
+    let on_commit = use_callback(
+        (props.update_somersloops.clone(), props.slots),
+        |edit_text: AttrValue, &(ref update_somersloops, slots)| {
+            if let Ok(value) = edit_text.trim().parse::<u32>() {
+                update_somersloops.emit(value.min(slots));
+            }
+        },
+    );
+
+    let title = format!("Somersloops ({} slots)", props.slots);
+    let value: AttrValue = props.somersloops.to_string().into();
+    let prefix = html! {
+        <span class="material-icons-outlined">{"all_inclusive"}</span>
+    };
+    html! {
+        <ClickEdit {value} class="Somersloops" {title} {prefix} {on_commit} />
+    }
+}
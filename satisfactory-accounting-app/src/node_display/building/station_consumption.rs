@@ -8,6 +8,7 @@
 use yew::prelude::*;
 
 use crate::clickedit::ClickEdit;
+use crate::use_settings;
 
 #[derive(Debug, PartialEq, Properties)]
 pub struct Props {
@@ -19,10 +20,11 @@ pub struct Props {
 
 #[function_component]
 pub fn StationConsumption(props: &Props) -> Html {
+    let separator = use_settings().decimal_separator;
     let on_commit = use_callback(
-        props.update_consumption.clone(),
-        |edit_text: AttrValue, update_consumption| {
-            if let Ok(value) = edit_text.parse::<f32>() {
+        (props.update_consumption.clone(), separator),
+        |edit_text: AttrValue, (update_consumption, separator)| {
+            if let Some(value) = separator.parse::<f32>(&edit_text) {
                 update_consumption.emit(value.max(0.0));
             }
         },
@@ -0,0 +1,138 @@
+// Copyright 2024 Zachary Stewart
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+use log::warn;
+use satisfactory_accounting::migration::migrate;
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::Clipboard;
+use yew::platform::spawn_local;
+use yew::prelude::*;
+
+use super::{Msg, NodeDisplay};
+use crate::share::{decode_clipboard, encode_clipboard};
+use crate::CtxHelper;
+
+/// Get the system clipboard, if the browser allows access to it from this page. It is
+/// missing entirely on pages which aren't served securely.
+fn system_clipboard() -> Option<Clipboard> {
+    let navigator = gloo::utils::window().navigator();
+    js_sys::Reflect::get(&navigator, &JsValue::from_str("clipboard"))
+        .ok()?
+        .dyn_into()
+        .ok()
+}
+
+/// Whether the user has selected some text on the page, which Ctrl+C should copy instead
+/// of the node.
+pub(super) fn has_text_selection() -> bool {
+    gloo::utils::window()
+        .get_selection()
+        .ok()
+        .flatten()
+        .is_some_and(|selection| !selection.is_collapsed())
+}
+
+impl NodeDisplay {
+    /// Write this node to the system clipboard as text, so it can be pasted into another
+    /// tab or world.
+    pub(super) fn copy_to_clipboard(&self, ctx: &Context<Self>) {
+        let text = match encode_clipboard(&ctx.props().node) {
+            Ok(text) => text,
+            Err(e) => {
+                warn!("Unable to encode node for the clipboard: {e}");
+                return;
+            }
+        };
+        let Some(clipboard) = system_clipboard() else {
+            ctx.link().send_message(Msg::ClipboardFailed {
+                message: "This browser doesn't allow access to the clipboard here.".into(),
+            });
+            return;
+        };
+        let link = ctx.link().clone();
+        spawn_local(async move {
+            if let Err(e) = JsFuture::from(clipboard.write_text(&text)).await {
+                warn!("Unable to write to the clipboard: {e:?}");
+                link.send_message(Msg::ClipboardFailed {
+                    message: "Unable to copy to the clipboard; the browser may have denied \
+                        permission."
+                        .into(),
+                });
+            }
+        });
+    }
+
+    /// Read a node from the system clipboard and add it to the end of this group once the
+    /// browser provides the text.
+    pub(super) fn paste_from_clipboard(&self, ctx: &Context<Self>) {
+        let Some(clipboard) = system_clipboard() else {
+            ctx.link().send_message(Msg::ClipboardFailed {
+                message: "This browser doesn't allow access to the clipboard here.".into(),
+            });
+            return;
+        };
+        let link = ctx.link().clone();
+        spawn_local(async move {
+            match JsFuture::from(clipboard.read_text()).await {
+                Ok(text) => link.send_message(Msg::ClipboardRead {
+                    text: text.as_string().unwrap_or_default(),
+                }),
+                Err(e) => {
+                    warn!("Unable to read the clipboard: {e:?}");
+                    link.send_message(Msg::ClipboardFailed {
+                        message: "Unable to paste from the clipboard; the browser may have \
+                            denied permission."
+                            .into(),
+                    });
+                }
+            }
+        });
+    }
+
+    /// Add the node encoded in text read from the clipboard to the end of this group.
+    /// Returns an error message to show if the text isn't a copied node.
+    pub(super) fn paste_clipboard_text(
+        &self,
+        ctx: &Context<Self>,
+        text: &str,
+    ) -> Result<(), AttrValue> {
+        let Some(group) = ctx.props().node.group() else {
+            return Err("Nodes can only be pasted into a group.".into());
+        };
+        let node = decode_clipboard(text).map_err(|e| AttrValue::from(e.to_string()))?;
+        // Give groups new ids, since the copy may still be in the tree it came from.
+        let report = migrate(&node.create_copy(), None, &ctx.db());
+        if !report.errors.is_empty() {
+            warn!(
+                "{} pasted buildings don't match the database",
+                report.errors.len()
+            );
+        }
+        let mut new_group = group.clone();
+        new_group.children.push(report.root);
+        let our_idx = ctx.props().path.last().copied().unwrap_or_default();
+        ctx.props().replace.emit((our_idx, new_group.into()));
+        Ok(())
+    }
+
+    /// Show the most recent clipboard failure, which can be clicked to dismiss it.
+    pub(super) fn clipboard_warning(&self, ctx: &Context<Self>) -> Html {
+        match &self.clipboard_error {
+            Some(title) => {
+                let onclick = ctx.link().callback(|_| Msg::DismissClipboardError);
+                html! {
+                    <span class="ClipboardError material-icons warning" title={title.clone()}
+                        {onclick}>
+                        {"content_paste_off"}
+                    </span>
+                }
+            }
+            None => html! {},
+        }
+    }
+}
@@ -0,0 +1,149 @@
+// Copyright 2024 Zachary Stewart
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+use std::collections::HashMap;
+
+use satisfactory_accounting::accounting::Node;
+use uuid::Uuid;
+use yew::prelude::*;
+
+use crate::node_display::{NodeMeta, NodeMetadata};
+
+/// Metadata updates which collapse every group in `root` deeper than `depth`, counting
+/// `root` as depth 0, and expand the rest. With no depth, every group is expanded. Groups
+/// which are already in the right state are left out.
+pub fn collapse_below(
+    metadata: &NodeMetadata,
+    root: &Node,
+    depth: Option<usize>,
+) -> HashMap<Uuid, NodeMeta> {
+    root.groups_with_depth()
+        .into_iter()
+        .filter_map(|(group, group_depth)| {
+            let old = metadata.meta(group.id);
+            let new = if depth.is_some_and(|depth| group_depth > depth) {
+                old.clone().collapse()
+            } else {
+                old.clone().expand()
+            };
+            (new != old).then_some((group.id, new))
+        })
+        .collect()
+}
+
+#[derive(Debug, PartialEq, Properties)]
+pub struct Props {
+    /// Root of the factory.
+    pub root: Node,
+    /// Callback to collapse every group deeper than the given depth below the root and
+    /// expand the rest, or expand every group if no depth is given.
+    pub collapse_below: Callback<Option<usize>>,
+}
+
+/// Toolbar buttons to expand or collapse the whole tree at once.
+#[function_component]
+pub fn CollapseControls(props: &Props) -> Html {
+    let levels_open = use_state(|| false);
+
+    let collapse = |depth: Option<usize>| {
+        let collapse_below = props.collapse_below.clone();
+        let levels_open = levels_open.clone();
+        Callback::from(move |_| {
+            levels_open.set(false);
+            collapse_below.emit(depth);
+        })
+    };
+    let toggle_levels = {
+        let levels_open = levels_open.clone();
+        Callback::from(move |_| levels_open.set(!*levels_open))
+    };
+    let deepest = props
+        .root
+        .groups_with_depth()
+        .into_iter()
+        .map(|(_, depth)| depth)
+        .max()
+        .unwrap_or_default();
+    // Collapsing below the deepest group wouldn't collapse anything.
+    let levels = (1..deepest).map(|level| {
+        html! {
+            <button class="level" onclick={collapse(Some(level))}
+                title={format!("Collapse groups more than {level} levels deep")}>
+                {level}
+            </button>
+        }
+    });
+    html! {
+        <span class="section CollapseControls">
+            <button class="expand-all" title="Expand All" onclick={collapse(None)}>
+                <span class="material-icons">{"unfold_more"}</span>
+            </button>
+            <button class="collapse-all" title="Collapse All" onclick={collapse(Some(0))}>
+                <span class="material-icons">{"unfold_less"}</span>
+            </button>
+            if deepest > 1 {
+                <span class="collapse-levels">
+                    <button class="levels-toggle" title="Collapse to Level"
+                        onclick={toggle_levels}>
+                        <span class="material-icons">{"format_list_numbered"}</span>
+                    </button>
+                    if *levels_open {
+                        <div class="levels-menu">
+                            { for levels }
+                        </div>
+                    }
+                </span>
+            }
+        </span>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use satisfactory_accounting::accounting::{Building, Group};
+
+    use super::*;
+
+    #[test]
+    fn collapse_deeper_groups() {
+        let group = |children: Vec<Node>| -> Node {
+            Group {
+                children,
+                ..Group::empty()
+            }
+            .into()
+        };
+        let root = group(vec![
+            group(vec![group(vec![]), Building::empty_node()]),
+            group(vec![group(vec![group(vec![])])]),
+        ]);
+        let ids: Vec<_> = root
+            .groups_with_depth()
+            .into_iter()
+            .map(|(group, depth)| (group.id, depth))
+            .collect();
+        let mut metadata = NodeMetadata::default();
+
+        metadata.batch_update(collapse_below(&metadata, &root, Some(1)));
+        for &(id, depth) in &ids {
+            assert_eq!(metadata.meta(id).collapsed, depth > 1, "depth {depth}");
+        }
+        // Groups already in the right state aren't updated again.
+        assert!(collapse_below(&metadata, &root, Some(1)).is_empty());
+
+        metadata.batch_update(collapse_below(&metadata, &root, Some(0)));
+        for &(id, depth) in &ids {
+            assert_eq!(metadata.meta(id).collapsed, depth > 0, "depth {depth}");
+        }
+
+        metadata.batch_update(collapse_below(&metadata, &root, None));
+        for &(id, _) in &ids {
+            let meta = metadata.meta(id);
+            assert!(!meta.collapsed && meta.expanded);
+        }
+    }
+}
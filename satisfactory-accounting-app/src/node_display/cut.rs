@@ -0,0 +1,147 @@
+// Copyright 2024 Zachary Stewart
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+use std::cell::RefCell;
+
+use log::warn;
+use satisfactory_accounting::accounting::NodeKind;
+use uuid::Uuid;
+use wasm_bindgen::JsCast;
+use web_sys::HtmlInputElement;
+use yew::prelude::*;
+
+use super::clipboard::has_text_selection;
+use super::sandbox::{cross_tree_move_notice, NodeTree};
+use super::{Msg, NodeDisplay};
+use crate::CtxHelper;
+
+/// Node waiting to be moved by paste.
+#[derive(Debug, Clone, PartialEq)]
+enum CutNode {
+    /// A building, which has no stable id and so is identified by its path.
+    Path(Vec<usize>),
+    /// A group, identified by its id.
+    Group(Uuid),
+}
+
+thread_local! {
+    static CUT: RefCell<Option<(NodeTree, CutNode)>> = const { RefCell::new(None) };
+}
+
+/// Forget any node which was cut but not yet pasted.
+pub fn clear_cut() {
+    CUT.with(|cut| *cut.borrow_mut() = None);
+}
+
+/// Forget a cut node which is identified by its path. Must be called whenever the tree
+/// changes, since the path may no longer be valid. Cut groups are found by id, so they
+/// survive edits.
+pub fn clear_cut_path() {
+    CUT.with(|cut| {
+        let mut cut = cut.borrow_mut();
+        if matches!(*cut, Some((_, CutNode::Path(_)))) {
+            *cut = None;
+        }
+    });
+}
+
+impl NodeDisplay {
+    /// Identifies this node while it is cut.
+    fn cut_node(&self, ctx: &Context<Self>) -> CutNode {
+        match ctx.props().node.kind() {
+            NodeKind::Group(group) => CutNode::Group(group.id),
+            NodeKind::Building(_) => CutNode::Path(ctx.props().path.clone()),
+        }
+    }
+
+    /// Whether this node is the one currently marked to be moved by paste.
+    pub(super) fn is_cut(&self, ctx: &Context<Self>) -> bool {
+        let node = (self.tree(ctx), self.cut_node(ctx));
+        CUT.with(|cut| cut.borrow().as_ref() == Some(&node))
+    }
+
+    /// Build an event handler for Ctrl+X and Ctrl+V on this node.
+    pub(super) fn cut_paste_handler(&self, ctx: &Context<Self>) -> Callback<KeyboardEvent> {
+        ctx.link().batch_callback(|e: KeyboardEvent| {
+            if !(e.ctrl_key() || e.meta_key()) {
+                return None;
+            }
+            // Leave cut and paste of text alone.
+            if e.target()
+                .is_some_and(|target| target.dyn_into::<HtmlInputElement>().is_ok())
+            {
+                return None;
+            }
+            let msg = match &*e.key() {
+                "x" | "X" => Msg::Cut,
+                "c" | "C" if !has_text_selection() => Msg::CopyToClipboard,
+                "v" | "V" => Msg::Paste,
+                _ => return None,
+            };
+            // Nodes are nested, so only the innermost focused node should handle this.
+            e.prevent_default();
+            e.stop_propagation();
+            Some(msg)
+        })
+    }
+
+    /// Mark this node to be moved by the next paste.
+    pub(super) fn cut(&self, ctx: &Context<Self>) -> bool {
+        if ctx.props().path.is_empty() {
+            warn!("Cannot cut the root node");
+            return false;
+        }
+        let node = (self.tree(ctx), self.cut_node(ctx));
+        CUT.with(|cut| *cut.borrow_mut() = Some(node));
+        true
+    }
+
+    /// Move the node which was cut to the end of this node, if this is a group. If nothing
+    /// was cut, paste from the system clipboard instead.
+    pub(super) fn paste(&self, ctx: &Context<Self>) {
+        let Some(group) = ctx.props().node.group() else {
+            warn!("Cannot paste into a building");
+            return;
+        };
+        let Some((tree, cut)) = CUT.with(|cut| cut.borrow().clone()) else {
+            self.paste_from_clipboard(ctx);
+            return;
+        };
+        if tree != self.tree(ctx) {
+            ctx.notify(cross_tree_move_notice());
+            return;
+        }
+        match cut {
+            CutNode::Path(src_path) => {
+                let path = &ctx.props().path;
+                if path.starts_with(&src_path) {
+                    warn!("Cannot paste a node into itself");
+                    return;
+                }
+                clear_cut();
+                let mut dest_path = path.clone();
+                dest_path.push(group.children.len());
+                ctx.link().send_message(Msg::MoveNode {
+                    src_path,
+                    dest_path,
+                    copy: false,
+                });
+            }
+            CutNode::Group(id) => {
+                clear_cut();
+                // The position is counted after the group is removed, so moving a child
+                // to the end of its own parent leaves one fewer sibling.
+                let already_child = group
+                    .children
+                    .iter()
+                    .any(|child| child.group().is_some_and(|child| child.id == id));
+                let position = group.children.len() - usize::from(already_child);
+                ctx.props().move_group.emit((id, group.id, position));
+            }
+        }
+    }
+}
@@ -7,15 +7,27 @@
 //       http://www.apache.org/licenses/LICENSE-2.0
 use std::cell::RefCell;
 
+use gloo::timers::callback::Timeout;
 use log::warn;
 use wasm_bindgen::JsCast;
 use web_sys::HtmlElement;
 use yew::prelude::*;
 
-use super::{Msg, NodeDisplay, DRAG_INSERT_POINT};
+use super::sandbox::{cross_tree_move_notice, NodeTree};
+use super::{Msg, NodeDisplay, Selection, DRAG_INSERT_POINT};
+use crate::CtxHelper;
+
+/// How long something must be dragged over a collapsed group before it expands, in
+/// milliseconds.
+const EXPAND_HOVER_DELAY_MS: u32 = 600;
 
 thread_local! {
-    static DRAGGING: RefCell<Option<Vec<usize>>> = RefCell::new(None);
+    static DRAGGING: RefCell<Option<(NodeTree, Vec<usize>)>> = const { RefCell::new(None) };
+}
+
+/// Forget any node being dragged, e.g. when the tree it was dragged from goes away.
+pub fn clear_dragging() {
+    DRAGGING.with(|dragging| *dragging.borrow_mut() = None);
 }
 
 impl NodeDisplay {
@@ -23,7 +35,16 @@ impl NodeDisplay {
     fn insert_pos_chooser(&self, ctx: &Context<Self>) -> InsertPosChooser {
         let children = self.children.clone();
         let path = ctx.props().path.clone();
-        InsertPosChooser { children, path }
+        let (selection, _) = ctx
+            .link()
+            .context::<Selection>(Callback::noop())
+            .expect("selection context to be set");
+        InsertPosChooser {
+            children,
+            path,
+            tree: self.tree(ctx),
+            selection,
+        }
     }
 
     /// Build an event handler for the ondragover event.
@@ -34,14 +55,30 @@ impl NodeDisplay {
     ) -> Callback<DragEvent> {
         let chooser = self.insert_pos_chooser(ctx);
         ctx.link().batch_callback(move |e: DragEvent| {
-            if let Some((insert_pos, would_stay_in_place, _)) = chooser.choose_insert_pos(&e) {
+            if let Some(insert_pos) = chooser.choose_file_insert_pos(&e) {
+                e.prevent_default();
+                e.stop_propagation();
+                Some(msgmaker(insert_pos))
+            } else if chooser.dragging_from_other_tree() {
+                // Accept the drop without showing an insert point, so that dropping
+                // explains why the node can't be moved here.
+                e.prevent_default();
+                e.stop_propagation();
+                Some(Msg::DragLeave)
+            } else if let Some((insert_pos, would_stay_in_place, _)) = chooser.choose_insert_pos(&e)
+            {
                 // If this is a valid drop point, prevent default to indicate that.
                 e.prevent_default();
                 // Drop points are nested, so if we're dropping here, we need to stop
                 // propagation so we don't get two insert points.
                 e.stop_propagation();
-                // But if the node would stay in place, hide the drop indicator.
-                if would_stay_in_place {
+                let copy = is_copy_drag(&e);
+                if let Some(data) = e.data_transfer() {
+                    data.set_drop_effect(if copy { "copy" } else { "move" });
+                }
+                // But if the node would stay in place, hide the drop indicator. A copy
+                // can still be dropped right next to the original.
+                if would_stay_in_place && !copy {
                     // Drag leave event is only used to clear the drop point indicator.
                     Some(Msg::DragLeave)
                 } else {
@@ -56,24 +93,52 @@ impl NodeDisplay {
     /// Build an event handler for the ondrop event.
     pub(super) fn drop_handler(&self, ctx: &Context<Self>) -> Callback<DragEvent> {
         let chooser = self.insert_pos_chooser(ctx);
+        let notifier = ctx.notifier();
         ctx.link().callback(move |e: DragEvent| {
-            if let Some((insert_pos, would_stay_in_place, src_path)) = chooser.choose_insert_pos(&e)
+            if let Some(insert_pos) = chooser.choose_file_insert_pos(&e) {
+                e.prevent_default();
+                e.stop_propagation();
+                // Only one factory is imported per drop.
+                match e
+                    .data_transfer()
+                    .and_then(|data| data.files())
+                    .and_then(|files| files.get(0))
+                {
+                    Some(file) => Msg::DropFile {
+                        insert_pos,
+                        file: file.into(),
+                    },
+                    None => Msg::DragLeave,
+                }
+            } else if chooser.dragging_from_other_tree() {
+                e.prevent_default();
+                e.stop_propagation();
+                clear_dragging();
+                notifier.notify(cross_tree_move_notice());
+                Msg::DragLeave
+            } else if let Some((insert_pos, would_stay_in_place, src_path)) =
+                chooser.choose_insert_pos(&e)
             {
                 // If this is a valid drop point, prevent default to indicate that.
                 e.prevent_default();
                 // Drop points are nested, so if we're dropping here, we need to stop
                 // propagation so we don't get two insert points.
                 e.stop_propagation();
-                if would_stay_in_place {
-                    DRAGGING.with(|dragging| *dragging.borrow_mut() = None);
+                let copy = is_copy_drag(&e);
+                if would_stay_in_place && !copy {
+                    clear_dragging();
                     Msg::DragLeave
                 } else {
-                    DRAGGING.with(|dragging| *dragging.borrow_mut() = None);
+                    clear_dragging();
                     let mut dest_path = chooser.path.clone();
                     dest_path.push(insert_pos);
+                    if chooser.moves_selection(&src_path) {
+                        return Msg::MoveSelection { dest_path, copy };
+                    }
                     Msg::MoveNode {
                         src_path,
                         dest_path,
+                        copy,
                     }
                 }
             } else {
@@ -85,17 +150,62 @@ impl NodeDisplay {
         })
     }
 
+    /// Build handlers for dragenter and dragleave on a collapsed group, which expand it
+    /// if a node or file is held over it for a moment, so it can be dropped inside.
+    pub(super) fn expand_on_hover_handlers(
+        &self,
+        ctx: &Context<Self>,
+    ) -> (Callback<DragEvent>, Callback<DragEvent>) {
+        let ondragenter = ctx.link().batch_callback(|e: DragEvent| {
+            let dragging_node = DRAGGING.with(|dragging| dragging.borrow().is_some());
+            let dragging_file = e.data_transfer().is_some_and(|data| {
+                data.types()
+                    .iter()
+                    .any(|kind| kind.as_string().as_deref() == Some("Files"))
+            });
+            (dragging_node || dragging_file).then_some(Msg::HoverCollapsed)
+        });
+        // Leave events from the group's own children are balanced by their enter events,
+        // so the counter only reaches zero when the drag leaves the whole group.
+        let ondragleave = ctx.link().callback(|_| Msg::LeaveCollapsed);
+        (ondragenter, ondragleave)
+    }
+
+    /// Start the timer to expand this group when a drag enters it, if not started.
+    pub(super) fn hover_collapsed(&mut self, ctx: &Context<Self>) {
+        self.hover_count += 1;
+        if self.expand_timer.is_none() {
+            let link = ctx.link().clone();
+            self.expand_timer = Some(Timeout::new(EXPAND_HOVER_DELAY_MS, move || {
+                link.send_message(Msg::ExpandForDrop)
+            }));
+        }
+    }
+
+    /// Cancel the timer to expand this group when a drag leaves it entirely.
+    pub(super) fn leave_collapsed(&mut self) {
+        self.hover_count = self.hover_count.saturating_sub(1);
+        if self.hover_count == 0 {
+            self.expand_timer = None;
+        }
+    }
+
     /// Creates a drag-handle for this element.
     pub(super) fn drag_handle(&self, ctx: &Context<Self>) -> Html {
         if ctx.props().path.is_empty() {
             html! {}
         } else {
-            let srcpath = ctx.props().path.clone();
-            let ondragstart = Callback::from(move |_| {
-                DRAGGING.with(|dragging| *dragging.borrow_mut() = Some(srcpath.clone()));
+            let source = (self.tree(ctx), ctx.props().path.clone());
+            let ondragstart = Callback::from(move |e: DragEvent| {
+                if let Some(data) = e.data_transfer() {
+                    data.set_effect_allowed("copyMove");
+                }
+                DRAGGING.with(|dragging| *dragging.borrow_mut() = Some(source.clone()));
             });
+            let onclick = self.select_handler(ctx);
             html! {
-                <div class="drag-handle" draggable="true" {ondragstart}>
+                <div class="drag-handle" draggable="true" {ondragstart} {onclick}
+                    title="Drag to move. Ctrl+click to select, Shift+click to select a range.">
                     <span class="material-icons">{"drag_handle"}</span>
                 </div>
             }
@@ -103,15 +213,40 @@ impl NodeDisplay {
     }
 }
 
+/// Whether the user is holding the modifier to copy the dragged node instead of moving it.
+fn is_copy_drag(event: &DragEvent) -> bool {
+    event.ctrl_key() || event.alt_key()
+}
+
 /// Helper to choose an insert position for a Node.
 struct InsertPosChooser {
     /// Children ref of the node. Used to find child client rects.
     children: NodeRef,
     /// Path to this node. Used to determine if the given node is a parent of this one.
     path: Vec<usize>,
+    /// Tree this node is in. Nodes can't be dropped into another tree.
+    tree: NodeTree,
+    /// Selected nodes, which are all moved when one of them is dragged.
+    selection: Selection,
 }
 
 impl InsertPosChooser {
+    /// Whether dragging the node at `src_path` moves the whole selection rather than just
+    /// that node.
+    fn moves_selection(&self, src_path: &[usize]) -> bool {
+        self.selection.len() > 1 && self.selection.contains(src_path)
+    }
+
+    /// Whether the node being dragged comes from a different tree than this node.
+    fn dragging_from_other_tree(&self) -> bool {
+        DRAGGING.with(|dragging| {
+            dragging
+                .borrow()
+                .as_ref()
+                .is_some_and(|(tree, _)| *tree != self.tree)
+        })
+    }
+
     /// Chose the insert position for the given drag event in the node this chooser is
     /// for.
     ///
@@ -122,16 +257,71 @@ impl InsertPosChooser {
     ///
     /// Return the src path to use when finding the element to move.
     fn choose_insert_pos(&self, event: &DragEvent) -> Option<(usize, bool, Vec<usize>)> {
-        let src_path = DRAGGING.with(|dragging| dragging.borrow().clone())?;
+        let (tree, src_path) = DRAGGING.with(|dragging| dragging.borrow().clone())?;
+        if tree != self.tree {
+            return None;
+        }
         // If the source path is longer than ours, the node may be a child or a peer's
         // child, but it cannot be a parent or ourself.
-        if src_path.len() <= self.path.len() {
-            if src_path == self.path[..src_path.len()] {
-                // Source is equal or a prefix of our path, so it is us or our parent.
+        if src_path.len() <= self.path.len() && src_path == self.path[..src_path.len()] {
+            // Source is equal or a prefix of our path, so it is us or our parent.
+            return None;
+        }
+        if self.moves_selection(&src_path) {
+            // Nothing selected can be dropped into itself, and since several nodes move,
+            // none of them stays in place.
+            if self
+                .selection
+                .paths()
+                .iter()
+                .any(|selected| self.path.starts_with(selected))
+            {
                 return None;
             }
+            return Some((self.insert_index(event)?, false, src_path));
+        }
+
+        let insert_idx = self.insert_index(event)?;
+
+        // Figure out if insert point would result in the node staying in the same place.
+        if src_path.len() == self.path.len() + 1 && src_path[..self.path.len()] == self.path {
+            // node is a child of this node.
+            let child_idx = src_path.last().copied().unwrap();
+            // Insert places an item in the list position before the specified index.
+            // So if a node is being placed before itself, it will stay in the same place.
+            // And if it is being placed before the next node, it will also stay in the
+            // same place.
+            if (child_idx..=child_idx + 1).contains(&insert_idx) {
+                return Some((insert_idx, true, src_path));
+            }
+        }
+
+        Some((insert_idx, false, src_path))
+    }
+
+    /// Choose the insert position for a file being dragged in from outside the page, or
+    /// None if the drag isn't carrying files.
+    fn choose_file_insert_pos(&self, event: &DragEvent) -> Option<usize> {
+        if DRAGGING.with(|dragging| dragging.borrow().is_some()) {
+            return None;
+        }
+        // The files themselves can only be read on drop, but their presence is listed
+        // in the types while dragging.
+        let has_files = event
+            .data_transfer()?
+            .types()
+            .iter()
+            .any(|kind| kind.as_string().as_deref() == Some("Files"));
+        if has_files {
+            self.insert_index(event)
+        } else {
+            None
         }
+    }
 
+    /// Index among the children of this node where a drop at the event's position would
+    /// insert.
+    fn insert_index(&self, event: &DragEvent) -> Option<usize> {
         let children = self.children.cast::<HtmlElement>()?.children();
         let drop_y = event.client_y() as f64;
         let mut child_idx = 0;
@@ -166,20 +356,6 @@ impl InsertPosChooser {
             insert_idx += 1;
         }
         // If no index was picked so far, insert point is at the end.
-
-        // Figure out if insert point would result in the node staying in the same place.
-        if src_path.len() == self.path.len() + 1 && src_path[..self.path.len()] == self.path {
-            // node is a child of this node.
-            let child_idx = src_path.last().copied().unwrap();
-            // Insert places an item in the list position before the specified index.
-            // So if a node is being placed before itself, it will stay in the same place.
-            // And if it is being placed before the next node, it will also stay in the
-            // same place.
-            if (child_idx..=child_idx + 1).contains(&insert_idx) {
-                return Some((insert_idx, true, src_path));
-            }
-        }
-
-        Some((insert_idx, false, src_path))
+        Some(insert_idx)
     }
 }
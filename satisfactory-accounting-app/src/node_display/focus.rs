@@ -0,0 +1,331 @@
+// Copyright 2024 Zachary Stewart
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+use log::warn;
+use satisfactory_accounting::accounting::Node;
+use uuid::Uuid;
+use wasm_bindgen::JsCast;
+use web_sys::{Element, HtmlElement};
+use yew::prelude::*;
+
+use super::graph_manipulation::find_group;
+use super::NodeDisplay;
+
+/// Where to find the field Enter edits, relative to a node's element: the name of a
+/// group, expanded or collapsed, or the building type of a building. Only direct
+/// sections are searched so the field of a child node isn't found instead.
+const EDIT_FIELD: &str = ":scope > .header > .GroupName, :scope > .summary > .GroupName, \
+    :scope > .section > .section > .name";
+
+/// Node with keyboard focus, kept by the app so it survives re-rendering the tree.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct NodeFocus {
+    /// Path to the focused node, if any node is focused.
+    path: Option<Vec<usize>>,
+    /// Id of the focused node if it is a group, which finds it again if it moves.
+    group: Option<Uuid>,
+}
+
+impl NodeFocus {
+    /// Focus on the node at `path` in `root`. Returns no focus if there is no such node.
+    pub fn at(root: &Node, path: Vec<usize>) -> Self {
+        match root.get_at(&path) {
+            Some(node) => Self {
+                group: node.group().map(|group| group.id),
+                path: Some(path),
+            },
+            None => Self::default(),
+        }
+    }
+
+    /// Path to the focused node, if any.
+    pub fn path(&self) -> Option<&[usize]> {
+        self.path.as_deref()
+    }
+
+    /// Whether the node at `path` is focused.
+    pub fn is(&self, path: &[usize]) -> bool {
+        self.path.as_deref() == Some(path)
+    }
+
+    /// Find the focused node again after `root` changed. A focused group is found by id
+    /// wherever it moved to, and a building stays focused if its path still leads to a
+    /// building. If the node vanished, focus falls back to its nearest ancestor which is
+    /// still in the tree.
+    pub fn resolve(&self, root: &Node) -> Self {
+        let Some(path) = &self.path else {
+            return Self::default();
+        };
+        let found = match self.group {
+            Some(id) => root.group().and_then(|root| find_group(root, id)),
+            None => root
+                .get_at(path)
+                .is_some_and(|node| node.building().is_some())
+                .then(|| path.clone()),
+        };
+        match found {
+            Some(path) => Self::at(root, path),
+            None => {
+                let mut parent = path.clone();
+                parent.pop();
+                while !parent.is_empty() && root.get_at(&parent).is_none() {
+                    parent.pop();
+                }
+                Self::at(root, parent)
+            }
+        }
+    }
+
+    /// Focus moved one step from the node at `from`, or None if there is no node in
+    /// that direction.
+    pub fn step(root: &Node, from: &[usize], step: FocusStep) -> Option<Self> {
+        let mut path = from.to_vec();
+        match step {
+            FocusStep::Previous => {
+                let last = path.last_mut()?;
+                *last = last.checked_sub(1)?;
+            }
+            FocusStep::Next => *path.last_mut()? += 1,
+            FocusStep::Parent => {
+                path.pop()?;
+            }
+            FocusStep::FirstChild => path.push(0),
+        }
+        root.get_at(&path)?;
+        Some(Self::at(root, path))
+    }
+}
+
+/// Direction to move the focus with the arrow keys.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FocusStep {
+    /// The previous sibling.
+    Previous,
+    /// The next sibling.
+    Next,
+    /// The parent group.
+    Parent,
+    /// The first child of a group.
+    FirstChild,
+}
+
+/// Change to the focus requested by a node.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FocusAction {
+    /// The node at this path received focus from the browser, e.g. by being clicked.
+    Focused(Vec<usize>),
+    /// Move the focus from the node at `from`, and give the new node browser focus.
+    Step { from: Vec<usize>, step: FocusStep },
+    /// Give the node at this path focus, e.g. a group jumped to from the breadcrumbs.
+    Set(Vec<usize>),
+    /// Browser focus left the tree.
+    Clear,
+}
+
+/// Value of the attribute which identifies the element of the node at `path`, so the app
+/// can give it browser focus.
+pub fn focus_attr(path: &[usize]) -> String {
+    path.iter()
+        .map(|idx| idx.to_string())
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+/// Give browser focus to the element of the node at `path`. Does nothing if the node isn't
+/// rendered, e.g. because it is inside a collapsed group.
+pub fn focus_element(path: &[usize]) {
+    let selector = format!("[data-focus-path=\"{}\"]", focus_attr(path));
+    if let Some(element) = gloo::utils::document()
+        .query_selector(&selector)
+        .ok()
+        .flatten()
+        .and_then(|element| element.dyn_into::<HtmlElement>().ok())
+    {
+        if let Err(e) = element.focus() {
+            warn!("Unable to focus node {path:?}: {e:?}");
+        }
+    }
+}
+
+/// Whether browser focus is on nothing in particular, as happens when the focused element
+/// is removed.
+pub fn focus_is_lost() -> bool {
+    let document = gloo::utils::document();
+    document.active_element().is_none_or(|active| {
+        document
+            .body()
+            .is_some_and(|body| *body.unchecked_ref::<Element>() == active)
+    })
+}
+
+impl NodeDisplay {
+    /// Whether this node has keyboard focus.
+    pub(super) fn is_focused(&self, ctx: &Context<Self>) -> bool {
+        let (focus, _) = ctx
+            .link()
+            .context::<NodeFocus>(Callback::noop())
+            .expect("focus context to be set");
+        focus.is(&ctx.props().path)
+    }
+
+    /// Build a handler which tells the app when this node's element gets browser focus.
+    pub(super) fn focus_handler(&self, ctx: &Context<Self>) -> Callback<FocusEvent> {
+        let path = ctx.props().path.clone();
+        let focus_action = ctx.props().focus_action.clone();
+        Callback::from(move |e: FocusEvent| {
+            // Focus events don't bubble, but check anyway so only the node itself counts.
+            if e.target() == e.current_target() {
+                focus_action.emit(FocusAction::Focused(path.clone()));
+            }
+        })
+    }
+
+    /// Build a handler for the root node which tells the app when browser focus leaves the
+    /// tree, so no node is shown focused while the user is elsewhere.
+    pub(super) fn focus_out_handler(&self, ctx: &Context<Self>) -> Option<Callback<FocusEvent>> {
+        if !ctx.props().path.is_empty() {
+            return None;
+        }
+        let focus_action = ctx.props().focus_action.clone();
+        Some(Callback::from(move |e: FocusEvent| {
+            // Removing a focused node also takes focus away, but the app finds the node's
+            // replacement rather than clearing the focus.
+            let removed = e
+                .target()
+                .and_then(|target| target.dyn_into::<web_sys::Node>().ok())
+                .is_some_and(|target| !target.is_connected());
+            let tree = e
+                .current_target()
+                .and_then(|tree| tree.dyn_into::<web_sys::Node>().ok());
+            let into = e
+                .related_target()
+                .and_then(|into| into.dyn_into::<web_sys::Node>().ok());
+            let within = tree.is_some_and(|tree| tree.contains(into.as_ref()));
+            if !removed && !within {
+                focus_action.emit(FocusAction::Clear);
+            }
+        }))
+    }
+
+    /// Build the keyboard handler for this node. The arrow keys move the focus, Enter
+    /// edits the node's name or building type, Delete removes it, and Ctrl+D duplicates
+    /// it; other shortcuts are cut, copy, and paste.
+    pub(super) fn keydown_handler(&self, ctx: &Context<Self>) -> Callback<KeyboardEvent> {
+        let cut_paste = self.cut_paste_handler(ctx);
+        let path = ctx.props().path.clone();
+        let focus_action = ctx.props().focus_action.clone();
+        let delete = ctx.props().delete.clone();
+        let copy = ctx.props().copy.clone();
+        Callback::from(move |e: KeyboardEvent| {
+            // Keys pressed in the fields of this node, or in other nodes, are theirs.
+            if e.target() != e.current_target() {
+                return cut_paste.emit(e);
+            }
+            let ctrl = e.ctrl_key() || e.meta_key();
+            let step = |step| FocusAction::Step {
+                from: path.clone(),
+                step,
+            };
+            let idx = path.last().copied();
+            match &*e.key() {
+                "ArrowUp" | "Up" if !ctrl => focus_action.emit(step(FocusStep::Previous)),
+                "ArrowDown" | "Down" if !ctrl => focus_action.emit(step(FocusStep::Next)),
+                "ArrowLeft" | "Left" if !ctrl => focus_action.emit(step(FocusStep::Parent)),
+                "ArrowRight" | "Right" if !ctrl => focus_action.emit(step(FocusStep::FirstChild)),
+                "Enter" if !ctrl => edit_field(&e),
+                // Focus falls back to the parent once the node is gone.
+                "Delete" if !ctrl => match (&delete, idx) {
+                    (Some(delete), Some(idx)) => delete.emit(idx),
+                    _ => return,
+                },
+                "d" | "D" if ctrl => match (&copy, idx) {
+                    (Some(copy), Some(idx)) => copy.emit(idx),
+                    _ => return,
+                },
+                _ => return cut_paste.emit(e),
+            }
+            // Nodes are nested, so only the focused node should handle this.
+            e.prevent_default();
+            e.stop_propagation();
+        })
+    }
+}
+
+/// Start editing the main field of the node whose element the event is for.
+fn edit_field(e: &KeyboardEvent) {
+    match e
+        .current_target()
+        .and_then(|target| target.dyn_into::<Element>().ok())
+        .and_then(|element| element.query_selector(EDIT_FIELD).ok().flatten())
+        .and_then(|field| field.dyn_into::<HtmlElement>().ok())
+    {
+        Some(field) => field.click(),
+        None => warn!("Focused node has no field to edit"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use satisfactory_accounting::accounting::{Building, Group};
+
+    use super::*;
+
+    /// Root with groups a(a0, a1) and b, and a building c.
+    fn root() -> Node {
+        let group = |children| -> Node {
+            Group {
+                children,
+                ..Group::empty()
+            }
+            .into()
+        };
+        group(vec![
+            group(vec![Building::empty_node(), Building::empty_node()]),
+            group(vec![]),
+            Building::empty_node(),
+        ])
+    }
+
+    #[test]
+    fn step_between_nodes() {
+        let root = root();
+        let step = |from: &[usize], step| {
+            NodeFocus::step(&root, from, step).map(|focus| focus.path().unwrap().to_vec())
+        };
+        assert_eq!(step(&[0, 1], FocusStep::Previous), Some(vec![0, 0]));
+        assert_eq!(step(&[0, 0], FocusStep::Previous), None);
+        assert_eq!(step(&[1], FocusStep::Next), Some(vec![2]));
+        assert_eq!(step(&[2], FocusStep::Next), None);
+        assert_eq!(step(&[0, 1], FocusStep::Parent), Some(vec![0]));
+        assert_eq!(step(&[0], FocusStep::Parent), Some(vec![]));
+        assert_eq!(step(&[], FocusStep::Parent), None);
+        assert_eq!(step(&[0], FocusStep::FirstChild), Some(vec![0, 0]));
+        assert_eq!(step(&[1], FocusStep::FirstChild), None);
+        assert_eq!(step(&[2], FocusStep::FirstChild), None);
+    }
+
+    #[test]
+    fn resolve_after_edits() {
+        let root = root();
+        let group_b = NodeFocus::at(&root, vec![1]);
+        let building = NodeFocus::at(&root, vec![0, 1]);
+        assert_eq!(NodeFocus::at(&root, vec![5]), NodeFocus::default());
+
+        // Moving b to the front keeps it focused at its new path.
+        let (without_b, b) = root.remove_at(&[1]).unwrap();
+        let moved = without_b.insert_at(&[0], b).unwrap();
+        assert!(group_b.resolve(&moved).is(&[0]));
+
+        // Deleting b or the building falls back to the parent.
+        assert!(group_b.resolve(&without_b).is(&[]));
+        let (without_building, _) = root.remove_at(&[0, 1]).unwrap();
+        assert!(building.resolve(&without_building).is(&[0]));
+
+        // A building keeps its focus when the tree changes elsewhere.
+        assert!(building.resolve(&without_b).is(&[0, 1]));
+    }
+}
@@ -8,14 +8,15 @@
 //! Utilities for manipulating the node graph.
 
 use log::warn;
-use satisfactory_accounting::accounting::{Group, Node, NodeKind};
+use satisfactory_accounting::accounting::{Group, GroupCopyVisitor, Node};
+use uuid::Uuid;
 
 /// Move a node from one position in a group to another. Both src and dest paths should be
 /// rooted at this group. Assumes that this node is the lowest common ancestor of src and
 /// dest, that is that src and dest have no parents in common below this node.
 pub fn move_child(group: &Group, src: &[usize], dest: &[usize]) -> Option<Group> {
     let (_, src_prefix) = src.split_last().expect("source path was empty");
-    let (_, dest_prefix) = dest.split_last().expect("source path was empty");
+    let (_, dest_prefix) = dest.split_last().expect("destination path was empty");
     assert!(
         src_prefix
             .iter()
@@ -25,97 +26,467 @@ pub fn move_child(group: &Group, src: &[usize], dest: &[usize]) -> Option<Group>
             == 0,
         "src and dest had overlapping prefixes"
     );
-    let src_first = src.first().copied().unwrap();
-    let mut dest_first = dest.first().copied().unwrap();
-    if src_prefix.is_empty() && src_first < dest_first {
+    let mut dest = dest.to_vec();
+    if src_prefix.is_empty() && src[0] < dest[0] {
         // If removal of src will affect dest, change the first index of dest.
-        dest_first -= 1;
+        dest[0] -= 1;
     }
 
-    if src_first >= group.children.len() {
-        warn!("Attempting to move from an out of bounds index");
-        return None;
-    }
+    let root: Node = group.clone().into();
+    let (root, moved) = root
+        .remove_at(src)
+        .map_err(|e| warn!("Unable to remove moved node: {e}"))
+        .ok()?;
+    root.insert_at(&dest, moved)
+        .map_err(|e| warn!("Unable to insert moved node: {e}"))
+        .ok()?
+        .group()
+        .cloned()
+}
 
-    let mut new_group = group.clone();
-    let moved = if src_prefix.is_empty() {
-        new_group.children.remove(src_first)
-    } else {
-        let (replacement, moved) = remove_child(&new_group.children[src_first], &src[1..])?;
-        new_group.children[src_first] = replacement;
-        moved
+/// Insert a copy of the node at `src` at `dest`, leaving the original in place. Both paths
+/// should be rooted at this group. Groups in the copy get new ids, and `visitor` sees each
+/// original group alongside its copy, e.g. to copy metadata.
+pub fn copy_child(
+    group: &Group,
+    src: &[usize],
+    dest: &[usize],
+    visitor: &impl GroupCopyVisitor,
+) -> Option<Group> {
+    let root: Node = group.clone().into();
+    let Some(copied) = root.get_at(src) else {
+        warn!("Node to copy was not found");
+        return None;
     };
+    let copied = copied.create_copy_with_visitor(visitor);
+    root.insert_at(dest, copied)
+        .map_err(|e| warn!("Unable to insert copied node: {e}"))
+        .ok()?
+        .group()
+        .cloned()
+}
 
-    if dest_first > new_group.children.len() {
-        warn!("Attempting to move to an out of boudns index");
-        return None;
+/// Sort the given paths into tree order, dropping duplicates, the root, and any path whose
+/// ancestor is also in the list, since that node moves along with its ancestor.
+pub fn outermost_paths(paths: &[Vec<usize>]) -> Vec<Vec<usize>> {
+    let mut paths: Vec<_> = paths.iter().filter(|path| !path.is_empty()).collect();
+    paths.sort();
+    paths.dedup();
+    let mut outermost: Vec<Vec<usize>> = Vec::with_capacity(paths.len());
+    for path in paths {
+        // Sorting puts an ancestor immediately before its descendants.
+        if !outermost.last().is_some_and(|prev| path.starts_with(prev)) {
+            outermost.push(path.clone());
+        }
     }
+    outermost
+}
 
-    if dest_prefix.is_empty() {
-        new_group.children.insert(dest_first, moved);
-    } else {
-        new_group.children[dest_first] =
-            insert_child(&new_group.children[dest_first], &dest[1..], moved)?;
+/// Remove the nodes at each of `srcs` from `root`, returning the new root and the removed
+/// nodes in tree order. `dest`, if given, is adjusted so it still points at the same
+/// position once the earlier nodes are gone. Assumes `srcs` came from [`outermost_paths`].
+fn remove_children(
+    root: Node,
+    srcs: &[Vec<usize>],
+    dest: &mut [usize],
+) -> Option<(Node, Vec<Node>)> {
+    let mut root = root;
+    let mut removed = Vec::with_capacity(srcs.len());
+    // Remove from the end so each removal leaves the remaining source paths intact.
+    for src in srcs.iter().rev() {
+        let (new_root, node) = root
+            .remove_at(src)
+            .map_err(|e| warn!("Unable to remove node: {e}"))
+            .ok()?;
+        root = new_root;
+        removed.push(node);
+        let (&idx, prefix) = src.split_last().expect("source path was empty");
+        let depth = prefix.len();
+        if depth < dest.len() && dest.starts_with(prefix) && idx < dest[depth] {
+            dest[depth] -= 1;
+        }
     }
+    removed.reverse();
+    Some((root, removed))
+}
 
-    Some(new_group)
+/// Move every node at `srcs` to `dest`, in tree order, where `dest` is the position to
+/// insert at before any nodes are removed. All paths should be rooted at `root`. Sources
+/// nested inside other sources move along with their ancestor. Returns none if `dest` is
+/// inside one of the moved nodes or any path is out of bounds.
+pub fn move_children(root: &Group, srcs: &[Vec<usize>], dest: &[usize]) -> Option<Group> {
+    let srcs = outermost_paths(srcs);
+    let (_, dest_parent) = dest.split_last().expect("destination path was empty");
+    if srcs.iter().any(|src| dest_parent.starts_with(src)) {
+        warn!("Cannot move a group into itself");
+        return None;
+    }
+    let mut dest = dest.to_vec();
+    let (mut new_root, moved) = remove_children(root.clone().into(), &srcs, &mut dest)?;
+    for node in moved {
+        new_root = new_root
+            .insert_at(&dest, node)
+            .map_err(|e| warn!("Unable to insert moved node: {e}"))
+            .ok()?;
+        *dest.last_mut().expect("destination path was empty") += 1;
+    }
+    new_root.group().cloned()
 }
 
-/// Recursively removes a child node. Returns the new group to replace the one modified
-/// and the node that was removed. Returns none if not a group or out of bounds.
-pub fn remove_child(node: &Node, child: &[usize]) -> Option<(Node, Node)> {
-    let group = match node.kind() {
-        NodeKind::Group(group) => group,
-        _ => {
-            warn!("Source for remove child did not point to a group");
+/// Insert copies of every node at `srcs` at `dest`, in tree order, leaving the originals in
+/// place. All paths should be rooted at `root`. `visitor` sees each copied group as in
+/// [`copy_child`].
+pub fn copy_children(
+    root: &Group,
+    srcs: &[Vec<usize>],
+    dest: &[usize],
+    visitor: &impl GroupCopyVisitor,
+) -> Option<Group> {
+    let srcs = outermost_paths(srcs);
+    let mut new_root: Node = root.clone().into();
+    let mut copies = Vec::with_capacity(srcs.len());
+    for src in &srcs {
+        let Some(copied) = new_root.get_at(src) else {
+            warn!("Node to copy was not found");
             return None;
-        }
-    };
+        };
+        copies.push(copied.create_copy_with_visitor(visitor));
+    }
+    let mut dest = dest.to_vec();
+    for copy in copies {
+        new_root = new_root
+            .insert_at(&dest, copy)
+            .map_err(|e| warn!("Unable to insert copied node: {e}"))
+            .ok()?;
+        *dest.last_mut().expect("destination path was empty") += 1;
+    }
+    new_root.group().cloned()
+}
 
-    let (&next_idx, rest) = child
-        .split_first()
-        .expect("Don't call remove_child with an empty path");
+/// Delete every node at `srcs`. All paths should be rooted at `root`. Returns none if any
+/// path is out of bounds.
+pub fn delete_children(root: &Group, srcs: &[Vec<usize>]) -> Option<Group> {
+    let srcs = outermost_paths(srcs);
+    let (new_root, _) = remove_children(root.clone().into(), &srcs, &mut [])?;
+    new_root.group().cloned()
+}
+
+/// Split `paths` into those which can be deleted and those whose nodes are locked against
+/// deletion or contain a locked node. All paths should be rooted at `root`. Paths which
+/// are out of bounds are left with the deletable ones.
+pub fn split_deletion_blocked(
+    root: &Group,
+    paths: Vec<Vec<usize>>,
+) -> (Vec<Vec<usize>>, Vec<Vec<usize>>) {
+    paths.into_iter().partition(|path| {
+        !path
+            .split_first()
+            .and_then(|(&first, rest)| root.children.get(first)?.get_at(rest))
+            .is_some_and(|node| node.deletion_blocked())
+    })
+}
 
-    if next_idx >= group.children.len() {
-        warn!("Attempting to remove from an out of bounds index");
+/// Move the group with id `moved` into the group with id `into`, placing it at `position`
+/// among the destination's children after the move. Both groups are found by id when the
+/// move is applied, so the move still lands in the right place if nodes were reordered
+/// since it was requested. Returns none if either group is missing, if the root would be
+/// moved, if a group would be moved into itself, or if position is out of bounds.
+pub fn move_group_by_id(root: &Group, moved: Uuid, into: Uuid, position: usize) -> Option<Group> {
+    let Some(src) = find_group(root, moved) else {
+        warn!("Group to move was not found");
+        return None;
+    };
+    if src.is_empty() {
+        warn!("Cannot move the root group");
         return None;
     }
-    let mut new_group = group.clone();
-    if rest.is_empty() {
-        let moved = new_group.children.remove(next_idx);
-        Some((new_group.into(), moved))
-    } else {
-        let (replacement, moved) = remove_child(&new_group.children[next_idx], rest)?;
-        new_group.children[next_idx] = replacement;
-        Some((new_group.into(), moved))
+    if find_group(root, into).is_some_and(|dest| dest.starts_with(&src)) {
+        warn!("Cannot move a group into itself");
+        return None;
     }
+    let root: Node = root.clone().into();
+    let (new_root, moved) = root
+        .remove_at(&src)
+        .map_err(|e| warn!("Unable to remove moved group: {e}"))
+        .ok()?;
+    // Removing the group may shift the destination, so find it again.
+    let Some(mut dest) = find_group(new_root.group()?, into) else {
+        warn!("Destination group was not found");
+        return None;
+    };
+    dest.push(position);
+    new_root
+        .insert_at(&dest, moved)
+        .map_err(|e| warn!("Unable to insert moved group: {e}"))
+        .ok()?
+        .group()
+        .cloned()
 }
 
-/// Recursively inserts a child node. Returns the new group to replace the one modified.
-/// Returns none if not a group or out of bounds.
-pub fn insert_child(node: &Node, child: &[usize], moved: Node) -> Option<Node> {
-    let group = match node.kind() {
-        NodeKind::Group(group) => group,
-        _ => {
-            warn!("Source for insert child did not point to a group");
-            return None;
+/// Find the path from `group` to the group with the given id. The path is empty if
+/// `group` itself has the id.
+pub fn find_group(group: &Group, id: Uuid) -> Option<Vec<usize>> {
+    if group.id == id {
+        return Some(Vec::new());
+    }
+    group.children.iter().enumerate().find_map(|(idx, child)| {
+        let mut path = find_group(child.group()?, id)?;
+        path.insert(0, idx);
+        Some(path)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use satisfactory_accounting::accounting::{BuildNode, Building};
+    use satisfactory_accounting::database::DatabaseVersion;
+
+    use super::*;
+
+    /// Create a named group with the given children.
+    fn group(name: &'static str, children: Vec<Node>) -> Node {
+        Group {
+            name: name.into(),
+            children,
+            ..Group::empty()
         }
-    };
+        .into()
+    }
 
-    let (&next_idx, rest) = child
-        .split_first()
-        .expect("Don't call insert_child with an empty path");
+    /// Describe the structure of a group by the names of its children, e.g. `a(b, c)`.
+    fn describe(group: &Group) -> String {
+        group
+            .children
+            .iter()
+            .map(|child| {
+                let child = child.group().unwrap();
+                if child.children.is_empty() {
+                    child.name.to_string()
+                } else {
+                    format!("{}({})", child.name, describe(child))
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
 
-    if next_idx > group.children.len() {
-        warn!("Attempting to insert to an out of bounds index");
-        return None;
+    /// Root with two outermost groups, containing nested groups.
+    fn root() -> Group {
+        let root = group(
+            "root",
+            vec![
+                group("a", vec![group("a0", vec![group("a00", vec![])])]),
+                group("b", vec![group("b0", vec![])]),
+            ],
+        );
+        root.group().unwrap().clone()
+    }
+
+    #[test]
+    fn move_deeply_nested_to_root_start() {
+        let moved = move_child(&root(), &[0, 0, 0], &[0]).unwrap();
+        assert_eq!(describe(&moved), "a00, a(a0), b(b0)");
+    }
+
+    #[test]
+    fn move_nested_to_root_between_and_end() {
+        let moved = move_child(&root(), &[0, 0], &[1]).unwrap();
+        assert_eq!(describe(&moved), "a, a0(a00), b(b0)");
+
+        let moved = move_child(&root(), &[1, 0], &[2]).unwrap();
+        assert_eq!(describe(&moved), "a(a0(a00)), b, b0");
+    }
+
+    #[test]
+    fn move_from_root_into_nested() {
+        // Removing an earlier sibling shifts the index of the destination group.
+        let moved = move_child(&root(), &[0], &[1, 1]).unwrap();
+        assert_eq!(describe(&moved), "b(b0, a(a0(a00)))");
+
+        let moved = move_child(&root(), &[1], &[0, 0, 1]).unwrap();
+        assert_eq!(describe(&moved), "a(a0(a00, b(b0)))");
+    }
+
+    #[test]
+    fn move_within_root() {
+        let moved = move_child(&root(), &[0], &[2]).unwrap();
+        assert_eq!(describe(&moved), "b(b0), a(a0(a00))");
+    }
+
+    #[test]
+    fn move_out_of_bounds_fails() {
+        assert!(move_child(&root(), &[2], &[0]).is_none());
+        assert!(move_child(&root(), &[0], &[3]).is_none());
+    }
+
+    #[test]
+    fn copy_keeps_original() {
+        let root = root();
+        let copied = copy_child(&root, &[0, 0], &[2], &|_: &Group, _: &mut Group| {}).unwrap();
+        assert_eq!(describe(&copied), "a(a0(a00)), b(b0), a0(a00)");
+        // The copy is deep, with its own ids.
+        assert_ne!(id_at(&copied, &[2]), id_at(&copied, &[0, 0]));
+        assert_ne!(id_at(&copied, &[2, 0]), id_at(&copied, &[0, 0, 0]));
+
+        // Copying next to the original is allowed, unlike moving in place.
+        let copied = copy_child(&root, &[1], &[1, 0], &|_: &Group, _: &mut Group| {}).unwrap();
+        assert_eq!(describe(&copied), "a(a0(a00)), b(b(b0), b0)");
+
+        assert!(copy_child(&root, &[2], &[0], &|_: &Group, _: &mut Group| {}).is_none());
+    }
+
+    /// Root with several siblings, for moving multiple nodes at once.
+    fn wide_root() -> Group {
+        let root = group(
+            "root",
+            vec![
+                group("a", vec![]),
+                group("b", vec![]),
+                group("c", vec![group("c0", vec![])]),
+                group("d", vec![]),
+                group("e", vec![]),
+            ],
+        );
+        root.group().unwrap().clone()
+    }
+
+    #[test]
+    fn outermost_paths_drop_nested() {
+        let paths = outermost_paths(&[vec![2, 0], vec![1], vec![2], vec![1], vec![]]);
+        assert_eq!(paths, vec![vec![1], vec![2]]);
+    }
+
+    #[test]
+    fn move_several_siblings_forward() {
+        // Removing the earlier siblings shifts the destination back.
+        let moved = move_children(&wide_root(), &[vec![0], vec![2]], &[4]).unwrap();
+        assert_eq!(describe(&moved), "b, d, a, c(c0), e");
+
+        let moved = move_children(&wide_root(), &[vec![3], vec![0]], &[5]).unwrap();
+        assert_eq!(describe(&moved), "b, c(c0), e, a, d");
+    }
+
+    #[test]
+    fn move_several_siblings_backward() {
+        let moved = move_children(&wide_root(), &[vec![1], vec![4]], &[0]).unwrap();
+        assert_eq!(describe(&moved), "b, e, a, c(c0), d");
+
+        // Moving around a destination between the moved nodes.
+        let moved = move_children(&wide_root(), &[vec![1], vec![3]], &[2]).unwrap();
+        assert_eq!(describe(&moved), "a, b, d, c(c0), e");
+    }
+
+    #[test]
+    fn move_several_into_nested() {
+        // Both the earlier siblings and the destination group's own index shift.
+        let moved = move_children(&wide_root(), &[vec![0], vec![1], vec![4]], &[2, 1]).unwrap();
+        assert_eq!(describe(&moved), "c(c0, a, b, e), d");
+
+        // Nodes inside a moved group move with it.
+        let moved = move_children(&wide_root(), &[vec![2, 0], vec![2]], &[0]).unwrap();
+        assert_eq!(describe(&moved), "c(c0), a, b, d, e");
+
+        let moved = move_children(&wide_root(), &[vec![2, 0], vec![4]], &[1]).unwrap();
+        assert_eq!(describe(&moved), "a, c0, e, b, c, d");
+    }
+
+    #[test]
+    fn invalid_bulk_moves_fail() {
+        assert!(move_children(&wide_root(), &[vec![0], vec![2]], &[2, 0]).is_none());
+        assert!(move_children(&wide_root(), &[vec![5]], &[0]).is_none());
+        assert!(move_children(&wide_root(), &[vec![0]], &[6]).is_none());
+    }
+
+    #[test]
+    fn copy_and_delete_several() {
+        let root = wide_root();
+        let copied = copy_children(
+            &root,
+            &[vec![3], vec![0]],
+            &[2],
+            &|_: &Group, _: &mut Group| {},
+        )
+        .unwrap();
+        assert_eq!(describe(&copied), "a, b, a, d, c(c0), d, e");
+
+        let deleted = delete_children(&root, &[vec![1], vec![2, 0], vec![3]]).unwrap();
+        assert_eq!(describe(&deleted), "a, c, e");
+        assert!(delete_children(&root, &[vec![1], vec![5]]).is_none());
+    }
+
+    #[test]
+    fn locked_nodes_are_not_deleted() {
+        let locked = Building {
+            delete_locked: true,
+            ..Building::empty()
+        }
+        .build_node(&DatabaseVersion::LATEST.load_database())
+        .unwrap();
+        let root = group(
+            "root",
+            vec![
+                group("a", vec![]),
+                group("b", vec![]),
+                group("c", vec![group("c0", vec![]), locked]),
+            ],
+        );
+        let root = root.group().unwrap();
+        let (deletable, blocked) =
+            split_deletion_blocked(root, vec![vec![1], vec![2], vec![2, 0], vec![2, 1]]);
+        assert_eq!(deletable, [vec![1], vec![2, 0]]);
+        assert_eq!(blocked, [vec![2], vec![2, 1]]);
+
+        let deleted = delete_children(root, &deletable).unwrap();
+        assert_eq!(deleted.children.len(), 2);
+        let c = deleted.children[1].group().unwrap();
+        assert_eq!(c.name, "c");
+        assert_eq!(c.children.len(), 1);
+        assert!(c.children[0].delete_locked());
+    }
+
+    /// Get the id of the group at the given path.
+    fn id_at(group: &Group, path: &[usize]) -> Uuid {
+        match path.split_first() {
+            Some((&idx, rest)) => id_at(group.children[idx].group().unwrap(), rest),
+            None => group.id,
+        }
+    }
+
+    #[test]
+    fn move_by_id() {
+        let root = root();
+        let a00 = id_at(&root, &[0, 0, 0]);
+        let b = id_at(&root, &[1]);
+        let moved = move_group_by_id(&root, a00, b, 0).unwrap();
+        assert_eq!(describe(&moved), "a(a0), b(a00, b0)");
+
+        let moved = move_group_by_id(&root, b, root.id, 0).unwrap();
+        assert_eq!(describe(&moved), "b(b0), a(a0(a00))");
+    }
+
+    #[test]
+    fn move_by_id_after_reordering() {
+        let root = root();
+        let a0 = id_at(&root, &[0, 0]);
+        let b0 = id_at(&root, &[1, 0]);
+        // Reorder the outer groups after the ids were captured; the paths captured
+        // earlier would now point at the wrong groups.
+        let reordered = move_child(&root, &[1], &[0]).unwrap();
+        assert_eq!(describe(&reordered), "b(b0), a(a0(a00))");
+        let moved = move_group_by_id(&reordered, a0, b0, 0).unwrap();
+        assert_eq!(describe(&moved), "b(b0(a0(a00))), a");
     }
 
-    let mut new_group = group.clone();
-    if rest.is_empty() {
-        new_group.children.insert(next_idx, moved);
-    } else {
-        new_group.children[next_idx] = insert_child(&new_group.children[next_idx], rest, moved)?;
+    #[test]
+    fn invalid_moves_by_id_fail() {
+        let root = root();
+        let a = id_at(&root, &[0]);
+        let a00 = id_at(&root, &[0, 0, 0]);
+        assert!(move_group_by_id(&root, root.id, a, 0).is_none());
+        assert!(move_group_by_id(&root, a, a00, 0).is_none());
+        assert!(move_group_by_id(&root, a, a, 0).is_none());
+        assert!(move_group_by_id(&root, Uuid::new_v4(), a, 0).is_none());
+        assert!(move_group_by_id(&root, a00, Uuid::new_v4(), 0).is_none());
+        assert!(move_group_by_id(&root, a00, root.id, 3).is_none());
     }
-    Some(new_group.into())
 }
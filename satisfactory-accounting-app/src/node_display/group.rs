@@ -5,22 +5,37 @@
 //   You may obtain a copy of the License at
 //
 //       http://www.apache.org/licenses/LICENSE-2.0
-use satisfactory_accounting::accounting::{Building, Group};
+use std::collections::HashSet;
+use std::rc::Rc;
+
+use satisfactory_accounting::accounting::{Building, Group, Node};
 use yew::prelude::*;
 
+use crate::node_display::balance::rounded;
 use crate::node_display::copies::VirtualCopies;
-use crate::node_display::{Msg, NodeDisplay, NodeMeta, DRAG_INSERT_POINT};
-use crate::CtxHelper;
+use crate::node_display::focus::focus_attr;
+use crate::node_display::{
+    collapse_below, Msg, NodeDisplay, NodeMeta, NodeMetadata, DRAG_INSERT_POINT,
+};
+use crate::{item_label, CtxHelper};
 
+use color_picker::ColorPicker;
+use distribute::{DistributeOutput, Distribution};
 use group_name::GroupName;
+pub(super) use interface::INTERFACE_WARNING_ICON;
+use interface::{describe_violation, InterfaceEditor};
+use sort_children::SortChildren;
 
+mod color_picker;
+mod distribute;
 mod group_name;
+mod interface;
+mod sort_children;
 
 impl NodeDisplay {
     /// Build the display for a Group.
     pub(super) fn view_group(&self, ctx: &Context<Self>, group: &Group) -> Html {
-        let meta = ctx.meta(group.id);
-        if meta.collapsed {
+        if self.is_collapsed(ctx, group) {
             self.view_group_collapsed(ctx, group)
         } else {
             self.view_group_expanded(ctx, group)
@@ -31,12 +46,21 @@ impl NodeDisplay {
     fn view_group_expanded(&self, ctx: &Context<Self>, group: &Group) -> Html {
         let link = ctx.link();
         let update_copies = link.callback(|copies| Msg::SetCopyCount { copies });
+        let cut = self.is_cut(ctx).then_some("cut");
+        let selected = self.is_selected(ctx).then_some("selected");
+        let dimmed = self.tag_dimmed(ctx);
+        let focused = self.is_focused(ctx).then_some("focused");
+        let (colored, color_style) = color_accent(group);
+        let onkeydown = self.keydown_handler(ctx);
+        let onfocus = self.focus_handler(ctx);
+        let focus_path = focus_attr(&ctx.props().path);
         let replace = link.callback(|(idx, replacement)| Msg::ReplaceChild { idx, replacement });
         let delete = link.callback(|idx| Msg::DeleteChild { idx });
         let copy = link.callback(|idx| Msg::CopyChild { idx });
-        let move_node = link.callback(|(src_path, dest_path)| Msg::MoveNode {
+        let move_node = link.callback(|(src_path, dest_path, copy)| Msg::MoveNode {
             src_path,
             dest_path,
+            copy,
         });
         let add_group = link.callback(|_| Msg::AddChild {
             child: Group::empty_node(),
@@ -53,13 +77,41 @@ impl NodeDisplay {
 
         let set_metadata = &ctx.props().set_metadata;
         let batch_set_metadata = &ctx.props().batch_set_metadata;
+        let trace_item = &ctx.props().trace_item;
+        let selection_action = &ctx.props().selection_action;
+        let focus_action = &ctx.props().focus_action;
+        let onfocusout = self.focus_out_handler(ctx);
+        let duplicates = duplicate_names(group);
+        let move_group = &ctx.props().move_group;
+        let open_sandbox = &ctx.props().open_sandbox;
+        let tag_matched = self.tag_matched(ctx);
+        let available_items = Rc::new(group.produced_items());
         html! {
-            <div class="NodeDisplay group expanded" key={group.id.as_u128()}>
+            <div class={classes!("NodeDisplay", "group", "expanded", colored, cut, selected, focused, dimmed)}
+                key={group.id.as_u128()} id={node_element_id(group)} style={color_style}
+                data-focus-path={focus_path} tabindex="-1" {onkeydown} {onfocus} {onfocusout}>
                 <div class="header">
                     {self.drag_handle(ctx)}
                     <GroupName name={group.name.clone()} {rename} />
+                    {self.tag_editor(ctx)}
+                    {self.note_editor(ctx)}
+                    {self.duplicate_name_warning(ctx, group)}
+                    {self.duplicate_buildings_warning(ctx, group)}
+                    {self.database_mismatch_warning(ctx, group)}
+                    {self.interface_warning(ctx)}
                     {self.child_warnings(ctx)}
+                    {self.clipboard_warning(ctx)}
+                    {self.power_badge(ctx)}
+                    {self.power_readout(ctx)}
+                    {self.floor_area(ctx)}
                     {self.collapse_button(ctx, group)}
+                    {self.sort_button(ctx, group)}
+                    {self.interface_editor(ctx, group)}
+                    {self.distribute_button(ctx)}
+                    {self.color_button(ctx, group)}
+                    {self.pin_button(ctx, group)}
+                    {self.export_button(ctx)}
+                    {self.sandbox_button(ctx)}
                     if !ctx.props().path.is_empty() {
                         <VirtualCopies copies={group.copies} {update_copies} />
                     }
@@ -73,6 +125,9 @@ impl NodeDisplay {
                         { for group.children.iter().cloned().enumerate().map(|(i, node)| {
                             let mut path = ctx.props().path.clone();
                             path.push(i);
+                            let duplicate_name = node
+                                .group()
+                                .is_some_and(|child| duplicates.contains(&name_key(&child.name)));
                             html! {
                                 <>
                                     if self.insert_pos == Some(i) {
@@ -83,8 +138,15 @@ impl NodeDisplay {
                                         delete={delete.clone()}
                                         copy={copy.clone()}
                                         move_node={move_node.clone()}
+                                        move_group={move_group.clone()}
+                                        open_sandbox={open_sandbox.clone()}
                                         set_metadata={set_metadata.clone()}
-                                        batch_set_metadata={batch_set_metadata.clone()} />
+                                        batch_set_metadata={batch_set_metadata.clone()}
+                                        trace_item={trace_item.clone()}
+                                        selection_action={selection_action.clone()}
+                                        focus_action={focus_action.clone()}
+                                        available_items={available_items.clone()}
+                                        {duplicate_name} {tag_matched} />
                                 </>
                             }
                         }) }
@@ -94,6 +156,9 @@ impl NodeDisplay {
                     </div>
                     {self.view_balance(ctx, true)}
                 </div>
+                if ctx.settings().show_ratios {
+                    {self.view_ratios(ctx)}
+                }
                 <div class="footer">
                     <button class="create create-group" title="Add Group"
                         onclick={add_group}>
@@ -111,14 +176,37 @@ impl NodeDisplay {
     fn view_group_collapsed(&self, ctx: &Context<Self>, group: &Group) -> Html {
         let rename = ctx.link().callback(|name| Msg::Rename { name });
         let update_copies = ctx.link().callback(|copies| Msg::SetCopyCount { copies });
+        let cut = self.is_cut(ctx).then_some("cut");
+        let selected = self.is_selected(ctx).then_some("selected");
+        let dimmed = self.tag_dimmed(ctx);
+        let focused = self.is_focused(ctx).then_some("focused");
+        let (colored, color_style) = color_accent(group);
+        let onkeydown = self.keydown_handler(ctx);
+        let onfocus = self.focus_handler(ctx);
+        let focus_path = focus_attr(&ctx.props().path);
+        let (ondragenter, ondragleave) = self.expand_on_hover_handlers(ctx);
         html! {
-            <div class="NodeDisplay group collapsed" key={group.id.as_u128()}>
+            <div class={classes!("NodeDisplay", "group", "collapsed", colored, cut, selected, focused, dimmed)}
+                key={group.id.as_u128()} id={node_element_id(group)} style={color_style}
+                data-focus-path={focus_path} tabindex="-1" {onkeydown} {onfocus} {ondragenter}
+                {ondragleave}>
                 <div class="summary">
                     {self.drag_handle(ctx)}
                     <GroupName name={group.name.clone()} {rename} />
                     {self.view_balance(ctx, false)}
+                    {self.duplicate_name_warning(ctx, group)}
+                    {self.duplicate_buildings_warning(ctx, group)}
+                    {self.database_mismatch_warning(ctx, group)}
+                    {self.interface_warning(ctx)}
                     {self.child_warnings(ctx)}
+                    {self.clipboard_warning(ctx)}
+                    {self.power_badge(ctx)}
+                    {self.power_readout(ctx)}
+                    {self.floor_area(ctx)}
                     {self.collapse_button(ctx, group)}
+                    {self.pin_button(ctx, group)}
+                    {self.export_button(ctx)}
+                    {self.sandbox_button(ctx)}
                     if !ctx.props().path.is_empty() {
                         <VirtualCopies copies={group.copies} {update_copies} />
                     }
@@ -129,27 +217,178 @@ impl NodeDisplay {
         }
     }
 
+    /// Whether this group should currently be shown collapsed, either because the user
+    /// collapsed it or because it was auto-collapsed for being balanced.
+    fn is_collapsed(&self, ctx: &Context<Self>, group: &Group) -> bool {
+        if ctx.props().path.is_empty() {
+            // No collapse for root.
+            return false;
+        }
+        let meta = ctx.meta(group.id);
+        meta.collapsed
+            || (ctx.settings().auto_collapse_balanced
+                && !meta.expanded
+                && is_balanced(&ctx.props().node, group))
+    }
+
+    /// Show the throughput of related items in this group as simplified ratios.
+    fn view_ratios(&self, ctx: &Context<Self>) -> Html {
+        let chains = ctx.props().node.ratio_chains();
+        if chains.is_empty() {
+            return html! {};
+        }
+        let db = ctx.db();
+        let names = ctx.item_names();
+        let chains = chains.iter().map(|chain| {
+            let ratio = chain
+                .items
+                .iter()
+                .map(|&(item, part)| {
+                    let (name, _) = item_label(db.get(item), item, &names);
+                    format!("{part} {name}")
+                })
+                .collect::<Vec<_>>()
+                .join(" : ");
+            let (ratio, title) = if chain.exact {
+                (ratio, None)
+            } else {
+                (
+                    format!("\u{2248} {ratio}"),
+                    Some("Rounded; these rates don't form a simple ratio"),
+                )
+            };
+            html! { <div class="ratio-chain" {title}>{ratio}</div> }
+        });
+        html! {
+            <div class="ratios" title="Item Ratios">
+                { for chains }
+            </div>
+        }
+    }
+
+    /// Get the button to choose an accent color for this group.
+    fn color_button(&self, ctx: &Context<Self>, group: &Group) -> Html {
+        let update = ctx.link().callback(|color| Msg::SetColor { color });
+        html! {
+            <ColorPicker color={group.color} {update} />
+        }
+    }
+
+    /// Get a button to pin this group to the top of the page, or unpin it.
+    fn pin_button(&self, ctx: &Context<Self>, group: &Group) -> Html {
+        if ctx.props().path.is_empty() {
+            // The root is always the whole page, so there's no point pinning it.
+            return html! {};
+        }
+        let meta = ctx.meta(group.id);
+        let pinned = meta.pinned;
+        let set_metadata = ctx.props().set_metadata.clone();
+        let update = (
+            group.id,
+            NodeMeta {
+                pinned: !pinned,
+                ..meta
+            },
+        );
+        let onclick = Callback::from(move |_| set_metadata.emit(update.clone()));
+        let (title, class) = if pinned {
+            ("Unpin", Some("pinned"))
+        } else {
+            ("Pin to Top", None)
+        };
+        html! {
+            <button class={classes!("pin", class)} {title} {onclick}>
+                <span class="material-icons">{"push_pin"}</span>
+            </button>
+        }
+    }
+
+    /// Get the control for sorting this group's children, if it has more than one.
+    fn sort_button(&self, ctx: &Context<Self>, group: &Group) -> Html {
+        if group.children.len() < 2 {
+            return html! {};
+        }
+        let sort = ctx
+            .link()
+            .callback(|(key, descending)| Msg::SortChildren { key, descending });
+        let items: Vec<_> = ctx
+            .props()
+            .node
+            .balance()
+            .balances
+            .keys()
+            .copied()
+            .collect();
+        html! { <SortChildren {items} {sort} /> }
+    }
+
+    /// Get a button to download this group as a standalone factory.
+    fn export_button(&self, ctx: &Context<Self>) -> Html {
+        let onclick = ctx.link().callback(|_| Msg::Export);
+        html! {
+            <button class="export" title="Export as Factory" {onclick}>
+                <span class="material-icons">{"download"}</span>
+            </button>
+        }
+    }
+
+    /// Get a button to experiment on a copy of this group in the sandbox, unless this is
+    /// already in the sandbox.
+    fn sandbox_button(&self, ctx: &Context<Self>) -> Html {
+        let Some(open_sandbox) = ctx.props().open_sandbox.clone() else {
+            return html! {};
+        };
+        let node = ctx.props().node.clone();
+        let onclick = Callback::from(move |_| open_sandbox.emit(node.clone()));
+        html! {
+            <button class="sandbox" title="Experiment in Sandbox" {onclick}>
+                <span class="material-icons">{"science"}</span>
+            </button>
+        }
+    }
+
     /// Get a collapse/expand button for this node.
     fn collapse_button(&self, ctx: &Context<Self>, group: &Group) -> Html {
         if ctx.props().path.is_empty() {
             // No collapse for root.
             html! {}
         } else {
-            let meta = ctx.meta(group.id);
+            let collapsed = self.is_collapsed(ctx, group);
             let set_metadata = ctx.props().set_metadata.clone();
+            let batch_set_metadata = ctx.props().batch_set_metadata.clone();
+            let (metadata, _) = ctx
+                .link()
+                .context::<NodeMetadata>(Callback::noop())
+                .expect("metadata context to be set");
+            let node = ctx.props().node.clone();
             let update = (
                 group.id,
                 NodeMeta {
-                    collapsed: !meta.collapsed,
-                    ..meta.clone()
+                    collapsed: !collapsed,
+                    expanded: collapsed,
+                    ..ctx.meta(group.id)
                 },
             );
-            let onclick = Callback::from(move |_| set_metadata.emit(update.clone()));
-            let title = if meta.collapsed { "Expand" } else { "Collapse" };
+            let onclick = Callback::from(move |e: MouseEvent| {
+                if e.shift_key() {
+                    // Apply to this group and every group inside it.
+                    let depth = (!collapsed).then_some(0);
+                    let mut updates = collapse_below(&metadata, &node, depth);
+                    updates.insert(update.0, update.1.clone());
+                    batch_set_metadata.emit(updates);
+                } else {
+                    set_metadata.emit(update.clone());
+                }
+            });
+            let title = if collapsed {
+                "Expand (Shift+Click to expand everything inside)"
+            } else {
+                "Collapse (Shift+Click to collapse everything inside)"
+            };
             html! {
                 <button class="expand-collapse" {onclick} {title}>
                     <span class="material-icons">
-                        if meta.collapsed {
+                        if collapsed {
                             {"expand_more"}
                         } else {
                             {"expand_less"}
@@ -160,6 +399,118 @@ impl NodeDisplay {
         }
     }
 
+    /// Get a warning if a sibling group has the same name as this one.
+    fn duplicate_name_warning(&self, ctx: &Context<Self>, group: &Group) -> Html {
+        if ctx.props().duplicate_name {
+            let title = format!(
+                "Another group here is also named \"{}\"; consider renaming one of them",
+                group.name
+            );
+            html! {
+                <span class="DuplicateName material-icons warning" {title}>
+                    {"drive_file_rename_outline"}
+                </span>
+            }
+        } else {
+            html! {}
+        }
+    }
+
+    /// Get a button to merge child buildings which have identical settings, if there are
+    /// any.
+    fn duplicate_buildings_warning(&self, ctx: &Context<Self>, group: &Group) -> Html {
+        let duplicates = group.duplicate_buildings();
+        if duplicates.is_empty() {
+            return html! {};
+        }
+        let count: usize = duplicates.iter().map(Vec::len).sum();
+        let title = format!(
+            "{count} buildings here have exactly the same type and settings as another; \
+            click to merge them"
+        );
+        let onclick = ctx.link().callback(|_| Msg::MergeDuplicates);
+        html! {
+            <button class="DuplicateBuildings" {title} {onclick}>
+                <span class="material-icons warning">{"call_merge"}</span>
+            </button>
+        }
+    }
+
+    /// Get a button to rebuild this group if its buildings were last built against
+    /// different databases.
+    fn database_mismatch_warning(&self, ctx: &Context<Self>, group: &Group) -> Html {
+        let versions = group.mismatched_database_versions();
+        if versions.is_empty() {
+            return html! {};
+        }
+        let names = versions
+            .iter()
+            .map(|version| version.name())
+            .collect::<Vec<_>>()
+            .join(", ");
+        let title = format!(
+            "Buildings here were last built against different databases ({names}); click \
+            to rebuild them against the current database"
+        );
+        let onclick = ctx.link().callback(|_| Msg::Revalidate);
+        html! {
+            <button class="DatabaseMismatch" {title} {onclick}>
+                <span class="material-icons warning">{"sync_problem"}</span>
+            </button>
+        }
+    }
+
+    /// Show a warning if the group's balance breaks its declared interface.
+    fn interface_warning(&self, ctx: &Context<Self>) -> Html {
+        let violations = ctx.props().node.interface_violations();
+        if violations.is_empty() {
+            return html! {};
+        }
+        let db = ctx.db();
+        let names = ctx.item_names();
+        let title = violations
+            .iter()
+            .map(|&violation| {
+                let item = violation.item();
+                describe_violation(violation, &item_label(db.get(item), item, &names).0)
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        html! {
+            <span class="InterfaceViolation material-icons warning" {title}>
+                {INTERFACE_WARNING_ICON}
+            </span>
+        }
+    }
+
+    /// Get the button to declare the items this group takes in and makes.
+    fn interface_editor(&self, ctx: &Context<Self>, group: &Group) -> Html {
+        let node = &ctx.props().node;
+        let update = ctx
+            .link()
+            .callback(|interface| Msg::SetInterface { interface });
+        html! {
+            <InterfaceEditor interface={group.interface.clone()}
+                balances={node.balance().balances.clone()}
+                byproducts={group.byproducts.clone()}
+                violations={node.interface_violations()} {update} />
+        }
+    }
+
+    /// Get the button to share an output between the child buildings which make it.
+    fn distribute_button(&self, ctx: &Context<Self>) -> Html {
+        let distribute = ctx
+            .link()
+            .callback(|distribution: Distribution| Msg::DistributeOutput {
+                item: distribution.item,
+                target: distribution.target,
+                weights: distribution.weights,
+            });
+        html! {
+            <DistributeOutput node={ctx.props().node.clone()} {distribute} />
+        }
+    }
+
     /// Show an icon to notify if any children have warnings.
     fn child_warnings(&self, ctx: &Context<Self>) -> Html {
         if ctx.props().node.children_had_warnings() {
@@ -173,4 +524,94 @@ impl NodeDisplay {
             html! {}
         }
     }
+
+    /// Get a summary of the floor space used by this group.
+    fn floor_area(&self, ctx: &Context<Self>) -> Html {
+        let area = ctx.props().node.floor_area(&ctx.db());
+        if area.area == 0.0 && area.unknown == 0 {
+            return html! {};
+        }
+        let title = if area.unknown > 0 {
+            format!(
+                "Estimated floor area; {} building(s) with unknown size not included",
+                area.unknown
+            )
+        } else {
+            "Estimated floor area".to_string()
+        };
+        html! {
+            <span class="FloorArea" {title}>
+                <span class="material-icons">{"square_foot"}</span>
+                <span class="value">{format!("{:.0} m\u{b2}", area.area)}</span>
+                if area.unknown > 0 {
+                    <span class="unknown">{"+?"}</span>
+                }
+            </span>
+        }
+    }
+
+    /// Get a badge showing whether this group generates enough power for itself.
+    fn power_badge(&self, ctx: &Context<Self>) -> Html {
+        let split = ctx.props().node.power_split();
+        if split.generated == 0.0 && split.consumed == 0.0 {
+            return html! {};
+        }
+        let (class, icon) = if split.is_self_sufficient() {
+            ("self-sufficient", "bolt")
+        } else {
+            ("grid-dependent", "power")
+        };
+        let title = format!(
+            "Generates {} MW, consumes {} MW",
+            rounded(split.generated),
+            rounded(split.consumed)
+        );
+        html! {
+            <span class={classes!("PowerBadge", "material-icons", class)} {title}>
+                {icon}
+            </span>
+        }
+    }
+}
+
+/// Whether a group is fully balanced: it has at least one child, doesn't need any items
+/// supplied from outside, and every item it outputs is one declared as a byproduct.
+fn is_balanced(node: &Node, group: &Group) -> bool {
+    !group.children.is_empty()
+        && node.balance().balances.iter().all(|(item, &rate)| {
+            rounded(rate) == 0.0 || (rate > 0.0 && group.byproducts.contains(item))
+        })
+}
+
+/// Id of the HTML element displaying a group, used to scroll to it.
+pub fn node_element_id(group: &Group) -> String {
+    format!("node-{}", group.id)
+}
+
+/// Class and style to show the accent color of a group, if it has one. The color is set as a
+/// custom property so the stylesheet decides where it goes.
+fn color_accent(group: &Group) -> (Option<&'static str>, Option<String>) {
+    match group.color {
+        Some(color) => (Some("colored"), Some(format!("--group-color: {color}"))),
+        None => (None, None),
+    }
+}
+
+/// Normalize a group name for comparing sibling names, so names which only differ in case
+/// or surrounding whitespace count as the same.
+fn name_key(name: &str) -> String {
+    name.trim().to_lowercase()
+}
+
+/// Find the names shared by more than one child group of `group`. Unnamed groups are
+/// ignored.
+fn duplicate_names(group: &Group) -> HashSet<String> {
+    let mut seen = HashSet::new();
+    group
+        .children
+        .iter()
+        .filter_map(|child| child.group())
+        .map(|child| name_key(&child.name))
+        .filter(|name| !name.is_empty() && !seen.insert(name.clone()))
+        .collect()
 }
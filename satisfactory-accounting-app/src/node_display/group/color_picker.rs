@@ -0,0 +1,96 @@
+// Copyright 2024 Zachary Stewart
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+use log::warn;
+use satisfactory_accounting::accounting::GroupColor;
+use yew::prelude::*;
+
+use crate::events::get_value_from_input_change;
+
+/// Colors offered for groups, with their names.
+const PALETTE: &[(&str, GroupColor)] = &[
+    ("Red", GroupColor::rgb(0xe5, 0x39, 0x35)),
+    ("Orange", GroupColor::rgb(0xfb, 0x8c, 0x00)),
+    ("Yellow", GroupColor::rgb(0xfd, 0xd8, 0x35)),
+    ("Green", GroupColor::rgb(0x43, 0xa0, 0x47)),
+    ("Teal", GroupColor::rgb(0x00, 0x89, 0x7b)),
+    ("Blue", GroupColor::rgb(0x1e, 0x88, 0xe5)),
+    ("Purple", GroupColor::rgb(0x8e, 0x24, 0xaa)),
+    ("Pink", GroupColor::rgb(0xd8, 0x1b, 0x60)),
+    ("Brown", GroupColor::rgb(0x6d, 0x4c, 0x41)),
+];
+
+#[derive(PartialEq, Properties)]
+pub struct Props {
+    /// Current color of the group, if any.
+    pub color: Option<GroupColor>,
+    /// Callback to change the color of the group, or remove it.
+    pub update: Callback<Option<GroupColor>>,
+}
+
+/// Button and menu for choosing the accent color of a group, from a palette or as any
+/// custom color.
+#[function_component]
+pub fn ColorPicker(props: &Props) -> Html {
+    let open = use_state(|| false);
+
+    let toggle_open = {
+        let open = open.clone();
+        Callback::from(move |_| open.set(!*open))
+    };
+    let choose = |color: Option<GroupColor>| {
+        let update = props.update.clone();
+        let open = open.clone();
+        Callback::from(move |_| {
+            open.set(false);
+            update.emit(color);
+        })
+    };
+    let custom = {
+        let update = props.update.clone();
+        let open = open.clone();
+        Callback::from(move |e| match get_value_from_input_change(e).parse() {
+            Ok(color) => {
+                open.set(false);
+                update.emit(Some(color));
+            }
+            Err(e) => warn!("Cannot use custom color: {e}"),
+        })
+    };
+    let swatches = PALETTE.iter().map(|&(name, color)| {
+        let chosen = (props.color == Some(color)).then_some("chosen");
+        html! {
+            <button class={classes!("swatch", chosen)} title={name}
+                style={format!("background-color: {color}")}
+                onclick={choose(Some(color))} />
+        }
+    });
+    let custom_value = props.color.unwrap_or(PALETTE[0].1).to_string();
+    let toggle_style = props.color.map(|color| format!("color: {color}"));
+    html! {
+        <span class="ColorPicker">
+            <button class="color-toggle" title="Group Color" style={toggle_style}
+                onclick={toggle_open}>
+                <span class="material-icons">{"palette"}</span>
+            </button>
+            if *open {
+                <div class="color-menu">
+                    { for swatches }
+                    <label class="custom-color" title="Custom Color">
+                        <span class="material-icons">{"colorize"}</span>
+                        <input type="color" value={custom_value} onchange={custom} />
+                    </label>
+                    if props.color.is_some() {
+                        <button class="clear-color" title="No Color" onclick={choose(None)}>
+                            <span class="material-icons">{"format_color_reset"}</span>
+                        </button>
+                    }
+                </div>
+            }
+        </span>
+    }
+}
@@ -0,0 +1,260 @@
+// Copyright 2024 Zachary Stewart
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+use std::collections::BTreeMap;
+
+use satisfactory_accounting::accounting::{Building, BuildingSettings, Node};
+use satisfactory_accounting::database::{Database, ItemId};
+use yew::prelude::*;
+
+use crate::clickedit::ClickEdit;
+use crate::node_display::balance::rounded;
+use crate::node_display::icon::Icon;
+use crate::{item_label, use_db, use_item_names, use_settings};
+
+/// Weight given to each building until the user changes it.
+const DEFAULT_WEIGHT: f32 = 1.0;
+
+/// Target rate of an item to spread across child buildings by weight.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Distribution {
+    /// Item to make.
+    pub item: ItemId,
+    /// Total rate to make, per minute.
+    pub target: f32,
+    /// Weight of each building, by child index.
+    pub weights: Vec<(usize, f32)>,
+}
+
+#[derive(Debug, PartialEq, Properties)]
+pub struct Props {
+    /// The group whose child buildings share the output.
+    pub node: Node,
+    /// Callback to spread a target rate of an item across child buildings by weight.
+    pub distribute: Callback<Distribution>,
+}
+
+/// Name and image of a building, using the recipe name for manufacturers so alternate
+/// recipes can be told apart.
+fn producer_label(db: &Database, building: &Building) -> (AttrValue, AttrValue) {
+    let Some(kind) = building.building.and_then(|id| db.get(id)) else {
+        return ("Unknown Building".into(), "".into());
+    };
+    let recipe = match &building.settings {
+        BuildingSettings::Manufacturer(settings) => settings.recipe.and_then(|id| db.get(id)),
+        _ => None,
+    };
+    let name = match recipe {
+        Some(recipe) => format!("{} ({})", recipe.name, kind.name),
+        None => kind.name.to_string(),
+    };
+    (name.into(), kind.image.clone().into())
+}
+
+/// Button and menu for spreading a target rate of an item across the child buildings
+/// which make it, such as ones using different alternate recipes, in proportion to
+/// weights the user chooses.
+#[function_component]
+pub fn DistributeOutput(props: &Props) -> Html {
+    let db = use_db();
+    let names = use_item_names();
+    let settings = use_settings();
+    let open = use_state(|| false);
+    let chosen = use_state(|| None::<ItemId>);
+    let weights = use_state(BTreeMap::<usize, f32>::new);
+    let target = use_state(|| None::<f32>);
+
+    let Some(group) = props.node.group() else {
+        return html! {};
+    };
+    // Only items made by more than one building have anything to share.
+    let shared: Vec<(ItemId, Vec<usize>)> = props
+        .node
+        .balance()
+        .balances
+        .keys()
+        .map(|&item| (item, group.producers_of(&db, item)))
+        .filter(|(_, producers)| producers.len() > 1)
+        .collect();
+    if shared.is_empty() {
+        return html! {};
+    }
+
+    let toggle_open = {
+        let open = open.clone();
+        Callback::from(move |_| open.set(!*open))
+    };
+    let button = html! {
+        <button class="distribute-toggle" title="Share an Output Between Buildings"
+            onclick={toggle_open}>
+            <span class="material-icons">{"call_split"}</span>
+        </button>
+    };
+    if !*open {
+        return html! {
+            <span class="DistributeOutput">{button}</span>
+        };
+    }
+
+    let (item, producers) = chosen
+        .and_then(|item| shared.iter().find(|(shared, _)| *shared == item))
+        .unwrap_or(&shared[0]);
+    let item = *item;
+    let items = shared.iter().map(|&(option, _)| {
+        let (name, image) = item_label(db.get(option), option, &names);
+        let choose = {
+            let chosen = chosen.clone();
+            let target = target.clone();
+            Callback::from(move |_| {
+                chosen.set(Some(option));
+                target.set(None);
+            })
+        };
+        let selected = (option == item).then_some("chosen");
+        html! {
+            <button class={classes!("item", selected)} title={name} onclick={choose}>
+                <Icon icon={image} />
+            </button>
+        }
+    });
+
+    let weighted: Vec<(usize, f32)> = producers
+        .iter()
+        .map(|&child| {
+            (
+                child,
+                weights.get(&child).copied().unwrap_or(DEFAULT_WEIGHT),
+            )
+        })
+        .collect();
+    // Until the user sets a target, keep what the buildings make now.
+    let current: f32 = producers
+        .iter()
+        .filter_map(|&child| group.children[child].balance().balances.get(&item))
+        .sum();
+    let target_rate = target.unwrap_or(current);
+    let rounding = settings.clock_rounding;
+    let result = group.distribute_output(&db, item, target_rate, &weighted, |clock| {
+        rounding.apply(clock)
+    });
+
+    let unit = settings.rate_unit;
+    let format_rate =
+        |rate: f32| format!("{}{}", settings.format(unit.convert(rate)), unit.suffix());
+    let separator = settings.decimal_separator;
+    let rows = weighted.iter().map(|&(child, weight)| {
+        let Some(building) = group.children[child].building() else {
+            return html! {};
+        };
+        let (name, image) = producer_label(&db, building);
+        let set_weight = {
+            let weights = weights.clone();
+            Callback::from(move |text: AttrValue| {
+                if let Some(weight) = separator
+                    .parse::<f32>(&text)
+                    .filter(|weight| weight.is_finite() && *weight >= 0.0)
+                {
+                    let mut new = (*weights).clone();
+                    new.insert(child, weight);
+                    weights.set(new);
+                }
+            })
+        };
+        let share = result
+            .as_ref()
+            .and_then(|result| result.shares.iter().find(|share| share.child == child));
+        let (share, short) = match share {
+            Some(share) => {
+                let text = format!(
+                    "{} at {}%",
+                    format_rate(share.clock.achieved),
+                    settings.format(share.clock.clock_speed * 100.0)
+                );
+                let short = (!share.clock.exact).then(|| {
+                    format!(
+                        "Makes {} instead of its {} share",
+                        format_rate(share.clock.achieved),
+                        format_rate(share.share)
+                    )
+                });
+                (text, short)
+            }
+            None => ("\u{2013}".to_string(), None),
+        };
+        let title = short.clone().unwrap_or_else(|| name.to_string());
+        html! {
+            <div class={classes!("distribute-row", short.map(|_| "short"))} {title}>
+                <Icon icon={image} />
+                <span class="producer">{name}</span>
+                <ClickEdit value={AttrValue::from(settings.format(weight))}
+                    title="Weight" on_commit={set_weight}
+                    prefix={html! { <span class="unit">{"\u{d7}"}</span> }} />
+                <span class="share">{share}</span>
+            </div>
+        }
+    });
+
+    let set_target = {
+        let target = target.clone();
+        Callback::from(move |text: AttrValue| {
+            if let Some(rate) = separator
+                .parse::<f32>(&text)
+                .filter(|rate| rate.is_finite() && *rate >= 0.0)
+            {
+                target.set(Some(unit.to_per_minute(rate)));
+            }
+        })
+    };
+    let summary = match &result {
+        Some(result) if result.exact() => html! {
+            <span class="summary">{"Every building makes its share"}</span>
+        },
+        Some(result) => html! {
+            <span class="summary short">
+                {format!("Makes {} instead of the target", format_rate(result.achieved()))}
+            </span>
+        },
+        None => html! {
+            <span class="summary short">{"Give at least one building a weight"}</span>
+        },
+    };
+    let apply = {
+        let distribute = props.distribute.clone();
+        let open = open.clone();
+        let weighted = weighted.clone();
+        Callback::from(move |_| {
+            open.set(false);
+            distribute.emit(Distribution {
+                item,
+                target: target_rate,
+                weights: weighted.clone(),
+            });
+        })
+    };
+    html! {
+        <span class="DistributeOutput">
+            {button}
+            <div class="distribute-menu">
+                <div class="items">{ for items }</div>
+                <div class="distribute-target">
+                    <span>{"Target"}</span>
+                    <ClickEdit value={AttrValue::from(rounded(unit.convert(target_rate)).to_string())}
+                        title="Total Output" on_commit={set_target}
+                        suffix={html! { <span class="unit">{unit.suffix()}</span> }} />
+                </div>
+                { for rows }
+                <div class="distribute-actions">
+                    {summary}
+                    <button class="apply" title="Set the Clock Speeds" disabled={result.is_none()}
+                        onclick={apply}>
+                        {"Apply"}
+                    </button>
+                </div>
+            </div>
+        </span>
+    }
+}
@@ -0,0 +1,168 @@
+// Copyright 2024 Zachary Stewart
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+use std::collections::{BTreeMap, BTreeSet};
+
+use satisfactory_accounting::accounting::{InterfaceViolation, Port};
+use satisfactory_accounting::database::ItemId;
+use yew::prelude::*;
+
+use crate::node_display::balance::rounded;
+use crate::node_display::icon::Icon;
+use crate::{item_label, use_db, use_item_names, use_settings};
+
+/// Icon flagging a group which breaks its declared interface.
+pub const INTERFACE_WARNING_ICON: &str = "rule";
+
+/// Explain a way a group breaks its declared interface, given the name of the item.
+pub fn describe_violation(violation: InterfaceViolation, name: &str) -> String {
+    match violation {
+        InterfaceViolation::UndeclaredInput(_) => format!("Consumes {name}, an undeclared input"),
+        InterfaceViolation::UndeclaredOutput(_) => {
+            format!("Makes a surplus of {name}, an undeclared output")
+        }
+        InterfaceViolation::UnusedInput(_) => format!("Doesn't consume the declared input {name}"),
+        InterfaceViolation::MissingOutput(_) => {
+            format!("Doesn't make a surplus of the declared output {name}")
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Properties)]
+pub struct Props {
+    /// Interface currently declared for the group.
+    pub interface: BTreeMap<ItemId, Port>,
+    /// Net rate of each item in the group's balance.
+    pub balances: BTreeMap<ItemId, f32>,
+    /// Surplus items of the group marked as byproducts, which needn't be declared.
+    pub byproducts: BTreeSet<ItemId>,
+    /// Ways the group's balance breaks the declared interface.
+    pub violations: Vec<InterfaceViolation>,
+    /// Callback to replace the declared interface.
+    pub update: Callback<BTreeMap<ItemId, Port>>,
+}
+
+/// Button and menu for declaring which items a group takes in and makes, so it acts as a
+/// module with a contract its balance is checked against.
+#[function_component]
+pub fn InterfaceEditor(props: &Props) -> Html {
+    let db = use_db();
+    let names = use_item_names();
+    let settings = use_settings();
+    let open = use_state(|| false);
+
+    let toggle_open = {
+        let open = open.clone();
+        Callback::from(move |_| open.set(!*open))
+    };
+    let set_port = |item: ItemId, port: Option<Port>| {
+        let update = props.update.clone();
+        let mut interface = props.interface.clone();
+        match port {
+            Some(port) => interface.insert(item, port),
+            None => interface.remove(&item),
+        };
+        Callback::from(move |_| update.emit(interface.clone()))
+    };
+    let items: BTreeSet<ItemId> = props
+        .balances
+        .keys()
+        .chain(props.interface.keys())
+        .copied()
+        .collect();
+    let rows = items.into_iter().map(|item| {
+        let (name, image) = item_label(db.get(item), item, &names);
+        let rate = props.balances.get(&item).copied().unwrap_or(0.0);
+        let port = props.interface.get(&item).copied();
+        let violation = props
+            .violations
+            .iter()
+            .find(|violation| violation.item() == item)
+            .map(|&violation| describe_violation(violation, &name));
+        let option = |choice: Option<Port>, label: &'static str, title: &'static str| {
+            let chosen = (port == choice).then_some("chosen");
+            html! {
+                <button class={classes!("port", chosen)} {title}
+                    onclick={set_port(item, choice)}>
+                    {label}
+                </button>
+            }
+        };
+        let violated = violation.is_some().then_some("violated");
+        html! {
+            <div class={classes!("interface-row", violated)}
+                title={violation.unwrap_or_else(|| name.to_string())}>
+                <Icon icon={image} />
+                <span class="rate">
+                    {settings.format(settings.rate_unit.convert(rate))}
+                    {settings.rate_unit.suffix()}
+                </span>
+                { option(Some(Port::Input), "In", "Declared Input") }
+                { option(Some(Port::Output), "Out", "Declared Output") }
+                { option(None, "\u{2013}", "Not Declared") }
+            </div>
+        }
+    });
+    let declare_balance = {
+        let update = props.update.clone();
+        let interface: BTreeMap<ItemId, Port> = props
+            .balances
+            .iter()
+            .filter_map(|(&item, &rate)| {
+                let rate = rounded(rate);
+                if rate < 0.0 {
+                    Some((item, Port::Input))
+                } else if rate > 0.0 && !props.byproducts.contains(&item) {
+                    Some((item, Port::Output))
+                } else {
+                    None
+                }
+            })
+            .collect();
+        Callback::from(move |_| update.emit(interface.clone()))
+    };
+    let clear = {
+        let update = props.update.clone();
+        Callback::from(move |_| update.emit(BTreeMap::new()))
+    };
+    let declared = !props.interface.is_empty();
+    let title = if declared {
+        let inputs = props
+            .interface
+            .values()
+            .filter(|&&port| port == Port::Input)
+            .count();
+        let outputs = props.interface.len() - inputs;
+        format!("Interface: {inputs} inputs, {outputs} outputs")
+    } else {
+        "Declare Interface".to_string()
+    };
+    html! {
+        <span class="InterfaceEditor">
+            <button class={classes!("interface-toggle", declared.then_some("declared"))}
+                {title} onclick={toggle_open}>
+                <span class="material-icons">{"input"}</span>
+            </button>
+            if *open {
+                <div class="interface-menu">
+                    { for rows }
+                    <div class="interface-actions">
+                        <button class="declare-balance" title="Declare the Current Balance"
+                            onclick={declare_balance}>
+                            {"From Balance"}
+                        </button>
+                        if declared {
+                            <button class="clear" title="Remove the Interface" onclick={clear}>
+                                {"Clear"}
+                            </button>
+                        }
+                    </div>
+                </div>
+            }
+        </span>
+    }
+}
@@ -0,0 +1,86 @@
+// Copyright 2024 Zachary Stewart
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+use satisfactory_accounting::accounting::ChildSortKey;
+use satisfactory_accounting::database::ItemId;
+use yew::prelude::*;
+
+use crate::node_display::icon::Icon;
+use crate::{item_label, use_db, use_item_names};
+
+#[derive(PartialEq, Properties)]
+pub struct Props {
+    /// Items in the group's balance, which its children can be sorted by.
+    pub items: Vec<ItemId>,
+    /// Callback to sort the children by a key, descending if the flag is set.
+    pub sort: Callback<(ChildSortKey, bool)>,
+}
+
+/// Button and menu for reordering the children of a group.
+#[function_component]
+pub fn SortChildren(props: &Props) -> Html {
+    let db = use_db();
+    let names = use_item_names();
+    let open = use_state(|| false);
+    let descending = use_state(|| false);
+
+    let toggle_open = {
+        let open = open.clone();
+        Callback::from(move |_| open.set(!*open))
+    };
+    let toggle_direction = {
+        let descending = descending.clone();
+        Callback::from(move |_| descending.set(!*descending))
+    };
+    let sort_by = |key: ChildSortKey| {
+        let sort = props.sort.clone();
+        let open = open.clone();
+        let descending = *descending;
+        Callback::from(move |_| {
+            open.set(false);
+            sort.emit((key, descending));
+        })
+    };
+    let items = props.items.iter().map(|&item| {
+        let (name, image) = item_label(db.get(item), item, &names);
+        html! {
+            <button class="sort-item" title={format!("Sort by {name}")}
+                onclick={sort_by(ChildSortKey::Item(item))}>
+                <Icon icon={image} />
+            </button>
+        }
+    });
+    let (direction_icon, direction_title) = if *descending {
+        ("arrow_downward", "Descending")
+    } else {
+        ("arrow_upward", "Ascending")
+    };
+    html! {
+        <span class="SortChildren">
+            <button class="sort-toggle" title="Sort Children" onclick={toggle_open}>
+                <span class="material-icons">{"sort"}</span>
+            </button>
+            if *open {
+                <div class="sort-menu">
+                    <button class="sort-direction" title={direction_title}
+                        onclick={toggle_direction}>
+                        <span class="material-icons">{direction_icon}</span>
+                    </button>
+                    <button class="sort-key" title="Sort by Name"
+                        onclick={sort_by(ChildSortKey::Name)}>
+                        <span class="material-icons">{"sort_by_alpha"}</span>
+                    </button>
+                    <button class="sort-key" title="Sort by Power Draw"
+                        onclick={sort_by(ChildSortKey::Power)}>
+                        <Icon icon="power-line" />
+                    </button>
+                    { for items }
+                </div>
+            }
+        </span>
+    }
+}
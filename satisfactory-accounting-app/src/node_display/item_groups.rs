@@ -0,0 +1,227 @@
+// Copyright 2024 Zachary Stewart
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+use std::collections::BTreeSet;
+
+use satisfactory_accounting::database::{Database, ItemId};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use yew::prelude::*;
+
+use crate::clickedit::ClickEdit;
+use crate::node_display::building::choose_from_list::{Choice, ChooseFromList};
+use crate::node_display::icon::Icon;
+use crate::use_db;
+
+/// Named set of items defined by the user.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ItemGroup {
+    /// Stable identifier, so filters survive renaming.
+    pub id: Uuid,
+    /// Name of the group.
+    pub name: AttrValue,
+    /// Items in the group.
+    pub items: BTreeSet<ItemId>,
+}
+
+/// Set of items that balances can be filtered to.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ItemFilter {
+    /// Items moved by pipes.
+    Fluids,
+    /// Items which can be burned for power.
+    Fuels,
+    /// A user-defined [`ItemGroup`], by ID.
+    Custom(Uuid),
+}
+
+impl ItemFilter {
+    /// Built-in filters, which don't need to be defined by the user.
+    pub const BUILTIN: &'static [ItemFilter] = &[Self::Fluids, Self::Fuels];
+
+    /// Whether the given item passes this filter.
+    pub fn matches(self, database: &Database, groups: &[ItemGroup], item: ItemId) -> bool {
+        match self {
+            Self::Fluids => database.get(item).is_some_and(|item| item.fluid),
+            Self::Fuels => database.get(item).is_some_and(|item| item.fuel.is_some()),
+            Self::Custom(id) => groups
+                .iter()
+                .find(|group| group.id == id)
+                .is_some_and(|group| group.items.contains(&item)),
+        }
+    }
+
+    /// Name to show for this filter.
+    pub fn name(self, groups: &[ItemGroup]) -> AttrValue {
+        match self {
+            Self::Fluids => "Fluids".into(),
+            Self::Fuels => "Fuels".into(),
+            Self::Custom(id) => groups
+                .iter()
+                .find(|group| group.id == id)
+                .map_or_else(|| "Deleted Group".into(), |group| group.name.clone()),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Properties)]
+pub struct Props {
+    /// Current item groups.
+    pub groups: Vec<ItemGroup>,
+    /// Callback to replace the item groups.
+    pub update: Callback<Vec<ItemGroup>>,
+}
+
+/// Editor for the user's custom item groups.
+#[function_component]
+pub fn ItemGroupsEditor(props: &Props) -> Html {
+    let groups = props.groups.iter().enumerate().map(|(idx, group)| {
+        let update_group = {
+            let groups = props.groups.clone();
+            let update = props.update.clone();
+            move |change: &dyn Fn(&mut Vec<ItemGroup>)| {
+                let mut groups = groups.clone();
+                change(&mut groups);
+                update.emit(groups);
+            }
+        };
+        let rename = {
+            let update_group = update_group.clone();
+            Callback::from(move |name: AttrValue| {
+                update_group(&|groups| groups[idx].name = name.clone())
+            })
+        };
+        let delete = {
+            let update_group = update_group.clone();
+            Callback::from(move |_| {
+                update_group(&|groups| {
+                    groups.remove(idx);
+                })
+            })
+        };
+        let update_items = Callback::from(move |items: BTreeSet<ItemId>| {
+            update_group(&|groups| groups[idx].items = items.clone())
+        });
+        html! {
+            <div class="item-group" key={group.id.to_string()}>
+                <div class="item-group-header">
+                    <ClickEdit value={group.name.clone()} title="Group Name" on_commit={rename} />
+                    <button class="delete-group" title="Delete Group" onclick={delete}>
+                        <span class="material-icons">{"delete"}</span>
+                    </button>
+                </div>
+                <ItemSetEditor items={group.items.clone()} update={update_items} />
+            </div>
+        }
+    });
+
+    let create = {
+        let groups = props.groups.clone();
+        let update = props.update.clone();
+        Callback::from(move |_| {
+            let mut groups = groups.clone();
+            groups.push(ItemGroup {
+                id: Uuid::new_v4(),
+                name: format!("Item Group {}", groups.len() + 1).into(),
+                items: BTreeSet::new(),
+            });
+            update.emit(groups);
+        })
+    };
+    html! {
+        <div class="ItemGroupsEditor">
+            { for groups }
+            <button class="create-group" title="New Item Group" onclick={create}>
+                <span class="material-icons">{"add"}</span>
+                <span>{"New Item Group"}</span>
+            </button>
+        </div>
+    }
+}
+
+#[derive(Debug, PartialEq, Properties)]
+pub struct ItemSetProps {
+    /// Items currently in the set.
+    pub items: BTreeSet<ItemId>,
+    /// Callback to replace the items in the set.
+    pub update: Callback<BTreeSet<ItemId>>,
+}
+
+/// Editor for a set of items, shown as icons which remove the item when clicked, with a
+/// button to add any item from the database.
+#[function_component]
+pub fn ItemSetEditor(props: &ItemSetProps) -> Html {
+    let db = use_db();
+    let adding = use_state(|| false);
+
+    let items = props.items.iter().map(|&item| {
+        let remove = {
+            let items = props.items.clone();
+            let update = props.update.clone();
+            Callback::from(move |_| {
+                let mut items = items.clone();
+                items.remove(&item);
+                update.emit(items);
+            })
+        };
+        let (name, image) = match db.get(item) {
+            Some(item) => (
+                item.name.to_string(),
+                Some(AttrValue::from(item.image.clone())),
+            ),
+            None => (format!("Unknown Item {item}"), None),
+        };
+        html! {
+            <span class="set-item" title={format!("Remove {name}")} onclick={remove}>
+                <Icon icon={image} />
+            </span>
+        }
+    });
+    let chooser = if *adding {
+        let choices: Vec<_> = db
+            .items
+            .values()
+            .filter(|item| !props.items.contains(&item.id))
+            .map(|item| Choice {
+                id: item.id,
+                name: item.name.clone().into(),
+                image: html! { <Icon icon={item.image.clone()} /> },
+                featured: None,
+                group: None,
+            })
+            .collect();
+        let selected = {
+            let adding = adding.clone();
+            let items = props.items.clone();
+            let update = props.update.clone();
+            Callback::from(move |item: ItemId| {
+                adding.set(false);
+                let mut items = items.clone();
+                items.insert(item);
+                update.emit(items);
+            })
+        };
+        let cancelled = {
+            let adding = adding.clone();
+            Callback::from(move |()| adding.set(false))
+        };
+        html! { <ChooseFromList<ItemId> {choices} {selected} {cancelled} /> }
+    } else {
+        let onclick = Callback::from(move |_| adding.set(true));
+        html! {
+            <button class="add-item" title="Add Item" {onclick}>
+                <span class="material-icons">{"add"}</span>
+            </button>
+        }
+    };
+    html! {
+        <div class="ItemSetEditor">
+            { for items }
+            { chooser }
+        </div>
+    }
+}
@@ -0,0 +1,101 @@
+// Copyright 2024 Zachary Stewart
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+use satisfactory_accounting::database::ItemId;
+use yew::prelude::*;
+
+use crate::node_display::balance::{
+    balance_style, EntryMarker, BELT_WARNING_ICON, CLOCK_HINT_ICON, PIPE_WARNING_ICON,
+};
+use crate::node_display::group::INTERFACE_WARNING_ICON;
+use crate::node_display::icon::Icon;
+use crate::{item_label, use_db, use_item_names, use_settings};
+
+/// Rate shown in the sample entries, per minute.
+const SAMPLE_RATE: f32 = 20.0;
+
+/// Key to the colors and icons used in balances. The samples are drawn with the same
+/// classes and icons as real balance entries, inside a bare node display so they pick up
+/// the same styles.
+#[function_component]
+pub fn BalanceLegend() -> Html {
+    let db = use_db();
+    let names = use_item_names();
+    let settings = use_settings();
+
+    let plate = ItemId::from("Desc_IronPlate_C");
+    let (_, image) = item_label(db.get(plate), plate, &names);
+    let sample = |rate: f32, marker: Option<EntryMarker>| {
+        let marker = marker.map(EntryMarker::class);
+        html! {
+            <div class={classes!("entry-row", balance_style(rate), marker)}>
+                <Icon icon={image.clone()} />
+                <div class="balance-value">
+                    {settings.format(settings.rate_unit.convert(rate))}
+                </div>
+            </div>
+        }
+    };
+    let row = |sample: Html, description: &'static str| {
+        html! {
+            <div class="legend-row">
+                <div class="legend-sample">{sample}</div>
+                <span class="legend-description">{description}</span>
+            </div>
+        }
+    };
+    let line_warning = |icon: &'static str| {
+        html! {
+            <div class="entry-row">
+                <span class="belt-warning material-icons warning">{icon}</span>
+                <span class="line-count">{"\u{d7}2"}</span>
+            </div>
+        }
+    };
+
+    let colors = [
+        (SAMPLE_RATE, "Surplus: more is made than used"),
+        (-SAMPLE_RATE, "Deficit: more is used than made"),
+        (0.0, "Balanced: as much is made as used"),
+    ]
+    .map(|(rate, description)| row(sample(rate, None), description));
+    let markers =
+        EntryMarker::ALL.map(|marker| row(sample(SAMPLE_RATE, Some(marker)), marker.description()));
+    html! {
+        <div class="BalanceLegend NodeDisplay">
+            <div class="balance vertical">
+                <h4>{"Colors"}</h4>
+                { for colors }
+                <h4>{"Markers"}</h4>
+                { for markers }
+                <h4>{"Icons"}</h4>
+                { row(line_warning(BELT_WARNING_ICON),
+                    "A building's input or output needs more than one belt") }
+                { row(line_warning(PIPE_WARNING_ICON),
+                    "A building's input or output needs more than one pipe") }
+                { row(html! {
+                    <div class="entry-row sink-entry">
+                        <Icon icon="awesome-sink" />
+                        <div class="balance-value">{settings.format(SAMPLE_RATE)}</div>
+                    </div>
+                }, "AWESOME Sink points the surplus is worth") }
+                { row(html! {
+                    <div class="item-entries">
+                        <span class="clock-hint material-icons-outlined scaled">
+                            {CLOCK_HINT_ICON}
+                        </span>
+                    </div>
+                }, "Input rates scale with the building's clock speed") }
+                { row(html! {
+                    <span class="InterfaceViolation material-icons warning">
+                        {INTERFACE_WARNING_ICON}
+                    </span>
+                }, "A group breaks the interface declared for it") }
+            </div>
+        </div>
+    }
+}
@@ -0,0 +1,245 @@
+// Copyright 2024 Zachary Stewart
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+use log::warn;
+use satisfactory_accounting::accounting::{
+    machines_for_output, BuildNode, Building, ManufacturerSettings, Node,
+};
+use satisfactory_accounting::database::{
+    BuildingId, BuildingKind, Database, ItemId, Recipe, RecipeId,
+};
+use yew::prelude::*;
+
+use crate::clickedit::ClickEdit;
+use crate::node_display::icon::Icon;
+use crate::node_display::{Choice, ChooseFromList};
+use crate::notifications::Notice;
+use crate::{item_label, use_db, use_item_names, use_notifier, use_settings};
+
+#[derive(Debug, PartialEq, Properties)]
+pub struct Props {
+    /// Callback to add the buildings the calculator sized to the factory.
+    pub add: Callback<Node>,
+}
+
+/// Target the user is sizing buildings for.
+#[derive(Debug, Clone, PartialEq)]
+struct Target {
+    /// Recipe to make the item with.
+    recipe: RecipeId,
+    /// Product of the recipe to make.
+    item: ItemId,
+    /// Rate of the item to make, per minute.
+    rate: f32,
+}
+
+/// Calculator for the number of buildings needed to make a target rate of an item with a
+/// particular recipe, which can add those buildings to the factory.
+#[function_component]
+pub fn MachineCalculator(props: &Props) -> Html {
+    let db = use_db();
+    let names = use_item_names();
+    let settings = use_settings();
+    let notifier = use_notifier();
+    let state = use_state(|| None::<Target>);
+    let choosing = use_state(|| true);
+
+    let recipe = state
+        .as_ref()
+        .and_then(|target| Some((target, db.get(target.recipe)?)));
+    if *choosing || recipe.is_none() {
+        let choices: Vec<_> = db
+            .recipes
+            .values()
+            .filter(|recipe| manufacturer_for(&db, recipe).is_some())
+            .map(|recipe| Choice {
+                id: recipe.id,
+                name: recipe.name.clone().into(),
+                image: html! { <Icon icon={recipe.image.clone()} /> },
+                featured: None,
+                group: None,
+            })
+            .collect();
+        let selected = {
+            let state = state.clone();
+            let choosing = choosing.clone();
+            let db = db.clone();
+            Callback::from(move |id: RecipeId| {
+                let Some(recipe) = db.get(id) else {
+                    return;
+                };
+                let Some(product) = recipe.products.first() else {
+                    warn!("Recipe {id} has no products");
+                    return;
+                };
+                // Keep the rate entered for the previous recipe, if any.
+                let rate = state.as_ref().map_or(60.0, |target| target.rate);
+                state.set(Some(Target {
+                    recipe: id,
+                    item: product.item,
+                    rate,
+                }));
+                choosing.set(false);
+            })
+        };
+        let cancelled = {
+            let choosing = choosing.clone();
+            Callback::from(move |()| choosing.set(false))
+        };
+        return html! {
+            <div class="MachineCalculator">
+                <p>{"Choose the recipe to make the item with."}</p>
+                <ChooseFromList<RecipeId> {choices} {selected} {cancelled} />
+            </div>
+        };
+    }
+    let (target, recipe) = recipe.expect("recipe was checked above");
+
+    let change_recipe = {
+        let choosing = choosing.clone();
+        Callback::from(move |_| choosing.set(true))
+    };
+    let products = recipe.products.iter().map(|product| {
+        let (name, image) = item_label(db.get(product.item), product.item, &names);
+        let chosen = (product.item == target.item).then_some("chosen");
+        let onclick = {
+            let state = state.clone();
+            let target = target.clone();
+            let item = product.item;
+            Callback::from(move |_| {
+                state.set(Some(Target {
+                    item,
+                    ..target.clone()
+                }))
+            })
+        };
+        html! {
+            <button class={classes!("product", chosen)} title={name} {onclick}>
+                <Icon icon={image} />
+            </button>
+        }
+    });
+    let set_rate = {
+        let state = state.clone();
+        let target = target.clone();
+        let separator = settings.decimal_separator;
+        let unit = settings.rate_unit;
+        Callback::from(move |text: AttrValue| {
+            if let Some(rate) = separator.parse::<f32>(&text) {
+                state.set(Some(Target {
+                    rate: unit.to_per_minute(rate),
+                    ..target.clone()
+                }));
+            }
+        })
+    };
+    let rate = settings.rate_unit.convert(target.rate);
+    let suffix = html! { <span class="unit">{settings.rate_unit.suffix()}</span> };
+
+    let (building_id, speed) = manufacturer_for(&db, recipe).expect("recipe was filtered");
+    let building_name = db.get(building_id).map_or_else(
+        || building_id.to_string(),
+        |building| building.name.to_string(),
+    );
+    let result = match machines_for_output(recipe, speed, target.item, target.rate) {
+        Some(machines) => {
+            let add = {
+                let add = props.add.clone();
+                let db = db.clone();
+                let notifier = notifier.clone();
+                let recipe_id = target.recipe;
+                Callback::from(move |_| {
+                    let building = Building {
+                        building: Some(building_id),
+                        settings: ManufacturerSettings {
+                            recipe: Some(recipe_id),
+                            clock_speed: machines.clock_speed,
+                            ..Default::default()
+                        }
+                        .into(),
+                        copies: machines.count,
+                        ..Default::default()
+                    };
+                    match building.build_node(&db) {
+                        Ok(node) => add.emit(node),
+                        Err(e) => notifier.notify(Notice::build_error(e, &db)),
+                    }
+                })
+            };
+            let format = |rate: f32| {
+                format!(
+                    "{}{}",
+                    settings.format(settings.rate_unit.convert(rate)),
+                    settings.rate_unit.suffix()
+                )
+            };
+            html! {
+                <div class="calculator-result">
+                    <div class="info-row">
+                        <span class="info-label">{"Exact"}</span>
+                        <span>{format!("{} \u{00d7} {building_name}",
+                            settings.format(machines.exact_count))}</span>
+                    </div>
+                    <div class="info-row">
+                        <span class="info-label">{"Build"}</span>
+                        <span>{format!("{} \u{00d7} {building_name} at {}%", machines.count,
+                            settings.format(machines.clock_speed * 100.0))}</span>
+                    </div>
+                    <div class="info-row">
+                        <span class="info-label">{"Per Building"}</span>
+                        <span>{format(machines.per_machine)}</span>
+                    </div>
+                    <div class="info-row">
+                        <span class="info-label">{"Surplus at 100%"}</span>
+                        <span>{format(machines.surplus)}</span>
+                    </div>
+                    <button class="add-machines" title="Add these buildings to the factory"
+                        onclick={add}>
+                        <span class="material-icons">{"add"}</span>
+                        {"Add Buildings"}
+                    </button>
+                </div>
+            }
+        }
+        None => html! {
+            <p class="calculator-error">{"Enter a rate above zero."}</p>
+        },
+    };
+    html! {
+        <div class="MachineCalculator">
+            <div class="info-row">
+                <span class="info-label">{"Recipe"}</span>
+                <span class="recipe" title="Change Recipe" onclick={change_recipe}>
+                    <Icon icon={recipe.image.clone()} />
+                    <span>{&recipe.name}</span>
+                </span>
+            </div>
+            <div class="info-row">
+                <span class="info-label">{"Product"}</span>
+                <span class="products">{ for products }</span>
+            </div>
+            <div class="info-row">
+                <span class="info-label">{"Target"}</span>
+                <ClickEdit value={AttrValue::from(settings.format(rate))} title="Target Rate"
+                    on_commit={set_rate} {suffix} />
+            </div>
+            { result }
+        </div>
+    }
+}
+
+/// Building the recipe is normally made in, with its manufacturing speed. Falls back to
+/// any manufacturer which can make it. None if no manufacturer in the database makes it.
+fn manufacturer_for(db: &Database, recipe: &Recipe) -> Option<(BuildingId, f32)> {
+    recipe
+        .produced_in
+        .iter()
+        .find_map(|&id| match &db.get(id)?.kind {
+            BuildingKind::Manufacturer(m) => Some((id, m.manufacturing_speed)),
+            _ => None,
+        })
+}
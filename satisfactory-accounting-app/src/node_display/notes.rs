@@ -0,0 +1,87 @@
+// Copyright 2024 Zachary Stewart
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+use log::warn;
+use web_sys::HtmlTextAreaElement;
+use yew::prelude::*;
+
+use crate::events::get_value_from_textarea_change;
+
+#[derive(Debug, PartialEq, Properties)]
+pub struct Props {
+    /// Current note of the node. May be empty.
+    pub note: AttrValue,
+    /// Callback to replace the note of the node.
+    pub update: Callback<AttrValue>,
+}
+
+/// Display and editing for the note attached to a node. Nodes without a note only show a
+/// button to add one.
+#[function_component]
+pub fn Note(props: &Props) -> Html {
+    let editing = use_state(|| false);
+    let input_ref = use_node_ref();
+    {
+        let input_ref = input_ref.clone();
+        use_effect_with(*editing, move |&editing| {
+            if editing {
+                if let Some(input) = input_ref.cast::<HtmlTextAreaElement>() {
+                    if let Err(e) = input.focus() {
+                        warn!("Failed to focus note: {:?}", e);
+                    }
+                }
+            }
+        });
+    }
+
+    if *editing {
+        let onchange = {
+            let editing = editing.clone();
+            let update = props.update.clone();
+            Callback::from(move |e: Event| {
+                editing.set(false);
+                update.emit(get_value_from_textarea_change(e));
+            })
+        };
+        // Blurring without a change doesn't fire a change event, so it ends editing here.
+        let onblur = {
+            let editing = editing.clone();
+            Callback::from(move |_| editing.set(false))
+        };
+        let onkeyup = {
+            let editing = editing.clone();
+            Callback::from(move |e: KeyboardEvent| {
+                if matches!(&*e.key(), "Esc" | "Escape") {
+                    editing.set(false);
+                }
+            })
+        };
+        html! {
+            <textarea class="Note editing" rows="3" title="Note" value={props.note.clone()}
+                ref={input_ref} {onchange} {onblur} {onkeyup} />
+        }
+    } else {
+        let onclick = {
+            let editing = editing.clone();
+            Callback::from(move |_| editing.set(true))
+        };
+        if props.note.trim().is_empty() {
+            html! {
+                <button class="Note add-note" title="Add Note" {onclick}>
+                    <span class="material-icons-outlined">{"sticky_note_2"}</span>
+                </button>
+            }
+        } else {
+            html! {
+                <span class="Note" title={props.note.clone()} {onclick}>
+                    <span class="material-icons-outlined">{"sticky_note_2"}</span>
+                    <span class="note-text">{props.note.clone()}</span>
+                </span>
+            }
+        }
+    }
+}
@@ -0,0 +1,94 @@
+// Copyright 2024 Zachary Stewart
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+use satisfactory_accounting::accounting::{Node, NodeKind};
+use yew::prelude::*;
+
+use crate::node_display::balance::rounded;
+
+#[derive(Debug, PartialEq, Properties)]
+pub struct Props {
+    /// Root of the factory to outline.
+    pub root: Node,
+    /// Callback to jump to the group at the given path.
+    pub jump_to: Callback<Vec<usize>>,
+}
+
+/// Compact outline of the groups in a factory, for navigating large trees.
+#[function_component]
+pub fn Overview(props: &Props) -> Html {
+    let mut entries = Vec::new();
+    collect_groups(&props.root, &mut Vec::new(), &mut entries);
+    html! {
+        <div class="Overview">
+            { for entries.into_iter().map(|entry| view_entry(entry, &props.jump_to)) }
+        </div>
+    }
+}
+
+/// Summary of a single group in the outline.
+struct OutlineEntry {
+    /// Path to the group from the root.
+    path: Vec<usize>,
+    /// Name of the group.
+    name: AttrValue,
+    /// Whether the group needs items that it doesn't produce itself.
+    unmet_inputs: bool,
+    /// Whether anything in the group failed to build.
+    has_warnings: bool,
+}
+
+/// Walk the tree and collect an outline entry for every group in pre-order.
+fn collect_groups(node: &Node, path: &mut Vec<usize>, entries: &mut Vec<OutlineEntry>) {
+    if let NodeKind::Group(group) = node.kind() {
+        entries.push(OutlineEntry {
+            path: path.clone(),
+            name: group.name.clone(),
+            unmet_inputs: node
+                .balance()
+                .balances
+                .values()
+                .any(|&rate| rounded(rate) < 0.0),
+            has_warnings: node.warning().is_some() || node.children_had_warnings(),
+        });
+        for (idx, child) in group.children.iter().enumerate() {
+            path.push(idx);
+            collect_groups(child, path, entries);
+            path.pop();
+        }
+    }
+}
+
+/// Display a single outline entry, indented by its depth.
+fn view_entry(entry: OutlineEntry, jump_to: &Callback<Vec<usize>>) -> Html {
+    let style = format!("padding-left: {}px", entry.path.len() * 16);
+    let (name, unnamed) = if entry.name.is_empty() {
+        (AttrValue::from("unnamed"), Some("unnamed"))
+    } else {
+        (entry.name, None)
+    };
+    let onclick = {
+        let jump_to = jump_to.clone();
+        let path = entry.path;
+        Callback::from(move |_| jump_to.emit(path.clone()))
+    };
+    html! {
+        <div class="overview-row" {style} {onclick}>
+            <span class={classes!("overview-name", unnamed)}>{name}</span>
+            if entry.has_warnings {
+                <span class="material-icons error" title="Contains buildings with errors">
+                    {"error"}
+                </span>
+            }
+            if entry.unmet_inputs {
+                <span class="material-icons warning" title="Has unmet inputs">
+                    {"warning"}
+                </span>
+            }
+        </div>
+    }
+}
@@ -0,0 +1,127 @@
+// Copyright 2024 Zachary Stewart
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+use satisfactory_accounting::accounting::{Group, Node, NodeKind};
+use uuid::Uuid;
+use yew::prelude::*;
+
+use crate::node_display::balance::balance_style;
+use crate::node_display::icon::Icon;
+use crate::node_display::{NodeMeta, NodeMetadata};
+use crate::{item_label, use_db, use_item_names, use_settings};
+
+#[derive(Debug, PartialEq, Properties)]
+pub struct Props {
+    /// Root of the factory to find pinned groups in.
+    pub root: Node,
+    /// Callback to jump to the group at the given path.
+    pub jump_to: Callback<Vec<usize>>,
+    /// Callback to update the metadata of a group, used to unpin it.
+    pub set_metadata: Callback<(Uuid, NodeMeta)>,
+}
+
+/// Summaries of the groups the user pinned, docked at the top of the page so they stay
+/// visible while scrolling.
+#[function_component]
+pub fn PinnedNodes(props: &Props) -> Html {
+    let metadata = use_context::<NodeMetadata>().expect("metadata context to be set");
+    let db = use_db();
+    let names = use_item_names();
+    let settings = use_settings();
+
+    let mut pinned = Vec::new();
+    collect_pinned(&props.root, &metadata, &mut Vec::new(), &mut pinned);
+    if pinned.is_empty() {
+        return html! {};
+    }
+    let entries = pinned.into_iter().map(|(path, node)| {
+        let group = node.group().expect("only groups are pinned");
+        let meta = metadata.meta(group.id);
+        let unpin = {
+            let set_metadata = props.set_metadata.clone();
+            let update = (
+                group.id,
+                NodeMeta {
+                    pinned: false,
+                    ..meta
+                },
+            );
+            Callback::from(move |_| set_metadata.emit(update.clone()))
+        };
+        let jump_to = {
+            let jump_to = props.jump_to.clone();
+            Callback::from(move |_| jump_to.emit(path.clone()))
+        };
+        let balance = node.balance();
+        let items = balance.balances.iter().map(|(&itemid, &rate)| {
+            let (name, image) = item_label(db.get(itemid), itemid, &names);
+            html! {
+                <div class={classes!("entry-row", balance_style(rate))} title={name}>
+                    <Icon icon={image} />
+                    <div class="balance-value">{settings.format(settings.rate_unit.convert(rate))}</div>
+                </div>
+            }
+        });
+        html! {
+            <div class="pinned-node" key={group.id.as_u128()}>
+                <div class="pinned-header">
+                    <span class="name" title="Jump to Group" onclick={jump_to}>
+                        {display_name(group)}
+                    </span>
+                    <button class="unpin" title="Unpin" onclick={unpin}>
+                        <span class="material-icons">{"close"}</span>
+                    </button>
+                </div>
+                <div class="balance horizontal">
+                    if !settings.hide_power {
+                        <div class={classes!("entry-row", "power-entry", balance_style(balance.power))}
+                            title="Power">
+                            <Icon icon="power-line" />
+                            <div class="balance-value">{settings.format(balance.power)}</div>
+                        </div>
+                    }
+                    <div class="item-entries">
+                        { for items }
+                    </div>
+                </div>
+            </div>
+        }
+    });
+    html! {
+        <div class="PinnedNodes">
+            { for entries }
+        </div>
+    }
+}
+
+/// Walk the tree and collect the path and node of every pinned group in pre-order.
+fn collect_pinned(
+    node: &Node,
+    metadata: &NodeMetadata,
+    path: &mut Vec<usize>,
+    pinned: &mut Vec<(Vec<usize>, Node)>,
+) {
+    if let NodeKind::Group(group) = node.kind() {
+        if metadata.meta(group.id).pinned {
+            pinned.push((path.clone(), node.clone()));
+        }
+        for (idx, child) in group.children.iter().enumerate() {
+            path.push(idx);
+            collect_pinned(child, metadata, path, pinned);
+            path.pop();
+        }
+    }
+}
+
+/// Name to show for a pinned group.
+fn display_name(group: &Group) -> AttrValue {
+    if group.name.is_empty() {
+        "unnamed".into()
+    } else {
+        group.name.clone()
+    }
+}
@@ -0,0 +1,93 @@
+// Copyright 2024 Zachary Stewart
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+use satisfactory_accounting::accounting::Node;
+use yew::prelude::*;
+
+use crate::node_display::balance::rounded;
+
+/// Number of distinct colors segments cycle through, matching the stylesheet.
+const PALETTE_SIZE: usize = 8;
+
+/// Width of the chart in SVG units. The chart is scaled to fit its container.
+const WIDTH: f32 = 1000.0;
+
+/// Height of the chart in SVG units.
+const HEIGHT: f32 = 40.0;
+
+#[derive(Debug, PartialEq, Properties)]
+pub struct Props {
+    /// Root of the factory whose top-level groups are charted.
+    pub root: Node,
+}
+
+/// Stacked bar showing each top-level group's share of the power consumed by the factory.
+/// Groups which generate more power than they use aren't included.
+#[function_component]
+pub fn PowerChart(props: &Props) -> Html {
+    let balances = props.root.group_balances();
+    let consumers: Vec<(AttrValue, f32)> = props
+        .root
+        .children()
+        .filter_map(|child| {
+            let group = child.group()?;
+            let consumed = -balances[&group.id].power;
+            let name = if group.name.is_empty() {
+                "unnamed".into()
+            } else {
+                group.name.clone()
+            };
+            (rounded(consumed) > 0.0).then_some((name, consumed))
+        })
+        .collect();
+    let total: f32 = consumers.iter().map(|&(_, consumed)| consumed).sum();
+    if consumers.is_empty() {
+        return html! {
+            <div class="PowerChart empty">{"No groups consume power."}</div>
+        };
+    }
+
+    let mut x = 0.0;
+    let segments = consumers.iter().enumerate().map(|(idx, (name, consumed))| {
+        let width = consumed / total * WIDTH;
+        let segment = html! {
+            <rect class={format!("segment segment-{}", idx % PALETTE_SIZE)}
+                x={x.to_string()} y="0" width={width.to_string()} height={HEIGHT.to_string()}>
+                <title>{describe(name, *consumed, total)}</title>
+            </rect>
+        };
+        x += width;
+        segment
+    });
+    let legend = consumers.iter().enumerate().map(|(idx, (name, consumed))| {
+        html! {
+            <div class="legend-entry">
+                <span class={format!("swatch segment-{}", idx % PALETTE_SIZE)} />
+                <span>{describe(name, *consumed, total)}</span>
+            </div>
+        }
+    });
+    html! {
+        <div class="PowerChart">
+            <svg viewBox={format!("0 0 {WIDTH} {HEIGHT}")} preserveAspectRatio="none">
+                { for segments }
+            </svg>
+            <div class="legend">
+                { for legend }
+            </div>
+        </div>
+    }
+}
+
+/// Label for a group's power consumption and its share of the total.
+fn describe(name: &str, consumed: f32, total: f32) -> String {
+    format!(
+        "{name}: {} MW ({:.0}%)",
+        rounded(consumed),
+        consumed / total * 100.0
+    )
+}
@@ -0,0 +1,285 @@
+// Copyright 2024 Zachary Stewart
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+use satisfactory_accounting::accounting::{
+    generators_for_power, BuildNode, Building, GeneratorSettings, Node,
+};
+use satisfactory_accounting::database::{BuildingId, BuildingKind, ItemId};
+use yew::prelude::*;
+
+use crate::clickedit::ClickEdit;
+use crate::node_display::icon::Icon;
+use crate::node_display::{Choice, ChooseFromList};
+use crate::notifications::Notice;
+use crate::{item_label, use_db, use_item_names, use_notifier, use_settings};
+
+/// Power target used until the user enters one, in MW.
+const DEFAULT_POWER: f32 = 1000.0;
+
+#[derive(Debug, PartialEq, Properties)]
+pub struct Props {
+    /// Callback to add the generators the planner sized to the factory.
+    pub add: Callback<Node>,
+}
+
+/// Generators and fuel the user is planning a power target for.
+#[derive(Debug, Clone, PartialEq)]
+struct Plan {
+    /// Generator to build.
+    generator: BuildingId,
+    /// Fuel to burn, if chosen.
+    fuel: Option<ItemId>,
+    /// Power to produce, in MW.
+    power: f32,
+}
+
+/// What the planner is asking the user to choose.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum Choosing {
+    Generator,
+    Fuel,
+    Nothing,
+}
+
+/// Planner for the number of generators needed to produce a target amount of power with
+/// a particular fuel, and the fuel and water they need. Can add those generators to the
+/// factory.
+#[function_component]
+pub fn PowerPlanner(props: &Props) -> Html {
+    let db = use_db();
+    let names = use_item_names();
+    let settings = use_settings();
+    let notifier = use_notifier();
+    let state = use_state(|| None::<Plan>);
+    let choosing = use_state(|| Choosing::Generator);
+
+    let generator = state
+        .as_ref()
+        .and_then(|plan| Some((plan, db.get(plan.generator)?)));
+    if *choosing == Choosing::Generator || generator.is_none() {
+        let choices: Vec<_> = db
+            .buildings
+            .values()
+            .filter(|building| matches!(building.kind, BuildingKind::Generator(_)))
+            .map(|building| Choice {
+                id: building.id,
+                name: building.name.clone().into(),
+                image: html! { <Icon icon={building.image.clone()} /> },
+                featured: None,
+                group: None,
+            })
+            .collect();
+        let selected = {
+            let state = state.clone();
+            let choosing = choosing.clone();
+            let db = db.clone();
+            Callback::from(move |id: BuildingId| {
+                let Some(BuildingKind::Generator(g)) = db.get(id).map(|b| &b.kind) else {
+                    return;
+                };
+                // Keep the fuel and target from the previous generator where they still
+                // apply, and pick the fuel if there is only one choice.
+                let previous = state.as_ref();
+                let fuel = match &g.allowed_fuel[..] {
+                    [only] => Some(*only),
+                    allowed => previous
+                        .and_then(|plan| plan.fuel)
+                        .filter(|fuel| allowed.contains(fuel)),
+                };
+                let power = previous.map_or(DEFAULT_POWER, |plan| plan.power);
+                choosing.set(if fuel.is_some() {
+                    Choosing::Nothing
+                } else {
+                    Choosing::Fuel
+                });
+                state.set(Some(Plan {
+                    generator: id,
+                    fuel,
+                    power,
+                }));
+            })
+        };
+        let cancelled = {
+            let choosing = choosing.clone();
+            Callback::from(move |()| choosing.set(Choosing::Nothing))
+        };
+        return html! {
+            <div class="PowerPlanner">
+                <p>{"Choose the generator to build."}</p>
+                <ChooseFromList<BuildingId> {choices} {selected} {cancelled} />
+            </div>
+        };
+    }
+    let (plan, generator) = generator.expect("generator was checked above");
+    let BuildingKind::Generator(g) = &generator.kind else {
+        unreachable!("only generators can be chosen");
+    };
+
+    if *choosing == Choosing::Fuel || plan.fuel.is_none() {
+        let choices: Vec<_> = g
+            .allowed_fuel
+            .iter()
+            .map(|&fuel| {
+                let (name, image) = item_label(db.get(fuel), fuel, &names);
+                Choice {
+                    id: fuel,
+                    name,
+                    image: html! { <Icon icon={image} /> },
+                    featured: None,
+                    group: None,
+                }
+            })
+            .collect();
+        let selected = {
+            let state = state.clone();
+            let choosing = choosing.clone();
+            let plan = plan.clone();
+            Callback::from(move |fuel: ItemId| {
+                choosing.set(Choosing::Nothing);
+                state.set(Some(Plan {
+                    fuel: Some(fuel),
+                    ..plan.clone()
+                }));
+            })
+        };
+        let cancelled = {
+            let choosing = choosing.clone();
+            Callback::from(move |()| choosing.set(Choosing::Nothing))
+        };
+        return html! {
+            <div class="PowerPlanner">
+                <p>{format!("Choose the fuel for the {}.", generator.name)}</p>
+                <ChooseFromList<ItemId> {choices} {selected} {cancelled} />
+            </div>
+        };
+    }
+    let fuel = plan.fuel.expect("fuel was checked above");
+
+    let choose = |next: Choosing| {
+        let choosing = choosing.clone();
+        Callback::from(move |_| choosing.set(next))
+    };
+    let set_power = {
+        let state = state.clone();
+        let plan = plan.clone();
+        let separator = settings.decimal_separator;
+        Callback::from(move |text: AttrValue| {
+            if let Some(power) = separator.parse::<f32>(&text) {
+                state.set(Some(Plan {
+                    power,
+                    ..plan.clone()
+                }));
+            }
+        })
+    };
+    let rate = |name: &AttrValue, rate: f32| {
+        html! {
+            <div class="info-row">
+                <span class="info-label">{name}</span>
+                <span>{format!("{}{}", settings.format(settings.rate_unit.convert(rate)),
+                    settings.rate_unit.suffix())}</span>
+            </div>
+        }
+    };
+    let (fuel_name, fuel_image) = item_label(db.get(fuel), fuel, &names);
+    let result = match generators_for_power(&db, generator, fuel, plan.power) {
+        Ok(Some(generators)) => {
+            let add = {
+                let add = props.add.clone();
+                let db = db.clone();
+                let notifier = notifier.clone();
+                let generator_id = generator.id;
+                let generators = generators.clone();
+                Callback::from(move |_| {
+                    let building = Building {
+                        building: Some(generator_id),
+                        settings: GeneratorSettings {
+                            fuel: Some(fuel),
+                            clock_speed: generators.clock_speed,
+                        }
+                        .into(),
+                        copies: generators.count,
+                        ..Default::default()
+                    };
+                    match building.build_node(&db) {
+                        Ok(node) => add.emit(node),
+                        Err(e) => notifier.notify(Notice::build_error(e, &db)),
+                    }
+                })
+            };
+            let water = (generators.water_rate > 0.0).then(|| {
+                let water = ItemId::water();
+                rate(
+                    &item_label(db.get(water), water, &names).0,
+                    generators.water_rate,
+                )
+            });
+            let byproducts = generators.byproducts.iter().map(|(&item, &amount)| {
+                let (name, _) = item_label(db.get(item), item, &names);
+                rate(&format!("{name} Byproduct").into(), amount)
+            });
+            html! {
+                <div class="calculator-result">
+                    <div class="info-row">
+                        <span class="info-label">{"Exact"}</span>
+                        <span>{format!("{} \u{00d7} {}",
+                            settings.format(generators.exact_count), generator.name)}</span>
+                    </div>
+                    <div class="info-row">
+                        <span class="info-label">{"Build"}</span>
+                        <span>{format!("{} \u{00d7} {} at {}%", generators.count,
+                            generator.name, settings.format(generators.clock_speed * 100.0))}</span>
+                    </div>
+                    <div class="info-row">
+                        <span class="info-label">{"Per Generator"}</span>
+                        <span>{format!("{} MW at 100%",
+                            settings.format(generators.per_generator))}</span>
+                    </div>
+                    { rate(&fuel_name, generators.fuel_rate) }
+                    { for water }
+                    { for byproducts }
+                    <button class="add-machines" title="Add these generators to the factory"
+                        onclick={add}>
+                        <span class="material-icons">{"add"}</span>
+                        {"Add Generators"}
+                    </button>
+                </div>
+            }
+        }
+        Ok(None) => html! {
+            <p class="calculator-error">{"Enter a power target above zero."}</p>
+        },
+        Err(e) => html! {
+            <p class="calculator-error">{e.describe(&db)}</p>
+        },
+    };
+    html! {
+        <div class="PowerPlanner">
+            <div class="info-row">
+                <span class="info-label">{"Generator"}</span>
+                <span class="recipe" title="Change Generator" onclick={choose(Choosing::Generator)}>
+                    <Icon icon={generator.image.clone()} />
+                    <span>{&generator.name}</span>
+                </span>
+            </div>
+            <div class="info-row">
+                <span class="info-label">{"Fuel"}</span>
+                <span class="recipe" title="Change Fuel" onclick={choose(Choosing::Fuel)}>
+                    <Icon icon={fuel_image} />
+                    <span>{&fuel_name}</span>
+                </span>
+            </div>
+            <div class="info-row">
+                <span class="info-label">{"Target"}</span>
+                <ClickEdit value={AttrValue::from(settings.format(plan.power))}
+                    title="Target Power" on_commit={set_power}
+                    suffix={html! { <span class="unit">{" MW"}</span> }} />
+            </div>
+            { result }
+        </div>
+    }
+}
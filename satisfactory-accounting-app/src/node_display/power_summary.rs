@@ -0,0 +1,54 @@
+// Copyright 2024 Zachary Stewart
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+use satisfactory_accounting::accounting::Node;
+use yew::prelude::*;
+
+use crate::use_settings;
+
+#[derive(Debug, PartialEq, Properties)]
+pub struct Props {
+    /// Root of the factory to summarize.
+    pub root: Node,
+}
+
+/// Net power of the whole factory along with its total generation and consumption, so the
+/// user can see how much headroom the grid has.
+#[function_component]
+pub fn PowerSummary(props: &Props) -> Html {
+    let settings = use_settings();
+    let split = props.root.power_split();
+    if split.generated == 0.0 && split.consumed == 0.0 {
+        return html! {};
+    }
+    let net = props.root.balance().power;
+    let (class, icon, title) = if split.is_self_sufficient() {
+        ("surplus", "bolt", "The grid generates enough power")
+    } else {
+        (
+            "deficit",
+            "power_off",
+            "The grid doesn't generate enough power",
+        )
+    };
+    html! {
+        <div class={classes!("PowerSummary", class)} {title}>
+            <span class="material-icons">{icon}</span>
+            <span class="net">
+                {"Net "}
+                if net > 0.0 { {"+"} }
+                {settings.format(net)}{" MW"}
+            </span>
+            <span class="split">
+                {"Generated "}{settings.format(split.generated)}{" MW"}
+            </span>
+            <span class="split">
+                {"Consumed "}{settings.format(split.consumed)}{" MW"}
+            </span>
+        </div>
+    }
+}
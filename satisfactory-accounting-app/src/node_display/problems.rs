@@ -0,0 +1,184 @@
+// Copyright 2024 Zachary Stewart
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+use satisfactory_accounting::accounting::{Node, UnconfiguredBuilding};
+use satisfactory_accounting::database::{Database, ItemId};
+use yew::prelude::*;
+
+use crate::node_display::balance::balance_style;
+use crate::node_display::icon::Icon;
+use crate::{item_label, use_db, use_item_names, use_settings};
+
+/// Which unbalanced items the problems list shows.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq)]
+pub enum ProblemFilter {
+    /// Items the factory consumes more of than it makes, which will stall it.
+    #[default]
+    Deficits,
+    /// Items the factory makes more of than it consumes.
+    Surpluses,
+    /// Both deficits and surpluses.
+    Both,
+}
+
+impl ProblemFilter {
+    /// All filters, in the order they are offered.
+    const ALL: [ProblemFilter; 3] = [Self::Deficits, Self::Surpluses, Self::Both];
+
+    /// Name to show for this filter.
+    fn name(self) -> &'static str {
+        match self {
+            Self::Deficits => "Deficits",
+            Self::Surpluses => "Surpluses",
+            Self::Both => "Deficits and Surpluses",
+        }
+    }
+
+    /// Whether an item with the given net rate passes this filter.
+    fn matches(self, rate: f32) -> bool {
+        match self {
+            Self::Deficits => rate < 0.0,
+            Self::Surpluses => rate > 0.0,
+            Self::Both => rate != 0.0,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Properties)]
+pub struct Props {
+    /// Root of the factory to check.
+    pub root: Node,
+    /// Callback to show where an item is produced and consumed.
+    pub find_item: Callback<ItemId>,
+    /// Callback to jump to the building at the given path.
+    pub jump_to: Callback<Vec<usize>>,
+}
+
+/// List of the items the whole factory doesn't balance, largest deficits first, followed
+/// by the buildings which are missing a recipe, resource, or fuel.
+#[function_component]
+pub fn Problems(props: &Props) -> Html {
+    let db = use_db();
+    let names = use_item_names();
+    let settings = use_settings();
+    let filter = use_state(ProblemFilter::default);
+
+    let filters = ProblemFilter::ALL.iter().map(|&option| {
+        let onclick = {
+            let filter = filter.clone();
+            Callback::from(move |_| filter.set(option))
+        };
+        let selected = (*filter == option).then_some("selected");
+        html! {
+            <button class={classes!("filter-chip", selected)} {onclick}>
+                {option.name()}
+            </button>
+        }
+    });
+
+    let mut items: Vec<_> = props
+        .root
+        .balance()
+        .balances
+        .iter()
+        .map(|(&item, &rate)| (item, rate))
+        // Skip values which only differ from zero by float error.
+        .filter(|&(_, rate)| filter.matches(rate) && settings.format(rate) != "0")
+        .collect();
+    items.sort_by(|(_, r1), (_, r2)| r1.total_cmp(r2));
+    let unit = settings.rate_unit;
+    let rows: Vec<Html> = items
+        .into_iter()
+        .map(|(item, rate)| {
+            let (name, image) = item_label(db.get(item), item, &names);
+            let onclick = {
+                let find_item = props.find_item.clone();
+                Callback::from(move |_| find_item.emit(item))
+            };
+            html! {
+                <tr class={classes!("problem-row", balance_style(rate))}
+                    title="Show where this item is produced and consumed" {onclick}>
+                    <td class="problem-item">
+                        <Icon icon={image} />
+                        <span>{name}</span>
+                    </td>
+                    <td class="problem-rate">
+                        {settings.format(unit.convert(rate))}{unit.suffix()}
+                    </td>
+                </tr>
+            }
+        })
+        .collect();
+    let unconfigured: Vec<Html> = props
+        .root
+        .unconfigured_buildings()
+        .into_iter()
+        .map(|found| unconfigured_row(&props.root, &db, found, &props.jump_to))
+        .collect();
+    html! {
+        <div class="Problems">
+            <div class="problem-filters">
+                { for filters }
+            </div>
+            if rows.is_empty() {
+                <p class="no-problems">{format!("No {}.", filter.name().to_lowercase())}</p>
+            } else {
+                <table>
+                    { for rows }
+                </table>
+            }
+            if !unconfigured.is_empty() {
+                <h4>{"Unconfigured Buildings"}</h4>
+                <table>
+                    { for unconfigured }
+                </table>
+            }
+        </div>
+    }
+}
+
+/// Row for a building which is missing a choice, showing the building and the group it
+/// is in.
+fn unconfigured_row(
+    root: &Node,
+    db: &Database,
+    found: UnconfiguredBuilding,
+    jump_to: &Callback<Vec<usize>>,
+) -> Html {
+    let building = root
+        .get_at(&found.path)
+        .and_then(|node| node.building())
+        .and_then(|building| building.building)
+        .and_then(|id| db.get(id));
+    let group = found
+        .path
+        .split_last()
+        .and_then(|(_, parent)| root.get_at(parent))
+        .and_then(|node| node.group())
+        .map(|group| group.name.clone())
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| "unnamed".into());
+    let onclick = {
+        let jump_to = jump_to.clone();
+        Callback::from(move |_| jump_to.emit(found.path.clone()))
+    };
+    html! {
+        <tr class="problem-row unconfigured" title="Jump to Building" {onclick}>
+            <td class="problem-item">
+                if let Some(building) = building {
+                    <Icon icon={building.image.clone()} />
+                    <span>{&building.name}</span>
+                } else {
+                    <span class="material-icons">{"help_outline"}</span>
+                    <span>{"Building"}</span>
+                }
+            </td>
+            <td class="problem-group">{group}</td>
+            <td class="problem-missing">{found.missing.to_string()}</td>
+        </tr>
+    }
+}
@@ -0,0 +1,147 @@
+// Copyright 2024 Zachary Stewart
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+use log::warn;
+use satisfactory_accounting::accounting::{Group, Node};
+use uuid::Uuid;
+use yew::prelude::*;
+
+use crate::node_display::graph_manipulation::find_group;
+use crate::node_display::NodeDisplay;
+use crate::notifications::Notice;
+
+/// Which tree a node is displayed in. Both trees are rooted at an empty path, so nodes
+/// cut or dragged by path can only be moved within the tree they came from.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum NodeTree {
+    /// The factory itself.
+    #[default]
+    Factory,
+    /// The working copy in the sandbox.
+    Sandbox,
+}
+
+/// Notice shown when the user tries to move a node between the factory and the sandbox.
+pub(super) fn cross_tree_move_notice() -> Notice {
+    Notice::new(
+        "Nodes can't be moved between the factory and the sandbox. Copy them with Ctrl+C \
+        and paste instead.",
+    )
+}
+
+impl NodeDisplay {
+    /// Get the tree this node is displayed in. Nodes outside of a [`NodeTree`] context
+    /// are in the factory.
+    pub(super) fn tree(&self, ctx: &Context<Self>) -> NodeTree {
+        ctx.link()
+            .context::<NodeTree>(Callback::noop())
+            .map(|(tree, _)| tree)
+            .unwrap_or_default()
+    }
+}
+
+/// Detached copy of a group which the user can experiment on without changing the
+/// factory, then either discard or merge back over the original.
+#[derive(Debug, Clone)]
+pub struct Sandbox {
+    /// Id of the group the sandbox was forked from.
+    original: Uuid,
+    /// Working copy of the group. Its own groups have fresh ids, so they don't share
+    /// metadata with the originals.
+    root: Node,
+}
+
+impl Sandbox {
+    /// Fork a sandbox from a copy of the given group. None if the node isn't a group.
+    pub fn fork(node: &Node) -> Option<Self> {
+        let group = node.group()?;
+        Some(Self {
+            original: group.id,
+            root: node.create_copy(),
+        })
+    }
+
+    /// Get the working copy of the group.
+    pub fn root(&self) -> &Node {
+        &self.root
+    }
+
+    /// Replace the working copy of the group.
+    pub fn set_root(&mut self, root: Node) {
+        if root.group().is_none() {
+            warn!("Sandbox root must be a group");
+            return;
+        }
+        self.root = root;
+    }
+
+    /// Build a new factory root with the original group replaced by the working copy.
+    /// The replacement keeps the original's id, so the group stays pinned or collapsed
+    /// the way it was. None if the original group is no longer in the factory.
+    pub fn merge_into(&self, root: &Node) -> Option<Node> {
+        let path = find_group(root.group()?, self.original)?;
+        let merged = Group {
+            id: self.original,
+            ..self.root.group()?.clone()
+        };
+        root.replace_at(&path, merged.into())
+            .map_err(|e| warn!("Unable to merge sandbox: {e}"))
+            .ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use satisfactory_accounting::accounting::Building;
+
+    use super::*;
+
+    #[test]
+    fn fork_and_merge_sandbox() {
+        let root: Node = Group {
+            children: vec![
+                Building::empty_node(),
+                Group {
+                    name: "Experiment".into(),
+                    children: vec![Group::empty_node()],
+                    ..Group::empty()
+                }
+                .into(),
+            ],
+            ..Group::empty()
+        }
+        .into();
+        let original = root.get_at(&[1]).unwrap().group().unwrap().clone();
+
+        let mut sandbox = Sandbox::fork(root.get_at(&[1]).unwrap()).unwrap();
+        let forked = sandbox.root().group().unwrap();
+        assert_ne!(forked.id, original.id);
+        assert_ne!(
+            forked.children[0].group().unwrap().id,
+            original.children[0].group().unwrap().id
+        );
+        assert!(Sandbox::fork(&Building::empty_node()).is_none());
+
+        sandbox.set_root(
+            Group {
+                name: "Changed".into(),
+                children: vec![Building::empty_node()],
+                ..forked.clone()
+            }
+            .into(),
+        );
+        let merged = sandbox.merge_into(&root).unwrap();
+        let merged = merged.get_at(&[1]).unwrap().group().unwrap();
+        assert_eq!(merged.id, original.id);
+        assert_eq!(merged.name, "Changed");
+        assert!(merged.children[0].building().is_some());
+
+        // The original is gone, so there's nothing to merge over.
+        let removed = root.remove_at(&[1]).unwrap().0;
+        assert!(sandbox.merge_into(&removed).is_none());
+    }
+}
@@ -0,0 +1,141 @@
+// Copyright 2024 Zachary Stewart
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+use std::collections::BTreeSet;
+use std::rc::Rc;
+
+use yew::prelude::*;
+
+use super::NodeDisplay;
+
+/// Nodes selected to be moved, copied, or deleted together, identified by path. Paths go
+/// stale when the tree changes, so the selection must be cleared whenever it does.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Selection {
+    /// Paths of the selected nodes.
+    paths: Rc<BTreeSet<Vec<usize>>>,
+    /// Most recently toggled node, where range selections start from.
+    anchor: Option<Vec<usize>>,
+}
+
+impl Selection {
+    /// Whether the node at the given path is selected.
+    pub fn contains(&self, path: &[usize]) -> bool {
+        self.paths.contains(path)
+    }
+
+    /// Number of selected nodes.
+    pub fn len(&self) -> usize {
+        self.paths.len()
+    }
+
+    /// Whether no nodes are selected.
+    pub fn is_empty(&self) -> bool {
+        self.paths.is_empty()
+    }
+
+    /// Paths of the selected nodes, in tree order.
+    pub fn paths(&self) -> Vec<Vec<usize>> {
+        self.paths.iter().cloned().collect()
+    }
+
+    /// Add the node at `path` to the selection, or remove it if already selected, and
+    /// start later range selections from it.
+    pub fn toggle(&mut self, path: Vec<usize>) {
+        let paths = Rc::make_mut(&mut self.paths);
+        if !paths.remove(&path) {
+            paths.insert(path.clone());
+        }
+        self.anchor = Some(path);
+    }
+
+    /// Select every node from the anchor to `path`. Only siblings can be selected as a
+    /// range, so if the anchor has a different parent this just toggles `path`.
+    pub fn select_range(&mut self, path: Vec<usize>) {
+        let Some(anchor) = &self.anchor else {
+            return self.toggle(path);
+        };
+        let (Some((&start, anchor_parent)), Some((&end, parent))) =
+            (anchor.split_last(), path.split_last())
+        else {
+            return self.toggle(path);
+        };
+        if anchor_parent != parent {
+            return self.toggle(path);
+        }
+        let paths = Rc::make_mut(&mut self.paths);
+        for idx in start.min(end)..=start.max(end) {
+            let mut sibling = parent.to_vec();
+            sibling.push(idx);
+            paths.insert(sibling);
+        }
+    }
+}
+
+/// Change to the selection requested by a node.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SelectionAction {
+    /// Toggle the node at `path`, or select the range up to it if `range` is set.
+    Select { path: Vec<usize>, range: bool },
+    /// Move the selected nodes to `dest_path`, or insert copies there if `copy` is set.
+    MoveTo { dest_path: Vec<usize>, copy: bool },
+}
+
+impl NodeDisplay {
+    /// Whether this node is part of the current selection.
+    pub(super) fn is_selected(&self, ctx: &Context<Self>) -> bool {
+        let (selection, _) = ctx
+            .link()
+            .context::<Selection>(Callback::noop())
+            .expect("selection context to be set");
+        selection.contains(&ctx.props().path)
+    }
+
+    /// Build a click handler for the drag handle, which toggles this node in the selection
+    /// with Ctrl and selects a range of siblings with Shift.
+    pub(super) fn select_handler(&self, ctx: &Context<Self>) -> Callback<MouseEvent> {
+        let path = ctx.props().path.clone();
+        let selection_action = ctx.props().selection_action.clone();
+        Callback::from(move |e: MouseEvent| {
+            let range = e.shift_key();
+            if range || e.ctrl_key() || e.meta_key() {
+                e.prevent_default();
+                selection_action.emit(SelectionAction::Select {
+                    path: path.clone(),
+                    range,
+                });
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn toggle_and_range() {
+        let mut selection = Selection::default();
+        selection.toggle(vec![0, 1]);
+        selection.select_range(vec![0, 3]);
+        assert_eq!(selection.paths(), vec![vec![0, 1], vec![0, 2], vec![0, 3]]);
+
+        // Ranges run backwards from the anchor too.
+        selection.toggle(vec![0, 2]);
+        selection.select_range(vec![0, 0]);
+        assert_eq!(
+            selection.paths(),
+            vec![vec![0, 0], vec![0, 1], vec![0, 2], vec![0, 3]]
+        );
+
+        // A node under a different parent can't be part of a range.
+        selection.select_range(vec![1]);
+        assert_eq!(selection.len(), 5);
+        selection.toggle(vec![1]);
+        assert!(!selection.contains(&[1]));
+    }
+}
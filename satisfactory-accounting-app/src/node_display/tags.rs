@@ -0,0 +1,60 @@
+// Copyright 2024 Zachary Stewart
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+use yew::prelude::*;
+
+use crate::clickedit::ClickEdit;
+
+#[derive(Debug, PartialEq, Properties)]
+pub struct Props {
+    /// Current tags of the node.
+    pub tags: Vec<AttrValue>,
+    /// Callback to replace the tags of the node.
+    pub update: Callback<Vec<AttrValue>>,
+}
+
+/// Display and editing for the tags of a node.
+#[function_component]
+pub fn Tags(props: &Props) -> Html {
+    let add = {
+        let tags = props.tags.clone();
+        let update = props.update.clone();
+        Callback::from(move |tag: AttrValue| {
+            let tag = tag.trim();
+            if !tag.is_empty() && !tags.iter().any(|existing| &**existing == tag) {
+                let mut tags = tags.clone();
+                tags.push(tag.to_owned().into());
+                update.emit(tags);
+            }
+        })
+    };
+    let tags = props.tags.iter().enumerate().map(|(idx, tag)| {
+        let remove = {
+            let tags = props.tags.clone();
+            let update = props.update.clone();
+            Callback::from(move |_| {
+                let mut tags = tags.clone();
+                tags.remove(idx);
+                update.emit(tags);
+            })
+        };
+        html! {
+            <span class="tag" title={format!("Remove tag \"{tag}\"")} onclick={remove}>
+                {tag.clone()}
+            </span>
+        }
+    });
+    let prefix = html! {
+        <span class="material-icons-outlined">{"sell"}</span>
+    };
+    html! {
+        <span class="Tags">
+            { for tags }
+            <ClickEdit value="" class="add-tag" title="Add Tag" on_commit={add} {prefix} />
+        </span>
+    }
+}
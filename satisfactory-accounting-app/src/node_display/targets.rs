@@ -0,0 +1,69 @@
+// Copyright 2024 Zachary Stewart
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+use satisfactory_accounting::accounting::Node;
+use satisfactory_accounting::bom::{BillOfMaterials, TargetStatus};
+use yew::prelude::*;
+
+use crate::node_display::balance::rounded;
+use crate::node_display::icon::Icon;
+use crate::{item_label, use_db, use_item_names};
+
+#[derive(Debug, PartialEq, Properties)]
+pub struct Props {
+    /// Target output rates.
+    pub bom: BillOfMaterials,
+    /// Root of the factory to compare against the targets.
+    pub root: Node,
+}
+
+/// Table showing whether the factory's net outputs meet each target.
+#[function_component]
+pub fn TargetScorecard(props: &Props) -> Html {
+    let db = use_db();
+    let names = use_item_names();
+    let rows = props
+        .bom
+        .compare(props.root.balance())
+        .into_iter()
+        .map(|comparison| {
+            let (name, image) = item_label(db.get(comparison.item), comparison.item, &names);
+            let (class, status) = match comparison.status {
+                TargetStatus::Met => ("met", "Met".to_string()),
+                TargetStatus::Over => (
+                    "over",
+                    format!("{} over", rounded(comparison.actual - comparison.target)),
+                ),
+                TargetStatus::Short => (
+                    "short",
+                    format!("{} short", rounded(comparison.target - comparison.actual)),
+                ),
+            };
+            html! {
+                <tr class={classes!("target-row", class)}>
+                    <td class="target-item" title={name.clone()}>
+                        <Icon icon={image} />
+                        <span>{name}</span>
+                    </td>
+                    <td>{rounded(comparison.target)}</td>
+                    <td>{rounded(comparison.actual)}</td>
+                    <td class="target-status">{status}</td>
+                </tr>
+            }
+        });
+    html! {
+        <table class="TargetScorecard">
+            <tr>
+                <th>{"Item"}</th>
+                <th>{"Target"}</th>
+                <th>{"Actual"}</th>
+                <th>{"Status"}</th>
+            </tr>
+            { for rows }
+        </table>
+    }
+}
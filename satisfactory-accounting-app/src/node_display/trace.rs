@@ -0,0 +1,203 @@
+// Copyright 2024 Zachary Stewart
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+use satisfactory_accounting::accounting::{ItemReport, ProductionTrace, TraceProducer};
+use satisfactory_accounting::database::Database;
+use satisfactory_accounting::factory::ItemNames;
+use yew::prelude::*;
+
+use crate::export::{download_item_report, ReportFormat};
+use crate::node_display::balance::rounded;
+use crate::node_display::icon::Icon;
+use crate::{item_label, use_db, use_item_names};
+
+#[derive(Debug, PartialEq, Properties)]
+pub struct Props {
+    /// Trace to display.
+    pub trace: ProductionTrace,
+}
+
+#[derive(Debug, PartialEq, Properties)]
+pub struct ReportProps {
+    /// Report to display.
+    pub report: ItemReport,
+    /// Callback to show the group at a path in the factory.
+    pub jump_to: Callback<Vec<usize>>,
+}
+
+/// Table of every building which produces or consumes a single item, with the running
+/// net of that item.
+#[function_component]
+pub fn ItemReportView(props: &ReportProps) -> Html {
+    let db = use_db();
+    let names = use_item_names();
+    let report = &props.report;
+    let download = |format| {
+        let db = db.clone();
+        let report = report.clone();
+        Callback::from(move |_| download_item_report(&report, &db, format))
+    };
+    let (name, image) = item_label(db.get(report.item), report.item, &names);
+    let rows = report.rows.iter().map(|row| {
+        let building = match row.building.building.and_then(|id| db.get(id)) {
+            Some(building) => AttrValue::from(building.name.clone()),
+            None => AttrValue::from("Unknown Building"),
+        };
+        let direction = if row.rate > 0.0 {
+            "produces"
+        } else {
+            "consumes"
+        };
+        // Buildings aren't shown separately, so jump to the group containing it.
+        let onclick = {
+            let parent = row.path[..row.path.len().saturating_sub(1)].to_vec();
+            props.jump_to.reform(move |_| parent.clone())
+        };
+        html! {
+            <tr class={classes!("report-row", direction)} title="Show in Factory" {onclick}>
+                <td class="report-location">{row.location.join(" / ")}</td>
+                <td class="report-building">{building}</td>
+                <td class="report-rate">{rounded(row.rate)}</td>
+                <td class="report-net">{rounded(row.running_net)}</td>
+            </tr>
+        }
+    });
+    html! {
+        <div class="ItemReport">
+            <div class="report-header">
+                <Icon icon={image} />
+                <span class="report-title">{name}{" across the factory"}</span>
+                <button class="download" title="Download CSV" onclick={download(ReportFormat::Csv)}>
+                    <span class="material-icons">{"table_view"}</span>
+                </button>
+                <button class="download" title="Download Text" onclick={download(ReportFormat::Text)}>
+                    <span class="material-icons">{"description"}</span>
+                </button>
+            </div>
+            if report.rows.is_empty() {
+                <p>{"No building produces or consumes this item."}</p>
+            } else {
+                <table>
+                    <tr>
+                        <th>{"Location"}</th>
+                        <th>{"Building"}</th>
+                        <th>{"Rate"}</th>
+                        <th>{"Running Net"}</th>
+                    </tr>
+                    { for rows }
+                    <tr class="report-total produces">
+                        <th colspan="3">{"Produced"}</th>
+                        <td class="report-net">{rounded(report.produced())}</td>
+                    </tr>
+                    <tr class="report-total consumes">
+                        <th colspan="3">{"Consumed"}</th>
+                        <td class="report-net">{rounded(report.consumed())}</td>
+                    </tr>
+                    <tr class="report-total">
+                        <th colspan="3">{"Net"}</th>
+                        <td class="report-net">{rounded(report.net())}</td>
+                    </tr>
+                </table>
+            }
+        </div>
+    }
+}
+
+/// Read-only, expandable tree showing where an item comes from.
+#[function_component]
+pub fn ProductionTraceView(props: &Props) -> Html {
+    let db = use_db();
+    let names = use_item_names();
+    let bottleneck = props.trace.bottleneck(&db);
+    html! {
+        <div class="ProductionTrace">
+            { view_trace(&db, &names, &props.trace, bottleneck) }
+        </div>
+    }
+}
+
+/// Display a traced item and the buildings which produce it.
+fn view_trace(
+    db: &Database,
+    names: &ItemNames,
+    trace: &ProductionTrace,
+    bottleneck: Option<&[usize]>,
+) -> Html {
+    let (name, image) = item_label(db.get(trace.item), trace.item, names);
+    let summary = html! {
+        <span class="trace-line">
+            <Icon icon={image} />
+            <span class="trace-name">{name}</span>
+            <span class="trace-rate">{rounded(trace.rate)}</span>
+            if rounded(trace.unsourced) > 0.0 {
+                <span class="trace-note unsourced" title="Not produced in this factory">
+                    {format!("{} imported", rounded(trace.unsourced))}
+                </span>
+            }
+            if trace.cyclic {
+                <span class="trace-note cyclic" title="Already traced further downstream">
+                    <span class="material-icons">{"loop"}</span>
+                </span>
+            }
+        </span>
+    };
+    if trace.producers.is_empty() {
+        html! { <div class="trace-item leaf">{summary}</div> }
+    } else {
+        html! {
+            <details class="trace-item" open={true}>
+                <summary>{summary}</summary>
+                <div class="trace-children">
+                    { for trace.producers.iter().map(|producer| view_producer(db, names, producer, bottleneck)) }
+                </div>
+            </details>
+        }
+    }
+}
+
+/// Display a building contributing to a trace and the inputs it consumes.
+fn view_producer(
+    db: &Database,
+    names: &ItemNames,
+    producer: &TraceProducer,
+    bottleneck: Option<&[usize]>,
+) -> Html {
+    let (name, image): (AttrValue, Option<AttrValue>) =
+        match producer.building.building.and_then(|id| db.get(id)) {
+            Some(building) => (
+                building.name.clone().into(),
+                Some(building.image.clone().into()),
+            ),
+            None => (AttrValue::from("Unknown Building"), None),
+        };
+    let summary = html! {
+        <span class="trace-line">
+            <Icon icon={image} />
+            <span class="trace-name">{name}</span>
+            <span class="trace-rate">{rounded(producer.rate)}</span>
+            if bottleneck == Some(&producer.path[..]) {
+                <span class="trace-note bottleneck"
+                    title="Highest clock speed in this chain, so least room to increase output">
+                    <span class="material-icons">{"speed"}</span>
+                    {format!("Bottleneck ({}%)", rounded(producer.building.settings.clock_speed() * 100.0))}
+                </span>
+            }
+        </span>
+    };
+    if producer.inputs.is_empty() {
+        html! { <div class="trace-producer leaf">{summary}</div> }
+    } else {
+        html! {
+            <details class="trace-producer" open={true}>
+                <summary>{summary}</summary>
+                <div class="trace-children">
+                    { for producer.inputs.iter().map(|input| view_trace(db, names, input, bottleneck)) }
+                </div>
+            </details>
+        }
+    }
+}
@@ -0,0 +1,227 @@
+// Copyright 2024 Zachary Stewart
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+use satisfactory_accounting::accounting::Node;
+use satisfactory_accounting::database::ItemId;
+use yew::prelude::*;
+
+use crate::node_display::balance::balance_style;
+use crate::node_display::building::choose_from_list::{Choice, ChooseFromList};
+use crate::node_display::icon::Icon;
+use crate::{item_label, use_db, use_item_names, use_settings};
+
+/// Most states shown in a trend, counting the current one.
+const TREND_LENGTH: usize = 30;
+
+/// Width of a sparkline in SVG units. Sparklines are scaled to fit their container.
+const WIDTH: f32 = 100.0;
+
+/// Height of a sparkline in SVG units.
+const HEIGHT: f32 = 20.0;
+
+/// Net rate of an item in the most recent of the given factory roots, oldest first.
+/// `roots` should be the states kept in the undo history followed by the current root.
+pub fn trend_series(roots: &[&Node], item: ItemId) -> Vec<f32> {
+    let recent = &roots[roots.len().saturating_sub(TREND_LENGTH)..];
+    recent
+        .iter()
+        .map(|root| root.balance().balances.get(&item).copied().unwrap_or(0.0))
+        .collect()
+}
+
+/// Points of an SVG polyline which draws `values` left to right, scaled to fill the
+/// sparkline. A single value or a flat series is drawn through the middle.
+fn sparkline_points(values: &[f32]) -> String {
+    let min = values.iter().copied().fold(f32::INFINITY, f32::min);
+    let max = values.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+    let y = |value: f32| {
+        if max > min {
+            HEIGHT - (value - min) / (max - min) * HEIGHT
+        } else {
+            HEIGHT / 2.0
+        }
+    };
+    match values {
+        [] => String::new(),
+        [value] => format!("0,{y} {WIDTH},{y}", y = y(*value)),
+        _ => {
+            let step = WIDTH / (values.len() - 1) as f32;
+            values
+                .iter()
+                .enumerate()
+                .map(|(idx, &value)| format!("{},{}", idx as f32 * step, y(value)))
+                .collect::<Vec<_>>()
+                .join(" ")
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Properties)]
+pub struct Props {
+    /// Root of the factory, whose balance provides the items which can be watched.
+    pub root: Node,
+    /// Watched items with their net rates over recent edits, oldest first, per minute.
+    pub series: Vec<(ItemId, Vec<f32>)>,
+    /// Callback to replace the list of watched items.
+    pub set_watched: Callback<Vec<ItemId>>,
+}
+
+/// Sparklines of how the factory's net rates of watched items changed over the edits
+/// kept in the undo history.
+#[function_component]
+pub fn ItemTrends(props: &Props) -> Html {
+    let db = use_db();
+    let names = use_item_names();
+    let settings = use_settings();
+    let choosing = use_state(|| false);
+
+    let watched: Vec<ItemId> = props.series.iter().map(|&(item, _)| item).collect();
+    let rows = props.series.iter().map(|(item, values)| {
+        let item = *item;
+        let (name, image) = item_label(db.get(item), item, &names);
+        let unwatch = {
+            let set_watched = props.set_watched.clone();
+            let watched = watched.clone();
+            Callback::from(move |_| {
+                set_watched.emit(watched.iter().copied().filter(|&w| w != item).collect())
+            })
+        };
+        let format = |rate: f32| {
+            format!(
+                "{}{}",
+                settings.format(settings.rate_unit.convert(rate)),
+                settings.rate_unit.suffix()
+            )
+        };
+        let current = values.last().copied().unwrap_or(0.0);
+        let first = values.first().copied().unwrap_or(0.0);
+        let title = match values.len() {
+            0 | 1 => format!("{name}: {} with no edits to compare", format(current)),
+            len => format!(
+                "{name}: {} to {} over the last {} edits",
+                format(first),
+                format(current),
+                len - 1
+            ),
+        };
+        html! {
+            <div class="trend" key={item.to_string()} {title}>
+                <Icon icon={image} />
+                <span class={classes!("balance-value", balance_style(current))}>
+                    {format(current)}
+                </span>
+                <svg class="sparkline" viewBox={format!("0 0 {WIDTH} {HEIGHT}")}
+                    preserveAspectRatio="none">
+                    <polyline points={sparkline_points(values)} />
+                </svg>
+                <button class="unwatch" title={format!("Stop Watching {name}")}
+                    onclick={unwatch}>
+                    <span class="material-icons">{"close"}</span>
+                </button>
+            </div>
+        }
+    });
+
+    let chooser = (*choosing).then(|| {
+        let choices: Vec<_> = props
+            .root
+            .balance()
+            .balances
+            .keys()
+            .filter(|item| !watched.contains(item))
+            .map(|&item| {
+                let (name, image) = item_label(db.get(item), item, &names);
+                Choice {
+                    id: item,
+                    name,
+                    image: html! { <Icon icon={image} /> },
+                    featured: None,
+                    group: None,
+                }
+            })
+            .collect();
+        let selected = {
+            let set_watched = props.set_watched.clone();
+            let choosing = choosing.clone();
+            let watched = watched.clone();
+            Callback::from(move |item| {
+                choosing.set(false);
+                let mut watched = watched.clone();
+                watched.push(item);
+                set_watched.emit(watched);
+            })
+        };
+        let cancelled = {
+            let choosing = choosing.clone();
+            Callback::from(move |()| choosing.set(false))
+        };
+        html! {
+            <ChooseFromList<ItemId> {choices} {selected} {cancelled} />
+        }
+    });
+    let start_choosing = {
+        let choosing = choosing.clone();
+        Callback::from(move |_| choosing.set(true))
+    };
+    html! {
+        <div class="ItemTrends">
+            { for rows }
+            if let Some(chooser) = chooser {
+                { chooser }
+            } else {
+                <button class="watch" title="Watch an Item's Net Rate over Recent Edits"
+                    onclick={start_choosing}>
+                    <span class="material-icons">{"show_chart"}</span>
+                    {"Watch Item"}
+                </button>
+            }
+        </div>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use satisfactory_accounting::accounting::{BuildNode, Group};
+    use satisfactory_accounting::database::DatabaseVersion;
+    use satisfactory_accounting::sample::iron_plate_constructor;
+
+    use super::*;
+
+    #[test]
+    fn sparkline_fills_chart() {
+        assert_eq!(sparkline_points(&[]), "");
+        assert_eq!(sparkline_points(&[3.0]), "0,10 100,10");
+        assert_eq!(sparkline_points(&[2.0, 2.0]), "0,10 100,10");
+        assert_eq!(sparkline_points(&[0.0, 10.0, 5.0]), "0,20 50,0 100,10");
+    }
+
+    #[test]
+    fn trend_keeps_recent_states() {
+        let db = DatabaseVersion::LATEST.load_database();
+        // Each state has one more constructor making iron plates than the last.
+        let roots: Vec<Node> = (0..TREND_LENGTH as u32 + 5)
+            .map(|copies| {
+                let constructor = iron_plate_constructor(1.0, copies).build_node(&db).unwrap();
+                Group {
+                    children: vec![constructor],
+                    ..Group::empty()
+                }
+                .into()
+            })
+            .collect();
+        let roots: Vec<&Node> = roots.iter().collect();
+        let series = trend_series(&roots, "Desc_IronPlate_C".into());
+        let expected: Vec<f32> = (5..TREND_LENGTH + 5)
+            .map(|copies| copies as f32 * 20.0)
+            .collect();
+        assert_eq!(series, expected);
+        assert_eq!(
+            trend_series(&roots, ItemId::water()),
+            vec![0.0; TREND_LENGTH]
+        );
+    }
+}
@@ -0,0 +1,233 @@
+// Copyright 2024 Zachary Stewart
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//! Short-lived notices telling the user why something they did didn't work.
+
+use satisfactory_accounting::accounting::{BuildError, Node};
+use satisfactory_accounting::database::Database;
+use yew::prelude::*;
+
+/// How long a notice stays up before it dismisses itself, in milliseconds.
+pub const NOTICE_TIMEOUT_MS: u32 = 6_000;
+/// Most notices shown at once. Older notices are dropped to make room for new ones.
+const MAX_SHOWN: usize = 4;
+
+/// Message to show the user.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Notice {
+    /// Readable description of what went wrong.
+    pub message: AttrValue,
+    /// Path to the node the notice is about, if any.
+    pub path: Option<Vec<usize>>,
+}
+
+impl Notice {
+    /// Create a notice which isn't about any particular node.
+    pub fn new(message: impl Into<AttrValue>) -> Self {
+        Self {
+            message: message.into(),
+            path: None,
+        }
+    }
+
+    /// Create a notice explaining why a building couldn't be built.
+    pub fn build_error(error: BuildError, db: &Database) -> Self {
+        Self::new(error.describe(db))
+    }
+
+    /// Attach the path of the node the notice is about.
+    pub fn at(self, path: Vec<usize>) -> Self {
+        Self {
+            path: Some(path),
+            ..self
+        }
+    }
+}
+
+/// Handle for showing notices, provided to the whole app through context.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Notifier(Callback<Notice>);
+
+impl Notifier {
+    /// Create a notifier which sends notices to the given callback.
+    pub fn new(callback: Callback<Notice>) -> Self {
+        Self(callback)
+    }
+
+    /// Show a notice to the user.
+    pub fn notify(&self, notice: Notice) {
+        self.0.emit(notice);
+    }
+}
+
+/// A notice currently being shown.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Shown {
+    /// ID used to dismiss this notice.
+    pub id: u64,
+    /// The notice.
+    pub notice: Notice,
+    /// Names of the groups containing the node the notice is about, from when it was
+    /// shown.
+    pub location: Option<AttrValue>,
+}
+
+/// Notices currently being shown, oldest first.
+#[derive(Debug, Default)]
+pub struct Notices {
+    /// ID to give the next notice.
+    next_id: u64,
+    /// Notices being shown.
+    shown: Vec<Shown>,
+}
+
+impl Notices {
+    /// Show a new notice, dropping the oldest if too many are shown. Returns the ID of
+    /// the new notice.
+    pub fn push(&mut self, root: &Node, notice: Notice) -> u64 {
+        let id = self.next_id;
+        self.next_id += 1;
+        let location = notice.path.as_deref().and_then(|path| location(root, path));
+        self.shown.push(Shown {
+            id,
+            notice,
+            location,
+        });
+        if self.shown.len() > MAX_SHOWN {
+            self.shown.remove(0);
+        }
+        id
+    }
+
+    /// Stop showing the notice with the given ID. Returns false if it was already gone.
+    pub fn dismiss(&mut self, id: u64) -> bool {
+        let len = self.shown.len();
+        self.shown.retain(|shown| shown.id != id);
+        self.shown.len() != len
+    }
+
+    /// Get the notices being shown, oldest first.
+    pub fn shown(&self) -> &[Shown] {
+        &self.shown
+    }
+}
+
+/// Trail of names of the groups containing the node at `path`, or None if the path
+/// doesn't lead to a node in a group.
+fn location(root: &Node, path: &[usize]) -> Option<AttrValue> {
+    root.get_at(path)?;
+    let names: Vec<_> = (0..path.len())
+        .filter_map(|depth| root.get_at(&path[..depth])?.group())
+        .map(|group| match &*group.name {
+            "" => "unnamed",
+            name => name,
+        })
+        .collect();
+    (!names.is_empty()).then(|| names.join(" \u{203a} ").into())
+}
+
+#[derive(Debug, PartialEq, Properties)]
+pub struct Props {
+    /// Notices to show, oldest first.
+    pub notices: Vec<Shown>,
+    /// Callback to dismiss the notice with the given ID.
+    pub dismiss: Callback<u64>,
+    /// Callback to jump to the node at the given path.
+    pub jump_to: Callback<Vec<usize>>,
+}
+
+/// Stack of notices in the corner of the app.
+#[function_component]
+pub fn Notifications(props: &Props) -> Html {
+    let notices = props.notices.iter().map(|shown| {
+        let dismiss = {
+            let dismiss = props.dismiss.clone();
+            let id = shown.id;
+            Callback::from(move |_| dismiss.emit(id))
+        };
+        let jump = shown.notice.path.clone().map(|path| {
+            let jump_to = props.jump_to.clone();
+            let dismiss = props.dismiss.clone();
+            let id = shown.id;
+            Callback::from(move |_| {
+                dismiss.emit(id);
+                jump_to.emit(path.clone());
+            })
+        });
+        html! {
+            <div class="notice" key={shown.id}>
+                <span class="material-icons warning">{"warning"}</span>
+                <div class="notice-text">
+                    <span class="notice-message">{&shown.notice.message}</span>
+                    if let Some(location) = &shown.location {
+                        <span class="notice-location">{"in "}{location}</span>
+                    }
+                </div>
+                if let Some(onclick) = jump {
+                    <button class="jump" title="Jump to Node" {onclick}>
+                        <span class="material-icons">{"arrow_forward"}</span>
+                    </button>
+                }
+                <button class="dismiss" title="Dismiss" onclick={dismiss}>
+                    <span class="material-icons">{"close"}</span>
+                </button>
+            </div>
+        }
+    });
+    html! {
+        <div class="Notifications" aria-live="polite">
+            { for notices }
+        </div>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use satisfactory_accounting::accounting::{Building, Group};
+
+    use super::*;
+
+    #[test]
+    fn notices_are_capped_and_dismissed() {
+        let root = Group::empty_node();
+        let mut notices = Notices::default();
+        let ids: Vec<_> = (0..MAX_SHOWN + 2)
+            .map(|i| notices.push(&root, Notice::new(format!("notice {i}"))))
+            .collect();
+        // The oldest notices make room for new ones.
+        let shown: Vec<_> = notices.shown().iter().map(|shown| shown.id).collect();
+        assert_eq!(shown, ids[2..]);
+        assert!(notices.dismiss(ids[3]));
+        assert!(!notices.dismiss(ids[3]));
+        assert!(!notices.dismiss(ids[0]));
+        assert_eq!(notices.shown().len(), MAX_SHOWN - 1);
+    }
+
+    #[test]
+    fn locate_notices() {
+        let root: Node = Group {
+            name: "Factory".into(),
+            children: vec![Group {
+                children: vec![Building::empty_node()],
+                ..Group::empty()
+            }
+            .into()],
+            ..Group::empty()
+        }
+        .into();
+        let mut notices = Notices::default();
+        notices.push(&root, Notice::new("building").at(vec![0, 0]));
+        notices.push(&root, Notice::new("root").at(vec![]));
+        notices.push(&root, Notice::new("gone").at(vec![3]));
+        let locations: Vec<_> = notices
+            .shown()
+            .iter()
+            .map(|shown| shown.location.as_deref())
+            .collect();
+        assert_eq!(locations, [Some("Factory \u{203a} unnamed"), None, None]);
+    }
+}
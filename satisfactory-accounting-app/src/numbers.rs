@@ -0,0 +1,195 @@
+// Copyright 2024 Zachary Stewart
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//! Parsing numbers entered by the user and formatting the ones shown to them.
+
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+/// Character the user types between the whole and fractional parts of a number.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub enum DecimalSeparator {
+    /// `66.67`, as in English.
+    #[default]
+    Period,
+    /// `66,67`, as in much of Europe. Periods are still accepted, since values shown for
+    /// editing use them.
+    Comma,
+}
+
+impl DecimalSeparator {
+    /// Parse a decimal number entered by the user. Surrounding whitespace is ignored.
+    pub fn parse<T: FromStr>(self, text: &str) -> Option<T> {
+        let text = text.trim();
+        match self {
+            Self::Period => text.parse().ok(),
+            Self::Comma => text.replace(',', ".").parse().ok(),
+        }
+    }
+}
+
+impl DecimalSeparator {
+    /// Character used between groups of thousands when formatting with this separator.
+    fn thousands(self) -> char {
+        match self {
+            Self::Period => ',',
+            Self::Comma => '.',
+        }
+    }
+
+    /// Character used before the fractional part when formatting with this separator.
+    fn decimal(self) -> char {
+        match self {
+            Self::Period => '.',
+            Self::Comma => ',',
+        }
+    }
+}
+
+/// Number of decimal places shown in balances.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Precision(pub u8);
+
+impl Precision {
+    /// Most decimal places allowed, beyond which f32 rates are mostly float error.
+    pub const MAX: u8 = 6;
+}
+
+impl Default for Precision {
+    fn default() -> Self {
+        Self(2)
+    }
+}
+
+/// Format a number for display, rounded to `precision` decimal places with trailing
+/// zeros trimmed and thousands grouped. Values which round to zero are shown as `0`, so
+/// float error doesn't show up as `-0`.
+pub fn format_number(value: f32, precision: Precision, separator: DecimalSeparator) -> String {
+    let places = precision.0.min(Precision::MAX) as usize;
+    // Round halves away from zero explicitly, since formatting rounds them to even.
+    let factor = 10f64.powi(places as i32);
+    let text = format!(
+        "{:.places$}",
+        ((value as f64).abs() * factor).round() / factor
+    );
+    let (whole, fraction) = text.split_once('.').unwrap_or((&text, ""));
+    let fraction = fraction.trim_end_matches('0');
+    if whole.chars().all(|c| c == '0') && fraction.is_empty() {
+        return "0".into();
+    }
+    let mut result = String::new();
+    if value < 0.0 {
+        result.push('-');
+    }
+    for (i, digit) in whole.chars().enumerate() {
+        if i > 0 && (whole.len() - i) % 3 == 0 {
+            result.push(separator.thousands());
+        }
+        result.push(digit);
+    }
+    if !fraction.is_empty() {
+        result.push(separator.decimal());
+        result.push_str(fraction);
+    }
+    result
+}
+
+/// Unit rates of items are shown and entered in. Balances are always per minute
+/// internally; this only affects display.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub enum RateUnit {
+    /// Items per minute, as the game shows them.
+    #[default]
+    PerMinute,
+    /// Items per second.
+    PerSecond,
+}
+
+impl RateUnit {
+    /// Convert a per-minute rate into this unit for display.
+    pub fn convert(self, rate: f32) -> f32 {
+        match self {
+            Self::PerMinute => rate,
+            Self::PerSecond => rate / 60.0,
+        }
+    }
+
+    /// Convert a rate entered in this unit back to per minute.
+    pub fn to_per_minute(self, rate: f32) -> f32 {
+        match self {
+            Self::PerMinute => rate,
+            Self::PerSecond => rate * 60.0,
+        }
+    }
+
+    /// Suffix to show after a rate in this unit.
+    pub fn suffix(self) -> &'static str {
+        match self {
+            Self::PerMinute => "/min",
+            Self::PerSecond => "/s",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn period_separator() {
+        assert_eq!(DecimalSeparator::Period.parse::<f32>("66.67"), Some(66.67));
+        assert_eq!(DecimalSeparator::Period.parse::<f32>(" 2 "), Some(2.0));
+        assert_eq!(DecimalSeparator::Period.parse::<f32>("66,67"), None);
+    }
+
+    #[test]
+    fn comma_separator() {
+        assert_eq!(DecimalSeparator::Comma.parse::<f32>("66,67"), Some(66.67));
+        assert_eq!(DecimalSeparator::Comma.parse::<f32>("66.67"), Some(66.67));
+        assert_eq!(DecimalSeparator::Comma.parse::<f32>("0,5"), Some(0.5));
+        assert_eq!(DecimalSeparator::Comma.parse::<f32>("1,2,3"), None);
+        assert_eq!(DecimalSeparator::Comma.parse::<f32>("abc"), None);
+    }
+
+    #[test]
+    fn rate_units() {
+        assert_eq!(RateUnit::PerMinute.convert(30.0), 30.0);
+        assert_eq!(RateUnit::PerSecond.convert(30.0), 0.5);
+        assert_eq!(RateUnit::PerSecond.to_per_minute(0.5), 30.0);
+        assert_eq!(RateUnit::PerMinute.to_per_minute(0.5), 0.5);
+    }
+
+    #[test]
+    fn format_numbers() {
+        let two = Precision::default();
+        let period = DecimalSeparator::Period;
+        assert_eq!(format_number(13.333333, two, period), "13.33");
+        assert_eq!(format_number(30.0, two, period), "30");
+        assert_eq!(format_number(7.5, two, period), "7.5");
+        assert_eq!(format_number(-2.25, Precision(1), period), "-2.3");
+        assert_eq!(format_number(1234567.0, two, period), "1,234,567");
+        assert_eq!(format_number(-1234.5, two, period), "-1,234.5");
+        assert_eq!(format_number(999.999, two, period), "1,000");
+        assert_eq!(format_number(12.5, Precision(0), period), "13");
+        assert_eq!(
+            format_number(1234.5, two, DecimalSeparator::Comma),
+            "1.234,5"
+        );
+    }
+
+    #[test]
+    fn format_tiny_numbers_as_zero() {
+        let two = Precision::default();
+        let period = DecimalSeparator::Period;
+        assert_eq!(format_number(-0.0000001, two, period), "0");
+        assert_eq!(format_number(0.004, two, period), "0");
+        assert_eq!(format_number(-0.0, two, period), "0");
+        assert_eq!(format_number(0.004, Precision(3), period), "0.004");
+    }
+}
@@ -0,0 +1,205 @@
+// Copyright 2024 Zachary Stewart
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//! Sharing factories outside the app, either as links with the tree encoded in the URL
+//! fragment or as text copied to the system clipboard.
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use satisfactory_accounting::accounting::Node;
+use satisfactory_accounting::compact::{self, CompactError};
+use satisfactory_accounting::factory::Factory;
+use thiserror::Error;
+
+/// Prefix of URL fragments which hold a shared factory.
+const SHARE_PREFIX: &str = "factory=";
+
+/// Prefix of clipboard text which holds a copied node. Both this and the compact format's
+/// own header must match, so text from other apps or from a newer version is rejected.
+const CLIPBOARD_PREFIX: &str = "satisfactory-accounting:";
+
+/// Length of link beyond which some browsers, chat apps, and link shorteners may truncate
+/// it.
+pub const MAX_SHARE_URL_LEN: usize = 8000;
+
+/// Error when reading a shared factory from a URL fragment or the clipboard.
+#[derive(Error, Debug)]
+pub enum ShareError {
+    #[error("Clipboard does not contain a node copied from Satisfactory Accounting.")]
+    NotCopiedNode,
+    #[error("Shared data is not valid base64: {0}")]
+    Base64(#[from] base64::DecodeError),
+    #[error("Shared data is not a valid factory: {0}")]
+    Compact(#[from] CompactError),
+}
+
+/// Encode a factory as a URL fragment, without the leading `#`.
+pub fn encode_share(factory: &Factory) -> Result<String, CompactError> {
+    let encoded = compact::encode(factory)?;
+    Ok(format!("{SHARE_PREFIX}{}", URL_SAFE_NO_PAD.encode(encoded)))
+}
+
+/// Decode the factory from a URL fragment, which may include the leading `#`. Returns
+/// None if the fragment isn't a shared factory at all. Older links hold just the root
+/// node, which is given empty metadata and no database version.
+pub fn decode_share(fragment: &str) -> Option<Result<Factory, ShareError>> {
+    let fragment = fragment.strip_prefix('#').unwrap_or(fragment);
+    let encoded = fragment.strip_prefix(SHARE_PREFIX)?;
+    let decoded = URL_SAFE_NO_PAD.decode(encoded).map_err(ShareError::from);
+    Some(decoded.and_then(|data| {
+        compact::decode(&data).or_else(|_| {
+            Ok(Factory {
+                metadata: Default::default(),
+                item_names: Default::default(),
+                database_version: None,
+                root: compact::decode(&data)?,
+            })
+        })
+    }))
+}
+
+/// Encode a node tree as text to put on the clipboard.
+pub fn encode_clipboard(node: &Node) -> Result<String, CompactError> {
+    let encoded = compact::encode(node)?;
+    Ok(format!(
+        "{CLIPBOARD_PREFIX}{}",
+        URL_SAFE_NO_PAD.encode(encoded)
+    ))
+}
+
+/// Decode a node tree from text read from the clipboard.
+pub fn decode_clipboard(text: &str) -> Result<Node, ShareError> {
+    let encoded = text
+        .trim()
+        .strip_prefix(CLIPBOARD_PREFIX)
+        .ok_or(ShareError::NotCopiedNode)?;
+    let data = URL_SAFE_NO_PAD.decode(encoded)?;
+    Ok(compact::decode(&data)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use satisfactory_accounting::accounting::{BuildNode, Group};
+    use satisfactory_accounting::database::DatabaseVersion;
+    use satisfactory_accounting::factory::FactoryMetadata;
+    use satisfactory_accounting::sample::iron_plate_constructor;
+
+    use super::*;
+
+    #[test]
+    fn share_round_trip() {
+        let db = DatabaseVersion::LATEST.load_database();
+        let constructor = iron_plate_constructor(1.0, 3).build_node(&db).unwrap();
+        let root = Group {
+            name: "Plates & Rods".into(),
+            children: vec![
+                constructor,
+                Group {
+                    name: "Empty".into(),
+                    ..Group::empty()
+                }
+                .into(),
+            ],
+            ..Group::empty()
+        };
+        let metadata = FactoryMetadata {
+            title: "Plate Works".into(),
+            author: "Pioneer".into(),
+            ..Default::default()
+        };
+        let factory = Factory::export(&root, metadata, &db, &Default::default());
+
+        let fragment = encode_share(&factory).unwrap();
+        assert!(fragment.starts_with(SHARE_PREFIX));
+        // The fragment must survive being put in a URL unescaped.
+        assert!(fragment
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '=' | '-' | '_')));
+        assert_eq!(decode_share(&fragment).unwrap().unwrap(), factory);
+        assert_eq!(
+            decode_share(&format!("#{fragment}")).unwrap().unwrap(),
+            factory
+        );
+    }
+
+    #[test]
+    fn decodes_links_holding_only_the_root() {
+        let root: Node = Group {
+            name: "Old Link".into(),
+            ..Group::empty()
+        }
+        .into();
+        let encoded = URL_SAFE_NO_PAD.encode(compact::encode(&root).unwrap());
+        let factory = decode_share(&format!("#{SHARE_PREFIX}{encoded}"))
+            .unwrap()
+            .unwrap();
+        assert_eq!(factory.root, root);
+        assert!(factory.metadata.is_empty());
+        assert_eq!(factory.database_version, None);
+    }
+
+    #[test]
+    fn clipboard_round_trip() {
+        let root: Node = Group {
+            name: "Copied".into(),
+            children: vec![Group::empty_node()],
+            ..Group::empty()
+        }
+        .into();
+        let text = encode_clipboard(&root).unwrap();
+        assert_eq!(decode_clipboard(&text).unwrap(), root);
+        // Whitespace picked up while copying between apps is ignored.
+        assert_eq!(decode_clipboard(&format!("  {text}\n")).unwrap(), root);
+    }
+
+    #[test]
+    fn rejects_foreign_clipboard_text() {
+        assert!(matches!(
+            decode_clipboard("some copied prose"),
+            Err(ShareError::NotCopiedNode)
+        ));
+        // A share link isn't clipboard data, even though it holds the same encoding.
+        let db = DatabaseVersion::LATEST.load_database();
+        let factory = Factory::export(
+            &Group::empty(),
+            Default::default(),
+            &db,
+            &Default::default(),
+        );
+        let fragment = encode_share(&factory).unwrap();
+        assert!(matches!(
+            decode_clipboard(&fragment),
+            Err(ShareError::NotCopiedNode)
+        ));
+        // Data from an unknown version of the compact format is rejected by its header.
+        let future = URL_SAFE_NO_PAD.encode(b"SAC\x09data");
+        assert!(matches!(
+            decode_clipboard(&format!("{CLIPBOARD_PREFIX}{future}")),
+            Err(ShareError::Compact(CompactError::UnsupportedVersion(9)))
+        ));
+    }
+
+    #[test]
+    fn ignores_other_fragments() {
+        assert!(decode_share("").is_none());
+        assert!(decode_share("#").is_none());
+        assert!(decode_share("#section-2").is_none());
+    }
+
+    #[test]
+    fn rejects_corrupt_links() {
+        assert!(matches!(
+            decode_share("#factory=not base64!"),
+            Some(Err(ShareError::Base64(_)))
+        ));
+        let not_compact = URL_SAFE_NO_PAD.encode(b"{\"name\": \"json\"}");
+        assert!(matches!(
+            decode_share(&format!("#factory={not_compact}")),
+            Some(Err(ShareError::Compact(_)))
+        ));
+    }
+}
@@ -0,0 +1,144 @@
+// Copyright 2024 Zachary Stewart
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//! Times balance computation on a factory with 1000 buildings, to make regressions in
+//! how much work an edit or a render does visible.
+//!
+//! Run with `cargo run --release -p satisfactory-accounting --example balance_bench`.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use satisfactory_accounting::accounting::{
+    BuildNode, Building, BuildingSettings, GeneratorSettings, Group, ManufacturerSettings, Node,
+};
+use satisfactory_accounting::database::{Database, DatabaseVersion};
+
+/// Number of groups at each of the three levels of the tree. The leaves are buildings,
+/// so the tree has `FANOUT.pow(3)` buildings.
+const FANOUT: usize = 10;
+
+/// Number of times each measurement is repeated.
+const ITERATIONS: u32 = 100;
+
+/// A building with the given settings, built with `db`.
+fn building(db: &Database, id: &str, settings: impl Into<BuildingSettings>) -> Node {
+    Building {
+        building: Some(id.into()),
+        settings: settings.into(),
+        ..Default::default()
+    }
+    .build_node(db)
+    .expect("benchmark building to be valid")
+}
+
+fn constructor(db: &Database, recipe: &str, clock_speed: f32) -> Node {
+    building(
+        db,
+        "Desc_ConstructorMk1_C",
+        ManufacturerSettings {
+            recipe: Some(recipe.into()),
+            clock_speed,
+            ..Default::default()
+        },
+    )
+}
+
+fn coal_generator(db: &Database) -> Node {
+    building(
+        db,
+        "Desc_GeneratorCoal_C",
+        GeneratorSettings {
+            fuel: Some("Desc_Coal_C".into()),
+            clock_speed: 1.0,
+        },
+    )
+}
+
+fn group(name: String, children: Vec<Node>) -> Node {
+    Group {
+        name: name.into(),
+        children,
+        ..Group::empty()
+    }
+    .into()
+}
+
+/// A three-level tree of groups with `FANOUT` children each, mixing manufacturers and
+/// generators so both item and power balances are exercised.
+fn factory(db: &Database) -> Node {
+    let recipes = ["Recipe_IronPlate_C", "Recipe_IronRod_C", "Recipe_Screw_C"];
+    let sections = (0..FANOUT)
+        .map(|i| {
+            let lines = (0..FANOUT)
+                .map(|j| {
+                    let buildings = (0..FANOUT)
+                        .map(|k| match k % 4 {
+                            3 => coal_generator(db),
+                            k => constructor(db, recipes[k], 0.5 + (i + j) as f32 * 0.05),
+                        })
+                        .collect();
+                    group(format!("Line {j}"), buildings)
+                })
+                .collect();
+            group(format!("Section {i}"), lines)
+        })
+        .collect();
+    group("Factory".to_owned(), sections)
+}
+
+/// Run `f` `ITERATIONS` times and print the mean time per run.
+fn time(label: &str, mut f: impl FnMut()) {
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        f();
+    }
+    let mean = start.elapsed() / ITERATIONS;
+    println!("{label:<48} {:>10.1?}", mean.max(Duration::from_nanos(1)));
+}
+
+fn main() {
+    let db = DatabaseVersion::LATEST.load_database();
+    let root = factory(&db);
+    let nodes = root.iter().count();
+    println!("Tree with {nodes} nodes, mean of {ITERATIONS} runs:");
+
+    time("Build the whole tree", || {
+        black_box(factory(&db));
+    });
+    time("Rebuild every balance", || {
+        black_box(root.rebuild(&db));
+    });
+    let edited = constructor(&db, "Recipe_IronPlate_C", 2.0);
+    time("Edit one building", || {
+        black_box(root.replace_at(&[4, 4, 0], edited.clone()).unwrap());
+    });
+    time("Read every balance", || {
+        for node in root.iter() {
+            black_box(node.balance());
+        }
+    });
+    // Fresh trees, so none of their power splits have been computed yet. They are kept
+    // until the end so dropping them isn't timed.
+    let mut fresh: Vec<_> = (0..ITERATIONS).map(|_| root.rebuild(&db)).collect();
+    let mut used = Vec::with_capacity(fresh.len());
+    time("Power split of every node, first time", || {
+        let root = fresh.pop().unwrap();
+        for node in root.iter() {
+            black_box(node.power_split());
+        }
+        used.push(root);
+    });
+    for node in root.iter() {
+        node.power_split();
+    }
+    time("Power split of every node, memoized", || {
+        for node in root.iter() {
+            black_box(node.power_split());
+        }
+    });
+}
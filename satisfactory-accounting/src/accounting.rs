@@ -5,6 +5,9 @@
 //   You may obtain a copy of the License at
 //
 //       http://www.apache.org/licenses/LICENSE-2.0
+use std::cell::OnceCell;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet};
 use std::fmt;
 use std::iter::FusedIterator;
 use std::rc::Rc;
@@ -14,13 +17,20 @@ use serde::{Deserialize, Deserializer, Serialize};
 use thiserror::Error;
 use uuid::Uuid;
 
-pub use self::balance::Balance;
+pub use self::balance::{Balance, SinkValue};
+pub use self::color::{GroupColor, ParseColorError};
+pub use self::ratio::RatioChain;
+pub use self::trace::{ItemReport, ItemReportRow, ProductionTrace, TraceProducer};
 use crate::database::{
-    BuildingId, BuildingKind, BuildingKindId, Database, Generator, Geothermal, ItemId,
-    Manufacturer, Miner, Pump, RecipeId, Station,
+    BuildingId, BuildingKind, BuildingKindId, BuildingType, Database, DatabaseVersion, Generator,
+    Geothermal, Item, ItemId, Manufacturer, Miner, Pump, Recipe, RecipeId, Station,
 };
+use crate::logistics::{BeltTier, Lines, PipeTier};
 
 mod balance;
+mod color;
+mod ratio;
+mod trace;
 
 /// Trait for types which can visit groups when creating copies.
 pub trait GroupCopyVisitor {
@@ -54,6 +64,10 @@ pub enum BuildError {
     UnknownItem(ItemId),
     #[error("Item ID {0} is not a fuel.")]
     NotFuel(ItemId),
+    #[error("Fuel {0} has no energy, so it cannot power a generator.")]
+    FuelHasNoEnergy(ItemId),
+    #[error("Generator would need to burn {rate:.1} of fuel {item} per minute, which is more than a single input can supply.")]
+    ImplausibleFuelRate { item: ItemId, rate: f32 },
     #[error("Recipe {recipe} is not compatible with building {building}.")]
     IncompatibleRecipe {
         recipe: RecipeId,
@@ -61,6 +75,12 @@ pub enum BuildError {
     },
     #[error("Item {item} is not compatible with building {building}.")]
     IncompatibleItem { item: ItemId, building: BuildingId },
+    #[error("Building {building} has {somersloops} Somersloops but only {slots} slots for them.")]
+    TooManySomersloops {
+        building: BuildingId,
+        somersloops: u32,
+        slots: u32,
+    },
     #[error("Mismatched BuildingKind between Building ({settings_kind:?}) and BuildingType ({type_kind:?}).")]
     MismatchedKind {
         /// BuildingKindId of the settings for the [`Building`].
@@ -76,12 +96,178 @@ impl BuildError {
     pub fn into_warning_node(self, kind: impl Into<NodeKind>) -> Node {
         Node::warn(kind, self)
     }
+
+    /// Describe this error for the user, naming the buildings, recipes, and items involved
+    /// rather than giving their IDs. IDs are only used for things missing from the
+    /// database.
+    pub fn describe(&self, database: &Database) -> String {
+        let building = |id: BuildingId| {
+            database
+                .get(id)
+                .map_or_else(|| id.to_string(), |b| b.name.to_string())
+        };
+        let recipe = |id: RecipeId| {
+            database
+                .get(id)
+                .map_or_else(|| id.to_string(), |r| r.name.to_string())
+        };
+        let item = |id: ItemId| {
+            database
+                .get(id)
+                .map_or_else(|| id.to_string(), |i| i.name.to_string())
+        };
+        match *self {
+            Self::NotFuel(id) => format!("{} is not a fuel.", item(id)),
+            Self::FuelHasNoEnergy(id) => {
+                format!(
+                    "{} has no energy, so it cannot power a generator.",
+                    item(id)
+                )
+            }
+            Self::ImplausibleFuelRate { item: id, rate } => format!(
+                "Generator would need to burn {rate:.1} {} per minute, which is more than a \
+                single input can supply.",
+                item(id)
+            ),
+            Self::IncompatibleRecipe {
+                recipe: recipe_id,
+                building: building_id,
+            } => format!(
+                "{} cannot use the recipe {}.",
+                building(building_id),
+                recipe(recipe_id)
+            ),
+            Self::IncompatibleItem {
+                item: item_id,
+                building: building_id,
+            } => format!("{} cannot use {}.", building(building_id), item(item_id)),
+            Self::TooManySomersloops {
+                building: building_id,
+                somersloops,
+                slots,
+            } => format!(
+                "{} only has room for {slots} Somersloops, not {somersloops}.",
+                building(building_id)
+            ),
+            Self::UnknownBuilding(_)
+            | Self::UnknownRecipe(_)
+            | Self::UnknownItem(_)
+            | Self::MismatchedKind { .. } => self.to_string(),
+        }
+    }
+}
+
+/// Error from following a path of child indexes through a tree of [`Node`]s. `depth` is
+/// the position in the path where the error was found.
+#[derive(Error, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PathError {
+    #[error("Index {index} at depth {depth} is out of bounds for a group with {len} children.")]
+    OutOfBounds {
+        depth: usize,
+        index: usize,
+        len: usize,
+    },
+    #[error("Node at depth {depth} is a building, which has no children.")]
+    NotAGroup { depth: usize },
+    #[error("Path is empty, but must refer to a child.")]
+    Empty,
+}
+
+/// A building which failed to build, found by [`Node::rebuild_with_report`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct NodeError {
+    /// Path from the root of the rebuilt tree to the building.
+    pub path: Vec<usize>,
+    /// Error found when building it.
+    pub error: BuildError,
+}
+
+/// Result of rebuilding a whole tree with [`Node::rebuild_with_report`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct RebuildReport {
+    /// The rebuilt tree.
+    pub root: Node,
+    /// Every building in the tree which failed to build, in tree order.
+    pub errors: Vec<NodeError>,
+}
+
+/// Choice the user has to make before a building does anything.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MissingChoice {
+    /// No building type is selected.
+    Building,
+    /// A manufacturer has no recipe.
+    Recipe,
+    /// A miner or pump has no resource.
+    Resource,
+    /// A generator or station has no fuel.
+    Fuel,
+}
+
+impl fmt::Display for MissingChoice {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Self::Building => "No building selected",
+            Self::Recipe => "No recipe selected",
+            Self::Resource => "No resource selected",
+            Self::Fuel => "No fuel selected",
+        })
+    }
+}
+
+/// A building which is missing a choice, found by [`Node::unconfigured_buildings`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnconfiguredBuilding {
+    /// Path from the root of the tree to the building.
+    pub path: Vec<usize>,
+    /// Choice the building is missing.
+    pub missing: MissingChoice,
+}
+
+/// Direction an item is declared to flow through a group's interface.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Port {
+    /// The group takes the item in from outside.
+    Input,
+    /// The group makes the item for use outside.
+    Output,
+}
+
+/// Way a group's balance breaks its declared interface, found by
+/// [`Node::interface_violations`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InterfaceViolation {
+    /// The group consumes an item which isn't a declared input.
+    UndeclaredInput(ItemId),
+    /// The group has a surplus of an item which isn't a declared output or a byproduct.
+    UndeclaredOutput(ItemId),
+    /// The group doesn't consume a declared input.
+    UnusedInput(ItemId),
+    /// The group doesn't have a surplus of a declared output.
+    MissingOutput(ItemId),
+}
+
+impl InterfaceViolation {
+    /// Item the violation is about.
+    pub fn item(self) -> ItemId {
+        match self {
+            Self::UndeclaredInput(item)
+            | Self::UndeclaredOutput(item)
+            | Self::UnusedInput(item)
+            | Self::MissingOutput(item) => item,
+        }
+    }
 }
 
 /// Accounting node. Each node has a [`Balance`] telling how much of each item it produces
 /// or consumes and how much power it generates or uses.
 ///
-/// Nodes are immutable. Modifying them requires creating new nodes.
+/// Nodes are immutable. Modifying them requires creating new nodes. The balance is
+/// computed once, when the node is created, from the already computed balances of its
+/// children. Editing a node only creates new nodes along the path to it; unchanged
+/// subtrees are shared, so their balances are never recomputed. Other totals over the
+/// subtree, like [`Node::power_split`], are computed the first time they're needed and
+/// kept with the node.
 #[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct Node(Rc<NodeInner>);
 
@@ -119,6 +305,20 @@ impl Node {
             balance,
             warning: None,
             children_had_warnings,
+            backed_up: BTreeMap::new(),
+            power_split: Memo::default(),
+        }))
+    }
+
+    /// Create a new building node whose outputs were limited by output caps.
+    fn capped(building: Building, balance: Balance, backed_up: BTreeMap<ItemId, f32>) -> Node {
+        Self(Rc::new(NodeInner {
+            kind: building.into(),
+            balance,
+            warning: None,
+            children_had_warnings: false,
+            backed_up,
+            power_split: Memo::default(),
         }))
     }
 
@@ -131,9 +331,18 @@ impl Node {
             balance: Balance::empty(),
             warning: Some(warning),
             children_had_warnings,
+            backed_up: BTreeMap::new(),
+            power_split: Memo::default(),
         }))
     }
 
+    /// Returns true if this node and `other` share the same underlying data, so they are
+    /// equal without comparing their contents. Nodes which are not the same may still be
+    /// equal.
+    pub fn same_as(&self, other: &Node) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+
     /// Get the kind of this node.
     pub fn kind(&self) -> &NodeKind {
         &self.0.kind
@@ -149,6 +358,12 @@ impl Node {
         self.0.warning
     }
 
+    /// Get how much of each item this building would produce beyond its output cap, per
+    /// minute. This output backs up rather than being delivered. Always empty for groups.
+    pub fn backed_up(&self) -> &BTreeMap<ItemId, f32> {
+        &self.0.backed_up
+    }
+
     /// Returns true if any child of this node (but not the node itself) has a build
     /// warning. Always false for buildings, since buildings cannot have children.
     pub fn children_had_warnings(&self) -> bool {
@@ -160,6 +375,58 @@ impl Node {
         self.kind().group()
     }
 
+    /// Whether this node itself is locked against deletion.
+    pub fn delete_locked(&self) -> bool {
+        match self.kind() {
+            NodeKind::Group(group) => group.delete_locked,
+            NodeKind::Building(building) => building.delete_locked,
+        }
+    }
+
+    /// Get the tags the user attached to this node.
+    pub fn tags(&self) -> &[IString] {
+        match self.kind() {
+            NodeKind::Group(group) => &group.tags,
+            NodeKind::Building(building) => &building.tags,
+        }
+    }
+
+    /// Get the note the user wrote about this node, which may be empty.
+    pub fn note(&self) -> &IString {
+        match self.kind() {
+            NodeKind::Group(group) => &group.note,
+            NodeKind::Building(building) => &building.note,
+        }
+    }
+
+    /// Whether any of this node's own tags contains `query`, ignoring case.
+    pub fn has_tag(&self, query: &str) -> bool {
+        let query = query.trim().to_lowercase();
+        self.tags()
+            .iter()
+            .any(|tag| tag.to_lowercase().contains(&query))
+    }
+
+    /// Whether this node or any of its descendants has a tag containing `query`, ignoring
+    /// case.
+    pub fn contains_tag(&self, query: &str) -> bool {
+        self.iter().any(|node| node.has_tag(query))
+    }
+
+    /// Whether deleting this node would delete any locked node, either this node or one
+    /// of its descendants.
+    pub fn deletion_blocked(&self) -> bool {
+        self.iter().any(|node| node.delete_locked())
+    }
+
+    /// Database versions which buildings in this tree were last built against. Buildings
+    /// with no recorded version are skipped.
+    pub fn database_versions(&self) -> BTreeSet<DatabaseVersion> {
+        self.iter()
+            .filter_map(|node| node.building()?.database_version)
+            .collect()
+    }
+
     /// Get the Building if this is a Building, otherwise None.
     pub fn building(&self) -> Option<&Building> {
         self.kind().building()
@@ -194,23 +461,366 @@ impl Node {
         }
     }
 
+    /// Rebuild this node with a new database, like [`Node::rebuild`], and collect every
+    /// building which failed to build.
+    pub fn rebuild_with_report(&self, new_db: &Database) -> RebuildReport {
+        fn collect_errors(node: &Node, path: &mut Vec<usize>, errors: &mut Vec<NodeError>) {
+            match node.kind() {
+                NodeKind::Group(group) => {
+                    for (idx, child) in group.children.iter().enumerate() {
+                        path.push(idx);
+                        collect_errors(child, path, errors);
+                        path.pop();
+                    }
+                }
+                NodeKind::Building(_) => {
+                    if let Some(error) = node.warning() {
+                        errors.push(NodeError {
+                            path: path.clone(),
+                            error,
+                        });
+                    }
+                }
+            }
+        }
+
+        let root = self.rebuild(new_db);
+        let mut errors = Vec::new();
+        collect_errors(&root, &mut Vec::new(), &mut errors);
+        RebuildReport { root, errors }
+    }
+
+    /// Find every building in this tree which is missing a recipe, resource, fuel, or
+    /// building type, in tree order. Such buildings build fine but contribute nothing
+    /// beyond their power use, so they are easy to forget.
+    pub fn unconfigured_buildings(&self) -> Vec<UnconfiguredBuilding> {
+        fn collect(node: &Node, path: &mut Vec<usize>, found: &mut Vec<UnconfiguredBuilding>) {
+            match node.kind() {
+                NodeKind::Group(group) => {
+                    for (idx, child) in group.children.iter().enumerate() {
+                        path.push(idx);
+                        collect(child, path, found);
+                        path.pop();
+                    }
+                }
+                NodeKind::Building(building) => {
+                    if let Some(missing) = building.missing_choice() {
+                        found.push(UnconfiguredBuilding {
+                            path: path.clone(),
+                            missing,
+                        });
+                    }
+                }
+            }
+        }
+
+        let mut found = Vec::new();
+        collect(self, &mut Vec::new(), &mut found);
+        found
+    }
+
+    /// Find the ways this group's balance breaks the interface declared for it, in item
+    /// order. Empty for buildings and for groups without a declared interface. Rates this
+    /// close to zero count as zero, so rounding error isn't flagged.
+    pub fn interface_violations(&self) -> Vec<InterfaceViolation> {
+        const TOLERANCE: f32 = 1e-3;
+
+        let Some(group) = self.group() else {
+            return Vec::new();
+        };
+        if group.interface.is_empty() {
+            return Vec::new();
+        }
+        let balances = &self.balance().balances;
+        let items: BTreeSet<ItemId> = balances
+            .keys()
+            .chain(group.interface.keys())
+            .copied()
+            .collect();
+        items
+            .into_iter()
+            .filter_map(|item| {
+                let rate = balances.get(&item).copied().unwrap_or(0.0);
+                match group.interface.get(&item) {
+                    Some(Port::Input) if rate > -TOLERANCE => {
+                        Some(InterfaceViolation::UnusedInput(item))
+                    }
+                    Some(Port::Output) if rate < TOLERANCE => {
+                        Some(InterfaceViolation::MissingOutput(item))
+                    }
+                    None if rate < -TOLERANCE => Some(InterfaceViolation::UndeclaredInput(item)),
+                    None if rate > TOLERANCE && !group.byproducts.contains(&item) => {
+                        Some(InterfaceViolation::UndeclaredOutput(item))
+                    }
+                    _ => None,
+                }
+            })
+            .collect()
+    }
+
     /// Get the children of this node, if any.
     pub fn children(
         &self,
-    ) -> impl '_ + Iterator<Item = Node> + ExactSizeIterator + DoubleEndedIterator + FusedIterator
-    {
+    ) -> impl '_ + ExactSizeIterator<Item = Node> + DoubleEndedIterator + FusedIterator {
         match self.kind() {
             NodeKind::Group(group) => group.children.iter().cloned(),
             NodeKind::Building(_) => [].iter().cloned(),
         }
     }
 
+    /// Items produced by at least one building anywhere in this node. Unlike
+    /// [`Group::produced_items`], items which are consumed again within the tree are
+    /// still included.
+    pub fn produced_items(&self) -> BTreeSet<ItemId> {
+        self.iter()
+            .filter(|node| node.building().is_some())
+            .flat_map(|node| {
+                node.balance()
+                    .balances
+                    .iter()
+                    .filter(|(_, &rate)| rate > 0.0)
+                    .map(|(&item, _)| item)
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
+    /// Get the descendant of this node at the given path of child indexes, or this node
+    /// if the path is empty. Returns None if the path doesn't exist.
+    pub fn get_at(&self, path: &[usize]) -> Option<&Node> {
+        path.iter()
+            .try_fold(self, |node, &idx| node.group()?.get_child(idx))
+    }
+
+    /// Create a new tree with the descendant at `path` replaced. An empty path replaces
+    /// this node entirely. The balances of all groups along the path are recomputed.
+    pub fn replace_at(&self, path: &[usize], replacement: Node) -> Result<Node, PathError> {
+        if path.is_empty() {
+            return Ok(replacement);
+        }
+        self.change_children(path, 0, |children, index, depth| {
+            let len = children.len();
+            let child =
+                children
+                    .get_mut(index)
+                    .ok_or(PathError::OutOfBounds { depth, index, len })?;
+            *child = replacement;
+            Ok(())
+        })
+        .map(|(root, ())| root)
+    }
+
+    /// Create a new tree with the descendant at `path` removed. Returns the new tree and
+    /// the removed node.
+    pub fn remove_at(&self, path: &[usize]) -> Result<(Node, Node), PathError> {
+        self.change_children(path, 0, |children, index, depth| {
+            let len = children.len();
+            if index >= len {
+                return Err(PathError::OutOfBounds { depth, index, len });
+            }
+            Ok(children.remove(index))
+        })
+    }
+
+    /// Create a new tree with `node` inserted at `path`. The last index of the path may be
+    /// one past the end of its group, to insert as the last child.
+    pub fn insert_at(&self, path: &[usize], node: Node) -> Result<Node, PathError> {
+        self.change_children(path, 0, |children, index, depth| {
+            let len = children.len();
+            if index > len {
+                return Err(PathError::OutOfBounds { depth, index, len });
+            }
+            children.insert(index, node);
+            Ok(())
+        })
+        .map(|(root, ())| root)
+    }
+
+    /// Follow `path` to the group containing its last index, and apply `change` to that
+    /// group's children with the last index and its depth. Rebuilds every group along the
+    /// path so their balances are recomputed.
+    fn change_children<T>(
+        &self,
+        path: &[usize],
+        depth: usize,
+        change: impl FnOnce(&mut Vec<Node>, usize, usize) -> Result<T, PathError>,
+    ) -> Result<(Node, T), PathError> {
+        let (&index, rest) = path.split_first().ok_or(PathError::Empty)?;
+        let mut group = self.group().ok_or(PathError::NotAGroup { depth })?.clone();
+        let value = if rest.is_empty() {
+            change(&mut group.children, index, depth)?
+        } else {
+            let len = group.children.len();
+            let child =
+                group
+                    .children
+                    .get(index)
+                    .ok_or(PathError::OutOfBounds { depth, index, len })?;
+            let (child, value) = child.change_children(rest, depth + 1, change)?;
+            group.children[index] = child;
+            value
+        };
+        Ok((group.into(), value))
+    }
+
     /// Pre-order traversal iterator of this node.
     pub fn iter(&self) -> NodeIter {
         NodeIter {
             to_visit: vec![self.clone()],
         }
     }
+
+    /// Every group in this tree, including this node if it is a group, with its depth
+    /// below this node, in tree order. This node is at depth 0.
+    pub fn groups_with_depth(&self) -> Vec<(&Group, usize)> {
+        fn collect<'a>(node: &'a Node, depth: usize, found: &mut Vec<(&'a Group, usize)>) {
+            if let NodeKind::Group(group) = node.kind() {
+                found.push((group, depth));
+                for child in &group.children {
+                    collect(child, depth + 1, found);
+                }
+            }
+        }
+
+        let mut found = Vec::new();
+        collect(self, 0, &mut found);
+        found
+    }
+
+    /// Estimate the floor space taken up by the buildings in this node, including virtual
+    /// copies.
+    pub fn floor_area(&self, database: &Database) -> FloorArea {
+        match self.kind() {
+            NodeKind::Group(group) => {
+                let mut total = FloorArea::default();
+                for child in &group.children {
+                    let child = child.floor_area(database);
+                    total.area += child.area;
+                    total.unknown += child.unknown;
+                }
+                total.area *= group.copies as f32;
+                total.unknown *= group.copies;
+                total
+            }
+            NodeKind::Building(building) => match building.building {
+                Some(id) => match database.get(id).and_then(|b| b.footprint) {
+                    Some(footprint) => FloorArea {
+                        area: footprint.area() * building.copies as f32,
+                        unknown: 0,
+                    },
+                    None => FloorArea {
+                        area: 0.0,
+                        unknown: building.copies,
+                    },
+                },
+                None => FloorArea::default(),
+            },
+        }
+    }
+
+    /// Get the total power generated and the total power consumed by buildings in this
+    /// node, including virtual copies. Unlike the balance, these are not netted against
+    /// each other. Computed the first time it is needed.
+    pub fn power_split(&self) -> PowerSplit {
+        *self
+            .0
+            .power_split
+            .get_or_init(|| self.compute_power_split())
+    }
+
+    /// Compute the power split from the (memoized) splits of this node's children.
+    fn compute_power_split(&self) -> PowerSplit {
+        match self.kind() {
+            NodeKind::Group(group) => {
+                let mut split = group
+                    .children
+                    .iter()
+                    .fold(PowerSplit::default(), |acc, child| {
+                        let child = child.power_split();
+                        PowerSplit {
+                            generated: acc.generated + child.generated,
+                            consumed: acc.consumed + child.consumed,
+                        }
+                    });
+                split.generated *= group.copies as f32;
+                split.consumed *= group.copies as f32;
+                split
+            }
+            NodeKind::Building(_) => PowerSplit {
+                generated: self.balance().power.max(0.0),
+                consumed: (-self.balance().power).max(0.0),
+            },
+        }
+    }
+
+    /// Get the balance of every group in this tree by group id, for charting without
+    /// walking the tree. Like [`Node::balance`], each balance includes the group's own
+    /// virtual copies but not those of its ancestors.
+    pub fn group_balances(&self) -> BTreeMap<Uuid, Balance> {
+        self.iter()
+            .filter_map(|node| node.group().map(|group| (group.id, node.balance().clone())))
+            .collect()
+    }
+
+    /// Get the number of power shards needed to run every building in this node at its
+    /// clock speed, including virtual copies.
+    pub fn power_shards(&self) -> u32 {
+        match self.kind() {
+            NodeKind::Group(group) => {
+                group
+                    .children
+                    .iter()
+                    .map(|child| child.power_shards())
+                    .sum::<u32>()
+                    * group.copies
+            }
+            NodeKind::Building(building) => {
+                power_shards_for_clock(building.settings.clock_speed()) * building.copies
+            }
+        }
+    }
+
+    /// Get the number of Somersloops slotted into every building in this node, including
+    /// virtual copies.
+    pub fn somersloops(&self) -> u32 {
+        match self.kind() {
+            NodeKind::Group(group) => {
+                group
+                    .children
+                    .iter()
+                    .map(|child| child.somersloops())
+                    .sum::<u32>()
+                    * group.copies
+            }
+            NodeKind::Building(building) => building.settings.somersloops() * building.copies,
+        }
+    }
+}
+
+/// Estimated floor space used by a node.
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct FloorArea {
+    /// Total area of buildings with a known footprint, in square meters.
+    pub area: f32,
+    /// Number of buildings which aren't included in `area` because their footprint is
+    /// unknown.
+    pub unknown: u32,
+}
+
+/// Power generation and consumption of a node, tracked separately.
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct PowerSplit {
+    /// Total power generated in MW.
+    pub generated: f32,
+    /// Total power consumed in MW, as a positive number.
+    pub consumed: f32,
+}
+
+impl PowerSplit {
+    /// Whether the node generates at least as much power as it consumes.
+    pub fn is_self_sufficient(&self) -> bool {
+        self.generated >= self.consumed
+    }
 }
 
 pub struct NodeIter {
@@ -246,6 +856,39 @@ struct NodeInner {
     /// Whether this node has any children with warnings.
     #[serde(skip)]
     children_had_warnings: bool,
+
+    /// Output of each item which was cut off by the building's output cap.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    backed_up: BTreeMap<ItemId, f32>,
+
+    /// Power generated and consumed by this subtree, once it has been needed.
+    #[serde(skip)]
+    power_split: Memo<PowerSplit>,
+}
+
+/// Value derived from an immutable node, computed the first time it is needed. Since
+/// the node never changes, the value never needs to be invalidated. Memos always compare
+/// equal, so whether a value has been computed yet doesn't affect node equality.
+#[derive(Default)]
+struct Memo<T>(OnceCell<T>);
+
+impl<T> Memo<T> {
+    /// Get the value, computing it if this is the first time it was needed.
+    fn get_or_init(&self, init: impl FnOnce() -> T) -> &T {
+        self.0.get_or_init(init)
+    }
+}
+
+impl<T> PartialEq for Memo<T> {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl<T> fmt::Debug for Memo<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("Memo")
+    }
 }
 
 /// Kind of node.
@@ -285,12 +928,228 @@ impl From<Building> for NodeKind {
     }
 }
 
+/// Lowest clock speed a building can be set to.
+pub const MIN_CLOCK_SPEED: f32 = 0.01;
+
+/// Highest clock speed a building can be set to.
+pub const MAX_CLOCK_SPEED: f32 = 2.5;
+
+/// Number of power shards a single building needs to run at the given clock speed. Each
+/// shard allows another 50% over the base clock speed.
+pub fn power_shards_for_clock(clock_speed: f32) -> u32 {
+    // Small tolerance so clock speeds entered as e.g. 1.5 don't need an extra shard from
+    // float error.
+    let overclock = clock_speed.min(MAX_CLOCK_SPEED) - 1.0 - 1e-6;
+    if overclock <= 0.0 {
+        0
+    } else {
+        (overclock / 0.5).ceil() as u32
+    }
+}
+
+/// Multiplier applied to the output of a building with `somersloops` of its `slots`
+/// filled. A full set doubles the building's output, and its power use grows with the
+/// square of this multiplier.
+pub fn production_amplification(somersloops: u32, slots: u32) -> f32 {
+    if slots == 0 {
+        1.0
+    } else {
+        1.0 + somersloops.min(slots) as f32 / slots as f32
+    }
+}
+
+/// Buildings needed to make a target rate of one product of a recipe, from
+/// [`machines_for_output`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MachinesForOutput {
+    /// Number of buildings needed if they all run at 100% clock speed. Usually fractional.
+    pub exact_count: f32,
+    /// Number of whole buildings to build, which is `exact_count` rounded up.
+    pub count: u32,
+    /// Clock speed at which `count` buildings together make exactly the target. Never more
+    /// than 100%, so no power shards are needed.
+    pub clock_speed: f32,
+    /// Output of a single building at 100% clock speed.
+    pub per_machine: f32,
+    /// How much more than the target `count` buildings would make at 100% clock speed.
+    pub surplus: f32,
+}
+
+/// Find how many buildings running `recipe` are needed to make `rate` of `item` per
+/// minute, and the clock speed to run them at to make exactly that much. The buildings
+/// have the given manufacturing speed. Returns None if the recipe doesn't make the item or
+/// the rate isn't positive.
+pub fn machines_for_output(
+    recipe: &Recipe,
+    manufacturing_speed: f32,
+    item: ItemId,
+    rate: f32,
+) -> Option<MachinesForOutput> {
+    // Counts this close to a whole number are float error, and don't need another
+    // building.
+    const TOLERANCE: f32 = 1e-4;
+    if rate <= 0.0 || !rate.is_finite() {
+        return None;
+    }
+    let amount: f32 = recipe
+        .products
+        .iter()
+        .filter(|product| product.item == item)
+        .map(|product| product.amount)
+        .sum();
+    let per_machine = amount * recipe.runs_per_minute(manufacturing_speed);
+    if per_machine <= 0.0 || !per_machine.is_finite() {
+        return None;
+    }
+    let exact_count = rate / per_machine;
+    let count = (exact_count - TOLERANCE).ceil().max(1.0);
+    Some(MachinesForOutput {
+        exact_count,
+        count: count as u32,
+        clock_speed: (exact_count / count).clamp(MIN_CLOCK_SPEED, 1.0),
+        per_machine,
+        surplus: (count * per_machine - rate).max(0.0),
+    })
+}
+
+/// Generators needed to produce a target amount of power, from [`generators_for_power`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct GeneratorsForPower {
+    /// Number of generators needed if they all run at 100% clock speed. Usually
+    /// fractional.
+    pub exact_count: f32,
+    /// Number of whole generators to build, which is `exact_count` rounded up.
+    pub count: u32,
+    /// Clock speed at which `count` generators together produce exactly the target. Never
+    /// more than 100%, so no power shards are needed.
+    pub clock_speed: f32,
+    /// Power produced by a single generator at 100% clock speed, in MW.
+    pub per_generator: f32,
+    /// Fuel burned by all the generators together, per minute.
+    pub fuel_rate: f32,
+    /// Water used by all the generators together, per minute. Zero for generators which
+    /// don't need water.
+    pub water_rate: f32,
+    /// Byproducts of burning the fuel, per minute.
+    pub byproducts: BTreeMap<ItemId, f32>,
+}
+
+/// Find how many of the given generator burning `fuel` are needed to produce `power` MW,
+/// the clock speed to run them at to produce exactly that much, and how much fuel and
+/// water they need. Fuel use is proportional to power produced, so the rates don't depend
+/// on how the power is split between generators. Returns an error if the generator can't
+/// burn the fuel, or None if the power isn't positive.
+pub fn generators_for_power(
+    database: &Database,
+    generator: &BuildingType,
+    fuel: ItemId,
+    power: f32,
+) -> Result<Option<GeneratorsForPower>, BuildError> {
+    // Counts this close to a whole number are float error, and don't need another
+    // generator.
+    const TOLERANCE: f32 = 1e-4;
+    let BuildingKind::Generator(g) = &generator.kind else {
+        return Err(BuildError::MismatchedKind {
+            settings_kind: BuildingKindId::Generator,
+            type_kind: generator.kind.kind_id(),
+        });
+    };
+    // Building a single generator checks that it can burn the fuel.
+    let single = Building {
+        building: Some(generator.id),
+        settings: GeneratorSettings {
+            fuel: Some(fuel),
+            clock_speed: 1.0,
+        }
+        .into(),
+        ..Default::default()
+    }
+    .build_node(database)?;
+    let balance = single.balance();
+    let per_generator = balance.power;
+    if !(power > 0.0 && power.is_finite() && per_generator > 0.0) {
+        return Ok(None);
+    }
+    let exact_count = power / per_generator;
+    let count = (exact_count - TOLERANCE).ceil().max(1.0);
+    let exponent = g.power_production.power_exponent;
+    let (clock_speed, scale) = if exponent == 0.0 {
+        // Generators which can't be clocked always run at full power.
+        (1.0, count)
+    } else {
+        // Power grows with clock speed by the power exponent, so invert that to find
+        // the clock speed giving each generator its share of the target.
+        let share = power / (count * per_generator);
+        (
+            share.powf(exponent).clamp(MIN_CLOCK_SPEED, 1.0),
+            exact_count,
+        )
+    };
+    let rate = |item: &ItemId| balance.balances.get(item).copied().unwrap_or_default();
+    let water_rate = if fuel == ItemId::water() {
+        0.0
+    } else {
+        -rate(&ItemId::water()) * scale
+    };
+    let byproducts = balance
+        .balances
+        .iter()
+        .filter(|&(_, &rate)| rate > 0.0)
+        .map(|(&item, &rate)| (item, rate * scale))
+        .collect();
+    Ok(Some(GeneratorsForPower {
+        exact_count,
+        count: count as u32,
+        clock_speed,
+        per_generator,
+        fuel_rate: -rate(&fuel) * scale,
+        water_rate,
+        byproducts,
+    }))
+}
+
 /// Provides the default number of virtual copies for Serde to allow deserializing from
 /// before that field was added.
 fn default_copies() -> u32 {
     1
 }
 
+/// What to sort the children of a group by.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ChildSortKey {
+    /// Name of the group, or of the building type for buildings, ignoring case.
+    Name,
+    /// Power drawn, so generators sort below every consumer.
+    Power,
+    /// Net rate of the given item, treating children without it as zero.
+    Item(ItemId),
+}
+
+impl ChildSortKey {
+    /// Compare two children by this key.
+    fn compare(self, a: &Node, b: &Node, database: &Database) -> Ordering {
+        match self {
+            Self::Name => {
+                let name = |node: &Node| match node.kind() {
+                    NodeKind::Group(group) => group.name.to_lowercase(),
+                    NodeKind::Building(building) => building
+                        .building
+                        .and_then(|id| database.get(id))
+                        .map(|building| building.name.to_lowercase())
+                        .unwrap_or_default(),
+                };
+                name(a).cmp(&name(b))
+            }
+            // Balances count consumption as negative power, so reverse for draw.
+            Self::Power => b.balance().power.total_cmp(&a.balance().power),
+            Self::Item(item) => {
+                let rate = |node: &Node| node.balance().balances.get(&item).copied();
+                rate(a).unwrap_or(0.0).total_cmp(&rate(b).unwrap_or(0.0))
+            }
+        }
+    }
+}
+
 /// A grouping of other nodes. It's balance is based on its child nodes.
 ///
 /// Note that cloning groups is used to update groups. When creating a new a copy of a
@@ -306,6 +1165,26 @@ pub struct Group {
     /// Number of virtual copies of this group. This acts as a multiplier on the balance.
     #[serde(default = "default_copies")]
     pub copies: u32,
+    /// Net outputs of this group which are byproducts intended to be sunk, rather than
+    /// products which are needed elsewhere.
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub byproducts: BTreeSet<ItemId>,
+    /// Items the user declared this group takes in or makes, so it can be checked as a
+    /// module against its actual balance. Empty if no interface is declared.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub interface: BTreeMap<ItemId, Port>,
+    /// Whether this group is protected from being deleted.
+    #[serde(default)]
+    pub delete_locked: bool,
+    /// Labels the user attached to this group, for organizing across the hierarchy.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<IString>,
+    /// Free-form note the user wrote about this group. May be empty.
+    #[serde(default, skip_serializing_if = "str::is_empty")]
+    pub note: IString,
+    /// Accent color the user chose to distinguish this group, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<GroupColor>,
 
     /// Uniquely identifies a group, even when the node is shared between trees (e.g. when
     /// saving nodes for undo/redo purposes).
@@ -319,6 +1198,12 @@ impl Group {
             name: Default::default(),
             children: Default::default(),
             copies: 1,
+            byproducts: Default::default(),
+            interface: Default::default(),
+            delete_locked: false,
+            tags: Vec::new(),
+            note: Default::default(),
+            color: None,
             id: Uuid::new_v4(),
         }
     }
@@ -328,6 +1213,25 @@ impl Group {
         Self::empty().into()
     }
 
+    /// Database versions which buildings in this group were last built against, if they
+    /// differ. Empty if the versions agree, or if every version is within a single child
+    /// group, which reports the mismatch itself.
+    pub fn mismatched_database_versions(&self) -> BTreeSet<DatabaseVersion> {
+        let versions: BTreeSet<_> = self
+            .children
+            .iter()
+            .flat_map(|child| child.database_versions())
+            .collect();
+        let within_child = self.children.iter().any(|child| {
+            child.group().is_some() && child.database_versions().len() == versions.len()
+        });
+        if versions.len() < 2 || within_child {
+            BTreeSet::new()
+        } else {
+            versions
+        }
+    }
+
     /// Compute the net balance for this group, using the *cached* values of child nodes.
     /// Caller is responsible for recaching child balances first if necessary.
     fn compute_balance(&self) -> Balance {
@@ -336,11 +1240,39 @@ impl Group {
         balance
     }
 
+    /// Items produced by at least one child of this group, whether or not they are
+    /// consumed again within the group.
+    pub fn produced_items(&self) -> BTreeSet<ItemId> {
+        self.children
+            .iter()
+            .flat_map(|child| child.balance().balances.iter())
+            .filter(|(_, &rate)| rate > 0.0)
+            .map(|(&item, _)| item)
+            .collect()
+    }
+
     /// Get a child of this node by index.
     pub fn get_child(&self, index: usize) -> Option<&Node> {
         self.children.get(index)
     }
 
+    /// Copy of this group with its children reordered by `key`. The sort is stable, so
+    /// children with equal keys keep their relative order, and only the direct children
+    /// move; nested groups are left as they are. Buildings are named by their building
+    /// type, looked up in `database`.
+    pub fn sorted_by(&self, key: ChildSortKey, descending: bool, database: &Database) -> Self {
+        let mut sorted = self.clone();
+        sorted.children.sort_by(|a, b| {
+            let ordering = key.compare(a, b, database);
+            if descending {
+                ordering.reverse()
+            } else {
+                ordering
+            }
+        });
+        sorted
+    }
+
     /// Create a true copy of this group, with a newly assigned Uuid. Unlike the result of
     /// `Clone`, the new value doesn't represent the same group, so can be used in the
     /// same tree as the original.
@@ -353,6 +1285,12 @@ impl Group {
                 .map(|child| child.create_copy())
                 .collect(),
             copies: self.copies,
+            byproducts: self.byproducts.clone(),
+            interface: self.interface.clone(),
+            delete_locked: self.delete_locked,
+            tags: self.tags.clone(),
+            note: self.note.clone(),
+            color: self.color,
             id: Uuid::new_v4(),
         }
     }
@@ -371,12 +1309,145 @@ impl Group {
                 .map(|child| child.create_copy_with_visitor(visitor))
                 .collect(),
             copies: self.copies,
+            byproducts: self.byproducts.clone(),
+            interface: self.interface.clone(),
+            delete_locked: self.delete_locked,
+            tags: self.tags.clone(),
+            note: self.note.clone(),
+            color: self.color,
             id: Uuid::new_v4(),
         };
         visitor.visit(self, &mut copy);
         copy
     }
 
+    /// Find the sets of child buildings which are duplicates of each other, as described
+    /// by [`Building::is_duplicate_of`]. Each set has at least two indexes in increasing
+    /// order, and the sets are ordered by their first index.
+    pub fn duplicate_buildings(&self) -> Vec<Vec<usize>> {
+        let mut sets: Vec<Vec<usize>> = Vec::new();
+        for (idx, child) in self.children.iter().enumerate() {
+            let Some(building) = child.building() else {
+                continue;
+            };
+            let existing = sets.iter_mut().find(|set| {
+                self.children[set[0]]
+                    .building()
+                    .is_some_and(|first| first.is_duplicate_of(building))
+            });
+            match existing {
+                Some(set) => set.push(idx),
+                None => sets.push(vec![idx]),
+            }
+        }
+        sets.retain(|set| set.len() > 1);
+        sets
+    }
+
+    /// Merge each set of duplicate child buildings found by
+    /// [`Group::duplicate_buildings`] into a single building at the position of the first
+    /// one. The merged building has the combined copies, output caps, tags, and notes of
+    /// the set, and is delete locked if any of them was. Returns None if there are no
+    /// duplicates to merge.
+    pub fn merge_duplicate_buildings(&self, database: &Database) -> Option<Group> {
+        let sets = self.duplicate_buildings();
+        if sets.is_empty() {
+            return None;
+        }
+        let mut merged = self.clone();
+        let mut removed = BTreeSet::new();
+        for set in &sets {
+            let buildings: Vec<&Building> = set
+                .iter()
+                .filter_map(|&idx| self.children[idx].building())
+                .collect();
+            let mut combined = buildings[0].clone();
+            for building in &buildings[1..] {
+                combined.copies += building.copies;
+                for (item, cap) in &building.output_caps {
+                    *combined.output_caps.entry(*item).or_default() += cap;
+                }
+                for tag in &building.tags {
+                    if !combined.tags.contains(tag) {
+                        combined.tags.push(tag.clone());
+                    }
+                }
+                if !building.note.is_empty() && !combined.note.contains(&*building.note) {
+                    combined.note = if combined.note.is_empty() {
+                        building.note.clone()
+                    } else {
+                        format!("{}\n{}", combined.note, building.note).into()
+                    };
+                }
+                combined.delete_locked |= building.delete_locked;
+            }
+            merged.children[set[0]] = combined.rebuild(database);
+            removed.extend(set[1..].iter().copied());
+        }
+        let mut idx = 0;
+        merged.children.retain(|_| {
+            idx += 1;
+            !removed.contains(&(idx - 1))
+        });
+        Some(merged)
+    }
+
+    /// Indexes of the child buildings which make `item` and can be clocked to make it
+    /// faster or slower, such as buildings using different alternate recipes for it.
+    pub fn producers_of(&self, database: &Database, item: ItemId) -> Vec<usize> {
+        self.children
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, child)| {
+                let building = child.building()?;
+                building.clock_for_output(database, item, 0.0)?;
+                Some(idx)
+            })
+            .collect()
+    }
+
+    /// Spread `target` per minute of `item` across the child buildings at the indexes in
+    /// `weights`, in proportion to their weights, by setting the clock speed of each
+    /// building to make its share. Clock speeds are rounded with `round` and clamped to
+    /// the range the game allows, so a building may miss its share. Returns None if the
+    /// weights don't add up to more than zero, any weight is negative, or any weighted
+    /// child isn't a building which can be clocked to make the item.
+    pub fn distribute_output(
+        &self,
+        database: &Database,
+        item: ItemId,
+        target: f32,
+        weights: &[(usize, f32)],
+        round: impl Fn(f32) -> f32,
+    ) -> Option<WeightedOutput> {
+        let total_weight: f32 = weights.iter().map(|&(_, weight)| weight).sum();
+        if total_weight <= 0.0 || weights.iter().any(|&(_, weight)| weight < 0.0) {
+            return None;
+        }
+        let mut group = self.clone();
+        let mut shares = Vec::with_capacity(weights.len());
+        for &(child, weight) in weights {
+            let building = self.children.get(child)?.building()?;
+            let share = target * weight / total_weight;
+            let clock = building
+                .clock_for_output(database, item, share)?
+                .rounded(share, &round);
+            let mut clocked = building.clone();
+            clocked.settings.set_clock_speed(clock.clock_speed);
+            group.children[child] = clocked.rebuild(database);
+            shares.push(OutputShare {
+                child,
+                share,
+                clock,
+            });
+        }
+        Some(WeightedOutput {
+            group,
+            target,
+            shares,
+        })
+    }
+
     /// Rebuild this node with a new database.
     fn rebuild(&self, new_db: &Database) -> Node {
         let mut copy = self.clone();
@@ -410,6 +1481,31 @@ pub struct Building {
     /// Number of copies of this building.
     #[serde(default = "default_copies")]
     pub copies: u32,
+    /// Whether this building is protected from being deleted.
+    #[serde(default)]
+    pub delete_locked: bool,
+    /// Whether the building's settings are hidden when displaying it.
+    #[serde(default)]
+    pub hide_settings: bool,
+    /// Whether the building's balance is hidden when displaying it.
+    #[serde(default)]
+    pub hide_balance: bool,
+    /// Labels the user attached to this building, for organizing across the hierarchy.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<IString>,
+    /// Free-form note the user wrote about this building. May be empty.
+    #[serde(default, skip_serializing_if = "str::is_empty")]
+    pub note: IString,
+    /// Maximum total output of each item across all copies, e.g. the capacity of the belt
+    /// carrying it away. Output beyond the cap backs up instead of counting towards the
+    /// balance.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub output_caps: BTreeMap<ItemId, f32>,
+    /// Version of the database this building was last successfully built against. None
+    /// for custom databases, which have no version, and for buildings which were never
+    /// built or were saved before versions were recorded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub database_version: Option<DatabaseVersion>,
 }
 
 impl Building {
@@ -423,6 +1519,145 @@ impl Building {
         Node::new(Self::empty(), Balance::empty())
     }
 
+    /// Find the clock speed at which all copies of this building together output `rate`
+    /// of `item` per minute. The clock speed is clamped to the range the game allows, so
+    /// the result says whether the rate is actually achievable. Returns None if the
+    /// building can't be overclocked or doesn't produce the item.
+    pub fn clock_for_output(
+        &self,
+        database: &Database,
+        item: ItemId,
+        rate: f32,
+    ) -> Option<ClockForOutput> {
+        if !database.get(self.building?)?.overclockable() || self.copies == 0 {
+            return None;
+        }
+        let mut single = self.clone();
+        single.copies = 1;
+        single.settings.set_clock_speed(1.0);
+        let per_machine = *single
+            .build_node(database)
+            .ok()?
+            .balance()
+            .balances
+            .get(&item)?;
+        if per_machine <= 0.0 {
+            return None;
+        }
+        let full_clock_output = per_machine * self.copies as f32;
+        let exact = rate / full_clock_output;
+        let clock_speed = exact.clamp(MIN_CLOCK_SPEED, MAX_CLOCK_SPEED);
+        Some(ClockForOutput {
+            clock_speed,
+            achieved: full_clock_output * clock_speed,
+            exact: clock_speed == exact,
+            per_machine,
+            full_clock_output,
+        })
+    }
+
+    /// Compare the power cost of the extra output from amplifying a single copy of this
+    /// building with Somersloops to the cost of building one more copy instead. Uses the
+    /// building's Somersloops if it has any, otherwise a full set. Returns None if the
+    /// building can't be amplified or doesn't produce anything.
+    pub fn amplification_comparison(&self, database: &Database) -> Option<AmplificationComparison> {
+        let BuildingKind::Manufacturer(m) = &database.get(self.building?)?.kind else {
+            return None;
+        };
+        let BuildingSettings::Manufacturer(settings) = &self.settings else {
+            return None;
+        };
+        if m.somersloop_slots == 0 {
+            return None;
+        }
+        let item = database.get(settings.recipe?)?.products.first()?.item;
+        let somersloops = if settings.somersloops > 0 {
+            settings.somersloops
+        } else {
+            m.somersloop_slots
+        };
+        let single = |somersloops| {
+            let building = Building {
+                settings: ManufacturerSettings {
+                    somersloops,
+                    ..settings.clone()
+                }
+                .into(),
+                copies: 1,
+                output_caps: BTreeMap::new(),
+                ..self.clone()
+            };
+            let node = building.build_node(database).ok()?;
+            let output = node.balance().balances.get(&item).copied()?;
+            Some((output, -node.balance().power))
+        };
+        let (copy_output, copy_power) = single(0)?;
+        let (amplified_output, amplified_power) = single(somersloops)?;
+        if copy_output <= 0.0 {
+            return None;
+        }
+        Some(AmplificationComparison {
+            item,
+            somersloops,
+            extra_output: amplified_output - copy_output,
+            extra_power: amplified_power - copy_power,
+            copy_output,
+            copy_power,
+        })
+    }
+
+    /// Choice the user still has to make before this building does anything, if any.
+    pub fn missing_choice(&self) -> Option<MissingChoice> {
+        if self.building.is_none() {
+            return Some(MissingChoice::Building);
+        }
+        match &self.settings {
+            BuildingSettings::Manufacturer(settings) if settings.recipe.is_none() => {
+                Some(MissingChoice::Recipe)
+            }
+            BuildingSettings::Miner(settings) if settings.resource.is_none() => {
+                Some(MissingChoice::Resource)
+            }
+            BuildingSettings::Pump(settings) if settings.resource.is_none() => {
+                Some(MissingChoice::Resource)
+            }
+            BuildingSettings::Generator(settings) if settings.fuel.is_none() => {
+                Some(MissingChoice::Fuel)
+            }
+            BuildingSettings::Station(settings) if settings.fuel.is_none() => {
+                Some(MissingChoice::Fuel)
+            }
+            _ => None,
+        }
+    }
+
+    /// Whether `other` is the same type of building with exactly the same settings and
+    /// output caps, so the two could be one node with their copies added together. Notes,
+    /// tags, and display options are ignored. Buildings with no type are never duplicates.
+    pub fn is_duplicate_of(&self, other: &Building) -> bool {
+        self.building.is_some()
+            && self.building == other.building
+            && self.settings == other.settings
+            && self.output_caps == other.output_caps
+    }
+
+    /// Whether this building's output of `item` is an averaged, expected rate rather than
+    /// an exact one.
+    pub fn output_is_averaged(&self, database: &Database, item: ItemId) -> bool {
+        let BuildingSettings::Manufacturer(settings) = &self.settings else {
+            return false;
+        };
+        settings
+            .recipe
+            .and_then(|id| database.get(id))
+            .is_some_and(|recipe| {
+                recipe
+                    .products
+                    .iter()
+                    .any(|product| product.item == item && product.averaged)
+            })
+    }
+
     /// Rebuild this node with a new database, converting errors to warnings.
     fn rebuild(&self, new_db: &Database) -> Node {
         match self.clone().build_node(new_db) {
@@ -433,7 +1668,7 @@ impl Building {
 }
 
 impl BuildNode for Building {
-    fn build_node(self, database: &Database) -> Result<Node, BuildError> {
+    fn build_node(mut self, database: &Database) -> Result<Node, BuildError> {
         let mut balance = Balance::empty();
         if let Some(building_id) = self.building {
             let building = database
@@ -470,7 +1705,21 @@ impl BuildNode for Building {
             }
         }
         balance *= self.copies as f32;
-        Ok(Node::new(self, balance))
+        self.database_version = database.version;
+        let mut backed_up = BTreeMap::new();
+        for (&item, &cap) in &self.output_caps {
+            if let Some(rate) = balance.balances.get_mut(&item) {
+                if *rate > cap {
+                    backed_up.insert(item, *rate - cap);
+                    *rate = cap;
+                }
+            }
+        }
+        if backed_up.is_empty() {
+            Ok(Node::new(self, balance))
+        } else {
+            Ok(Node::capped(self, balance, backed_up))
+        }
     }
 }
 
@@ -480,8 +1729,133 @@ impl Default for Building {
             building: None,
             settings: BuildingSettings::PowerConsumer,
             copies: 1,
+            delete_locked: false,
+            hide_settings: false,
+            hide_balance: false,
+            tags: Vec::new(),
+            note: Default::default(),
+            output_caps: BTreeMap::new(),
+            database_version: None,
+        }
+    }
+}
+
+/// Clock speed needed to reach a target output, from [`Building::clock_for_output`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ClockForOutput {
+    /// Clock speed to set on every copy of the building.
+    pub clock_speed: f32,
+    /// Total output of all copies at `clock_speed`.
+    pub achieved: f32,
+    /// Whether `clock_speed` reaches the target exactly, rather than being clamped.
+    pub exact: bool,
+    /// Output of a single copy of the building at 100% clock speed.
+    pub per_machine: f32,
+    /// Total output of all copies at 100% clock speed.
+    pub full_clock_output: f32,
+}
+
+impl ClockForOutput {
+    /// Round the clock speed, e.g. to the precision clock speeds are entered with, and
+    /// update the achieved output to match. The result is only exact if the rounded
+    /// clock speed still reaches `target`.
+    pub fn rounded(self, target: f32, round: impl FnOnce(f32) -> f32) -> Self {
+        // Output within this much of the target counts as reaching it.
+        const TOLERANCE: f32 = 1e-3;
+        let clock_speed = round(self.clock_speed).clamp(MIN_CLOCK_SPEED, MAX_CLOCK_SPEED);
+        let achieved = self.full_clock_output * clock_speed;
+        Self {
+            clock_speed,
+            achieved,
+            exact: (achieved - target).abs() < TOLERANCE,
+            ..self
         }
     }
+
+    /// Number of buildings needed to produce `target` with every building at 100% clock
+    /// speed. If this is fractional, the target can only be met by underclocking or
+    /// overclocking.
+    pub fn buildings_at_full_clock(&self, target: f32) -> f32 {
+        target / self.per_machine
+    }
+}
+
+/// Power cost of extra output from Somersloops compared to building another copy, from
+/// [`Building::amplification_comparison`]. Rates are per minute for a single copy.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AmplificationComparison {
+    /// Product the comparison is for, which is the recipe's primary product.
+    pub item: ItemId,
+    /// Number of Somersloops slotted for the comparison.
+    pub somersloops: u32,
+    /// Extra output of `item` from amplifying.
+    pub extra_output: f32,
+    /// Extra power used by amplifying, in MW.
+    pub extra_power: f32,
+    /// Output of `item` from one more unamplified copy.
+    pub copy_output: f32,
+    /// Power used by one more unamplified copy, in MW.
+    pub copy_power: f32,
+}
+
+impl AmplificationComparison {
+    /// Power in MW per item/min of extra output when amplifying.
+    pub fn amplified_power_per_output(&self) -> f32 {
+        self.extra_power / self.extra_output
+    }
+
+    /// Power in MW per item/min of extra output when building another copy.
+    pub fn copy_power_per_output(&self) -> f32 {
+        self.copy_power / self.copy_output
+    }
+}
+
+/// Target output spread across several buildings by weight, from
+/// [`Group::distribute_output`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct WeightedOutput {
+    /// The group with the clock speeds of the weighted buildings set.
+    pub group: Group,
+    /// Total output the buildings were asked to make.
+    pub target: f32,
+    /// Share of the target given to each building, in the order they were weighted.
+    pub shares: Vec<OutputShare>,
+}
+
+impl WeightedOutput {
+    /// Total output of the weighted buildings at their new clock speeds.
+    pub fn achieved(&self) -> f32 {
+        self.shares.iter().map(|share| share.clock.achieved).sum()
+    }
+
+    /// How much less than the target the buildings make together. Negative if buildings
+    /// couldn't be clocked down far enough and make more.
+    pub fn shortfall(&self) -> f32 {
+        self.target - self.achieved()
+    }
+
+    /// Whether every building makes exactly its share.
+    pub fn exact(&self) -> bool {
+        self.shares.iter().all(|share| share.clock.exact)
+    }
+}
+
+/// Share of a weighted target given to one building.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OutputShare {
+    /// Index of the building among the group's children.
+    pub child: usize,
+    /// Output the building was asked to make.
+    pub share: f32,
+    /// Clock speed set on the building to make its share.
+    pub clock: ClockForOutput,
+}
+
+impl OutputShare {
+    /// How much less than its share the building makes. Negative if it makes more.
+    pub fn shortfall(&self) -> f32 {
+        self.share - self.clock.achieved
+    }
 }
 
 /// Settings for a building of a particular kind.
@@ -523,6 +1897,14 @@ impl BuildingSettings {
         }
     }
 
+    /// Get the number of Somersloops amplifying the building.
+    pub fn somersloops(&self) -> u32 {
+        match self {
+            Self::Manufacturer(m) => m.somersloops,
+            _ => 0,
+        }
+    }
+
     /// Set the clock speed of the building if possible.
     pub fn set_clock_speed(&mut self, clock_speed: f32) {
         match self {
@@ -598,6 +1980,9 @@ pub struct ManufacturerSettings {
     /// Clock setting of this building. Ranges from 0.01 to 2.50 (unit is fraction, not
     /// percent).
     pub clock_speed: f32,
+    /// Number of Somersloops slotted into the building to amplify its output.
+    #[serde(default)]
+    pub somersloops: u32,
 }
 
 impl Default for ManufacturerSettings {
@@ -605,6 +1990,7 @@ impl Default for ManufacturerSettings {
         Self {
             recipe: None,
             clock_speed: 1.0,
+            somersloops: 0,
         }
     }
 }
@@ -630,9 +2016,20 @@ impl ManufacturerSettings {
                 });
             }
 
-            balance.power = -m.power_consumption.get_consumption_rate(self.clock_speed);
+            if self.somersloops > m.somersloop_slots {
+                return Err(BuildError::TooManySomersloops {
+                    building: building_id,
+                    somersloops: self.somersloops,
+                    slots: m.somersloop_slots,
+                });
+            }
+            let amplification = production_amplification(self.somersloops, m.somersloop_slots);
+
+            balance.power = -m.power_consumption.get_consumption_rate(self.clock_speed)
+                * amplification
+                * amplification;
             let recipe_runs_per_minute =
-                60.0 / recipe.time * m.manufacturing_speed * self.clock_speed;
+                recipe.runs_per_minute(m.manufacturing_speed * self.clock_speed);
 
             for input in &recipe.ingredients {
                 *balance.balances.entry(input.item).or_default() -=
@@ -640,7 +2037,7 @@ impl ManufacturerSettings {
             }
             for output in &recipe.products {
                 *balance.balances.entry(output.item).or_default() +=
-                    output.amount * recipe_runs_per_minute;
+                    output.amount * recipe_runs_per_minute * amplification;
             }
         }
         Ok(balance)
@@ -649,6 +2046,7 @@ impl ManufacturerSettings {
     /// Create a copy of these settings for a different manufacturer.
     fn copy_settings(&self, m: &Manufacturer) -> Self {
         let mut ms = self.clone();
+        ms.somersloops = ms.somersloops.min(m.somersloop_slots);
         // leave clock the same and reset the recipe if our current recipe isn't allowed.
         // If the new building allows only one recipe, choose that.
         if let Some(recipe) = ms.recipe {
@@ -667,19 +2065,14 @@ impl ManufacturerSettings {
 }
 
 /// Purity of a source resource.
-#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ResourcePurity {
     Impure,
+    #[default]
     Normal,
     Pure,
 }
 
-impl Default for ResourcePurity {
-    fn default() -> Self {
-        ResourcePurity::Normal
-    }
-}
-
 impl ResourcePurity {
     /// Get the speed multiplier for this resource purity level.
     pub fn speed_multiplier(self) -> f32 {
@@ -725,6 +2118,7 @@ impl ResourcePurity {
     }
 
     /// Gets the purity matching an ident from [`Self::ident`].
+    #[allow(clippy::result_unit_err)]
     pub fn from_ident(ident: &str) -> Result<Self, ()> {
         match ident {
             "impure" => Ok(Self::Impure),
@@ -736,9 +2130,8 @@ impl ResourcePurity {
 
     /// Get an iterator over the values of this enum.
     pub fn values(
-    ) -> impl Iterator<Item = ResourcePurity> + DoubleEndedIterator + ExactSizeIterator + FusedIterator
-    {
-        (&[Self::Impure, Self::Normal, Self::Pure]).iter().copied()
+    ) -> impl DoubleEndedIterator<Item = ResourcePurity> + ExactSizeIterator + FusedIterator {
+        [Self::Impure, Self::Normal, Self::Pure].iter().copied()
     }
 }
 
@@ -755,7 +2148,9 @@ pub struct MinerSettings {
     pub resource: Option<ItemId>,
     /// Clock setting of this building. Ranges from 0.01 to 2.50.
     pub clock_speed: f32,
-    /// Purity of the node this miner is built on.
+    /// Purity of the node this miner is built on. Saves from before purity was tracked
+    /// are treated as normal.
+    #[serde(default)]
     pub purity: ResourcePurity,
 }
 
@@ -820,6 +2215,13 @@ impl MinerSettings {
     }
 }
 
+/// Maximum rate per minute that a generator can plausibly be fed `fuel` through its single
+/// fuel input. This is the throughput of the fastest conveyor belt for solid fuels, or of
+/// the fastest pipeline for fluids.
+pub fn max_fuel_input_rate(fuel: &Item) -> f32 {
+    Lines::capacity(fuel, BeltTier::Mk6, PipeTier::Mk2)
+}
+
 /// Building which produces power by burning items.
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct GeneratorSettings {
@@ -860,6 +2262,10 @@ impl GeneratorSettings {
                 });
             }
 
+            if !(energy.energy > 0.0 && energy.energy.is_finite()) {
+                return Err(BuildError::FuelHasNoEnergy(fuel_id));
+            }
+
             balance.power = g.power_production.get_production_rate(self.clock_speed);
             if g.used_water > 0.0 {
                 balance
@@ -871,6 +2277,12 @@ impl GeneratorSettings {
             let fuel_burn_time = energy.energy / balance.power;
             // Rate of fuel usage in items/min.
             let fuel_burn_rate = 60.0 / fuel_burn_time;
+            if !fuel_burn_rate.is_finite() || fuel_burn_rate > max_fuel_input_rate(fuel) {
+                return Err(BuildError::ImplausibleFuelRate {
+                    item: fuel_id,
+                    rate: fuel_burn_rate,
+                });
+            }
 
             for byproduct in &energy.byproducts {
                 // Byproducts amounts are per fuel burned.
@@ -986,20 +2398,12 @@ impl PumpSettings {
 }
 
 /// Building which produces power directly from a geothermal resource pad.
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
 pub struct GeothermalSettings {
     /// Purity of the pad, affects generated power.
     pub purity: ResourcePurity,
 }
 
-impl Default for GeothermalSettings {
-    fn default() -> Self {
-        Self {
-            purity: Default::default(),
-        }
-    }
-}
-
 impl GeothermalSettings {
     fn get_balance(&self, g: &Geothermal) -> Balance {
         Balance::power_only(self.purity.speed_multiplier() * g.power)
@@ -1069,3 +2473,1349 @@ mod private {
     impl Sealed for Group {}
     impl Sealed for Building {}
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::{DatabaseVersion, U7Subversion};
+    use crate::sample::{iron_plate_constructor, manufacturer};
+
+    /// Build a coal generator burning the given fuel.
+    fn coal_generator(fuel: &str, clock_speed: f32) -> Building {
+        Building {
+            building: Some("Desc_GeneratorCoal_C".into()),
+            settings: GeneratorSettings {
+                fuel: Some(fuel.into()),
+                clock_speed,
+            }
+            .into(),
+            copies: 1,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn power_split_is_not_netted() {
+        let db = DatabaseVersion::LATEST.load_database();
+        let generator = coal_generator("Desc_Coal_C", 1.0).build_node(&db).unwrap();
+        let constructor = iron_plate_constructor(1.0, 4).build_node(&db).unwrap();
+        let group: Node = Group {
+            children: vec![generator, constructor],
+            copies: 2,
+            ..Group::empty()
+        }
+        .into();
+
+        let split = group.power_split();
+        assert_eq!(split.generated, 150.0);
+        assert_eq!(split.consumed, 32.0);
+        assert!(split.is_self_sufficient());
+        assert_eq!(split.generated - split.consumed, group.balance().power);
+
+        // The memoized split is reused and doesn't make otherwise equal nodes differ.
+        assert_eq!(group.power_split(), split);
+        let json = serde_json::to_string(&group).unwrap();
+        assert_eq!(serde_json::from_str::<Node>(&json).unwrap(), group);
+    }
+
+    #[test]
+    fn sort_children() {
+        let db = DatabaseVersion::LATEST.load_database();
+        let constructor = |copies| iron_plate_constructor(1.0, copies).build_node(&db).unwrap();
+        let named = |name: &'static str, children| -> Node {
+            Group {
+                name: name.into(),
+                children,
+                ..Group::empty()
+            }
+            .into()
+        };
+        let nested = named("b", vec![constructor(1)]);
+        let group = Group {
+            children: vec![
+                nested.clone(),
+                coal_generator("Desc_Coal_C", 1.0).build_node(&db).unwrap(),
+                named("A", vec![]),
+                constructor(4),
+                named("a", vec![]),
+            ],
+            ..Group::empty()
+        };
+        let order = |sorted: Group| -> Vec<usize> {
+            sorted
+                .children
+                .iter()
+                .map(|child| group.children.iter().position(|c| c == child).unwrap())
+                .collect()
+        };
+
+        // Names ignore case, and equal names keep their order in either direction.
+        assert_eq!(
+            order(group.sorted_by(ChildSortKey::Name, false, &db)),
+            [2, 4, 0, 1, 3]
+        );
+        assert_eq!(
+            order(group.sorted_by(ChildSortKey::Name, true, &db)),
+            [3, 1, 0, 2, 4]
+        );
+        // Generators draw negative power.
+        assert_eq!(
+            order(group.sorted_by(ChildSortKey::Power, false, &db)),
+            [1, 2, 4, 0, 3]
+        );
+        let plates = ChildSortKey::Item("Desc_IronPlate_C".into());
+        assert_eq!(order(group.sorted_by(plates, true, &db)), [3, 0, 1, 2, 4]);
+
+        // Nested groups are moved whole, without sorting their own children.
+        let sorted = group.sorted_by(ChildSortKey::Name, false, &db);
+        assert_eq!(sorted.children[2], nested);
+        assert_eq!(sorted.id, group.id);
+    }
+
+    #[test]
+    fn floor_area_sums_copies() {
+        let db = DatabaseVersion::LATEST.load_database();
+        let line: Node = Group {
+            children: vec![
+                manufacturer("Desc_SmelterMk1_C", "Recipe_IngotIron_C", 1.0, 2)
+                    .build_node(&db)
+                    .unwrap(),
+                iron_plate_constructor(1.0, 1).build_node(&db).unwrap(),
+                Building {
+                    building: Some("Desc_TruckStation_C".into()),
+                    settings: BuildingSettings::Station(Default::default()),
+                    copies: 1,
+                    ..Default::default()
+                }
+                .build_node(&db)
+                .unwrap(),
+                Building::empty_node(),
+            ],
+            copies: 2,
+            ..Group::empty()
+        }
+        .into();
+
+        // Smelters are 6x9 and constructors are 8x10.
+        let area = line.floor_area(&db);
+        assert_eq!(area.area, 2.0 * (2.0 * 54.0 + 80.0));
+        // The truck station has no footprint.
+        assert_eq!(area.unknown, 2);
+    }
+
+    #[test]
+    fn power_shards_for_overclocking() {
+        assert_eq!(power_shards_for_clock(0.5), 0);
+        assert_eq!(power_shards_for_clock(1.0), 0);
+        assert_eq!(power_shards_for_clock(1.01), 1);
+        assert_eq!(power_shards_for_clock(1.5), 1);
+        assert_eq!(power_shards_for_clock(2.0), 2);
+        assert_eq!(power_shards_for_clock(2.25), 3);
+        assert_eq!(power_shards_for_clock(2.5), 3);
+
+        let db = DatabaseVersion::LATEST.load_database();
+        let constructor = |clock_speed, copies| {
+            iron_plate_constructor(clock_speed, copies)
+                .build_node(&db)
+                .unwrap()
+        };
+        let line: Node = Group {
+            children: vec![
+                constructor(2.5, 2),
+                constructor(1.2, 1),
+                constructor(0.8, 4),
+            ],
+            copies: 3,
+            ..Group::empty()
+        }
+        .into();
+        assert_eq!(line.power_shards(), 3 * (2 * 3 + 1));
+    }
+
+    #[test]
+    fn somersloops_count_copies() {
+        let db = DatabaseVersion::LATEST.load_database();
+        let line: Node = Group {
+            children: vec![
+                amplified_assembler(2).build_node(&db).unwrap(),
+                Building {
+                    copies: 4,
+                    ..amplified_assembler(1)
+                }
+                .build_node(&db)
+                .unwrap(),
+                iron_plate_constructor(2.5, 2).build_node(&db).unwrap(),
+            ],
+            copies: 3,
+            ..Group::empty()
+        }
+        .into();
+        assert_eq!(line.somersloops(), 3 * (2 + 4));
+    }
+
+    #[test]
+    fn locked_descendants_block_deletion() {
+        let locked = Building {
+            delete_locked: true,
+            ..Default::default()
+        };
+        let inner: Node = Group {
+            children: vec![Building::empty_node(), Node::new(locked, Balance::empty())],
+            ..Group::empty()
+        }
+        .into();
+        let outer: Node = Group {
+            children: vec![inner.clone(), Building::empty_node()],
+            ..Group::empty()
+        }
+        .into();
+        assert!(!outer.delete_locked());
+        assert!(outer.deletion_blocked());
+        assert!(inner.deletion_blocked());
+        assert!(!outer.group().unwrap().children[1].deletion_blocked());
+    }
+
+    #[test]
+    fn all_generator_fuels_build() {
+        let db = DatabaseVersion::LATEST.load_database();
+        for building in db.buildings.values() {
+            if let BuildingKind::Generator(g) = &building.kind {
+                for &fuel in &g.allowed_fuel {
+                    let bldg = Building {
+                        building: Some(building.id),
+                        settings: GeneratorSettings {
+                            fuel: Some(fuel),
+                            clock_speed: 2.5,
+                        }
+                        .into(),
+                        copies: 1,
+                        ..Default::default()
+                    };
+                    if let Err(e) = bldg.build_node(&db) {
+                        panic!("{} burning {} failed: {}", building.id, fuel, e);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn zero_energy_fuel_is_rejected() {
+        let mut db = DatabaseVersion::LATEST.load_database();
+        let coal: ItemId = "Desc_Coal_C".into();
+        db.items
+            .get_mut(&coal)
+            .unwrap()
+            .fuel
+            .as_mut()
+            .unwrap()
+            .energy = 0.0;
+        assert_eq!(
+            coal_generator("Desc_Coal_C", 1.0).build_node(&db),
+            Err(BuildError::FuelHasNoEnergy(coal)),
+        );
+    }
+
+    #[test]
+    fn implausible_fuel_rate_is_rejected() {
+        let mut db = DatabaseVersion::LATEST.load_database();
+        let coal: ItemId = "Desc_Coal_C".into();
+        // 75 MW at 250% needs 62.5 coal/min at 300 MJ, so 1 MJ needs 11250/min.
+        db.items
+            .get_mut(&coal)
+            .unwrap()
+            .fuel
+            .as_mut()
+            .unwrap()
+            .energy = 1.0;
+        assert!(matches!(
+            coal_generator("Desc_Coal_C", 2.5).build_node(&db),
+            Err(BuildError::ImplausibleFuelRate { item, .. }) if item == coal,
+        ));
+    }
+
+    #[test]
+    fn fluid_fuel_rate_is_limited_by_pipes() {
+        let mut db = DatabaseVersion::LATEST.load_database();
+        let fuel: ItemId = "Desc_LiquidFuel_C".into();
+        // 250 MW at 20 MJ needs 750 m³/min, more than a pipeline carries but less than
+        // a belt.
+        db.items
+            .get_mut(&fuel)
+            .unwrap()
+            .fuel
+            .as_mut()
+            .unwrap()
+            .energy = 20.0;
+        let generator = Building {
+            building: Some("Desc_GeneratorFuel_C".into()),
+            settings: GeneratorSettings {
+                fuel: Some(fuel),
+                clock_speed: 1.0,
+            }
+            .into(),
+            ..Default::default()
+        };
+        assert!(matches!(
+            generator.build_node(&db),
+            Err(BuildError::ImplausibleFuelRate { item, rate }) if item == fuel && rate == 750.0,
+        ));
+
+        // The same rate of a solid fuel fits on a belt.
+        let coal: ItemId = "Desc_Coal_C".into();
+        db.items
+            .get_mut(&coal)
+            .unwrap()
+            .fuel
+            .as_mut()
+            .unwrap()
+            .energy = 6.0;
+        let node = coal_generator("Desc_Coal_C", 1.0).build_node(&db).unwrap();
+        assert_eq!(node.balance().balances[&coal], -750.0);
+    }
+
+    #[test]
+    fn clock_for_total_output() {
+        let db = DatabaseVersion::LATEST.load_database();
+        // Iron plates are 20/min per constructor at 100%.
+        let constructors = iron_plate_constructor(1.0, 4);
+        let plate = "Desc_IronPlate_C".into();
+        let clock = constructors.clock_for_output(&db, plate, 45.0).unwrap();
+        assert_eq!(clock.clock_speed, 0.5625);
+        assert_eq!(clock.achieved, 45.0);
+        assert!(clock.exact);
+
+        let clock = constructors.clock_for_output(&db, plate, 1000.0).unwrap();
+        assert_eq!(clock.clock_speed, MAX_CLOCK_SPEED);
+        assert_eq!(clock.achieved, 200.0);
+        assert!(!clock.exact);
+
+        assert!(constructors
+            .clock_for_output(&db, "Desc_IronIngot_C".into(), 10.0)
+            .is_none());
+    }
+
+    #[test]
+    fn group_balances_by_id() {
+        let (root, _) = crate::sample::sample_factory();
+        let balances = root.group_balances();
+        let groups = root.iter().filter(|node| node.group().is_some()).count();
+        assert_eq!(balances.len(), groups);
+        for node in root.iter() {
+            if let Some(group) = node.group() {
+                assert_eq!(&balances[&group.id], node.balance());
+            }
+        }
+        // The balance of a group includes its own virtual copies.
+        let parts = root
+            .children()
+            .find(|child| child.group().is_some_and(|group| group.name == "Parts"))
+            .unwrap();
+        let parts = &balances[&parts.group().unwrap().id];
+        assert_eq!(parts.balances[&"Desc_IronPlate_C".into()], 120.0);
+    }
+
+    #[test]
+    fn output_caps_back_up_excess() {
+        let db = DatabaseVersion::LATEST.load_database();
+        let plate = "Desc_IronPlate_C".into();
+        let ingot = "Desc_IronIngot_C".into();
+        // 4 constructors make 80 plates/min, but the belt only carries 60.
+        let mut constructors = iron_plate_constructor(1.0, 4);
+        constructors.output_caps.insert(plate, 60.0);
+        let node = constructors.clone().build_node(&db).unwrap();
+        assert_eq!(node.balance().balances[&plate], 60.0);
+        assert_eq!(node.backed_up()[&plate], 20.0);
+        // Inputs are still consumed at the full rate.
+        assert_eq!(node.balance().balances[&ingot], -120.0);
+
+        let json = serde_json::to_string(&node).unwrap();
+        assert_eq!(serde_json::from_str::<Node>(&json).unwrap(), node);
+
+        // Caps which aren't reached have no effect.
+        constructors.output_caps.insert(plate, 120.0);
+        let node = constructors.build_node(&db).unwrap();
+        assert_eq!(node.balance().balances[&plate], 80.0);
+        assert!(node.backed_up().is_empty());
+    }
+
+    #[test]
+    fn search_by_tag() {
+        let db = DatabaseVersion::LATEST.load_database();
+        let tagged = Building {
+            tags: vec!["Phase-2".into(), "needs-review".into()],
+            ..Default::default()
+        };
+        let group: Node = Group {
+            children: vec![tagged.build_node(&db).unwrap(), Building::empty_node()],
+            tags: vec!["steel".into()],
+            ..Group::empty()
+        }
+        .into();
+        let child = group.group().unwrap().get_child(0).unwrap();
+        assert!(child.has_tag("phase-2"));
+        assert!(child.has_tag("review"));
+        assert!(!child.has_tag("steel"));
+        assert!(group.has_tag("steel"));
+        assert!(!group.has_tag("phase"));
+        assert!(group.contains_tag("PHASE"));
+        assert!(!group.contains_tag("copper"));
+
+        let json = serde_json::to_string(&group).unwrap();
+        assert_eq!(serde_json::from_str::<Node>(&json).unwrap(), group);
+    }
+
+    #[test]
+    fn notes_are_optional() {
+        let db = DatabaseVersion::LATEST.load_database();
+        let noted = Building {
+            note: "waiting on coal".into(),
+            ..Default::default()
+        };
+        let group: Node = Group {
+            children: vec![noted.build_node(&db).unwrap(), Building::empty_node()],
+            note: "TODO aluminum".into(),
+            ..Group::empty()
+        }
+        .into();
+        assert_eq!(&**group.note(), "TODO aluminum");
+        let children = &group.group().unwrap().children;
+        assert_eq!(&**children[0].note(), "waiting on coal");
+        assert!(children[1].note().is_empty());
+
+        let json = serde_json::to_value(&group).unwrap();
+        assert_eq!(serde_json::from_value::<Node>(json.clone()).unwrap(), group);
+        // Empty notes are left out entirely.
+        let json = serde_json::to_value(Building::empty_node()).unwrap();
+        assert!(!json.to_string().contains("note"));
+    }
+
+    #[test]
+    fn merge_duplicate_siblings() {
+        let db = DatabaseVersion::LATEST.load_database();
+        let smelter = manufacturer("Desc_SmelterMk1_C", "Recipe_IngotIron_C", 1.0, 1);
+        let children = [
+            Building {
+                note: "west line".into(),
+                ..iron_plate_constructor(1.0, 2)
+            },
+            smelter.clone(),
+            // Notes, tags, and display options don't matter.
+            Building {
+                note: "east line".into(),
+                tags: vec!["plates".into()],
+                hide_balance: true,
+                ..iron_plate_constructor(1.0, 1)
+            },
+            // Settings are compared exactly.
+            iron_plate_constructor(1.0001, 1),
+            Building::empty(),
+            Building::empty(),
+            smelter,
+        ];
+        let group = Group {
+            children: children
+                .into_iter()
+                .map(|building| building.build_node(&db).unwrap())
+                .collect(),
+            ..Group::empty()
+        };
+        assert_eq!(group.duplicate_buildings(), [vec![0, 2], vec![1, 6]]);
+
+        let merged = group.merge_duplicate_buildings(&db).unwrap();
+        let buildings: Vec<_> = merged
+            .children
+            .iter()
+            .map(|child| child.building().unwrap())
+            .collect();
+        assert_eq!(buildings.len(), 5);
+        assert_eq!(buildings[0].copies, 3);
+        assert_eq!(&*buildings[0].note, "west line\neast line");
+        assert_eq!(buildings[0].tags, [IString::from("plates")]);
+        assert_eq!(buildings[1].copies, 2);
+        assert_eq!(buildings[2].settings.clock_speed(), 1.0001);
+        assert!(merged.duplicate_buildings().is_empty());
+        assert_eq!(merged.merge_duplicate_buildings(&db), None);
+
+        // Merging doesn't change what the group makes.
+        let before = Node::from(group);
+        let after = Node::from(merged);
+        assert!((before.balance().power - after.balance().power).abs() < 1e-3);
+        assert_eq!(
+            before.balance().balances.keys().collect::<Vec<_>>(),
+            after.balance().balances.keys().collect::<Vec<_>>()
+        );
+        for (item, rate) in &before.balance().balances {
+            assert!((rate - after.balance().balances[item]).abs() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn group_colors_are_optional() {
+        let colored: Node = Group {
+            color: Some(GroupColor::rgb(0xe5, 0x39, 0x35)),
+            ..Group::empty()
+        }
+        .into();
+        let json = serde_json::to_value(&colored).unwrap();
+        assert!(json.to_string().contains(r##""color":"#e53935""##));
+        assert_eq!(serde_json::from_value::<Node>(json).unwrap(), colored);
+        assert_eq!(
+            colored.create_copy().group().unwrap().color,
+            colored.group().unwrap().color
+        );
+        // Groups without a color leave it out, and older groups load without one.
+        let json = serde_json::to_value(Group::empty_node()).unwrap();
+        assert!(!json.to_string().contains("color"));
+    }
+
+    #[test]
+    fn groups_in_tree_order_with_depth() {
+        let named = |name: &'static str, children: Vec<Node>| -> Node {
+            Group {
+                name: name.into(),
+                children,
+                ..Group::empty()
+            }
+            .into()
+        };
+        let root = named(
+            "root",
+            vec![
+                named("a", vec![named("a1", vec![]), Building::empty_node()]),
+                Building::empty_node(),
+                named("b", vec![named("b1", vec![named("b2", vec![])])]),
+            ],
+        );
+        let found: Vec<_> = root
+            .groups_with_depth()
+            .into_iter()
+            .map(|(group, depth)| (&*group.name, depth))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                ("root", 0),
+                ("a", 1),
+                ("a1", 2),
+                ("b", 1),
+                ("b1", 2),
+                ("b2", 3)
+            ]
+        );
+        assert!(Building::empty_node().groups_with_depth().is_empty());
+    }
+
+    #[test]
+    fn copies_get_fresh_ids() {
+        let db = DatabaseVersion::LATEST.load_database();
+        let plates = iron_plate_constructor(1.5, 3);
+        let original: Node = Group {
+            name: "Plates".into(),
+            children: vec![
+                plates.build_node(&db).unwrap(),
+                Group {
+                    children: vec![Building::empty_node()],
+                    ..Group::empty()
+                }
+                .into(),
+            ],
+            ..Group::empty()
+        }
+        .into();
+        let first = original.create_copy();
+        let second = original.create_copy_with_visitor(&|_: &Group, _: &mut Group| {});
+
+        let ids = |node: &Node| -> Vec<Uuid> {
+            node.iter()
+                .filter_map(|node| node.group().map(|group| group.id))
+                .collect()
+        };
+        let all_ids: BTreeSet<_> = [&original, &first, &second]
+            .into_iter()
+            .flat_map(ids)
+            .collect();
+        assert_eq!(all_ids.len(), 6);
+
+        // Apart from the IDs, the copies are identical to the original. Children are
+        // compared separately, since they contain IDs too.
+        for copy in [&first, &second] {
+            for (copied, node) in copy.iter().zip(original.iter()) {
+                assert_eq!(copied.balance(), node.balance());
+                match (copied.kind(), node.kind()) {
+                    (NodeKind::Group(copied), NodeKind::Group(group)) => {
+                        assert_eq!(copied.children.len(), group.children.len());
+                        assert_eq!(
+                            Group {
+                                id: group.id,
+                                children: Vec::new(),
+                                ..copied.clone()
+                            },
+                            Group {
+                                children: Vec::new(),
+                                ..group.clone()
+                            }
+                        );
+                    }
+                    (NodeKind::Building(copied), NodeKind::Building(building)) => {
+                        assert_eq!(copied, building)
+                    }
+                    _ => panic!("copy has a different shape than the original"),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn describe_errors_by_name() {
+        let db = DatabaseVersion::LATEST.load_database();
+        let error = BuildError::IncompatibleRecipe {
+            recipe: "Recipe_IronPlate_C".into(),
+            building: "Desc_SmelterMk1_C".into(),
+        };
+        assert_eq!(
+            error.describe(&db),
+            "Smelter cannot use the recipe Iron Plate."
+        );
+        assert_eq!(
+            BuildError::NotFuel("Desc_IronPlate_C".into()).describe(&db),
+            "Iron Plate is not a fuel."
+        );
+        // Things missing from the database can only be described by ID.
+        let missing = BuildError::UnknownRecipe("Recipe_Missing_C".into());
+        assert_eq!(missing.describe(&db), missing.to_string());
+    }
+
+    #[test]
+    fn check_declared_interface() {
+        let db = DatabaseVersion::LATEST.load_database();
+        let constructor = iron_plate_constructor(1.0, 1).build_node(&db).unwrap();
+        let ingot: ItemId = "Desc_IronIngot_C".into();
+        let plate: ItemId = "Desc_IronPlate_C".into();
+        let screw: ItemId = "Desc_IronScrew_C".into();
+        let group = |interface: Vec<(ItemId, Port)>, byproducts: Vec<ItemId>| -> Node {
+            Group {
+                children: vec![constructor.clone()],
+                interface: interface.into_iter().collect(),
+                byproducts: byproducts.into_iter().collect(),
+                ..Group::empty()
+            }
+            .into()
+        };
+
+        // Without a declared interface, nothing is checked.
+        assert!(group(vec![], vec![]).interface_violations().is_empty());
+        assert!(constructor.interface_violations().is_empty());
+        let matching = group(vec![(ingot, Port::Input), (plate, Port::Output)], vec![]);
+        assert!(matching.interface_violations().is_empty());
+
+        let undeclared = group(vec![(screw, Port::Output)], vec![]);
+        assert_eq!(
+            undeclared.interface_violations(),
+            [
+                InterfaceViolation::UndeclaredInput(ingot),
+                InterfaceViolation::UndeclaredOutput(plate),
+                InterfaceViolation::MissingOutput(screw),
+            ]
+        );
+        // Byproducts don't need to be declared.
+        let byproduct = group(vec![(ingot, Port::Input)], vec![plate]);
+        assert!(byproduct.interface_violations().is_empty());
+        let reversed = group(vec![(ingot, Port::Output), (plate, Port::Input)], vec![]);
+        assert_eq!(
+            reversed.interface_violations(),
+            [
+                InterfaceViolation::MissingOutput(ingot),
+                InterfaceViolation::UnusedInput(plate),
+            ]
+        );
+    }
+
+    #[test]
+    fn find_unconfigured_buildings() {
+        let db = DatabaseVersion::LATEST.load_database();
+        let constructor = Building {
+            building: Some("Desc_ConstructorMk1_C".into()),
+            settings: ManufacturerSettings::default().into(),
+            ..Default::default()
+        };
+        let miner = Building {
+            building: Some("Desc_MinerMk1_C".into()),
+            settings: MinerSettings::default().into(),
+            ..Default::default()
+        };
+        let generator = Building {
+            building: Some("Desc_GeneratorCoal_C".into()),
+            settings: GeneratorSettings::default().into(),
+            ..Default::default()
+        };
+        // Missing choices aren't errors; the buildings only use power.
+        let miner_node = miner.build_node(&db).unwrap();
+        assert_eq!(miner_node.warning(), None);
+        assert!(miner_node.balance().balances.is_empty());
+        let root: Node = Group {
+            children: vec![
+                constructor.clone().build_node(&db).unwrap(),
+                Group {
+                    children: vec![miner_node, generator.build_node(&db).unwrap()],
+                    ..Group::empty()
+                }
+                .into(),
+                Building::empty_node(),
+            ],
+            ..Group::empty()
+        }
+        .into();
+        let found: Vec<_> = root
+            .unconfigured_buildings()
+            .into_iter()
+            .map(|found| (found.path, found.missing))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                (vec![0], MissingChoice::Recipe),
+                (vec![1, 0], MissingChoice::Resource),
+                (vec![1, 1], MissingChoice::Fuel),
+                (vec![2], MissingChoice::Building),
+            ]
+        );
+
+        let configured = Building {
+            settings: ManufacturerSettings {
+                recipe: Some("Recipe_IronPlate_C".into()),
+                ..Default::default()
+            }
+            .into(),
+            ..constructor
+        };
+        assert_eq!(configured.missing_choice(), None);
+        assert!(configured
+            .build_node(&db)
+            .unwrap()
+            .unconfigured_buildings()
+            .is_empty());
+    }
+
+    #[test]
+    fn miner_purity_defaults_to_normal() {
+        let settings: MinerSettings =
+            serde_json::from_str(r#"{"resource":"Desc_OreIron_C","clock_speed":1.0}"#).unwrap();
+        assert_eq!(settings.purity, ResourcePurity::Normal);
+    }
+
+    #[test]
+    fn rebuild_reports_errors() {
+        let db = DatabaseVersion::LATEST.load_database();
+        let plates = iron_plate_constructor(1.0, 1);
+        let broken = manufacturer("Desc_ConstructorMk1_C", "Recipe_Missing_C", 1.0, 1);
+        let root: Node = Group {
+            children: vec![
+                plates.build_node(&db).unwrap(),
+                Group {
+                    children: vec![
+                        Building::empty_node(),
+                        BuildError::UnknownRecipe("Recipe_Missing_C".into())
+                            .into_warning_node(broken),
+                    ],
+                    ..Group::empty()
+                }
+                .into(),
+            ],
+            ..Group::empty()
+        }
+        .into();
+
+        let report = root.rebuild_with_report(&db);
+        assert_eq!(report.root, root.rebuild(&db));
+        assert_eq!(
+            report.errors,
+            vec![NodeError {
+                path: vec![1, 1],
+                error: BuildError::UnknownRecipe("Recipe_Missing_C".into()),
+            }]
+        );
+    }
+
+    #[test]
+    fn produced_items_of_children() {
+        let db = DatabaseVersion::LATEST.load_database();
+        let plates = iron_plate_constructor(1.0, 1);
+        let group = Group {
+            children: vec![plates.build_node(&db).unwrap()],
+            ..Group::empty()
+        };
+        let produced = group.produced_items();
+        assert!(produced.contains(&"Desc_IronPlate_C".into()));
+        assert!(!produced.contains(&"Desc_IronIngot_C".into()));
+
+        let (factory, _) = crate::sample::sample_factory();
+        let everywhere = factory.produced_items();
+        // Screws are all consumed within the sample, but are still produced in it.
+        assert!(everywhere.contains(&"Desc_IronScrew_C".into()));
+        assert!(!everywhere.contains(&"Desc_Coal_C".into()));
+
+        let plate_recipe = &db[RecipeId::from("Recipe_IronPlateReinforced_C")];
+        assert_eq!(plate_recipe.input_overlap(&produced), 1);
+        let ingot_recipe = &db[RecipeId::from("Recipe_IngotIron_C")];
+        assert_eq!(ingot_recipe.input_overlap(&produced), 0);
+    }
+
+    #[test]
+    fn inputs_scale_with_clock() {
+        let db = DatabaseVersion::LATEST.load_database();
+        let ingot = "Desc_IronIngot_C".into();
+        let plate = "Desc_IronPlate_C".into();
+        let constructor = |clock_speed| {
+            iron_plate_constructor(clock_speed, 1)
+                .build_node(&db)
+                .unwrap()
+        };
+        for clock_speed in [0.5, 1.0, 1.5, 2.5] {
+            let node = constructor(clock_speed);
+            let balances = &node.balance().balances;
+            assert_eq!(balances[&ingot], -30.0 * clock_speed);
+            assert_eq!(balances[&plate], 20.0 * clock_speed);
+        }
+
+        // Generator fuel scales the same way as the power it produces.
+        let coal = "Desc_Coal_C".into();
+        let full = coal_generator("Desc_Coal_C", 1.0).build_node(&db).unwrap();
+        let half = coal_generator("Desc_Coal_C", 0.5).build_node(&db).unwrap();
+        assert_eq!(
+            half.balance().balances[&coal],
+            full.balance().balances[&coal] / 2.0
+        );
+        assert_eq!(half.balance().power, full.balance().power / 2.0);
+    }
+
+    #[test]
+    fn power_scales_nonlinearly_with_clock() {
+        const TOLERANCE: f32 = 1e-3;
+        let db = DatabaseVersion::LATEST.load_database();
+        let plate = "Desc_IronPlate_C".into();
+        // In-game power draw of a constructor, which is 4 MW * clock^1.321929.
+        for (clock_speed, power) in [(1.0, 4.0), (1.5, 6.8366), (2.5, 13.431)] {
+            let node = iron_plate_constructor(clock_speed, 1)
+                .build_node(&db)
+                .unwrap();
+            let balance = node.balance();
+            assert!(
+                (balance.power + power).abs() < TOLERANCE,
+                "{} MW at {clock_speed}",
+                balance.power
+            );
+            // Item rates stay linear in the clock speed.
+            assert_eq!(balance.balances[&plate], 20.0 * clock_speed);
+        }
+    }
+
+    /// Assemblers making reinforced iron plates with the given number of Somersloops.
+    fn amplified_assembler(somersloops: u32) -> Building {
+        let mut assembler = manufacturer(
+            "Desc_AssemblerMk1_C",
+            "Recipe_IronPlateReinforced_C",
+            1.0,
+            1,
+        );
+        if let BuildingSettings::Manufacturer(settings) = &mut assembler.settings {
+            settings.somersloops = somersloops;
+        }
+        assembler
+    }
+
+    #[test]
+    fn somersloops_amplify_output_and_power() {
+        let db = DatabaseVersion::LATEST.load_database();
+        let reinforced = "Desc_IronPlateReinforced_C".into();
+        let plate = "Desc_IronPlate_C".into();
+        // An assembler has 2 slots and makes 5 plates/min from 30 iron plates with 15 MW.
+        for (somersloops, output, power) in [(0, 5.0, 15.0), (1, 7.5, 33.75), (2, 10.0, 60.0)] {
+            let node = amplified_assembler(somersloops).build_node(&db).unwrap();
+            let balance = node.balance();
+            assert_eq!(balance.balances[&reinforced], output);
+            assert!((balance.power + power).abs() < 1e-3, "{}", balance.power);
+            // Inputs aren't amplified.
+            assert_eq!(balance.balances[&plate], -30.0);
+        }
+        assert_eq!(
+            amplified_assembler(3).build_node(&db),
+            Err(BuildError::TooManySomersloops {
+                building: "Desc_AssemblerMk1_C".into(),
+                somersloops: 3,
+                slots: 2,
+            })
+        );
+
+        // Older saves have no Somersloops, and older databases no slots.
+        let settings: ManufacturerSettings =
+            serde_json::from_str(r#"{"recipe":"Recipe_IronPlate_C","clock_speed":1.0}"#).unwrap();
+        assert_eq!(settings.somersloops, 0);
+        let u7 = DatabaseVersion::U7(U7Subversion::Initial).load_database();
+        assert!(matches!(
+            amplified_assembler(1).build_node(&u7),
+            Err(BuildError::TooManySomersloops { slots: 0, .. })
+        ));
+
+        // Switching to a building with fewer slots keeps as many as fit.
+        let BuildingKind::Manufacturer(constructor) =
+            &db[BuildingId::from("Desc_ConstructorMk1_C")].kind
+        else {
+            panic!("constructor is not a manufacturer");
+        };
+        let settings = amplified_assembler(2)
+            .settings
+            .build_new_settings(&BuildingKind::Manufacturer(constructor.clone()));
+        let BuildingSettings::Manufacturer(settings) = settings else {
+            panic!("settings changed kind");
+        };
+        assert_eq!(settings.somersloops, 1);
+    }
+
+    #[test]
+    fn amplification_compared_to_another_copy() {
+        let db = DatabaseVersion::LATEST.load_database();
+        // Without Somersloops, the comparison is for a full set. A fully amplified
+        // constructor makes 20 more plates/min for 12 more MW, where another copy makes
+        // 20 plates/min for 4 MW.
+        let constructor = iron_plate_constructor(1.0, 3)
+            .amplification_comparison(&db)
+            .unwrap();
+        assert_eq!(constructor.somersloops, 1);
+        assert_eq!(constructor.item, "Desc_IronPlate_C".into());
+        assert_eq!(constructor.extra_output, 20.0);
+        assert_eq!(constructor.copy_output, 20.0);
+        assert!((constructor.amplified_power_per_output() - 0.6).abs() < 1e-5);
+        assert!((constructor.copy_power_per_output() - 0.2).abs() < 1e-5);
+
+        // Otherwise it is for the building's own Somersloops.
+        let assembler = amplified_assembler(1)
+            .amplification_comparison(&db)
+            .unwrap();
+        assert_eq!(assembler.somersloops, 1);
+        assert_eq!(assembler.extra_output, 2.5);
+        assert!((assembler.amplified_power_per_output() - 7.5).abs() < 1e-4);
+        assert!((assembler.copy_power_per_output() - 3.0).abs() < 1e-5);
+
+        // Packagers can't be amplified.
+        let packager = manufacturer("Desc_Packager_C", "Recipe_PackagedWater_C", 1.0, 1);
+        assert_eq!(packager.amplification_comparison(&db), None);
+    }
+
+    #[test]
+    fn distribute_output_by_weight() {
+        let db = DatabaseVersion::LATEST.load_database();
+        // A smelter makes 30 iron ingots per minute at 100%, and a foundry with the iron
+        // alloy recipe makes 75.
+        let group = Group {
+            children: vec![
+                manufacturer("Desc_SmelterMk1_C", "Recipe_IngotIron_C", 1.0, 1)
+                    .build_node(&db)
+                    .unwrap(),
+                Building::empty_node(),
+                manufacturer("Desc_FoundryMk1_C", "Recipe_Alternate_IngotIron_C", 1.0, 1)
+                    .build_node(&db)
+                    .unwrap(),
+            ],
+            ..Group::empty()
+        };
+        let ingot = "Desc_IronIngot_C".into();
+        assert_eq!(group.producers_of(&db, ingot), vec![0, 2]);
+
+        let weighted = group
+            .distribute_output(&db, ingot, 90.0, &[(0, 1.0), (2, 2.0)], |clock| clock)
+            .unwrap();
+        assert_eq!(weighted.shares[0].share, 30.0);
+        assert_eq!(weighted.shares[0].clock.clock_speed, 1.0);
+        assert_eq!(weighted.shares[1].share, 60.0);
+        assert!((weighted.shares[1].clock.clock_speed - 0.8).abs() < 1e-6);
+        assert!(weighted.exact());
+        let node: Node = weighted.group.into();
+        assert!((node.balance().balances[&ingot] - 90.0).abs() < 1e-3);
+
+        // Shares beyond what a building can make at its highest clock fall short.
+        let weighted = group
+            .distribute_output(&db, ingot, 400.0, &[(0, 1.0), (2, 1.0)], |clock| clock)
+            .unwrap();
+        assert_eq!(weighted.shares[0].clock.clock_speed, MAX_CLOCK_SPEED);
+        assert_eq!(weighted.shares[0].shortfall(), 125.0);
+        assert_eq!(weighted.shares[1].shortfall(), 12.5);
+        assert_eq!(weighted.shortfall(), 137.5);
+        assert!(!weighted.exact());
+
+        assert!(group
+            .distribute_output(&db, ingot, 90.0, &[(0, 1.0), (1, 1.0)], |clock| clock)
+            .is_none());
+        assert!(group
+            .distribute_output(&db, ingot, 90.0, &[(0, 0.0), (2, 0.0)], |clock| clock)
+            .is_none());
+        assert!(group
+            .distribute_output(&db, ingot, 90.0, &[(0, 2.0), (2, -1.0)], |clock| clock)
+            .is_none());
+    }
+
+    #[test]
+    fn rounded_clock_for_output() {
+        let db = DatabaseVersion::LATEST.load_database();
+        // Iron plates are 20/min per constructor at 100%.
+        let constructors = iron_plate_constructor(1.0, 3);
+        let plate = "Desc_IronPlate_C".into();
+        let clock = constructors.clock_for_output(&db, plate, 50.0).unwrap();
+        assert_eq!(clock.buildings_at_full_clock(50.0), 2.5);
+        let whole_percent = |clock: f32| (clock * 100.0).round() / 100.0;
+        // 50/60 is 83.33%, which can't be entered as a whole percentage.
+        let rounded = clock.rounded(50.0, whole_percent);
+        assert_eq!(rounded.clock_speed, 0.83);
+        assert!((rounded.achieved - 49.8).abs() < 1e-3);
+        assert!(!rounded.exact);
+
+        let clock = constructors.clock_for_output(&db, plate, 45.0).unwrap();
+        assert_eq!(clock.buildings_at_full_clock(45.0), 2.25);
+        let rounded = clock.rounded(45.0, whole_percent);
+        assert_eq!(rounded.clock_speed, 0.75);
+        assert!(rounded.exact);
+    }
+
+    #[test]
+    fn clock_for_exact_rates() {
+        let db = DatabaseVersion::LATEST.load_database();
+        // A constructor makes 15 iron rods per minute at 100%.
+        let constructor = manufacturer("Desc_ConstructorMk1_C", "Recipe_IronRod_C", 1.0, 1);
+        let rod = "Desc_IronRod_C".into();
+        let clock = constructor.clock_for_output(&db, rod, 10.0).unwrap();
+        assert!((clock.clock_speed - 2.0 / 3.0).abs() < 1e-6);
+        assert!(clock.exact);
+        let clock = constructor.clock_for_output(&db, rod, 30.0).unwrap();
+        assert_eq!(clock.clock_speed, 2.0);
+        // Rates beyond what the game allows are clamped, and say they weren't reached.
+        let clock = constructor.clock_for_output(&db, rod, 60.0).unwrap();
+        assert_eq!(clock.clock_speed, MAX_CLOCK_SPEED);
+        assert_eq!(clock.achieved, 37.5);
+        assert!(!clock.exact);
+        let clock = constructor.clock_for_output(&db, rod, 0.0).unwrap();
+        assert_eq!(clock.clock_speed, MIN_CLOCK_SPEED);
+        assert!(!clock.exact);
+
+        // Either output of a refinery can be the target.
+        let refinery = manufacturer("Desc_OilRefinery_C", "Recipe_Plastic_C", 1.0, 1);
+        let plastic = refinery
+            .clock_for_output(&db, "Desc_Plastic_C".into(), 10.0)
+            .unwrap();
+        assert_eq!(plastic.clock_speed, 0.5);
+        let residue = refinery
+            .clock_for_output(&db, "Desc_HeavyOilResidue_C".into(), 10.0)
+            .unwrap();
+        assert_eq!(residue.clock_speed, 1.0);
+        assert!(refinery.clock_for_output(&db, rod, 10.0).is_none());
+    }
+
+    #[test]
+    fn machines_for_target_output() {
+        let db = DatabaseVersion::LATEST.load_database();
+        let plastic = "Desc_Plastic_C".into();
+        let recipe = &db[RecipeId::from("Recipe_Plastic_C")];
+        // A refinery makes 20 plastic per minute.
+        let machines = machines_for_output(recipe, 1.0, plastic, 60.0).unwrap();
+        assert_eq!(machines.exact_count, 3.0);
+        assert_eq!(machines.count, 3);
+        assert_eq!(machines.clock_speed, 1.0);
+        assert_eq!(machines.surplus, 0.0);
+
+        let machines = machines_for_output(recipe, 1.0, plastic, 50.0).unwrap();
+        assert_eq!(machines.count, 3);
+        assert!((machines.clock_speed - 5.0 / 6.0).abs() < 1e-6);
+        assert!((machines.surplus - 10.0).abs() < 1e-4);
+        let made = machines.count as f32 * machines.clock_speed * machines.per_machine;
+        assert!((made - 50.0).abs() < 1e-3);
+
+        // Byproducts can be targeted too, and faster buildings need fewer copies.
+        let residue = "Desc_HeavyOilResidue_C".into();
+        assert_eq!(
+            machines_for_output(recipe, 1.0, residue, 40.0)
+                .unwrap()
+                .count,
+            4
+        );
+        assert_eq!(
+            machines_for_output(recipe, 2.0, plastic, 60.0)
+                .unwrap()
+                .count,
+            2
+        );
+
+        assert!(machines_for_output(recipe, 1.0, "Desc_IronPlate_C".into(), 60.0).is_none());
+        assert!(machines_for_output(recipe, 1.0, plastic, 0.0).is_none());
+        assert!(machines_for_output(recipe, 1.0, plastic, f32::NAN).is_none());
+    }
+
+    #[test]
+    fn averaged_output_uses_expected_rate() {
+        let mut db = DatabaseVersion::LATEST.load_database();
+        let plate = "Desc_IronPlate_C".into();
+        let recipe = db.recipes.get_mut(&"Recipe_IronPlate_C".into()).unwrap();
+        // Pretend each run produces 1 or 2 plates with equal chance.
+        let product = recipe
+            .products
+            .iter_mut()
+            .find(|product| product.item == plate)
+            .unwrap();
+        product.amount = 1.5;
+        product.averaged = true;
+
+        let building = iron_plate_constructor(1.0, 1);
+        // The recipe takes 6 seconds, so it runs 10 times per minute.
+        let node = building.clone().build_node(&db).unwrap();
+        assert_eq!(node.balance().balances[&plate], 15.0);
+        assert!(building.output_is_averaged(&db, plate));
+        assert!(!building.output_is_averaged(&db, "Desc_IronIngot_C".into()));
+    }
+
+    /// Small deterministic random number generator for building random trees.
+    struct XorShift(u64);
+
+    impl XorShift {
+        fn below(&mut self, n: usize) -> usize {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            (self.0 % n as u64) as usize
+        }
+    }
+
+    /// Build a random tree of groups with some leaf buildings, up to `depth` levels deep.
+    fn random_tree(rng: &mut XorShift, db: &Database, depth: usize) -> Node {
+        let children = (0..rng.below(4))
+            .map(|_| {
+                if depth == 0 || rng.below(3) == 0 {
+                    Building::default().build_node(db).unwrap()
+                } else {
+                    random_tree(rng, db, depth - 1)
+                }
+            })
+            .collect();
+        Group {
+            children,
+            ..Group::empty()
+        }
+        .into()
+    }
+
+    /// Every path to a node in the tree, including the empty path to the root.
+    fn all_paths(node: &Node, prefix: &mut Vec<usize>, paths: &mut Vec<Vec<usize>>) {
+        paths.push(prefix.clone());
+        if let Some(group) = node.group() {
+            for (idx, child) in group.children.iter().enumerate() {
+                prefix.push(idx);
+                all_paths(child, prefix, paths);
+                prefix.pop();
+            }
+        }
+    }
+
+    #[test]
+    fn path_access_on_random_trees() {
+        let db = DatabaseVersion::LATEST.load_database();
+        let mut rng = XorShift(0x5eed);
+        for _ in 0..50 {
+            let root = random_tree(&mut rng, &db, 4);
+            let mut paths = Vec::new();
+            all_paths(&root, &mut Vec::new(), &mut paths);
+            let marker: Node = Group {
+                name: "marker".into(),
+                ..Group::empty()
+            }
+            .into();
+            for path in &paths {
+                let node = root.get_at(path).unwrap();
+
+                let replaced = root.replace_at(path, marker.clone()).unwrap();
+                assert_eq!(replaced.get_at(path), Some(&marker));
+
+                if let Some((_, parent)) = path.split_last() {
+                    // Removing and reinserting a node gives back the same tree.
+                    let (removed, moved) = root.remove_at(path).unwrap();
+                    assert_eq!(&moved, node);
+                    assert_eq!(
+                        removed.iter().count(),
+                        root.iter().count() - node.iter().count()
+                    );
+                    assert_eq!(removed.insert_at(path, moved).unwrap(), root);
+
+                    // Indexes past the end of the parent are rejected.
+                    let len = root.get_at(parent).unwrap().group().unwrap().children.len();
+                    let mut past_end = parent.to_vec();
+                    past_end.push(len);
+                    assert!(root.get_at(&past_end).is_none());
+                    let error = PathError::OutOfBounds {
+                        depth: parent.len(),
+                        index: len,
+                        len,
+                    };
+                    assert_eq!(root.remove_at(&past_end), Err(error));
+                    assert_eq!(root.replace_at(&past_end, marker.clone()), Err(error));
+                    assert!(root.insert_at(&past_end, marker.clone()).is_ok());
+                }
+
+                // Paths can't descend into buildings.
+                if node.building().is_some() {
+                    let mut below = path.clone();
+                    below.push(0);
+                    let error = PathError::NotAGroup { depth: path.len() };
+                    assert!(root.get_at(&below).is_none());
+                    assert_eq!(root.insert_at(&below, marker.clone()), Err(error));
+                }
+            }
+            assert_eq!(root.remove_at(&[]), Err(PathError::Empty));
+        }
+    }
+
+    #[test]
+    fn merge_balances() {
+        let ore: ItemId = "Desc_OreIron_C".into();
+        let ingot: ItemId = "Desc_IronIngot_C".into();
+        let smelter = Balance::new(-4.0, [(ore, -30.0), (ingot, 30.0)]);
+        let miner = Balance::new(-5.0, [(ore, 30.0)]);
+
+        let total = smelter.clone() + &miner;
+        assert_eq!(total, Balance::new(-9.0, [(ore, 0.0), (ingot, 30.0)]));
+        assert_eq!(total - &miner, smelter);
+
+        let merged = Balance::merge_all([&smelter, &miner], false);
+        assert_eq!(merged, smelter.clone() + &miner);
+        let merged = Balance::merge_all([&smelter, &miner], true);
+        assert_eq!(merged, Balance::new(-9.0, [(ingot, 30.0)]));
+        assert_eq!(Balance::merge_all([], true), Balance::empty());
+    }
+
+    #[test]
+    fn sink_value_of_surplus() {
+        let db = DatabaseVersion::LATEST.load_database();
+        let plate: ItemId = "Desc_IronPlate_C".into();
+        let balance = Balance::new(
+            -4.0,
+            [
+                (plate, 20.0),
+                ("Desc_IronIngot_C".into(), -30.0),
+                (ItemId::water(), 10.0),
+            ],
+        );
+        let value = balance.sink_value(&db, &BTreeSet::new());
+        // Inputs aren't counted, only surplus.
+        assert_eq!(value.points, db[plate].sink_points as f32 * 20.0);
+        assert!(value.points > 0.0);
+        assert_eq!(value.unsinkable, vec![ItemId::water()]);
+
+        // Excluded items count for nothing, and aren't reported as unsinkable.
+        let value = balance.sink_value(&db, &[plate, ItemId::water()].into());
+        assert_eq!(value, SinkValue::default());
+    }
+
+    #[test]
+    fn generator_fuel_rates() {
+        let db = DatabaseVersion::LATEST.load_database();
+        let generator = |building: &str, fuel: &str| {
+            Building {
+                building: Some(building.into()),
+                settings: GeneratorSettings {
+                    fuel: Some(fuel.into()),
+                    clock_speed: 1.0,
+                }
+                .into(),
+                copies: 1,
+                ..Default::default()
+            }
+            .build_node(&db)
+            .unwrap()
+            .balance()
+            .clone()
+        };
+        let assert_rate = |balance: &Balance, item: &str, rate: f32| {
+            let actual = balance.balances[&item.into()];
+            assert!((actual - rate).abs() < 1e-4, "{item}: {actual} != {rate}");
+        };
+
+        let coal = generator("Desc_GeneratorCoal_C", "Desc_Coal_C");
+        assert_eq!(coal.power, 75.0);
+        assert_rate(&coal, "Desc_Coal_C", -15.0);
+        assert_rate(&coal, "Desc_Water_C", -45.0);
+        assert_eq!(coal.balances.len(), 2);
+
+        let fuel = generator("Desc_GeneratorFuel_C", "Desc_LiquidFuel_C");
+        assert_eq!(fuel.power, 250.0);
+        assert_rate(&fuel, "Desc_LiquidFuel_C", -20.0);
+        assert_eq!(fuel.balances.len(), 1);
+
+        let nuclear = generator("Desc_GeneratorNuclear_C", "Desc_NuclearFuelRod_C");
+        assert_eq!(nuclear.power, 2500.0);
+        assert_rate(&nuclear, "Desc_NuclearFuelRod_C", -0.2);
+        assert_rate(&nuclear, "Desc_NuclearWaste_C", 10.0);
+        assert!(nuclear.balances[&ItemId::water()] < 0.0);
+    }
+
+    #[test]
+    fn generators_for_target_power() {
+        let db = DatabaseVersion::LATEST.load_database();
+        let building = |id: &str| &db[BuildingId::from(id)];
+        let close = |actual: f32, expected: f32| (actual - expected).abs() < 1e-3;
+
+        let fuel = building("Desc_GeneratorFuel_C");
+        let liquid_fuel = "Desc_LiquidFuel_C".into();
+        let plan = generators_for_power(&db, fuel, liquid_fuel, 2400.0)
+            .unwrap()
+            .unwrap();
+        assert!(close(plan.exact_count, 9.6));
+        assert_eq!(plan.count, 10);
+        assert!(plan.clock_speed < 1.0);
+        assert!(close(plan.per_generator, 250.0));
+        assert!(close(plan.fuel_rate, 192.0));
+        assert_eq!(plan.water_rate, 0.0);
+        assert!(plan.byproducts.is_empty());
+        // At the planned clock speed, the generators really do make the target.
+        let built = Building {
+            building: Some(fuel.id),
+            settings: GeneratorSettings {
+                fuel: Some(liquid_fuel),
+                clock_speed: plan.clock_speed,
+            }
+            .into(),
+            copies: plan.count,
+            ..Default::default()
+        }
+        .build_node(&db)
+        .unwrap();
+        assert!(close(built.balance().power, 2400.0));
+        assert!(close(
+            -built.balance().balances[&liquid_fuel],
+            plan.fuel_rate
+        ));
+
+        // Exact multiples don't need an extra generator.
+        let coal = building("Desc_GeneratorCoal_C");
+        let plan = generators_for_power(&db, coal, "Desc_Coal_C".into(), 750.0)
+            .unwrap()
+            .unwrap();
+        assert_eq!((plan.count, plan.clock_speed), (10, 1.0));
+        assert!(close(plan.fuel_rate, 150.0));
+        assert!(close(plan.water_rate, 450.0));
+
+        let nuclear = building("Desc_GeneratorNuclear_C");
+        let plan = generators_for_power(&db, nuclear, "Desc_NuclearFuelRod_C".into(), 5000.0)
+            .unwrap()
+            .unwrap();
+        assert_eq!(plan.count, 2);
+        assert!(close(plan.byproducts[&"Desc_NuclearWaste_C".into()], 20.0));
+
+        assert_eq!(
+            generators_for_power(&db, coal, liquid_fuel, 750.0),
+            Err(BuildError::IncompatibleItem {
+                item: liquid_fuel,
+                building: coal.id,
+            })
+        );
+        assert!(matches!(
+            generators_for_power(&db, building("Desc_SmelterMk1_C"), liquid_fuel, 750.0),
+            Err(BuildError::MismatchedKind { .. })
+        ));
+        assert_eq!(generators_for_power(&db, fuel, liquid_fuel, 0.0), Ok(None));
+    }
+}
@@ -5,13 +5,13 @@
 //   You may obtain a copy of the License at
 //
 //       http://www.apache.org/licenses/LICENSE-2.0
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 use std::iter::Sum;
 use std::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub, SubAssign};
 
 use serde::{Deserialize, Serialize};
 
-use crate::database::ItemId;
+use crate::database::{Database, ItemId};
 
 /// The balance of a node, including items produced or consumed and power used.
 #[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
@@ -43,6 +43,49 @@ impl Balance {
             balances: balances.into_iter().collect(),
         }
     }
+
+    /// Sum the given balances. If `drop_zeros` is set, items whose net rate is exactly
+    /// zero are left out of the result, rather than kept as balanced entries.
+    pub fn merge_all<'a>(
+        balances: impl IntoIterator<Item = &'a Balance>,
+        drop_zeros: bool,
+    ) -> Self {
+        let mut total: Balance = balances.into_iter().sum();
+        if drop_zeros {
+            total.drop_zeros();
+        }
+        total
+    }
+
+    /// Remove items whose net rate is exactly zero.
+    pub fn drop_zeros(&mut self) {
+        self.balances.retain(|_, rate| *rate != 0.0);
+    }
+
+    /// Compute how many AWESOME Sink points per minute the surplus items in this balance
+    /// are worth. Items in `excluded` aren't counted at all, as if they weren't surplus.
+    pub fn sink_value(&self, database: &Database, excluded: &BTreeSet<ItemId>) -> SinkValue {
+        let mut value = SinkValue::default();
+        for (&id, &rate) in &self.balances {
+            if rate <= 0.0 || excluded.contains(&id) {
+                continue;
+            }
+            match database.get(id).map(|item| item.sink_points) {
+                Some(points) if points > 0 => value.points += points as f32 * rate,
+                _ => value.unsinkable.push(id),
+            }
+        }
+        value
+    }
+}
+
+/// AWESOME Sink value of the surplus items in a balance.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct SinkValue {
+    /// Points per minute from sinking all surplus items.
+    pub points: f32,
+    /// Surplus items which can't be sunk, so don't count towards the points.
+    pub unsinkable: Vec<ItemId>,
 }
 
 impl Add for Balance {
@@ -0,0 +1,111 @@
+// Copyright 2024 Zachary Stewart
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// Accent color the user chose for a group. Serialized as a CSS hex color like
+/// `#1e88e5`, so it is always safe to put into a style.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct GroupColor {
+    /// Red component.
+    pub r: u8,
+    /// Green component.
+    pub g: u8,
+    /// Blue component.
+    pub b: u8,
+}
+
+impl GroupColor {
+    /// Create a color from its red, green, and blue components.
+    pub const fn rgb(r: u8, g: u8, b: u8) -> Self {
+        Self { r, g, b }
+    }
+}
+
+/// Error when parsing a [`GroupColor`] from text.
+#[derive(Debug, Clone, Error, PartialEq, Eq)]
+#[error("{0:?} is not a color; expected a hex code like #1e88e5")]
+pub struct ParseColorError(String);
+
+impl FromStr for GroupColor {
+    type Err = ParseColorError;
+
+    /// Parse a hex color in the form `#rrggbb` or `#rgb`. The `#` is optional.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || ParseColorError(s.to_owned());
+        let hex = s.trim();
+        let hex = hex.strip_prefix('#').unwrap_or(hex);
+        if !hex.is_ascii() {
+            return Err(err());
+        }
+        let component = |digits: &str| u8::from_str_radix(digits, 16).map_err(|_| err());
+        match hex.len() {
+            6 => Ok(Self::rgb(
+                component(&hex[0..2])?,
+                component(&hex[2..4])?,
+                component(&hex[4..6])?,
+            )),
+            // Each digit of the short form is doubled, so #fa0 is #ffaa00.
+            3 => Ok(Self::rgb(
+                component(&hex[0..1])? * 0x11,
+                component(&hex[1..2])? * 0x11,
+                component(&hex[2..3])? * 0x11,
+            )),
+            _ => Err(err()),
+        }
+    }
+}
+
+impl TryFrom<String> for GroupColor {
+    type Error = ParseColorError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<GroupColor> for String {
+    fn from(color: GroupColor) -> Self {
+        color.to_string()
+    }
+}
+
+impl fmt::Display for GroupColor {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "#{:02x}{:02x}{:02x}", self.r, self.g, self.b)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_colors() {
+        assert_eq!("#1e88e5".parse(), Ok(GroupColor::rgb(0x1e, 0x88, 0xe5)));
+        assert_eq!(" 1E88E5 ".parse(), Ok(GroupColor::rgb(0x1e, 0x88, 0xe5)));
+        assert_eq!("#fa0".parse(), Ok(GroupColor::rgb(0xff, 0xaa, 0x00)));
+        for bad in ["", "#", "#12345", "#gggggg", "red", "#1e88e5; x", "#ééé"] {
+            assert!(bad.parse::<GroupColor>().is_err(), "{bad:?} parsed");
+        }
+        assert_eq!(GroupColor::rgb(1, 2, 255).to_string(), "#0102ff");
+    }
+
+    #[test]
+    fn serialize_as_hex() {
+        let color = GroupColor::rgb(0x43, 0xa0, 0x47);
+        let json = serde_json::to_string(&color).unwrap();
+        assert_eq!(json, "\"#43a047\"");
+        assert_eq!(serde_json::from_str::<GroupColor>(&json).unwrap(), color);
+        assert!(serde_json::from_str::<GroupColor>("\"url(x)\"").is_err());
+    }
+}
@@ -0,0 +1,253 @@
+// Copyright 2024 Zachary Stewart
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+use std::collections::BTreeMap;
+
+use crate::accounting::{Balance, Node, NodeKind};
+use crate::database::ItemId;
+
+/// Rates smaller than this are ignored, to avoid relating items through floating point
+/// noise.
+const EPSILON: f32 = 1e-6;
+
+/// Largest multiplier tried when looking for a whole-number ratio.
+const MAX_MULTIPLIER: u32 = 120;
+
+/// How close a scaled rate must be to a whole number to be treated as one.
+const TOLERANCE: f32 = 1e-3;
+
+/// A set of items connected by the buildings which consume and produce them, with the
+/// rate each one flows through the factory expressed as a simplified whole-number ratio.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RatioChain {
+    /// Items in the chain with their part of the ratio. Items which aren't produced within
+    /// the chain come first, followed by the items made from them.
+    pub items: Vec<(ItemId, u32)>,
+    /// Whether the ratio is exact. If the rates don't form a ratio of reasonably small
+    /// whole numbers, the ratio is rounded.
+    pub exact: bool,
+}
+
+impl Node {
+    /// Group the items used by buildings under this node into chains of related items,
+    /// and express each chain's throughput as a ratio. The throughput of an item is the
+    /// larger of how much is produced and how much is consumed, including virtual copies.
+    /// Chains with only one item are omitted, since they have no ratio.
+    pub fn ratio_chains(&self) -> Vec<RatioChain> {
+        let mut buildings = Vec::new();
+        collect_balances(self, 1.0, &mut buildings);
+
+        let mut produced = BTreeMap::<ItemId, f32>::new();
+        let mut consumed = BTreeMap::<ItemId, f32>::new();
+        // Map each item to a representative of its chain.
+        let mut chain_of = BTreeMap::<ItemId, ItemId>::new();
+        for balance in &buildings {
+            let items: Vec<ItemId> = balance
+                .balances
+                .iter()
+                .filter(|(_, rate)| rate.abs() > EPSILON)
+                .map(|(&item, _)| item)
+                .collect();
+            for (&item, &rate) in &balance.balances {
+                if rate > EPSILON {
+                    *produced.entry(item).or_default() += rate;
+                } else if rate < -EPSILON {
+                    *consumed.entry(item).or_default() -= rate;
+                }
+            }
+            if let Some((&first, rest)) = items.split_first() {
+                let root = find(&mut chain_of, first);
+                for &item in rest {
+                    let other = find(&mut chain_of, item);
+                    if other != root {
+                        chain_of.insert(other, root);
+                    }
+                }
+            }
+        }
+
+        let levels = levels(&buildings);
+        let mut chains = BTreeMap::<ItemId, Vec<(ItemId, f32)>>::new();
+        for item in chain_of.keys().copied().collect::<Vec<_>>() {
+            let root = find(&mut chain_of, item);
+            let throughput = produced
+                .get(&item)
+                .copied()
+                .unwrap_or(0.0)
+                .max(consumed.get(&item).copied().unwrap_or(0.0));
+            chains.entry(root).or_default().push((item, throughput));
+        }
+        chains
+            .into_values()
+            .filter(|items| items.len() > 1)
+            .map(|mut items| {
+                items.sort_by_key(|&(item, _)| (levels.get(&item).copied().unwrap_or(0), item));
+                let (ratio, exact) =
+                    simplify(&items.iter().map(|&(_, rate)| rate).collect::<Vec<_>>());
+                RatioChain {
+                    items: items
+                        .into_iter()
+                        .zip(ratio)
+                        .map(|((item, _), part)| (item, part))
+                        .collect(),
+                    exact,
+                }
+            })
+            .collect()
+    }
+}
+
+/// Collect the balances of all buildings under `node`, scaled by the copies of all
+/// containing groups.
+fn collect_balances(node: &Node, multiplier: f32, buildings: &mut Vec<Balance>) {
+    match node.kind() {
+        NodeKind::Group(group) => {
+            let multiplier = multiplier * group.copies as f32;
+            for child in &group.children {
+                collect_balances(child, multiplier, buildings);
+            }
+        }
+        NodeKind::Building(_) => buildings.push(node.balance().clone() * multiplier),
+    }
+}
+
+/// Find the representative of the chain containing `item`, adding it as its own chain if
+/// it hasn't been seen.
+fn find(chain_of: &mut BTreeMap<ItemId, ItemId>, item: ItemId) -> ItemId {
+    let mut current = item;
+    loop {
+        let parent = *chain_of.entry(current).or_insert(current);
+        if parent == current {
+            break;
+        }
+        current = parent;
+    }
+    // Point the item straight at its representative to keep later lookups short.
+    chain_of.insert(item, current);
+    current
+}
+
+/// Find how many production steps each item is from the items which aren't produced at
+/// all. Items in production loops stop growing after a bounded number of passes.
+fn levels(buildings: &[Balance]) -> BTreeMap<ItemId, u32> {
+    let mut levels = BTreeMap::<ItemId, u32>::new();
+    for _ in 0..buildings.len().max(1) {
+        let mut changed = false;
+        for balance in buildings {
+            let input_level = balance
+                .balances
+                .iter()
+                .filter(|(_, &rate)| rate < -EPSILON)
+                .map(|(item, _)| levels.get(item).copied().unwrap_or(0))
+                .max();
+            let Some(input_level) = input_level else {
+                continue;
+            };
+            for (&item, _) in balance.balances.iter().filter(|(_, &rate)| rate > EPSILON) {
+                let level = levels.entry(item).or_default();
+                if *level < input_level + 1 {
+                    *level = input_level + 1;
+                    changed = true;
+                }
+            }
+        }
+        if !changed {
+            break;
+        }
+    }
+    levels
+}
+
+/// Convert rates to the smallest whole-number ratio between them. Returns whether the
+/// ratio is exact, or had to be rounded.
+fn simplify(rates: &[f32]) -> (Vec<u32>, bool) {
+    let smallest = rates
+        .iter()
+        .copied()
+        .filter(|&rate| rate > EPSILON)
+        .fold(f32::INFINITY, f32::min);
+    if !smallest.is_finite() {
+        return (vec![0; rates.len()], true);
+    }
+    let relative: Vec<f32> = rates.iter().map(|rate| rate / smallest).collect();
+    let exact = (1..=MAX_MULTIPLIER).find(|&multiplier| {
+        relative.iter().all(|&rate| {
+            let scaled = rate * multiplier as f32;
+            (scaled - scaled.round()).abs() <= TOLERANCE
+        })
+    });
+    let multiplier = exact.unwrap_or(MAX_MULTIPLIER);
+    let parts: Vec<u32> = relative
+        .iter()
+        .map(|rate| (rate * multiplier as f32).round() as u32)
+        .collect();
+    let divisor = parts.iter().copied().fold(0, gcd).max(1);
+    (
+        parts.into_iter().map(|part| part / divisor).collect(),
+        exact.is_some(),
+    )
+}
+
+/// Greatest common divisor.
+fn gcd(a: u32, b: u32) -> u32 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::accounting::{BuildNode, Group};
+    use crate::database::DatabaseVersion;
+    use crate::sample::manufacturer;
+
+    #[test]
+    fn iron_plates_ratio() {
+        let db = DatabaseVersion::LATEST.load_database();
+        // 3 smelters make 90 ingots from 90 ore, which 3 constructors turn into 60 plates.
+        let factory: Node = Group {
+            children: vec![
+                manufacturer("Desc_SmelterMk1_C", "Recipe_IngotIron_C", 1.0, 3)
+                    .build_node(&db)
+                    .unwrap(),
+                manufacturer("Desc_ConstructorMk1_C", "Recipe_IronPlate_C", 1.0, 3)
+                    .build_node(&db)
+                    .unwrap(),
+                manufacturer("Desc_ConstructorMk1_C", "Recipe_Concrete_C", 1.0, 1)
+                    .build_node(&db)
+                    .unwrap(),
+            ],
+            ..Group::empty()
+        }
+        .into();
+        let chains = factory.ratio_chains();
+        assert_eq!(chains.len(), 2);
+        let iron = chains.iter().find(|chain| chain.items.len() == 3).unwrap();
+        assert_eq!(
+            iron.items,
+            vec![
+                ("Desc_OreIron_C".into(), 3),
+                ("Desc_IronIngot_C".into(), 3),
+                ("Desc_IronPlate_C".into(), 2),
+            ]
+        );
+        assert!(iron.exact);
+    }
+
+    #[test]
+    fn simplify_ratios() {
+        assert_eq!(simplify(&[30.0, 30.0, 20.0]), (vec![3, 3, 2], true));
+        assert_eq!(simplify(&[7.5, 15.0]), (vec![1, 2], true));
+        assert_eq!(simplify(&[1.0, 2.0 / 3.0]), (vec![3, 2], true));
+        assert_eq!(simplify(&[1.0, 0.0]), (vec![1, 0], true));
+        let (_, exact) = simplify(&[1.0, std::f32::consts::SQRT_2]);
+        assert!(!exact);
+    }
+}
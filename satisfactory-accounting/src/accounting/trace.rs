@@ -0,0 +1,589 @@
+// Copyright 2024 Zachary Stewart
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+use std::fmt::Write;
+
+use implicit_clone::unsync::IString;
+
+use crate::accounting::{Balance, Building, Node, NodeKind, MAX_CLOCK_SPEED};
+use crate::database::{Database, ItemId};
+
+/// Rates smaller than this are treated as zero when tracing, to avoid tracing through
+/// floating point noise.
+const EPSILON: f32 = 1e-6;
+
+/// Trace of where a particular item comes from within a factory. Forms a
+/// bill-of-materials tree, alternating between items and the buildings which produce
+/// them.
+///
+/// All rates are in units-per-minute for the factory as a whole, so they include the
+/// virtual copies of every group containing a building.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProductionTrace {
+    /// Item being traced.
+    pub item: ItemId,
+    /// Rate of this item which is needed.
+    pub rate: f32,
+    /// Buildings which supply this item, with the portion of `rate` each one supplies.
+    pub producers: Vec<TraceProducer>,
+    /// Portion of `rate` which isn't produced anywhere in the factory, and so must be
+    /// imported.
+    pub unsourced: f32,
+    /// Whether tracing stopped here because this item is already being traced further
+    /// downstream (e.g. a recycling loop).
+    pub cyclic: bool,
+}
+
+/// A building which contributes to a [`ProductionTrace`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraceProducer {
+    /// Path from the root of the factory to the building.
+    pub path: Vec<usize>,
+    /// The contributing building.
+    pub building: Building,
+    /// Rate at which this building's output goes towards the traced item.
+    pub rate: f32,
+    /// Inputs consumed by this building to supply `rate`, each traced further upstream.
+    pub inputs: Vec<ProductionTrace>,
+}
+
+impl TraceProducer {
+    /// How much further this building's clock could be raised before hitting the cap, or
+    /// None if it can't be overclocked.
+    pub fn clock_headroom(&self, database: &Database) -> Option<f32> {
+        let building = database.get(self.building.building?)?;
+        if building.overclockable() {
+            Some(MAX_CLOCK_SPEED - self.building.settings.clock_speed())
+        } else {
+            None
+        }
+    }
+}
+
+/// Every building in a factory which produces or consumes a single item, for diagnosing
+/// where that item's balance comes from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ItemReport {
+    /// Item being reported on.
+    pub item: ItemId,
+    /// Buildings which produce or consume the item, in tree order.
+    pub rows: Vec<ItemReportRow>,
+}
+
+/// A building's contribution to an [`ItemReport`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ItemReportRow {
+    /// Path from the root of the factory to the building.
+    pub path: Vec<usize>,
+    /// Names of the groups containing the building, excluding the root.
+    pub location: Vec<IString>,
+    /// The contributing building.
+    pub building: Building,
+    /// Rate at which the building produces (positive) or consumes (negative) the item,
+    /// including the copies of all containing groups.
+    pub rate: f32,
+    /// Net rate of the item from this row and all rows before it.
+    pub running_net: f32,
+}
+
+impl ItemReport {
+    /// Build a report of every building in `factory` which produces or consumes `item`.
+    pub fn new(factory: &Node, item: ItemId) -> Self {
+        let mut leaves = Vec::new();
+        collect_leaves(factory, &mut Vec::new(), 1.0, &mut leaves);
+        let mut running_net = 0.0;
+        let rows = leaves
+            .into_iter()
+            .filter_map(|leaf| {
+                let rate = leaf.balance.balances.get(&item).copied()?;
+                if rate.abs() <= EPSILON {
+                    return None;
+                }
+                running_net += rate;
+                Some(ItemReportRow {
+                    location: group_names(factory, &leaf.path),
+                    path: leaf.path,
+                    building: leaf.building,
+                    rate,
+                    running_net,
+                })
+            })
+            .collect();
+        Self { item, rows }
+    }
+
+    /// Net rate of the item across the whole factory.
+    pub fn net(&self) -> f32 {
+        self.rows.last().map_or(0.0, |row| row.running_net)
+    }
+
+    /// Total rate of the item made by all producers.
+    pub fn produced(&self) -> f32 {
+        self.rows
+            .iter()
+            .map(|row| row.rate)
+            .filter(|&rate| rate > 0.0)
+            .sum()
+    }
+
+    /// Total rate of the item used by all consumers, as a negative number.
+    pub fn consumed(&self) -> f32 {
+        self.rows
+            .iter()
+            .map(|row| row.rate)
+            .filter(|&rate| rate < 0.0)
+            .sum()
+    }
+
+    /// Format the report as CSV, with a header row.
+    pub fn to_csv(&self, database: &Database) -> String {
+        let mut csv = String::from("Location,Building,Rate,Running Net\n");
+        for row in &self.rows {
+            let _ = writeln!(
+                csv,
+                "{},{},{},{}",
+                csv_field(&row.location.join(" / ")),
+                csv_field(&building_name(database, &row.building)),
+                row.rate,
+                row.running_net,
+            );
+        }
+        csv
+    }
+
+    /// Format the report as plain text, with one line per building.
+    pub fn to_text(&self, database: &Database) -> String {
+        let mut text = match database.get(self.item) {
+            Some(item) => format!("{}\n", item.name),
+            None => format!("Unknown Item {}\n", self.item),
+        };
+        for row in &self.rows {
+            let mut location = row.location.join(" / ");
+            if !location.is_empty() {
+                location.push_str(" / ");
+            }
+            let _ = writeln!(
+                text,
+                "{location}{}: {:+} (net {})",
+                building_name(database, &row.building),
+                row.rate,
+                row.running_net,
+            );
+        }
+        let _ = writeln!(text, "Net: {}", self.net());
+        text
+    }
+}
+
+/// Name of a building's type, for reports.
+fn building_name(database: &Database, building: &Building) -> String {
+    match building.building.and_then(|id| database.get(id)) {
+        Some(building) => building.name.to_string(),
+        None => "Unknown Building".to_string(),
+    }
+}
+
+/// Quote a CSV field if it contains characters which would otherwise break the row.
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Names of the groups along `path` from `factory`, excluding `factory` itself.
+fn group_names(factory: &Node, path: &[usize]) -> Vec<IString> {
+    let mut names = Vec::new();
+    let mut node = factory;
+    for &idx in path {
+        let Some(child) = node.group().and_then(|group| group.get_child(idx)) else {
+            break;
+        };
+        node = child;
+        if let Some(group) = node.group() {
+            names.push(group.name.clone());
+        }
+    }
+    names
+}
+
+/// Building in a factory, with its balance scaled by the copies of all containing groups.
+struct Leaf {
+    path: Vec<usize>,
+    building: Building,
+    balance: Balance,
+}
+
+impl Node {
+    /// Find every building under this node with a nonzero balance of `item`, in tree order,
+    /// as the path to the building and its rate. The rate includes virtual copies of the
+    /// building and of the groups containing it. Producers have positive rates and
+    /// consumers negative rates.
+    pub fn find_item_flows(&self, item: ItemId) -> Vec<(Vec<usize>, f32)> {
+        let mut leaves = Vec::new();
+        collect_leaves(self, &mut Vec::new(), 1.0, &mut leaves);
+        leaves
+            .into_iter()
+            .filter_map(|leaf| {
+                let rate = leaf.balance.balances.get(&item).copied()?;
+                (rate.abs() > EPSILON).then_some((leaf.path, rate))
+            })
+            .collect()
+    }
+}
+
+impl ProductionTrace {
+    /// Trace the production of `item` by the node at `output` within `factory`. The
+    /// buildings under that node which produce the item form the root of the trace, and
+    /// their inputs are matched to producers across the whole factory. If several
+    /// buildings produce an input, each is assumed to supply a share in proportion to
+    /// its production.
+    ///
+    /// Returns None if `output` doesn't refer to a node in the factory.
+    pub fn new(factory: &Node, output: &[usize], item: ItemId) -> Option<Self> {
+        factory.get_at(output)?;
+
+        let mut leaves = Vec::new();
+        collect_leaves(factory, &mut Vec::new(), 1.0, &mut leaves);
+
+        let roots: Vec<&Leaf> = leaves
+            .iter()
+            .filter(|leaf| leaf.path.starts_with(output))
+            .collect();
+        let rate = total_production(&roots, item);
+        let mut visiting = vec![item];
+        Some(Self {
+            item,
+            rate,
+            producers: trace_producers(&leaves, &roots, item, 1.0, &mut visiting),
+            unsourced: 0.0,
+            cyclic: false,
+        })
+    }
+
+    /// Find the building in this trace with the least room left to overclock, which is
+    /// the most likely bottleneck when trying to increase output. Returns the path of
+    /// that building within the factory. Buildings which can't be overclocked are never
+    /// considered bottlenecks.
+    pub fn bottleneck(&self, database: &Database) -> Option<&[usize]> {
+        let mut best: Option<(&TraceProducer, f32)> = None;
+        self.visit_producers(&mut |producer| {
+            if let Some(headroom) = producer.clock_headroom(database) {
+                if best.is_none_or(|(_, best)| headroom < best) {
+                    best = Some((producer, headroom));
+                }
+            }
+        });
+        best.map(|(producer, _)| &producer.path[..])
+    }
+
+    /// Call `visit` for every producer in this trace, in pre-order.
+    fn visit_producers<'a>(&'a self, visit: &mut impl FnMut(&'a TraceProducer)) {
+        for producer in &self.producers {
+            visit(producer);
+            for input in &producer.inputs {
+                input.visit_producers(visit);
+            }
+        }
+    }
+
+    /// Trace `rate` of `item`, sourced from anywhere in the factory.
+    fn trace(leaves: &[Leaf], item: ItemId, rate: f32, visiting: &mut Vec<ItemId>) -> Self {
+        if visiting.contains(&item) {
+            return Self {
+                item,
+                rate,
+                producers: Vec::new(),
+                unsourced: 0.0,
+                cyclic: true,
+            };
+        }
+        let producers: Vec<&Leaf> = leaves
+            .iter()
+            .filter(|leaf| production(leaf, item) > 0.0)
+            .collect();
+        let total = total_production(&producers, item);
+        let supplied = rate.min(total);
+        let producers = if supplied > EPSILON {
+            visiting.push(item);
+            let producers = trace_producers(leaves, &producers, item, supplied / total, visiting);
+            visiting.pop();
+            producers
+        } else {
+            Vec::new()
+        };
+        Self {
+            item,
+            rate,
+            producers,
+            unsourced: (rate - supplied).max(0.0),
+            cyclic: false,
+        }
+    }
+}
+
+/// Build the trace entries for each of `producers`, where `fraction` is the fraction of
+/// each producer's output of `item` which goes towards the trace.
+fn trace_producers(
+    leaves: &[Leaf],
+    producers: &[&Leaf],
+    item: ItemId,
+    fraction: f32,
+    visiting: &mut Vec<ItemId>,
+) -> Vec<TraceProducer> {
+    producers
+        .iter()
+        .filter(|leaf| production(leaf, item) > 0.0)
+        .map(|leaf| TraceProducer {
+            path: leaf.path.clone(),
+            building: leaf.building.clone(),
+            rate: production(leaf, item) * fraction,
+            inputs: leaf
+                .balance
+                .balances
+                .iter()
+                .filter(|(_, &rate)| rate * fraction < -EPSILON)
+                .map(|(&input, &rate)| {
+                    ProductionTrace::trace(leaves, input, -rate * fraction, visiting)
+                })
+                .collect(),
+        })
+        .collect()
+}
+
+/// Amount of `item` produced by the given leaf, or zero if it doesn't produce it.
+fn production(leaf: &Leaf, item: ItemId) -> f32 {
+    leaf.balance
+        .balances
+        .get(&item)
+        .copied()
+        .filter(|rate| *rate > 0.0)
+        .unwrap_or(0.0)
+}
+
+/// Total production of `item` by all of the given leaves.
+fn total_production(leaves: &[&Leaf], item: ItemId) -> f32 {
+    leaves.iter().map(|leaf| production(leaf, item)).sum()
+}
+
+/// Flatten all buildings under `node` into `leaves`, scaling them by `multiplier` and any
+/// group copies.
+fn collect_leaves(node: &Node, path: &mut Vec<usize>, multiplier: f32, leaves: &mut Vec<Leaf>) {
+    match node.kind() {
+        NodeKind::Group(group) => {
+            let multiplier = multiplier * group.copies as f32;
+            for (idx, child) in group.children.iter().enumerate() {
+                path.push(idx);
+                collect_leaves(child, path, multiplier, leaves);
+                path.pop();
+            }
+        }
+        NodeKind::Building(building) => leaves.push(Leaf {
+            path: path.clone(),
+            building: building.clone(),
+            balance: node.balance().clone() * multiplier,
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::accounting::{BuildNode, Group, MinerSettings};
+    use crate::database::DatabaseVersion;
+    use crate::sample::manufacturer;
+
+    fn iron_miner(db: &Database) -> Node {
+        Building {
+            building: Some("Desc_MinerMk1_C".into()),
+            settings: MinerSettings {
+                resource: Some("Desc_OreIron_C".into()),
+                ..Default::default()
+            }
+            .into(),
+            copies: 1,
+            ..Default::default()
+        }
+        .build_node(db)
+        .unwrap()
+    }
+
+    fn group(children: Vec<Node>) -> Node {
+        Group {
+            children,
+            ..Group::empty()
+        }
+        .into()
+    }
+
+    #[test]
+    fn traces_plates_back_to_ore() {
+        let db = DatabaseVersion::LATEST.load_database();
+        // 2 constructors make 40 plates from 60 ingots, made by 2 smelters from the 60
+        // ore of a single miner.
+        let factory = group(vec![
+            group(vec![iron_miner(&db)]),
+            manufacturer("Desc_SmelterMk1_C", "Recipe_IngotIron_C", 1.0, 2)
+                .build_node(&db)
+                .unwrap(),
+            group(vec![manufacturer(
+                "Desc_ConstructorMk1_C",
+                "Recipe_IronPlate_C",
+                1.0,
+                2,
+            )
+            .build_node(&db)
+            .unwrap()]),
+        ]);
+        let plate: ItemId = "Desc_IronPlate_C".into();
+        let ingot: ItemId = "Desc_IronIngot_C".into();
+        let ore: ItemId = "Desc_OreIron_C".into();
+
+        let trace = ProductionTrace::new(&factory, &[2], plate).unwrap();
+        assert_eq!(trace.rate, 40.0);
+        assert_eq!(trace.producers.len(), 1);
+        let constructor = &trace.producers[0];
+        assert_eq!(constructor.path, vec![2, 0]);
+        assert_eq!(constructor.inputs.len(), 1);
+
+        let ingots = &constructor.inputs[0];
+        assert_eq!(ingots.item, ingot);
+        assert_eq!(ingots.rate, 60.0);
+        assert_eq!(ingots.unsourced, 0.0);
+        assert_eq!(ingots.producers[0].path, vec![1]);
+
+        let ores = &ingots.producers[0].inputs[0];
+        assert_eq!(ores.item, ore);
+        assert_eq!(ores.rate, 60.0);
+        assert_eq!(ores.producers[0].path, vec![0, 0]);
+        assert_eq!(ores.producers[0].rate, 60.0);
+        assert_eq!(ores.unsourced, 0.0);
+    }
+
+    #[test]
+    fn bottleneck_is_highest_clock() {
+        let db = DatabaseVersion::LATEST.load_database();
+        let mut smelter = manufacturer("Desc_SmelterMk1_C", "Recipe_IngotIron_C", 1.0, 1)
+            .build_node(&db)
+            .unwrap()
+            .building()
+            .unwrap()
+            .clone();
+        smelter.settings.set_clock_speed(2.0);
+        let factory = group(vec![
+            iron_miner(&db),
+            smelter.build_node(&db).unwrap(),
+            manufacturer("Desc_ConstructorMk1_C", "Recipe_IronPlate_C", 1.0, 2)
+                .build_node(&db)
+                .unwrap(),
+        ]);
+        let trace = ProductionTrace::new(&factory, &[2], "Desc_IronPlate_C".into()).unwrap();
+        assert_eq!(trace.producers[0].clock_headroom(&db), Some(1.5));
+        assert_eq!(trace.bottleneck(&db), Some(&[1][..]));
+    }
+
+    #[test]
+    fn item_report_running_net() {
+        let db = DatabaseVersion::LATEST.load_database();
+        let factory = group(vec![
+            manufacturer("Desc_SmelterMk1_C", "Recipe_IngotIron_C", 1.0, 1)
+                .build_node(&db)
+                .unwrap(),
+            Group {
+                name: "Plates, Rods".into(),
+                children: vec![
+                    manufacturer("Desc_ConstructorMk1_C", "Recipe_IronPlate_C", 1.0, 1)
+                        .build_node(&db)
+                        .unwrap(),
+                    manufacturer("Desc_ConstructorMk1_C", "Recipe_IronRod_C", 1.0, 1)
+                        .build_node(&db)
+                        .unwrap(),
+                ],
+                copies: 2,
+                ..Group::empty()
+            }
+            .into(),
+        ]);
+        let report = ItemReport::new(&factory, "Desc_IronIngot_C".into());
+        let rates: Vec<_> = report.rows.iter().map(|row| row.rate).collect();
+        assert_eq!(rates, vec![30.0, -60.0, -30.0]);
+        let nets: Vec<_> = report.rows.iter().map(|row| row.running_net).collect();
+        assert_eq!(nets, vec![30.0, -30.0, -60.0]);
+        assert_eq!(report.net(), -60.0);
+        assert_eq!(report.produced(), 30.0);
+        assert_eq!(report.consumed(), -90.0);
+        assert_eq!(report.rows[1].path, vec![1, 0]);
+        assert_eq!(report.rows[1].location, vec![IString::from("Plates, Rods")]);
+
+        let csv = report.to_csv(&db);
+        let mut lines = csv.lines();
+        assert_eq!(lines.next(), Some("Location,Building,Rate,Running Net"));
+        assert_eq!(lines.next(), Some(",Smelter,30,30"));
+        assert_eq!(lines.next(), Some("\"Plates, Rods\",Constructor,-60,-30"));
+
+        let text = report.to_text(&db);
+        assert!(text.starts_with("Iron Ingot\nSmelter: +30 (net 30)\n"));
+        assert!(text.ends_with("Net: -60\n"));
+    }
+
+    #[test]
+    fn find_producers_and_consumers() {
+        let db = DatabaseVersion::LATEST.load_database();
+        let factory = group(vec![
+            iron_miner(&db),
+            manufacturer("Desc_SmelterMk1_C", "Recipe_IngotIron_C", 1.0, 1)
+                .build_node(&db)
+                .unwrap(),
+            Group {
+                children: vec![
+                    manufacturer("Desc_SmelterMk1_C", "Recipe_IngotIron_C", 1.0, 2)
+                        .build_node(&db)
+                        .unwrap(),
+                    manufacturer("Desc_ConstructorMk1_C", "Recipe_IronPlate_C", 1.0, 1)
+                        .build_node(&db)
+                        .unwrap(),
+                ],
+                copies: 3,
+                ..Group::empty()
+            }
+            .into(),
+        ]);
+        let ore = "Desc_OreIron_C".into();
+        assert_eq!(
+            factory.find_item_flows(ore),
+            vec![(vec![0], 60.0), (vec![1], -30.0), (vec![2, 0], -180.0)]
+        );
+        assert_eq!(
+            factory.find_item_flows("Desc_IronPlate_C".into()),
+            vec![(vec![2, 1], 60.0)]
+        );
+        assert!(factory.find_item_flows("Desc_Coal_C".into()).is_empty());
+        // Searching a subtree gives paths relative to it.
+        let inner = factory.get_at(&[2]).unwrap();
+        assert_eq!(inner.find_item_flows(ore), vec![(vec![0], -180.0)]);
+    }
+
+    #[test]
+    fn missing_producers_are_unsourced() {
+        let db = DatabaseVersion::LATEST.load_database();
+        let factory = group(vec![manufacturer(
+            "Desc_ConstructorMk1_C",
+            "Recipe_IronPlate_C",
+            1.0,
+            1,
+        )
+        .build_node(&db)
+        .unwrap()]);
+        let trace = ProductionTrace::new(&factory, &[], "Desc_IronPlate_C".into()).unwrap();
+        let ingots = &trace.producers[0].inputs[0];
+        assert!(ingots.producers.is_empty());
+        assert_eq!(ingots.unsourced, 30.0);
+
+        assert!(ProductionTrace::new(&factory, &[3], "Desc_IronPlate_C".into()).is_none());
+    }
+}
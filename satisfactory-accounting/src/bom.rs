@@ -0,0 +1,200 @@
+// Copyright 2024 Zachary Stewart
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//! Target output rates for a factory, and how the factory measures up against them.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::accounting::Balance;
+use crate::database::{Database, ItemId};
+
+/// Actual rates within this much of the target count as meeting it, matching the
+/// precision rates are displayed with.
+const TOLERANCE: f32 = 0.01;
+
+/// Error from parsing a bill of materials.
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum BomParseError {
+    #[error("Line {line}: expected \"Item Name: rate\".")]
+    MissingRate { line: usize },
+    #[error("Line {line}: no item named \"{name}\".")]
+    UnknownItem { line: usize, name: String },
+    #[error("Line {line}: \"{rate}\" is not a valid rate.")]
+    InvalidRate { line: usize, rate: String },
+}
+
+/// Target net output rate of each item, in units per minute.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct BillOfMaterials(BTreeMap<ItemId, f32>);
+
+impl BillOfMaterials {
+    /// Parse a bill of materials with one `Item Name: rate` entry per line. Items can be
+    /// given by name, ignoring case, or by id. Blank lines and lines starting with `#`
+    /// are ignored. Repeated items add up.
+    pub fn parse(text: &str, database: &Database) -> Result<Self, BomParseError> {
+        let mut targets = BTreeMap::new();
+        for (idx, line) in text.lines().enumerate() {
+            let line_number = idx + 1;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (name, rate) = line
+                .rsplit_once(':')
+                .ok_or(BomParseError::MissingRate { line: line_number })?;
+            let (name, rate) = (name.trim(), rate.trim());
+            let item = database
+                .items
+                .values()
+                .find(|item| item.name.eq_ignore_ascii_case(name) || item.id.to_string() == name)
+                .ok_or_else(|| BomParseError::UnknownItem {
+                    line: line_number,
+                    name: name.to_owned(),
+                })?;
+            let rate: f32 = rate
+                .parse()
+                .ok()
+                .filter(|rate: &f32| rate.is_finite() && *rate >= 0.0)
+                .ok_or_else(|| BomParseError::InvalidRate {
+                    line: line_number,
+                    rate: rate.to_owned(),
+                })?;
+            *targets.entry(item.id).or_default() += rate;
+        }
+        Ok(Self(targets))
+    }
+
+    /// Write the bill of materials in the format read by [`BillOfMaterials::parse`].
+    /// Items missing from the database are written by id.
+    pub fn format(&self, database: &Database) -> String {
+        self.0
+            .iter()
+            .map(|(&id, rate)| match database.get(id) {
+                Some(item) => format!("{}: {rate}\n", item.name),
+                None => format!("{id}: {rate}\n"),
+            })
+            .collect()
+    }
+
+    /// Returns true if there are no targets.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Compare the net outputs in `balance` against each target, in item id order.
+    pub fn compare(&self, balance: &Balance) -> Vec<TargetComparison> {
+        self.0
+            .iter()
+            .map(|(&item, &target)| {
+                let actual = balance.balances.get(&item).copied().unwrap_or(0.0);
+                let status = if (actual - target).abs() < TOLERANCE {
+                    TargetStatus::Met
+                } else if actual > target {
+                    TargetStatus::Over
+                } else {
+                    TargetStatus::Short
+                };
+                TargetComparison {
+                    item,
+                    target,
+                    actual,
+                    status,
+                }
+            })
+            .collect()
+    }
+}
+
+/// How the net output of an item compares to its target.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum TargetStatus {
+    /// The factory makes exactly the target rate.
+    Met,
+    /// The factory makes more than the target rate.
+    Over,
+    /// The factory makes less than the target rate.
+    Short,
+}
+
+/// Comparison of one item's net output against its target.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct TargetComparison {
+    /// Item being compared.
+    pub item: ItemId,
+    /// Target net output, in units per minute.
+    pub target: f32,
+    /// Actual net output, in units per minute.
+    pub actual: f32,
+    /// How the actual output compares to the target.
+    pub status: TargetStatus,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::DatabaseVersion;
+
+    #[test]
+    fn parse_and_format() {
+        let db = DatabaseVersion::LATEST.load_database();
+        let bom = BillOfMaterials::parse(
+            "# Phase 2\nreinforced iron plate: 5\n\nDesc_Rotor_C: 4\nRotor: 0.5\n",
+            &db,
+        )
+        .unwrap();
+        let expected = BillOfMaterials(BTreeMap::from([
+            ("Desc_IronPlateReinforced_C".into(), 5.0),
+            ("Desc_Rotor_C".into(), 4.5),
+        ]));
+        assert_eq!(bom, expected);
+        assert_eq!(BillOfMaterials::parse(&bom.format(&db), &db).unwrap(), bom);
+    }
+
+    #[test]
+    fn parse_errors() {
+        let db = DatabaseVersion::LATEST.load_database();
+        assert_eq!(
+            BillOfMaterials::parse("Rotor: 4\nRotor 4", &db),
+            Err(BomParseError::MissingRate { line: 2 })
+        );
+        assert_eq!(
+            BillOfMaterials::parse("Unobtanium: 4", &db),
+            Err(BomParseError::UnknownItem {
+                line: 1,
+                name: "Unobtanium".into()
+            })
+        );
+        assert_eq!(
+            BillOfMaterials::parse("Rotor: lots", &db),
+            Err(BomParseError::InvalidRate {
+                line: 1,
+                rate: "lots".into()
+            })
+        );
+    }
+
+    #[test]
+    fn compare_targets() {
+        let rotor = "Desc_Rotor_C".into();
+        let plate = "Desc_IronPlate_C".into();
+        let screw = "Desc_IronScrew_C".into();
+        let bom = BillOfMaterials(BTreeMap::from([(rotor, 4.0), (plate, 20.0), (screw, 10.0)]));
+        let balance = Balance::new(-100.0, [(rotor, 4.0), (plate, 30.0), (screw, -5.0)]);
+        let statuses: BTreeMap<_, _> = bom
+            .compare(&balance)
+            .into_iter()
+            .map(|comparison| (comparison.item, comparison.status))
+            .collect();
+        assert_eq!(statuses[&rotor], TargetStatus::Met);
+        assert_eq!(statuses[&plate], TargetStatus::Over);
+        assert_eq!(statuses[&screw], TargetStatus::Short);
+    }
+}
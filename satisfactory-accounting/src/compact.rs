@@ -0,0 +1,101 @@
+// Copyright 2024 Zachary Stewart
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//! Compact binary serialization of node trees and factories, for places where JSON is too
+//! large, such as sharing a factory in a URL.
+//!
+//! The format is a short header identifying the format and its version, followed by the
+//! tree encoded as MessagePack and compressed with DEFLATE. JSON remains the interchange
+//! format; this is only meant to be decoded by this library.
+
+use miniz_oxide::deflate::compress_to_vec;
+use miniz_oxide::inflate::decompress_to_vec_with_limit;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use thiserror::Error;
+
+/// Bytes at the start of every compact encoding.
+const MAGIC: &[u8; 3] = b"SAC";
+
+/// Version of the compact format written by [`encode`].
+pub const FORMAT_VERSION: u8 = 1;
+
+/// Compression level used for DEFLATE.
+const COMPRESSION_LEVEL: u8 = 9;
+
+/// Largest decompressed size accepted when decoding, to avoid decompression bombs.
+const MAX_DECOMPRESSED_SIZE: usize = 64 * 1024 * 1024;
+
+/// Error when encoding or decoding the compact format.
+#[derive(Error, Debug)]
+pub enum CompactError {
+    #[error("Data is not in the compact factory format.")]
+    BadHeader,
+    #[error("Compact format version {0} is not supported.")]
+    UnsupportedVersion(u8),
+    #[error("Unable to decompress data.")]
+    Decompress,
+    #[error("Unable to encode factory: {0}")]
+    Encode(#[from] rmp_serde::encode::Error),
+    #[error("Unable to decode factory: {0}")]
+    Decode(#[from] rmp_serde::decode::Error),
+}
+
+/// Encode a node tree, or a factory holding one, in the compact format.
+pub fn encode<T: Serialize>(value: &T) -> Result<Vec<u8>, CompactError> {
+    // Struct fields are encoded by name so that optional and defaulted fields still work.
+    let packed = rmp_serde::to_vec_named(value)?;
+    let mut encoded = Vec::with_capacity(MAGIC.len() + 1 + packed.len() / 4);
+    encoded.extend_from_slice(MAGIC);
+    encoded.push(FORMAT_VERSION);
+    encoded.extend(compress_to_vec(&packed, COMPRESSION_LEVEL));
+    Ok(encoded)
+}
+
+/// Decode a node tree, or a factory holding one, from the compact format.
+pub fn decode<T: DeserializeOwned>(data: &[u8]) -> Result<T, CompactError> {
+    let data = data.strip_prefix(MAGIC).ok_or(CompactError::BadHeader)?;
+    let (&version, data) = data.split_first().ok_or(CompactError::BadHeader)?;
+    if version != FORMAT_VERSION {
+        return Err(CompactError::UnsupportedVersion(version));
+    }
+    let packed = decompress_to_vec_with_limit(data, MAX_DECOMPRESSED_SIZE)
+        .map_err(|_| CompactError::Decompress)?;
+    Ok(rmp_serde::from_slice(&packed)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::accounting::Node;
+    use crate::sample::sample_factory;
+
+    #[test]
+    fn round_trip_is_smaller_than_json() {
+        let (factory, _) = sample_factory();
+        let json = serde_json::to_vec(&factory).unwrap();
+        let compact = encode(&factory).unwrap();
+        assert_eq!(decode::<Node>(&compact).unwrap(), factory);
+        assert!(compact.len() * 5 < json.len());
+    }
+
+    #[test]
+    fn rejects_bad_headers() {
+        assert!(matches!(
+            decode::<Node>(b"{}"),
+            Err(CompactError::BadHeader)
+        ));
+        assert!(matches!(
+            decode::<Node>(b"SAC"),
+            Err(CompactError::BadHeader)
+        ));
+        assert!(matches!(
+            decode::<Node>(b"SAC\x02"),
+            Err(CompactError::UnsupportedVersion(2))
+        ));
+    }
+}
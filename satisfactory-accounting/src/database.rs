@@ -6,7 +6,7 @@
 //
 //       http://www.apache.org/licenses/LICENSE-2.0
 use std::cmp::Ordering;
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 use std::fmt;
 use std::hash::Hash;
 use std::ops::Index;
@@ -14,6 +14,7 @@ use std::rc::Rc;
 
 use internment::Intern;
 use serde::{Deserialize, Serialize};
+use thiserror::Error;
 
 use crate::accounting::{
     BuildingSettings, GeneratorSettings, ManufacturerSettings, MinerSettings, PumpSettings,
@@ -69,7 +70,10 @@ macro_rules! db_version_info {
                 $(
                     $version_pat => {
                         const SERIALIZED_DB: &str = include_str!($file);
-                        serde_json::from_str(SERIALIZED_DB).expect(concat!("Failed to parse ", $file))
+                        let mut database: Database = serde_json::from_str(SERIALIZED_DB)
+                            .expect(concat!("Failed to parse ", $file));
+                        database.version = Some(self);
+                        database
                     }
                 )*
             }
@@ -216,6 +220,10 @@ impl fmt::Display for DatabaseVersion {
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Database {
     /// Which version of the database this is, if it corresponds to a particular version.
+    /// Custom databases have no version.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<DatabaseVersion>,
+    /// Prefix added to the path of every icon.
     #[serde(default)]
     pub icon_prefix: String,
     /// Core recipe storage. We only store machine recipes.
@@ -226,7 +234,89 @@ pub struct Database {
     pub buildings: BTreeMap<BuildingId, BuildingType>,
 }
 
+/// Problem found when loading a database which didn't come with the library, such as one
+/// for a modded game.
+#[derive(Error, Debug)]
+pub enum DatabaseError {
+    #[error("Unable to parse database: {0}")]
+    Parse(#[from] serde_json::Error),
+    #[error("Database has no buildings.")]
+    Empty,
+    #[error("Entry {key} has mismatched ID {id}.")]
+    MismatchedId { key: String, id: String },
+    #[error("Recipe {0} doesn't take a positive, finite amount of time.")]
+    InvalidTime(RecipeId),
+    #[error("Recipe {recipe} uses unknown item {item}.")]
+    UnknownItem { recipe: RecipeId, item: ItemId },
+    #[error("Recipe {recipe} is made in unknown building {building}.")]
+    UnknownBuilding {
+        recipe: RecipeId,
+        building: BuildingId,
+    },
+}
+
 impl Database {
+    /// Parse a database from JSON and check that it is consistent, so it can be used in
+    /// place of the standard ones. The result has no version.
+    pub fn from_json(json: &str) -> Result<Self, DatabaseError> {
+        let mut database: Database = serde_json::from_str(json)?;
+        database.version = None;
+        database.validate()?;
+        Ok(database)
+    }
+
+    /// Check that every entry is stored under its own ID and that recipes only use items
+    /// and buildings in this database. Buildings may list fuels, resources, or recipes the
+    /// database lacks, as the standard databases do; those are shown as unknown.
+    pub fn validate(&self) -> Result<(), DatabaseError> {
+        fn check_key<T: Id>(key: T, id: T) -> Result<(), DatabaseError> {
+            if key == id {
+                Ok(())
+            } else {
+                Err(DatabaseError::MismatchedId {
+                    key: key.to_string(),
+                    id: id.to_string(),
+                })
+            }
+        }
+
+        if self.buildings.is_empty() {
+            return Err(DatabaseError::Empty);
+        }
+        for (&key, item) in &self.items {
+            check_key(key, item.id)?;
+        }
+        for (&key, building) in &self.buildings {
+            check_key(key, building.id)?;
+        }
+        for (&key, recipe) in &self.recipes {
+            check_key(key, recipe.id)?;
+            if !recipe.time.is_finite() || recipe.time <= 0.0 {
+                return Err(DatabaseError::InvalidTime(key));
+            }
+            if let Some(item) = recipe
+                .ingredients
+                .iter()
+                .chain(&recipe.products)
+                .map(|amount| amount.item)
+                .find(|&item| self.get(item).is_none())
+            {
+                return Err(DatabaseError::UnknownItem { recipe: key, item });
+            }
+            if let Some(&building) = recipe
+                .produced_in
+                .iter()
+                .find(|&&building| self.get(building).is_none())
+            {
+                return Err(DatabaseError::UnknownBuilding {
+                    recipe: key,
+                    building,
+                });
+            }
+        }
+        Ok(())
+    }
+
     /// Get an item, recipe, or building by id.
     pub fn get<T: Id>(&self, id: T) -> Option<&<T as Id>::Info> {
         id.fetch(self)
@@ -237,7 +327,8 @@ impl Database {
         DatabaseVersion::U7(U7Subversion::Initial).load_database()
     }
 
-    /// Compare this database to another database, ignoring their icon prefixes.
+    /// Compare this database to another database, ignoring their icon prefixes and
+    /// versions.
     pub fn compare_ignore_prefix(&self, other: &Database) -> bool {
         self.recipes == other.recipes
             && self.items == other.items
@@ -397,17 +488,44 @@ pub struct Recipe {
     pub products: Vec<ItemAmount>,
     /// True if this is an alternate recipe.
     pub is_alternate: bool,
-    /// Buildings which can produce this recipe.
+    /// Buildings which can produce this recipe. The first is the building the recipe is
+    /// normally made in.
     pub produced_in: Vec<BuildingId>,
 }
 
+impl Recipe {
+    /// Whether this is a standard recipe normally made in the given building, rather than
+    /// an alternate or a recipe whose usual home is another building.
+    pub fn is_standard_for(&self, building: BuildingId) -> bool {
+        !self.is_alternate && self.produced_in.first() == Some(&building)
+    }
+
+    /// Number of times this recipe completes per minute in a building running at `speed`,
+    /// the product of its manufacturing speed and clock speed.
+    pub fn runs_per_minute(&self, speed: f32) -> f32 {
+        60.0 / self.time * speed
+    }
+
+    /// Number of distinct ingredients of this recipe which are in `available`.
+    pub fn input_overlap(&self, available: &BTreeSet<ItemId>) -> usize {
+        self.ingredients
+            .iter()
+            .filter(|ingredient| available.contains(&ingredient.item))
+            .count()
+    }
+}
+
 /// An input or output: a certain number of items produced or consumed.
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ItemAmount {
     /// Id of the item(s).
     pub item: ItemId,
-    /// Number of items produced/consumed. Can only be fractional for fluids.
+    /// Number of items produced/consumed. Can only be fractional for fluids, or for
+    /// averaged outputs.
     pub amount: f32,
+    /// True if the actual amount varies between runs and `amount` is the expected value.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub averaged: bool,
 }
 
 /// A solid or liquid item used in crafting.
@@ -433,6 +551,12 @@ pub struct Item {
     pub mined_by: Vec<BuildingId>,
     /// Speed that this resource is mined at.
     pub mining_speed: f32,
+    /// Whether this item is a fluid, moved by pipes rather than belts.
+    #[serde(default)]
+    pub fluid: bool,
+    /// AWESOME Sink points awarded for each of this item sunk, or 0 if it can't be sunk.
+    #[serde(default)]
+    pub sink_points: u32,
 }
 
 /// Settings for an item used as fuel.
@@ -467,6 +591,9 @@ pub struct BuildingType {
     pub description: String,
     /// Kind of the building.
     pub kind: BuildingKind,
+    /// Floor space taken up by the building, if known.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub footprint: Option<Footprint>,
 }
 
 impl BuildingType {
@@ -489,6 +616,95 @@ impl BuildingType {
             BuildingKind::Station(_) => false,
         }
     }
+
+    /// Get the broad category of machine this building belongs to.
+    pub fn category(&self) -> BuildingCategory {
+        match &self.kind {
+            BuildingKind::Manufacturer(_) => match self.id.as_str() {
+                "Desc_SmelterMk1_C" | "Desc_FoundryMk1_C" => BuildingCategory::Smelting,
+                "Desc_OilRefinery_C" | "Desc_Blender_C" | "Desc_Packager_C" => {
+                    BuildingCategory::Refining
+                }
+                "Desc_HadronCollider_C" | "Desc_Converter_C" | "Desc_QuantumEncoder_C" => {
+                    BuildingCategory::Advanced
+                }
+                "Desc_WaterPump_C" => BuildingCategory::Extraction,
+                "Desc_Portal_C" | "Desc_PortalSatellite_C" => BuildingCategory::Other,
+                _ => BuildingCategory::Production,
+            },
+            BuildingKind::Miner(_) | BuildingKind::Pump(_) => BuildingCategory::Extraction,
+            BuildingKind::Generator(_) | BuildingKind::Geothermal(_) => BuildingCategory::Power,
+            BuildingKind::Station(_) => BuildingCategory::Logistics,
+            BuildingKind::PowerConsumer(_) => BuildingCategory::Other,
+        }
+    }
+}
+
+/// Size of a building on the floor, in meters.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Footprint {
+    /// Width of the building.
+    pub width: f32,
+    /// Length of the building.
+    pub length: f32,
+}
+
+impl Footprint {
+    /// Floor area of the building in square meters.
+    pub fn area(&self) -> f32 {
+        self.width * self.length
+    }
+}
+
+/// Broad category of machine, used to visually distinguish different types of buildings.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum BuildingCategory {
+    /// Buildings which turn ores into ingots.
+    Smelting,
+    /// Buildings which assemble solid parts.
+    Production,
+    /// Buildings which primarily process fluids.
+    Refining,
+    /// Late-game buildings such as the Particle Accelerator and Quantum Encoder.
+    Advanced,
+    /// Buildings which extract raw resources.
+    Extraction,
+    /// Buildings which generate power.
+    Power,
+    /// Vehicle stations.
+    Logistics,
+    /// Anything else.
+    Other,
+}
+
+impl BuildingCategory {
+    /// Get a string suitable for human display of this category.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Smelting => "Smelting",
+            Self::Production => "Production",
+            Self::Refining => "Refining",
+            Self::Advanced => "Advanced",
+            Self::Extraction => "Extraction",
+            Self::Power => "Power",
+            Self::Logistics => "Logistics",
+            Self::Other => "Other",
+        }
+    }
+
+    /// Get a string suitable for identifying this category.
+    pub fn ident(self) -> &'static str {
+        match self {
+            Self::Smelting => "smelting",
+            Self::Production => "production",
+            Self::Refining => "refining",
+            Self::Advanced => "advanced",
+            Self::Extraction => "extraction",
+            Self::Power => "power",
+            Self::Logistics => "logistics",
+            Self::Other => "other",
+        }
+    }
 }
 
 /// Which kind of building this is (affects how resources are produced/consumed).
@@ -589,6 +805,21 @@ pub enum BuildingKindId {
     Station,
 }
 
+impl BuildingKindId {
+    /// Get a string suitable for human display of this kind of building.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Manufacturer => "Manufacturers",
+            Self::Miner => "Miners",
+            Self::Generator => "Generators",
+            Self::Pump => "Pumps",
+            Self::Geothermal => "Geothermal",
+            Self::PowerConsumer => "Power Consumers",
+            Self::Station => "Stations",
+        }
+    }
+}
+
 /// Power-usage information for a building.
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Power {
@@ -621,6 +852,10 @@ pub struct Manufacturer {
     pub available_recipes: Vec<RecipeId>,
     /// Power usage of manufacturing.
     pub power_consumption: Power,
+    /// Number of Somersloops which can be slotted to amplify production. Zero for buildings
+    /// which can't be amplified, and in databases from before 1.0.
+    #[serde(default)]
+    pub somersloop_slots: u32,
 }
 
 /// Miner settings of a building.
@@ -686,3 +921,70 @@ pub struct Station {
 mod private {
     pub trait Sealed {}
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn standard_databases_are_valid() {
+        for &version in DatabaseVersion::ALL {
+            if let Err(e) = version.load_database().validate() {
+                panic!("{version} is invalid: {e}");
+            }
+        }
+    }
+
+    #[test]
+    fn standard_databases_have_footprints() {
+        for &version in DatabaseVersion::ALL {
+            let db = version.load_database();
+            for id in [
+                "Desc_ConstructorMk1_C",
+                "Desc_MinerMk1_C",
+                "Desc_GeneratorCoal_C",
+            ] {
+                let building = db.get(BuildingId::from(id)).unwrap();
+                assert!(
+                    building.footprint.is_some(),
+                    "{version} has no footprint for {id}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn load_custom_database() {
+        let db = DatabaseVersion::LATEST.load_database();
+        let json = serde_json::to_string(&db).unwrap();
+        let custom = Database::from_json(&json).unwrap();
+        assert_eq!(custom.version, None);
+        assert!(custom.compare_ignore_prefix(&db));
+
+        assert!(matches!(
+            Database::from_json("{\"recipes\": []}"),
+            Err(DatabaseError::Parse(_))
+        ));
+
+        let mut broken = db.clone();
+        let plate = RecipeId::from("Recipe_IronPlate_C");
+        broken
+            .recipes
+            .get_mut(&plate)
+            .unwrap()
+            .produced_in
+            .push("Desc_Modded_C".into());
+        let json = serde_json::to_string(&broken).unwrap();
+        assert!(matches!(
+            Database::from_json(&json),
+            Err(DatabaseError::UnknownBuilding { recipe, .. }) if recipe == plate
+        ));
+
+        let mut broken = db.clone();
+        broken.items.remove(&ItemId::water());
+        assert!(matches!(
+            broken.validate(),
+            Err(DatabaseError::UnknownItem { item, .. }) if item == ItemId::water()
+        ));
+    }
+}
@@ -0,0 +1,226 @@
+// Copyright 2024 Zachary Stewart
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//! Envelope for sharing a whole factory along with information describing it.
+
+use std::collections::BTreeMap;
+
+use implicit_clone::unsync::IString;
+use serde::{Deserialize, Serialize};
+
+use crate::accounting::{Group, Node};
+use crate::database::{Database, DatabaseVersion, ItemId};
+
+/// Descriptive information about a factory which travels with it when it is exported or
+/// shared. Every field is optional and empty by default.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FactoryMetadata {
+    /// Title of the factory.
+    #[serde(default, skip_serializing_if = "str::is_empty")]
+    pub title: IString,
+    /// Person who built the factory.
+    #[serde(default, skip_serializing_if = "str::is_empty")]
+    pub author: IString,
+    /// Free-form description of the factory.
+    #[serde(default, skip_serializing_if = "str::is_empty")]
+    pub description: IString,
+    /// Version of the game the factory was designed for.
+    #[serde(default, skip_serializing_if = "str::is_empty")]
+    pub game_version: IString,
+}
+
+impl FactoryMetadata {
+    /// Returns true if none of the metadata fields are set.
+    pub fn is_empty(&self) -> bool {
+        self.title.is_empty()
+            && self.author.is_empty()
+            && self.description.is_empty()
+            && self.game_version.is_empty()
+    }
+}
+
+/// Name and icon of an item, remembered so the item can still be shown meaningfully if it
+/// is missing from a later database.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ItemLabel {
+    /// Name of the item.
+    pub name: IString,
+    /// Slug of the item's icon.
+    pub image: IString,
+}
+
+/// Last known labels of the items used in a factory.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct ItemNames(BTreeMap<ItemId, ItemLabel>);
+
+impl ItemNames {
+    /// Build the labels for every item in the balance of any node under `root`. Items in
+    /// the database use its current name; items missing from it keep their label from
+    /// `previous`, if any. Items no longer used are forgotten.
+    pub fn collect(root: &Node, database: &Database, previous: &ItemNames) -> Self {
+        let mut names = BTreeMap::new();
+        for node in root.iter() {
+            for &id in node.balance().balances.keys() {
+                if names.contains_key(&id) {
+                    continue;
+                }
+                let label = match database.get(id) {
+                    Some(item) => Some(ItemLabel {
+                        name: item.name.to_string().into(),
+                        image: item.image.to_string().into(),
+                    }),
+                    None => previous.get(id).cloned(),
+                };
+                if let Some(label) = label {
+                    names.insert(id, label);
+                }
+            }
+        }
+        Self(names)
+    }
+
+    /// Get the last known label for an item.
+    pub fn get(&self, id: ItemId) -> Option<&ItemLabel> {
+        self.0.get(&id)
+    }
+
+    /// Returns true if no labels are known.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+/// A factory root node together with its metadata, as exported or shared.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Factory {
+    /// Information describing the factory.
+    #[serde(default, skip_serializing_if = "FactoryMetadata::is_empty")]
+    pub metadata: FactoryMetadata,
+    /// Last known labels of items in the factory, for items missing from the database
+    /// used by whoever loads it.
+    #[serde(default, skip_serializing_if = "ItemNames::is_empty")]
+    pub item_names: ItemNames,
+    /// Version of the database the factory was built with, used to migrate IDs which
+    /// have since been renamed. None for custom databases and older exports.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub database_version: Option<DatabaseVersion>,
+    /// Root node of the factory.
+    pub root: Node,
+}
+
+impl Factory {
+    /// Build a standalone factory with a copy of `group` as its root. Group ids are
+    /// regenerated so the factory can be loaded alongside the one it came from, and the
+    /// new root is reduced to a single copy, since the root can't have virtual copies.
+    pub fn export(
+        group: &Group,
+        metadata: FactoryMetadata,
+        database: &Database,
+        previous_names: &ItemNames,
+    ) -> Self {
+        let mut root = group.create_copy();
+        root.copies = 1;
+        let root: Node = root.into();
+        let item_names = ItemNames::collect(&root, database, previous_names);
+        Self {
+            metadata,
+            item_names,
+            database_version: database.version,
+            root,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::accounting::{BuildNode, Group};
+    use crate::sample::iron_plate_constructor;
+
+    #[test]
+    fn metadata_round_trip() {
+        let factory = Factory {
+            metadata: FactoryMetadata {
+                title: "Steel Mill".into(),
+                author: "Pioneer".into(),
+                description: "Makes steel beams and pipes.".into(),
+                game_version: "1.0".into(),
+            },
+            item_names: Default::default(),
+            database_version: Some(DatabaseVersion::LATEST),
+            root: Group::empty_node(),
+        };
+        let json = serde_json::to_string(&factory).unwrap();
+        assert_eq!(serde_json::from_str::<Factory>(&json).unwrap(), factory);
+    }
+
+    #[test]
+    fn metadata_is_optional() {
+        let factory = Factory {
+            metadata: Default::default(),
+            item_names: Default::default(),
+            database_version: None,
+            root: Group::empty_node(),
+        };
+        let json = serde_json::to_value(&factory).unwrap();
+        assert!(json.get("metadata").is_none());
+        assert!(json.get("database_version").is_none());
+        assert_eq!(serde_json::from_value::<Factory>(json).unwrap(), factory);
+    }
+
+    #[test]
+    fn export_group_as_root() {
+        let db = DatabaseVersion::LATEST.load_database();
+        let inner = Group {
+            name: "Plates".into(),
+            children: vec![iron_plate_constructor(1.0, 1).build_node(&db).unwrap()],
+            copies: 3,
+            ..Group::empty()
+        };
+        let metadata = FactoryMetadata {
+            title: "Plates".into(),
+            ..Default::default()
+        };
+        let factory = Factory::export(&inner, metadata.clone(), &db, &Default::default());
+        assert_eq!(factory.metadata, metadata);
+        assert_eq!(factory.database_version, Some(DatabaseVersion::LATEST));
+
+        let root = factory.root.group().unwrap();
+        assert_ne!(root.id, inner.id);
+        assert_eq!(root.name, inner.name);
+        assert_eq!(root.copies, 1);
+        assert_eq!(root.children, inner.children);
+        // The balance is for a single copy of the exported group.
+        assert_eq!(
+            factory.root.balance().balances[&"Desc_IronPlate_C".into()],
+            20.0
+        );
+        assert!(factory.item_names.get("Desc_IronPlate_C".into()).is_some());
+    }
+
+    #[test]
+    fn item_names_survive_missing_items() {
+        let db = DatabaseVersion::LATEST.load_database();
+        let root: Node = Group {
+            children: vec![iron_plate_constructor(1.0, 1).build_node(&db).unwrap()],
+            ..Group::empty()
+        }
+        .into();
+        let plate = "Desc_IronPlate_C".into();
+        let names = ItemNames::collect(&root, &db, &Default::default());
+        assert_eq!(&*names.get(plate).unwrap().name, "Iron Plate");
+        assert!(names.get("Desc_IronIngot_C".into()).is_some());
+
+        let mut changed = db.clone();
+        changed.items.remove(&plate);
+        let kept = ItemNames::collect(&root, &changed, &names);
+        assert_eq!(kept, names);
+        let lost = ItemNames::collect(&root, &changed, &Default::default());
+        assert!(lost.get(plate).is_none());
+    }
+}
@@ -6,4 +6,11 @@
 //
 //       http://www.apache.org/licenses/LICENSE-2.0
 pub mod accounting;
+pub mod bom;
+pub mod compact;
 pub mod database;
+pub mod factory;
+pub mod logistics;
+pub mod migration;
+#[cfg(any(test, feature = "sample"))]
+pub mod sample;
@@ -0,0 +1,176 @@
+// Copyright 2024 Zachary Stewart
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//! Estimates of the belts and pipes needed to move items around a factory.
+
+use serde::{Deserialize, Serialize};
+
+use crate::database::Item;
+
+/// Rates within this much of a multiple of a line's capacity still fit on that many lines,
+/// to avoid asking for an extra belt because of floating point noise.
+const EPSILON: f32 = 1e-3;
+
+/// Tier of conveyor belt used to move solid items.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub enum BeltTier {
+    Mk1,
+    Mk2,
+    Mk3,
+    Mk4,
+    Mk5,
+    #[default]
+    Mk6,
+}
+
+impl BeltTier {
+    /// All belt tiers, slowest first.
+    pub const ALL: &'static [BeltTier] = &[
+        Self::Mk1,
+        Self::Mk2,
+        Self::Mk3,
+        Self::Mk4,
+        Self::Mk5,
+        Self::Mk6,
+    ];
+
+    /// Items per minute that a belt of this tier can carry.
+    pub fn throughput(self) -> f32 {
+        match self {
+            Self::Mk1 => 60.0,
+            Self::Mk2 => 120.0,
+            Self::Mk3 => 270.0,
+            Self::Mk4 => 480.0,
+            Self::Mk5 => 780.0,
+            Self::Mk6 => 1200.0,
+        }
+    }
+
+    /// Display name of this tier.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Mk1 => "Conveyor Belt Mk.1",
+            Self::Mk2 => "Conveyor Belt Mk.2",
+            Self::Mk3 => "Conveyor Belt Mk.3",
+            Self::Mk4 => "Conveyor Belt Mk.4",
+            Self::Mk5 => "Conveyor Belt Mk.5",
+            Self::Mk6 => "Conveyor Belt Mk.6",
+        }
+    }
+}
+
+/// Tier of pipeline used to move fluids.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub enum PipeTier {
+    Mk1,
+    #[default]
+    Mk2,
+}
+
+impl PipeTier {
+    /// All pipe tiers, slowest first.
+    pub const ALL: &'static [PipeTier] = &[Self::Mk1, Self::Mk2];
+
+    /// Cubic meters per minute that a pipeline of this tier can carry.
+    pub fn throughput(self) -> f32 {
+        match self {
+            Self::Mk1 => 300.0,
+            Self::Mk2 => 600.0,
+        }
+    }
+
+    /// Display name of this tier.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Mk1 => "Pipeline Mk.1",
+            Self::Mk2 => "Pipeline Mk.2",
+        }
+    }
+}
+
+/// Belts or pipes needed to carry a rate of some item.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Lines {
+    /// Whether the lines are pipes rather than belts.
+    pub pipes: bool,
+    /// Number of parallel belts or pipes needed.
+    pub count: u32,
+    /// Number of splitters (or pipe junctions) needed to divide a single stream across
+    /// all of the lines. Each splitter adds two outputs.
+    pub splitters: u32,
+}
+
+impl Lines {
+    /// Capacity of a single line carrying `item`, in items per minute, using belts of the
+    /// given tier for solid items and pipes of the given tier for fluids.
+    pub fn capacity(item: &Item, belt: BeltTier, pipe: PipeTier) -> f32 {
+        if item.fluid {
+            pipe.throughput()
+        } else {
+            belt.throughput()
+        }
+    }
+
+    /// Compute the lines needed to carry `rate` of `item` per minute, using belts of the
+    /// given tier for solid items and pipes of the given tier for fluids.
+    pub fn for_rate(item: &Item, rate: f32, belt: BeltTier, pipe: PipeTier) -> Self {
+        let capacity = Self::capacity(item, belt, pipe);
+        let count = ((rate.abs() / capacity) - EPSILON).ceil().max(1.0) as u32;
+        Self {
+            pipes: item.fluid,
+            count,
+            splitters: count / 2,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::{DatabaseVersion, ItemId};
+
+    #[test]
+    fn belts_and_pipes() {
+        let db = DatabaseVersion::LATEST.load_database();
+        let plate = db.get(ItemId::from("Desc_IronPlate_C")).unwrap();
+        let water = db.get(ItemId::from("Desc_Water_C")).unwrap();
+
+        let lines = Lines::for_rate(plate, 480.0, BeltTier::Mk4, PipeTier::Mk2);
+        assert_eq!((lines.pipes, lines.count, lines.splitters), (false, 1, 0));
+        let lines = Lines::for_rate(plate, 481.0, BeltTier::Mk4, PipeTier::Mk2);
+        assert_eq!((lines.count, lines.splitters), (2, 1));
+        let lines = Lines::for_rate(plate, 1400.0, BeltTier::Mk4, PipeTier::Mk2);
+        assert_eq!((lines.count, lines.splitters), (3, 1));
+
+        let lines = Lines::for_rate(water, 1500.0, BeltTier::Mk1, PipeTier::Mk2);
+        assert_eq!((lines.pipes, lines.count, lines.splitters), (true, 3, 1));
+    }
+
+    #[test]
+    fn fluids_use_pipe_limits() {
+        let db = DatabaseVersion::LATEST.load_database();
+        let crude = db.get(ItemId::from("Desc_LiquidOil_C")).unwrap();
+        let water = db.get(ItemId::from("Desc_Water_C")).unwrap();
+
+        // Fluids ignore the belt tier entirely, even one slower than the pipe.
+        for belt in [BeltTier::Mk1, BeltTier::Mk6] {
+            let lines = Lines::for_rate(crude, 300.0, belt, PipeTier::Mk1);
+            assert_eq!((lines.pipes, lines.count), (true, 1));
+            let lines = Lines::for_rate(crude, 301.0, belt, PipeTier::Mk1);
+            assert_eq!(lines.count, 2);
+
+            let lines = Lines::for_rate(water, 600.0, belt, PipeTier::Mk2);
+            assert_eq!((lines.pipes, lines.count), (true, 1));
+            let lines = Lines::for_rate(water, 601.0, belt, PipeTier::Mk2);
+            assert_eq!(lines.count, 2);
+        }
+        assert_eq!(
+            Lines::capacity(water, BeltTier::Mk1, PipeTier::Mk2),
+            PipeTier::Mk2.throughput()
+        );
+    }
+}
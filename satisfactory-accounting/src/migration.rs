@@ -0,0 +1,301 @@
+// Copyright 2024 Zachary Stewart
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//! Migrating factories built with one version of the database to another.
+//!
+//! Game updates occasionally rename buildings, recipes, or items, which leaves factories
+//! saved with an older database referring to IDs the new one doesn't have. [`migrate`]
+//! rewrites those stale IDs using the remaps registered in [`MIGRATIONS`], then rebuilds
+//! the tree. Any ID that still isn't in the database is kept as-is, so the building
+//! becomes a warning node showing the raw ID rather than being dropped.
+//!
+//! # Registering a remap
+//!
+//! When a new database version renames something, add a [`Migration`] to the end of
+//! [`MIGRATIONS`] whose `to` is the new [`DatabaseVersion`], listing each renamed ID as
+//! an `(old, new)` pair. Migrations must be kept in version order. A remap is only
+//! applied when the database being migrated to doesn't have the old ID, so it is safe to
+//! apply to trees whose version isn't known.
+
+use std::collections::BTreeMap;
+
+use crate::accounting::{
+    BuildNode, Building, BuildingSettings, Group, Node, NodeKind, RebuildReport,
+};
+use crate::database::{
+    BuildingId, Database, DatabaseVersion, Id, ItemId, RecipeId, V1_0Subversion,
+};
+
+/// IDs which were renamed in a particular version of the database.
+#[derive(Debug, Clone, Copy)]
+pub struct Migration {
+    /// Version of the database which introduced the new IDs.
+    pub to: DatabaseVersion,
+    /// Renamed buildings, as `(old, new)` pairs.
+    pub buildings: &'static [(&'static str, &'static str)],
+    /// Renamed recipes, as `(old, new)` pairs.
+    pub recipes: &'static [(&'static str, &'static str)],
+    /// Renamed items, as `(old, new)` pairs.
+    pub items: &'static [(&'static str, &'static str)],
+}
+
+/// Every registered migration, in version order.
+pub const MIGRATIONS: &[Migration] = &[Migration {
+    to: DatabaseVersion::V1_0(V1_0Subversion::Initial),
+    // 1.0 replaced the Biomass Burner with an automatable one.
+    buildings: &[(
+        "Desc_GeneratorBiomass_C",
+        "Desc_GeneratorBiomass_Automated_C",
+    )],
+    recipes: &[],
+    items: &[],
+}];
+
+/// Combined remaps of IDs to apply when migrating between two database versions.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct IdRemap {
+    /// New IDs of renamed buildings.
+    pub buildings: BTreeMap<BuildingId, BuildingId>,
+    /// New IDs of renamed recipes.
+    pub recipes: BTreeMap<RecipeId, RecipeId>,
+    /// New IDs of renamed items.
+    pub items: BTreeMap<ItemId, ItemId>,
+}
+
+impl IdRemap {
+    /// Combine the migrations after `from` up to and including `to`. An unknown `from`
+    /// includes every migration since the first database, and an unknown `to`, e.g. for
+    /// a custom database, includes every migration after `from`. IDs renamed more than
+    /// once map straight to their latest name.
+    pub fn between(from: Option<DatabaseVersion>, to: Option<DatabaseVersion>) -> Self {
+        let mut remap = Self::default();
+        for migration in MIGRATIONS {
+            if from.is_some_and(|from| migration.to <= from)
+                || to.is_some_and(|to| migration.to > to)
+            {
+                continue;
+            }
+            add_renames(&mut remap.buildings, migration.buildings);
+            add_renames(&mut remap.recipes, migration.recipes);
+            add_renames(&mut remap.items, migration.items);
+        }
+        remap
+    }
+
+    /// Returns true if this remap doesn't rename anything.
+    pub fn is_empty(&self) -> bool {
+        self.buildings.is_empty() && self.recipes.is_empty() && self.items.is_empty()
+    }
+
+    /// Copy of `node` with every ID missing from `database` replaced by its new name,
+    /// rebuilt with `database`. IDs the database has, and IDs with no remap, are left
+    /// alone.
+    pub fn apply(&self, node: &Node, database: &Database) -> Node {
+        match node.kind() {
+            NodeKind::Group(group) => Group {
+                children: group
+                    .children
+                    .iter()
+                    .map(|child| self.apply(child, database))
+                    .collect(),
+                byproducts: group
+                    .byproducts
+                    .iter()
+                    .map(|&item| remap_id(&self.items, item, database))
+                    .collect(),
+                ..group.clone()
+            }
+            .into(),
+            NodeKind::Building(building) => {
+                let building = self.apply_building(building, database);
+                match building.clone().build_node(database) {
+                    Ok(node) => node,
+                    Err(e) => e.into_warning_node(building),
+                }
+            }
+        }
+    }
+
+    /// Copy of `building` with its stale IDs replaced.
+    fn apply_building(&self, building: &Building, database: &Database) -> Building {
+        let item = |id: Option<ItemId>| id.map(|id| remap_id(&self.items, id, database));
+        let mut building = building.clone();
+        building.building = building
+            .building
+            .map(|id| remap_id(&self.buildings, id, database));
+        match &mut building.settings {
+            BuildingSettings::Manufacturer(settings) => {
+                settings.recipe = settings
+                    .recipe
+                    .map(|id| remap_id(&self.recipes, id, database));
+            }
+            BuildingSettings::Miner(settings) => settings.resource = item(settings.resource),
+            BuildingSettings::Generator(settings) => settings.fuel = item(settings.fuel),
+            BuildingSettings::Pump(settings) => settings.resource = item(settings.resource),
+            BuildingSettings::Station(settings) => settings.fuel = item(settings.fuel),
+            BuildingSettings::Geothermal(_) | BuildingSettings::PowerConsumer => {}
+        }
+        building.output_caps = building
+            .output_caps
+            .into_iter()
+            .map(|(id, cap)| (remap_id(&self.items, id, database), cap))
+            .collect();
+        building
+    }
+}
+
+/// Add `(old, new)` renames to `map`, pointing any earlier renames to `old` at `new`.
+fn add_renames<T>(map: &mut BTreeMap<T, T>, renames: &[(&'static str, &'static str)])
+where
+    T: Id + Ord + From<&'static str>,
+{
+    for &(old, new) in renames {
+        let (old, new) = (T::from(old), T::from(new));
+        for target in map.values_mut() {
+            if *target == old {
+                *target = new;
+            }
+        }
+        map.insert(old, new);
+    }
+}
+
+/// Get the new name of `id`, if it is missing from `database` and has been renamed.
+fn remap_id<T: Id + Ord>(map: &BTreeMap<T, T>, id: T, database: &Database) -> T {
+    if database.get(id).is_some() {
+        id
+    } else {
+        map.get(&id).copied().unwrap_or(id)
+    }
+}
+
+/// Migrate a tree built with the database version `from` to `database`, remapping
+/// renamed IDs, and report the buildings which still fail to build. `from` is None if
+/// the version the tree was built with isn't known.
+pub fn migrate(root: &Node, from: Option<DatabaseVersion>, database: &Database) -> RebuildReport {
+    IdRemap::between(from, database.version)
+        .apply(root, database)
+        .rebuild_with_report(database)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::accounting::{BuildError, GeneratorSettings};
+    use crate::database::U7Subversion;
+
+    const U7: DatabaseVersion = DatabaseVersion::U7(U7Subversion::Initial);
+
+    fn biomass_burner(building: &str, fuel: &str) -> Building {
+        Building {
+            building: Some(building.into()),
+            settings: GeneratorSettings {
+                fuel: Some(fuel.into()),
+                clock_speed: 1.0,
+            }
+            .into(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn remaps_between_versions() {
+        let old: BuildingId = "Desc_GeneratorBiomass_C".into();
+        assert!(IdRemap::between(Some(U7), Some(U7)).is_empty());
+        assert!(IdRemap::between(Some(DatabaseVersion::LATEST), None).is_empty());
+        assert!(IdRemap::between(None, Some(U7)).is_empty());
+        for remap in [
+            IdRemap::between(Some(U7), Some(DatabaseVersion::LATEST)),
+            IdRemap::between(None, None),
+        ] {
+            assert_eq!(
+                remap.buildings.get(&old),
+                Some(&"Desc_GeneratorBiomass_Automated_C".into())
+            );
+        }
+    }
+
+    #[test]
+    fn chained_renames() {
+        let mut map = BTreeMap::<ItemId, ItemId>::new();
+        add_renames(&mut map, &[("Desc_A_C", "Desc_B_C")]);
+        add_renames(&mut map, &[("Desc_B_C", "Desc_C_C")]);
+        assert_eq!(map[&"Desc_A_C".into()], "Desc_C_C".into());
+        assert_eq!(map[&"Desc_B_C".into()], "Desc_C_C".into());
+    }
+
+    #[test]
+    fn migrate_u7_factory() {
+        let u7 = U7.load_database();
+        let latest = DatabaseVersion::LATEST.load_database();
+        let burner = biomass_burner("Desc_GeneratorBiomass_C", "Desc_Leaves_C");
+        let geothermal = Building {
+            building: Some("Desc_GeneratorGeoThermal_C".into()),
+            settings: u7[BuildingId::from("Desc_GeneratorGeoThermal_C")].get_default_settings(),
+            ..Default::default()
+        };
+        let root: Node = Group {
+            children: vec![
+                burner.clone().build_node(&u7).unwrap(),
+                geothermal.clone().build_node(&u7).unwrap(),
+            ],
+            ..Group::empty()
+        }
+        .into();
+
+        let report = migrate(&root, u7.version, &latest);
+        let expected = biomass_burner("Desc_GeneratorBiomass_Automated_C", "Desc_Leaves_C");
+        // The geothermal generator failed to build, so it still records the old database.
+        let stale = Building {
+            database_version: u7.version,
+            ..geothermal
+        };
+        assert_eq!(
+            report.root,
+            Group {
+                children: vec![
+                    expected.build_node(&latest).unwrap(),
+                    BuildError::UnknownBuilding("Desc_GeneratorGeoThermal_C".into())
+                        .into_warning_node(stale),
+                ],
+                ..root.group().unwrap().clone()
+            }
+            .into()
+        );
+        assert_eq!(report.errors.len(), 1);
+        assert_eq!(report.errors[0].path, vec![1]);
+        assert_eq!(
+            report.root.group().unwrap().mismatched_database_versions(),
+            [U7, DatabaseVersion::LATEST].into()
+        );
+        assert!(root
+            .group()
+            .unwrap()
+            .mismatched_database_versions()
+            .is_empty());
+        // Groups above the one holding the mismatch don't report it again.
+        let outer = Group {
+            children: vec![report.root, Group::empty_node()],
+            ..Group::empty()
+        };
+        assert!(outer.mismatched_database_versions().is_empty());
+    }
+
+    #[test]
+    fn keeps_ids_the_database_has() {
+        let mut db = DatabaseVersion::LATEST.load_database();
+        let old =
+            U7.load_database().buildings[&BuildingId::from("Desc_GeneratorBiomass_C")].clone();
+        db.buildings.insert(old.id, old);
+        let burner = biomass_burner("Desc_GeneratorBiomass_C", "Desc_Leaves_C");
+        let node = burner.clone().build_node(&db).unwrap();
+
+        let report = migrate(&node, None, &db);
+        assert!(report.errors.is_empty());
+        assert_eq!(report.root, node);
+    }
+}
@@ -0,0 +1,233 @@
+// Copyright 2024 Zachary Stewart
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//       http://www.apache.org/licenses/LICENSE-2.0
+//! Sample factory for exercising the accounting functions in tests. Available in this
+//! crate's own tests, and to other crates with the `sample` feature.
+
+use crate::accounting::{
+    BuildNode, Building, BuildingSettings, GeneratorSettings, GeothermalSettings, Group,
+    ManufacturerSettings, MinerSettings, Node, PumpSettings, ResourcePurity, StationSettings,
+};
+use crate::database::{Database, DatabaseVersion, U7Subversion};
+
+/// Id of the geothermal generator, which is no longer in the latest database.
+const GEOTHERMAL: &str = "Desc_GeneratorGeoThermal_C";
+
+/// Database matching [`sample_factory`]. This is the latest database with the geothermal
+/// generator from U7 added back, so that every building kind is available.
+pub fn sample_database() -> Database {
+    let mut database = DatabaseVersion::LATEST.load_database();
+    let geothermal = DatabaseVersion::U7(U7Subversion::Initial)
+        .load_database()
+        .buildings
+        .remove(&GEOTHERMAL.into())
+        .expect("U7 database to have a geothermal generator");
+    database.buildings.insert(geothermal.id, geothermal);
+    database
+}
+
+/// Copies of a manufacturer making the given recipe.
+pub fn manufacturer(building: &str, recipe: &str, clock_speed: f32, copies: u32) -> Building {
+    Building {
+        building: Some(building.into()),
+        settings: ManufacturerSettings {
+            recipe: Some(recipe.into()),
+            clock_speed,
+            ..Default::default()
+        }
+        .into(),
+        copies,
+        ..Default::default()
+    }
+}
+
+/// Constructors making iron plates from ingots, the simplest manufacturer to test with.
+pub fn iron_plate_constructor(clock_speed: f32, copies: u32) -> Building {
+    manufacturer(
+        "Desc_ConstructorMk1_C",
+        "Recipe_IronPlate_C",
+        clock_speed,
+        copies,
+    )
+}
+
+/// Build a small but complete factory along with the database it was built from. It
+/// uses every kind of building, nested groups, virtual copies, overclocking and a
+/// byproduct, and builds without any warnings.
+///
+/// The factory makes reinforced iron plates from mined iron ore:
+///
+/// * "Iron" mines ore and smelts it into ingots.
+/// * "Parts" has 2 copies, each turning ingots into plates, rods and screws.
+/// * "Reinforced Plates" assembles the plates and screws.
+/// * "Power" runs coal and geothermal generators and a water extractor.
+/// * "Logistics" has a truck station, a resource well and a radar tower.
+pub fn sample_factory() -> (Node, Database) {
+    let database = sample_database();
+    let building = |id: &str, settings: BuildingSettings, copies: u32| -> Node {
+        Building {
+            building: Some(id.into()),
+            settings,
+            copies,
+            ..Default::default()
+        }
+        .build_node(&database)
+        .expect("sample building to build")
+    };
+    let manufacturer = |id: &str, recipe: &str, clock_speed: f32, copies: u32| -> Node {
+        manufacturer(id, recipe, clock_speed, copies)
+            .build_node(&database)
+            .expect("sample building to build")
+    };
+    let group = |name: &'static str, children: Vec<Node>, copies: u32| -> Node {
+        Group {
+            name: name.into(),
+            children,
+            copies,
+            ..Group::empty()
+        }
+        .build_node(&database)
+        .expect("sample group to build")
+    };
+
+    let iron = group(
+        "Iron",
+        vec![
+            building(
+                "Desc_MinerMk2_C",
+                MinerSettings {
+                    resource: Some("Desc_OreIron_C".into()),
+                    clock_speed: 1.5,
+                    purity: ResourcePurity::Normal,
+                }
+                .into(),
+                1,
+            ),
+            manufacturer("Desc_SmelterMk1_C", "Recipe_IngotIron_C", 1.0, 6),
+        ],
+        1,
+    );
+    let parts = group(
+        "Parts",
+        vec![
+            manufacturer("Desc_ConstructorMk1_C", "Recipe_IronPlate_C", 1.0, 3),
+            manufacturer("Desc_ConstructorMk1_C", "Recipe_IronRod_C", 1.0, 1),
+            manufacturer("Desc_ConstructorMk1_C", "Recipe_Screw_C", 1.0, 1),
+        ],
+        2,
+    );
+    let reinforced = group(
+        "Reinforced Plates",
+        vec![manufacturer(
+            "Desc_AssemblerMk1_C",
+            "Recipe_IronPlateReinforced_C",
+            1.0,
+            2,
+        )],
+        1,
+    );
+    let power = group(
+        "Power",
+        vec![
+            building(
+                "Desc_GeneratorCoal_C",
+                GeneratorSettings {
+                    fuel: Some("Desc_Coal_C".into()),
+                    clock_speed: 1.0,
+                }
+                .into(),
+                2,
+            ),
+            building(
+                GEOTHERMAL,
+                GeothermalSettings {
+                    purity: ResourcePurity::Pure,
+                }
+                .into(),
+                1,
+            ),
+            manufacturer("Desc_WaterPump_C", "_Patch_Recipe_ExtractWater_C", 1.0, 3),
+        ],
+        1,
+    );
+    let logistics = group(
+        "Logistics",
+        vec![
+            building(
+                "Desc_TruckStation_C",
+                StationSettings {
+                    fuel: Some("Desc_Fuel_C".into()),
+                    consumption: 2.0,
+                }
+                .into(),
+                1,
+            ),
+            building(
+                "Desc_FrackingSmasher_C",
+                PumpSettings {
+                    resource: Some("Desc_Water_C".into()),
+                    clock_speed: 1.0,
+                    pure_pads: 1,
+                    normal_pads: 2,
+                    impure_pads: 0,
+                }
+                .into(),
+                1,
+            ),
+            building("Desc_RadarTower_C", BuildingSettings::PowerConsumer, 1),
+        ],
+        1,
+    );
+    let mut root = Group {
+        name: "Sample Factory".into(),
+        children: vec![iron, parts, reinforced, power, logistics],
+        ..Group::empty()
+    };
+    root.byproducts.insert("Desc_IronRod_C".into());
+    let root = root.build_node(&database).expect("sample factory to build");
+    (root, database)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use super::*;
+    use crate::database::BuildingKindId;
+
+    #[test]
+    fn sample_builds_cleanly() {
+        let (root, _) = sample_factory();
+        assert!(root.warning().is_none());
+        assert!(!root.children_had_warnings());
+    }
+
+    #[test]
+    fn sample_uses_every_building_kind() {
+        let (root, _) = sample_factory();
+        let kinds: BTreeSet<_> = root
+            .iter()
+            .filter_map(|node| node.building().map(|b| b.settings.kind_id()))
+            .collect();
+        let all = BTreeSet::from([
+            BuildingKindId::Manufacturer,
+            BuildingKindId::Miner,
+            BuildingKindId::Generator,
+            BuildingKindId::Pump,
+            BuildingKindId::Geothermal,
+            BuildingKindId::PowerConsumer,
+            BuildingKindId::Station,
+        ]);
+        assert_eq!(kinds, all);
+    }
+
+    #[test]
+    fn sample_rebuilds_identically() {
+        let (root, database) = sample_factory();
+        assert_eq!(root.rebuild(&database), root);
+    }
+}
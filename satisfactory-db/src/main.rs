@@ -8,8 +8,8 @@
 use std::collections::{BTreeMap, HashMap, HashSet};
 
 use satisfactory_accounting::database::{
-    BuildingKind, BuildingType, Database, Fuel, Generator, Geothermal, Item, ItemAmount, ItemId,
-    Manufacturer, Miner, Power, PowerConsumer, Pump, Recipe, Station,
+    BuildingKind, BuildingType, Database, Footprint, Fuel, Generator, Geothermal, Item, ItemAmount,
+    ItemId, Manufacturer, Miner, Power, PowerConsumer, Pump, Recipe, Station,
 };
 
 mod rawdata;
@@ -87,6 +87,49 @@ fn main() {
     /// As of 1.0 Drones can use any fuel.
     const DRONE_FUELS: &[&str] = TRUCK_FUELS;
 
+    /// Width and length in meters of major buildings. The size in the source data is
+    /// always zero, so these are taken from the wiki.
+    const FOOTPRINTS: &[(&str, f32, f32)] = &[
+        ("Desc_SmelterMk1_C", 6.0, 9.0),
+        ("Desc_FoundryMk1_C", 10.0, 9.0),
+        ("Desc_ConstructorMk1_C", 8.0, 10.0),
+        ("Desc_AssemblerMk1_C", 10.0, 15.0),
+        ("Desc_ManufacturerMk1_C", 18.0, 20.0),
+        ("Desc_OilRefinery_C", 10.0, 20.0),
+        ("Desc_Packager_C", 8.0, 8.0),
+        ("Desc_Blender_C", 18.0, 16.0),
+        ("Desc_HadronCollider_C", 24.0, 38.0),
+        ("Desc_QuantumEncoder_C", 22.0, 48.0),
+        ("Desc_Converter_C", 16.0, 16.0),
+        ("Desc_MinerMk1_C", 6.0, 14.0),
+        ("Desc_MinerMk2_C", 6.0, 14.0),
+        ("Desc_MinerMk3_C", 6.0, 14.0),
+        ("Desc_OilPump_C", 8.0, 8.0),
+        ("Desc_WaterPump_C", 20.0, 20.0),
+        ("Desc_FrackingSmasher_C", 10.0, 10.0),
+        ("Desc_GeneratorBiomass_Automated_C", 8.0, 8.0),
+        ("Desc_GeneratorCoal_C", 10.0, 26.0),
+        ("Desc_GeneratorFuel_C", 20.0, 20.0),
+        ("Desc_GeneratorNuclear_C", 36.0, 43.0),
+        ("Desc_GeneratorGeoThermal_C", 20.0, 20.0),
+        ("Desc_ResourceSink_C", 16.0, 13.0),
+    ];
+
+    /// Number of Somersloops each production building can be amplified with. This isn't
+    /// in the source data, so it is taken from the wiki.
+    const SOMERSLOOP_SLOTS: &[(&str, u32)] = &[
+        ("Desc_SmelterMk1_C", 1),
+        ("Desc_ConstructorMk1_C", 1),
+        ("Desc_AssemblerMk1_C", 2),
+        ("Desc_FoundryMk1_C", 2),
+        ("Desc_OilRefinery_C", 2),
+        ("Desc_Converter_C", 2),
+        ("Desc_ManufacturerMk1_C", 4),
+        ("Desc_Blender_C", 4),
+        ("Desc_HadronCollider_C", 4),
+        ("Desc_QuantumEncoder_C", 4),
+    ];
+
     let miners: HashMap<_, _> = raw
         .miners
         .values()
@@ -146,14 +189,9 @@ fn main() {
             id: recipe.class_name.as_str().into(),
             image: recipe
                 .products
-                .iter()
-                .next()
+                .first()
                 .map(|ia| ia.item.as_str().into())
-                .or(recipe
-                    .ingredients
-                    .iter()
-                    .next()
-                    .map(|ia| ia.item.as_str().into()))
+                .or(recipe.ingredients.first().map(|ia| ia.item.as_str().into()))
                 .unwrap_or_default(),
             time: recipe.time,
             ingredients: recipe
@@ -162,6 +200,7 @@ fn main() {
                 .map(|ia| ItemAmount {
                     item: ia.item.as_str().into(),
                     amount: ia.amount,
+                    averaged: false,
                 })
                 .collect(),
             products: recipe
@@ -170,6 +209,7 @@ fn main() {
                 .map(|ia| ItemAmount {
                     item: ia.item.as_str().into(),
                     amount: ia.amount,
+                    averaged: false,
                 })
                 .collect(),
             is_alternate: recipe.alternate,
@@ -192,6 +232,7 @@ fn main() {
                 products: vec![ItemAmount {
                     item: ItemId::water(),
                     amount: 1.0,
+                    averaged: false,
                 }],
                 is_alternate: false,
                 produced_in: vec!["Desc_WaterPump_C".into()],
@@ -206,28 +247,34 @@ fn main() {
                     ItemAmount {
                         item: "Desc_SAMFluctuator_C".into(),
                         amount: 5.0,
+                        averaged: false,
                     },
                     ItemAmount {
                         item: "Desc_CrystalShard_C".into(),
                         amount: 3.0,
+                        averaged: false,
                     },
                     ItemAmount {
                         item: "Desc_QuantumOscillator_C".into(),
                         amount: 3.0,
+                        averaged: false,
                     },
                     ItemAmount {
                         item: "Desc_QuantumEnergy_C".into(),
                         amount: 24.0,
+                        averaged: false,
                     },
                 ],
                 products: vec![
                     ItemAmount {
                         item: "Desc_AlienPowerFuel_C".into(),
                         amount: 1.0,
+                        averaged: false,
                     },
                     ItemAmount {
                         item: "Desc_DarkEnergy_C".into(),
                         amount: 24.0,
+                        averaged: false,
                     },
                 ],
                 is_alternate: false,
@@ -243,6 +290,7 @@ fn main() {
                 ingredients: vec![ItemAmount {
                     item: "Desc_SingularityCell_C".into(),
                     amount: 1.0,
+                    averaged: false,
                 }],
                 products: Vec::new(),
                 is_alternate: false,
@@ -269,10 +317,12 @@ fn main() {
                         "Desc_NuclearFuelRod_C" => vec![ItemAmount {
                             item: "Desc_NuclearWaste_C".into(),
                             amount: 50.0,
+                            averaged: false,
                         }],
                         "Desc_PlutoniumFuelRod_C" => vec![ItemAmount {
                             item: "Desc_PlutoniumWaste_C".into(),
                             amount: 10.0,
+                            averaged: false,
                         }],
                         _ => Vec::new(),
                     },
@@ -285,6 +335,13 @@ fn main() {
             } else {
                 0.0
             },
+            fluid: item.liquid,
+            // Fluids have a point value but can't be put in the sink.
+            sink_points: if item.liquid {
+                0
+            } else {
+                item.sink_points.unwrap_or_default()
+            },
             // These will be patched in later.
             produced_by: Vec::new(),
             consumed_by: Vec::new(),
@@ -302,6 +359,8 @@ fn main() {
                         .into(),
                 fuel: None,
                 mining_speed: 0.0,
+                fluid: false,
+                sink_points: 0,
                 produced_by: Vec::new(),
                 consumed_by: Vec::new(),
                 mined_by: Vec::new(),
@@ -363,6 +422,10 @@ fn main() {
                                 .expect("Manufacturer missing power_consumption_exponent")
                         },
                     },
+                    somersloop_slots: SOMERSLOOP_SLOTS
+                        .iter()
+                        .find(|(id, _)| *id == building.class_name)
+                        .map_or(0, |&(_, slots)| slots),
                 })
             } else if generators.contains_key(building.class_name.as_str()) {
                 // Geothermal is a special case.
@@ -461,6 +524,10 @@ fn main() {
                         .expect("Power consumer missing power consumption"),
                 })
             },
+            footprint: FOOTPRINTS
+                .iter()
+                .find(|(id, _, _)| *id == building.class_name)
+                .map(|&(_, width, length)| Footprint { width, length }),
         })
         .map(|building| (building.id, building))
         .collect();
@@ -501,7 +568,7 @@ fn main() {
             BuildingKind::Miner(m) => {
                 for item in &m.allowed_resources {
                     items
-                        .get_mut(&item)
+                        .get_mut(item)
                         .expect("Missing resource extracted by building")
                         .mined_by
                         .push(building.id);
@@ -510,7 +577,7 @@ fn main() {
             BuildingKind::Pump(p) => {
                 for item in &p.allowed_resources {
                     items
-                        .get_mut(&item)
+                        .get_mut(item)
                         .expect("Missing resource extracted by building")
                         .mined_by
                         .push(building.id);
@@ -521,6 +588,7 @@ fn main() {
     }
 
     let database = Database {
+        version: None,
         icon_prefix: "v1.0/".to_string(),
         recipes,
         items,